@@ -3,9 +3,14 @@ use std::str::FromStr;
 
 use num_bigint::BigInt;
 
+use crate::format::FormatSpec;
 use crate::types::Params;
 use crate::util::{BinaryOperator, Location, UnaryOperator};
 
+/// Prefix for the names of temporary vars generated by the parser.
+/// These can't clash with user names since idents can't contain dots.
+pub const TEMP_PREFIX: &str = "$tmp.";
+
 /// Program - a list of statements.
 #[derive(PartialEq)]
 pub struct Program {
@@ -41,8 +46,10 @@ pub struct Statement {
 pub enum StatementKind {
     Jump(String),
     Label(String, Expr),
-    Break(Expr),
-    Continue,
+    // Break and continue can target an enclosing labeled loop
+    Break(Option<String>, Expr),
+    Continue(Option<String>),
+    Raise(Expr),
     Expr(Expr),
 }
 
@@ -60,17 +67,38 @@ impl Statement {
         Self::new(StatementKind::Label(name, expr), start, end)
     }
 
-    pub fn new_break(expr: Expr, start: Location, end: Location) -> Self {
-        Self::new(StatementKind::Break(expr), start, end)
+    pub fn new_break(
+        label: Option<String>,
+        expr: Expr,
+        start: Location,
+        end: Location,
+    ) -> Self {
+        Self::new(StatementKind::Break(label, expr), start, end)
+    }
+
+    pub fn new_continue(label: Option<String>, start: Location, end: Location) -> Self {
+        Self::new(StatementKind::Continue(label), start, end)
     }
 
-    pub fn new_continue(start: Location, end: Location) -> Self {
-        Self::new(StatementKind::Continue, start, end)
+    pub fn new_raise(expr: Expr, start: Location, end: Location) -> Self {
+        Self::new(StatementKind::Raise(expr), start, end)
     }
 
     pub fn new_expr(expr: Expr, start: Location, end: Location) -> Self {
         Self::new(StatementKind::Expr(expr), start, end)
     }
+
+    /// Check if statement is an assignment or multiple assignment.
+    pub fn is_assignment(&self) -> bool {
+        match &self.kind {
+            StatementKind::Expr(expr) => matches!(
+                &expr.kind,
+                ExprKind::BinaryOp(_, BinaryOperator::Assign, _)
+                    | ExprKind::MultiAssign(..)
+            ),
+            _ => false,
+        }
+    }
 }
 
 impl fmt::Debug for Statement {
@@ -87,8 +115,11 @@ impl fmt::Debug for StatementKind {
                 write!(f, "Label: {} {expr:?}", label_index)
             }
             Self::Jump(label_index) => write!(f, "Jump: {}", label_index),
-            Self::Break(expr) => write!(f, "break {expr:?}"),
-            Self::Continue => write!(f, "Continue"),
+            Self::Break(None, expr) => write!(f, "break {expr:?}"),
+            Self::Break(Some(label), expr) => write!(f, "break {label} {expr:?}"),
+            Self::Continue(None) => write!(f, "Continue"),
+            Self::Continue(Some(label)) => write!(f, "Continue {label}"),
+            Self::Raise(expr) => write!(f, "raise {expr:?}"),
         }
     }
 }
@@ -104,16 +135,23 @@ pub struct Expr {
 #[derive(Clone, PartialEq)]
 pub enum ExprKind {
     Tuple(Vec<Expr>),
+    List(Vec<Expr>),
     Literal(Literal),
     FormatString(Vec<Expr>),
+    FormatValue(Box<Expr>, FormatSpec),
     Ident(Ident),
     Block(StatementBlock),
     Conditional(Vec<(Expr, StatementBlock)>, Option<StatementBlock>),
-    Loop(Box<Expr>, StatementBlock),
+    Loop(Option<String>, Box<Expr>, StatementBlock),
+    // try block, name to bind the error to in the catch block, catch
+    // block
+    Try(StatementBlock, Option<String>, StatementBlock),
     Func(Func),
     Call(Call),
     UnaryOp(UnaryOperator, Box<Expr>),
     BinaryOp(Box<Expr>, BinaryOperator, Box<Expr>),
+    // Unpack a tuple into multiple names: `a, b = t`
+    MultiAssign(Vec<String>, Box<Expr>),
 }
 
 impl Expr {
@@ -125,6 +163,10 @@ impl Expr {
         Self::new(ExprKind::Tuple(items), start, end)
     }
 
+    pub fn new_list(items: Vec<Expr>, start: Location, end: Location) -> Self {
+        Self::new(ExprKind::List(items), start, end)
+    }
+
     fn new_literal(literal: Literal, start: Location, end: Location) -> Self {
         Self::new(ExprKind::Literal(literal), start, end)
     }
@@ -161,6 +203,15 @@ impl Expr {
         Self::new(ExprKind::FormatString(items), start, end)
     }
 
+    pub fn new_format_value(
+        expr: Expr,
+        spec: FormatSpec,
+        start: Location,
+        end: Location,
+    ) -> Self {
+        Self::new(ExprKind::FormatValue(Box::new(expr), spec), start, end)
+    }
+
     pub fn new_block(block: StatementBlock, start: Location, end: Location) -> Self {
         Self::new(ExprKind::Block(block), start, end)
     }
@@ -175,12 +226,23 @@ impl Expr {
     }
 
     pub fn new_loop(
+        label: Option<String>,
         expr: Expr,
         block: StatementBlock,
         start: Location,
         end: Location,
     ) -> Self {
-        Self::new(ExprKind::Loop(Box::new(expr), block), start, end)
+        Self::new(ExprKind::Loop(label, Box::new(expr), block), start, end)
+    }
+
+    pub fn new_try(
+        block: StatementBlock,
+        name: Option<String>,
+        catch_block: StatementBlock,
+        start: Location,
+        end: Location,
+    ) -> Self {
+        Self::new(ExprKind::Try(block, name, catch_block), start, end)
     }
 
     pub fn new_ident(ident: Ident, start: Location, end: Location) -> Self {
@@ -189,11 +251,13 @@ impl Expr {
 
     pub fn new_func(
         params: Params,
+        defaults: Vec<Expr>,
+        rest: Option<String>,
         block: StatementBlock,
         start: Location,
         end: Location,
     ) -> Self {
-        Self::new(ExprKind::Func(Func::new(params, block)), start, end)
+        Self::new(ExprKind::Func(Func::new(params, defaults, rest, block)), start, end)
     }
 
     pub fn new_call(
@@ -232,6 +296,15 @@ impl Expr {
         Self::new(ExprKind::BinaryOp(Box::new(a), operator, Box::new(b)), start, end)
     }
 
+    pub fn new_multi_assign(
+        names: Vec<String>,
+        value: Expr,
+        start: Location,
+        end: Location,
+    ) -> Self {
+        Self::new(ExprKind::MultiAssign(names, Box::new(value)), start, end)
+    }
+
     /// Check if expression is ellipsis.
     pub fn is_ellipsis(&self) -> bool {
         match &self.kind {
@@ -240,6 +313,33 @@ impl Expr {
         }
     }
 
+    /// Check if evaluating the expression might read the var with the
+    /// specified name. This is conservative: expressions that aren't
+    /// examined (e.g., blocks) are assumed to read the var. Function
+    /// bodies aren't evaluated when a function is defined, so they
+    /// don't count.
+    pub fn may_read_var(&self, name: &str) -> bool {
+        let any = |exprs: &[Expr]| exprs.iter().any(|expr| expr.may_read_var(name));
+        match &self.kind {
+            ExprKind::Literal(_) => false,
+            ExprKind::Ident(ident) => match &ident.kind {
+                IdentKind::Ident(n)
+                | IdentKind::SpecialIdent(n)
+                | IdentKind::TypeIdent(n) => n == name,
+            },
+            ExprKind::Tuple(items)
+            | ExprKind::List(items)
+            | ExprKind::FormatString(items) => any(items),
+            ExprKind::FormatValue(expr, _) | ExprKind::UnaryOp(_, expr) => {
+                expr.may_read_var(name)
+            }
+            ExprKind::BinaryOp(a, _, b) => a.may_read_var(name) || b.may_read_var(name),
+            ExprKind::Func(func) => any(&func.defaults),
+            ExprKind::Call(call) => call.callable.may_read_var(name) || any(&call.args),
+            _ => true,
+        }
+    }
+
     // Expression type checkers ----------------------------------------
 
     /// Check if expression is `true` literal or `1`.
@@ -297,18 +397,32 @@ impl fmt::Debug for ExprKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Tuple(items) => write!(f, "{:?}", items),
+            Self::List(items) => write!(f, "List {:?}", items),
             Self::Literal(literal) => write!(f, "{:?}", literal),
             Self::FormatString(items) => write!(f, "{:?}", items),
+            Self::FormatValue(expr, spec) => write!(f, "{expr:?}:{spec}"),
             Self::Ident(ident) => write!(f, "{:?}", ident),
             Self::Block(block) => write!(f, "{:?}", block),
             Self::Conditional(branches, default) => {
                 write!(f, "{branches:?} {default:?}")
             }
-            Self::Loop(expr, block) => write!(f, "loop {expr:?}\n{block:?}"),
+            Self::Loop(None, expr, block) => write!(f, "loop {expr:?}\n{block:?}"),
+            Self::Loop(Some(label), expr, block) => {
+                write!(f, "{label}: loop {expr:?}\n{block:?}")
+            }
+            Self::Try(block, None, catch_block) => {
+                write!(f, "try {block:?}\ncatch {catch_block:?}")
+            }
+            Self::Try(block, Some(name), catch_block) => {
+                write!(f, "try {block:?}\ncatch {name} {catch_block:?}")
+            }
             Self::Func(func) => write!(f, "{:?}", func),
             Self::Call(func) => write!(f, "{:?}", func),
             Self::UnaryOp(op, b) => write!(f, "({:?}{:?})", op, b),
             Self::BinaryOp(a, op, b) => write!(f, "({:?} {:?} {:?})", a, op, b),
+            Self::MultiAssign(names, value) => {
+                write!(f, "({} = {:?})", names.join(", "), value)
+            }
         }
     }
 }
@@ -342,12 +456,21 @@ impl fmt::Debug for StatementBlock {
 #[derive(Clone, PartialEq)]
 pub struct Func {
     pub params: Params,
+    /// Default value expressions for the trailing params
+    pub defaults: Vec<Expr>,
+    /// Name of the param that collects any extra args (`rest...`)
+    pub rest: Option<String>,
     pub block: StatementBlock,
 }
 
 impl Func {
-    pub fn new(params: Params, block: StatementBlock) -> Self {
-        Self { params, block }
+    pub fn new(
+        params: Params,
+        defaults: Vec<Expr>,
+        rest: Option<String>,
+        block: StatementBlock,
+    ) -> Self {
+        Self { params, defaults, rest, block }
     }
 }
 
@@ -472,6 +595,11 @@ impl Ident {
     pub fn new_type_ident(name: String) -> Self {
         Self::new(IdentKind::TypeIdent(name))
     }
+
+    /// Make the Nth temporary ident.
+    pub fn new_temp_ident(n: usize) -> Self {
+        Self::new_special_ident(format!("{TEMP_PREFIX}{n}"))
+    }
 }
 
 impl fmt::Debug for Ident {
@@ -0,0 +1,56 @@
+use crate::types::{Args, CallResult};
+use crate::vm::{RuntimeErr, VM};
+
+/// Check that the first arg is true according to its `as_bool`. If
+/// it isn't, fail with the optional message passed as the second arg.
+///
+/// Returns Nil
+pub fn assert(args: Args, vm: &mut VM) -> CallResult {
+    let (cond, message) = match &args[..] {
+        [cond] => (cond, None),
+        [cond, message] => (cond, Some(message)),
+        _ => {
+            return Err(RuntimeErr::new_type_err(format!(
+                "assert() expected 1 or 2 args; got {}",
+                args.len()
+            )))
+        }
+    };
+    if vm.as_bool(cond)? {
+        Ok(None)
+    } else {
+        let message = match message {
+            Some(message) => message.to_string(),
+            None => "assertion failed".to_owned(),
+        };
+        Err(RuntimeErr::new_assertion_failed(message))
+    }
+}
+
+/// Call the zero-arg function passed as the second arg and check that
+/// it fails with the kind of runtime error named by the first arg
+/// (e.g., "TypeErr"). If it doesn't fail or fails with a different
+/// kind of error, an assertion error is raised.
+///
+/// Returns Nil
+pub fn assert_raises(args: Args, vm: &mut VM) -> CallResult {
+    let kind_name = args.first().unwrap();
+    let func = args.get(1).unwrap();
+    let kind_name = if let Some(kind_name) = kind_name.str_val() {
+        kind_name
+    } else {
+        return Err(RuntimeErr::new_type_err(format!(
+            "assert_raises() expected an error kind name; got {kind_name:?}"
+        )));
+    };
+    match vm.call_obj(func, vec![]) {
+        Ok(_) => Err(RuntimeErr::new_assertion_failed(format!(
+            "expected {kind_name}; no error was raised"
+        ))),
+        Err(err) if err.kind.name() == kind_name => Ok(None),
+        Err(err) => Err(RuntimeErr::new_assertion_failed(format!(
+            "expected {kind_name}; got {}",
+            err.kind
+        ))),
+    }
+}
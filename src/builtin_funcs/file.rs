@@ -1,39 +1,52 @@
-use std::fs::{read_to_string, File};
+use std::fs::{read_to_string, write, File};
 use std::io::{BufRead, BufReader};
 
 use crate::types::{Args, CallResult};
-use crate::vm::{RuntimeErr, RuntimeErrKind, VM};
+use crate::vm::{RuntimeErr, VM};
 
 /// Read file into a string.
 /// Returns Str
 pub fn read_file(args: Args, vm: &mut VM) -> CallResult {
-    let arg = args.get(0).unwrap();
-    if let Some(file_name) = arg.str_val() {
-        match read_to_string(file_name) {
-            Ok(contents) => Ok(Some(vm.ctx.builtins.new_str(contents))),
-            Err(err) => {
-                Err(RuntimeErr::new(RuntimeErrKind::CouldNotReadFile(err.to_string())))
-            }
-        }
-    } else {
-        Err(RuntimeErr::new_type_err("Expected string"))
-    }
+    let file_name = get_file_name(&args)?;
+    let contents = read_to_string(file_name).map_err(io_err)?;
+    Ok(Some(vm.ctx.builtins.new_str(contents)))
 }
 
 /// Read lines of file into tuple.
 /// Returns Tuple<Str>
 pub fn read_file_lines(args: Args, vm: &mut VM) -> CallResult {
-    let arg = args.get(0).unwrap();
-    if let Some(file_name) = arg.str_val() {
-        let file = File::open(file_name).unwrap();
-        let reader = BufReader::new(file);
-        let mut items = vec![];
-        for line in reader.lines() {
-            let item = vm.ctx.builtins.new_str(line.unwrap());
-            items.push(item);
-        }
-        Ok(Some(vm.ctx.builtins.new_tuple(items)))
+    let file_name = get_file_name(&args)?;
+    let file = File::open(file_name).map_err(io_err)?;
+    let reader = BufReader::new(file);
+    let mut items = vec![];
+    for line in reader.lines() {
+        let item = vm.ctx.builtins.new_str(line.map_err(io_err)?);
+        items.push(item);
+    }
+    Ok(Some(vm.ctx.builtins.new_tuple(items)))
+}
+
+/// Write string to file, replacing the file's contents if it already
+/// exists.
+/// Returns Nil
+pub fn write_file(args: Args, _vm: &mut VM) -> CallResult {
+    let file_name = get_file_name(&args)?;
+    let arg = args.get(1).unwrap();
+    if let Some(contents) = arg.str_val() {
+        write(file_name, contents).map_err(io_err)?;
+        Ok(None)
     } else {
         Err(RuntimeErr::new_type_err("Expected string"))
     }
 }
+
+// Utilities -----------------------------------------------------------
+
+fn get_file_name(args: &Args) -> Result<String, RuntimeErr> {
+    let arg = args.first().unwrap();
+    arg.str_val().ok_or_else(|| RuntimeErr::new_type_err("Expected string"))
+}
+
+fn io_err(err: std::io::Error) -> RuntimeErr {
+    RuntimeErr::new_io_err(err.to_string())
+}
@@ -0,0 +1,33 @@
+use crate::types::{Args, CallResult};
+use crate::vm::{RuntimeErr, VM};
+
+/// Generate a method that applies an `f64` method to a Float. These
+/// always return a Float, even when the result is a whole number.
+macro_rules! make_float_method {
+    ( $name:ident ) => {
+        /// Returns Float
+        pub fn $name(args: Args, vm: &mut VM) -> CallResult {
+            let value = get_this(&args, stringify!($name))?;
+            Ok(Some(vm.ctx.builtins.new_float(value.$name())))
+        }
+    };
+}
+
+make_float_method!(abs);
+make_float_method!(floor);
+make_float_method!(ceil);
+make_float_method!(round);
+make_float_method!(trunc);
+
+// Utilities -----------------------------------------------------------
+
+fn get_this(args: &Args, name: &str) -> Result<f64, RuntimeErr> {
+    let this = args.first().unwrap();
+    if let Some(value) = this.float_val() {
+        Ok(value)
+    } else {
+        Err(RuntimeErr::new_type_err(format!(
+            "Builtin {name} function expected a Float"
+        )))
+    }
+}
@@ -0,0 +1,17 @@
+use crate::types::{Args, CallResult};
+use crate::vm::{RuntimeErr, VM};
+
+/// Disassemble a function's instructions.
+/// Returns Str
+pub fn dis(args: Args, vm: &mut VM) -> CallResult {
+    let arg = args.first().unwrap();
+    if let Some(func) = arg.as_func() {
+        let lines = vm.format_chunk(&func.chunk);
+        Ok(Some(vm.ctx.builtins.new_str(lines.join("\n"))))
+    } else {
+        let type_name = arg.type_name();
+        Err(RuntimeErr::new_type_err(format!(
+            "Can only disassemble functions; got {type_name}"
+        )))
+    }
+}
@@ -0,0 +1,37 @@
+use std::io::Write;
+
+use crate::types::{Args, CallResult};
+use crate::vm::{RuntimeErr, VM};
+
+/// Print the prompt, then read a line from stdin.
+/// Returns Str (or Nil on EOF)
+pub fn input(args: Args, vm: &mut VM) -> CallResult {
+    let prompt = args.first().unwrap();
+    if let Some(prompt) = prompt.str_val() {
+        write!(vm.stdout, "{prompt}")
+            .and_then(|()| vm.stdout.flush())
+            .map_err(|err| RuntimeErr::new_io_err(err.to_string()))?;
+        read_line(vec![], vm)
+    } else {
+        Err(RuntimeErr::new_type_err("Expected string"))
+    }
+}
+
+/// Read a line from stdin, stripping the trailing newline.
+/// Returns Str (or Nil on EOF)
+pub fn read_line(_args: Args, vm: &mut VM) -> CallResult {
+    let mut line = String::new();
+    match vm.stdin.read_line(&mut line) {
+        Ok(0) => Ok(None),
+        Ok(_) => {
+            if line.ends_with('\n') {
+                line.pop();
+                if line.ends_with('\r') {
+                    line.pop();
+                }
+            }
+            Ok(Some(vm.ctx.builtins.new_str(line)))
+        }
+        Err(err) => Err(RuntimeErr::new_io_err(err.to_string())),
+    }
+}
@@ -0,0 +1,76 @@
+use num_bigint::BigInt;
+use num_traits::{Signed, ToPrimitive, Zero};
+
+use crate::types::{Args, CallResult};
+use crate::vm::{RuntimeErr, VM};
+
+/// Generate a method that converts an Int to a fixed-width integer
+/// type and back. If the value doesn't fit in the fixed-width type,
+/// nil is returned instead of wrapping or panicking.
+macro_rules! make_checked_conversion {
+    ( $name:ident, $conv:ident ) => {
+        /// Returns Int or nil
+        pub fn $name(args: Args, vm: &mut VM) -> CallResult {
+            let value = get_this(&args, stringify!($name))?;
+            Ok(value.$conv().map(|value| vm.ctx.builtins.new_int(value)))
+        }
+    };
+}
+
+make_checked_conversion!(to_u8, to_u8);
+make_checked_conversion!(to_i8, to_i8);
+make_checked_conversion!(to_u16, to_u16);
+make_checked_conversion!(to_i16, to_i16);
+make_checked_conversion!(to_u32, to_u32);
+make_checked_conversion!(to_i32, to_i32);
+make_checked_conversion!(to_u64, to_u64);
+make_checked_conversion!(to_i64, to_i64);
+
+/// Returns Int
+pub fn abs(args: Args, vm: &mut VM) -> CallResult {
+    let value = get_this(&args, "abs")?;
+    Ok(Some(vm.ctx.builtins.new_int(value.abs())))
+}
+
+/// Returns Bool
+pub fn is_even(args: Args, vm: &mut VM) -> CallResult {
+    let value = get_this(&args, "is_even")?;
+    Ok(Some(vm.ctx.builtins.bool_obj_from_bool((value % 2u8).is_zero())))
+}
+
+/// Returns Bool
+pub fn is_odd(args: Args, vm: &mut VM) -> CallResult {
+    let value = get_this(&args, "is_odd")?;
+    Ok(Some(vm.ctx.builtins.bool_obj_from_bool(!(value % 2u8).is_zero())))
+}
+
+/// Generate a rounding method. Rounding an Int is a no-op, so these
+/// methods return the Int as is (rather than converting it to a Float
+/// like the Float methods of the same names do).
+macro_rules! make_rounding_method {
+    ( $name:ident ) => {
+        /// Returns Int
+        pub fn $name(args: Args, _vm: &mut VM) -> CallResult {
+            get_this(&args, stringify!($name))?;
+            Ok(Some(args[0].clone()))
+        }
+    };
+}
+
+make_rounding_method!(floor);
+make_rounding_method!(ceil);
+make_rounding_method!(round);
+make_rounding_method!(trunc);
+
+// Utilities -----------------------------------------------------------
+
+fn get_this(args: &Args, name: &str) -> Result<BigInt, RuntimeErr> {
+    let this = args.first().unwrap();
+    if let Some(value) = this.int_val() {
+        Ok(value)
+    } else {
+        Err(RuntimeErr::new_type_err(format!(
+            "Builtin {name} function expected an Int"
+        )))
+    }
+}
@@ -0,0 +1,62 @@
+use num_traits::ToPrimitive;
+
+use crate::types::{Args, CallResult};
+use crate::vm::{RuntimeErr, VM};
+
+/// Set every item in the list to the specified value.
+///
+/// Returns Nil
+pub fn fill(args: Args, _vm: &mut VM) -> CallResult {
+    let this = args.first().unwrap();
+    if let Some(this) = this.as_list() {
+        let value = args.get(1).unwrap();
+        this.items_mut().fill(value.clone());
+        Ok(None)
+    } else {
+        Err(RuntimeErr::new_type_err("Builtin fill function expected a list"))
+    }
+}
+
+/// Resize the list to the specified length. If the list grows, new
+/// items are set to the specified value. If it shrinks, items are
+/// removed from the end.
+///
+/// Returns Nil
+pub fn resize(args: Args, _vm: &mut VM) -> CallResult {
+    let this = args.first().unwrap();
+    if let Some(this) = this.as_list() {
+        let len = match args.get(1).unwrap().int_val() {
+            Some(len) => len,
+            None => {
+                return Err(RuntimeErr::new_type_err("resize() expected an Int length"))
+            }
+        };
+        let len = match len.to_usize() {
+            Some(len) => len,
+            None => {
+                return Err(RuntimeErr::new_value_err(format!(
+                    "resize() expected a non-negative length; got {len}"
+                )))
+            }
+        };
+        let value = args.get(2).unwrap();
+        this.items_mut().resize(len, value.clone());
+        Ok(None)
+    } else {
+        Err(RuntimeErr::new_type_err("Builtin resize function expected a list"))
+    }
+}
+
+/// Reverse the list in place. The list is modified rather than copied,
+/// so the change is visible through all references to it.
+///
+/// Returns Nil
+pub fn reverse(args: Args, _vm: &mut VM) -> CallResult {
+    let this = args.first().unwrap();
+    if let Some(this) = this.as_list() {
+        this.items_mut().reverse();
+        Ok(None)
+    } else {
+        Err(RuntimeErr::new_type_err("Builtin reverse function expected a list"))
+    }
+}
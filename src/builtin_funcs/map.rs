@@ -0,0 +1,78 @@
+use crate::types::{Args, CallResult, ObjectRef};
+use crate::vm::{RuntimeErr, VM};
+
+use super::util::check_callable;
+
+/// Returns List
+pub fn keys(args: Args, vm: &mut VM) -> CallResult {
+    let this = args.first().unwrap();
+    if let Some(this) = this.as_map() {
+        Ok(Some(vm.ctx.builtins.new_list(this.keys())))
+    } else {
+        Err(RuntimeErr::new_type_err("Builtin keys function expected a map"))
+    }
+}
+
+/// Returns List
+pub fn values(args: Args, vm: &mut VM) -> CallResult {
+    let this = args.first().unwrap();
+    if let Some(this) = this.as_map() {
+        Ok(Some(vm.ctx.builtins.new_list(this.values())))
+    } else {
+        Err(RuntimeErr::new_type_err("Builtin values function expected a map"))
+    }
+}
+
+/// Get the keys whose values satisfy the predicate `pred(value)`.
+///
+/// Returns List
+pub fn keys_where(args: Args, vm: &mut VM) -> CallResult {
+    let this = args.first().unwrap();
+    let pred = args.get(1).unwrap();
+    let this = if let Some(this) = this.as_map() {
+        this
+    } else {
+        return Err(RuntimeErr::new_type_err(
+            "Builtin keys_where function expected a map",
+        ));
+    };
+    check_callable(pred)?;
+    // Copy the entries so the map isn't borrowed while the predicate
+    // is running, in case the predicate modifies the map.
+    let entries: Vec<(ObjectRef, ObjectRef)> = this.entries().iter().cloned().collect();
+    let mut keys = vec![];
+    for (key, value) in entries {
+        let result = vm.call_obj(pred, vec![value])?;
+        if vm.as_bool(&result)? {
+            keys.push(key);
+        }
+    }
+    Ok(Some(vm.ctx.builtins.new_list(keys)))
+}
+
+/// Convert a map with Str keys to a Namespace so its entries can be
+/// accessed as attributes.
+///
+/// Returns Namespace
+pub fn to_namespace(args: Args, vm: &mut VM) -> CallResult {
+    let this = args.first().unwrap();
+    let this = if let Some(this) = this.as_map() {
+        this
+    } else {
+        return Err(RuntimeErr::new_type_err(
+            "Builtin to_namespace function expected a map",
+        ));
+    };
+    let mut vars = vec![];
+    for (key, value) in this.entries().iter() {
+        if let Some(name) = key.str_val() {
+            vars.push((name, value.clone()));
+        } else {
+            let type_name = key.type_name();
+            return Err(RuntimeErr::new_type_err(format!(
+                "Cannot convert map with {type_name} key to namespace: {key:?}"
+            )));
+        }
+    }
+    Ok(Some(vm.ctx.builtins.new_namespace_from_vars(vars)))
+}
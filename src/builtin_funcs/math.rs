@@ -0,0 +1,53 @@
+use num_traits::ToPrimitive;
+
+use crate::types::{Args, CallResult};
+use crate::vm::{RuntimeErr, VM};
+
+/// Get the lesser of two objects. If they're equal, the first is
+/// returned.
+/// Returns Any
+pub fn min(args: Args, vm: &mut VM) -> CallResult {
+    let a = args.first().unwrap();
+    let b = args.get(1).unwrap();
+    let result = if b.less_than(&**a, &vm.ctx)? { b } else { a };
+    Ok(Some(result.clone()))
+}
+
+/// Get the greater of two objects. If they're equal, the first is
+/// returned.
+/// Returns Any
+pub fn max(args: Args, vm: &mut VM) -> CallResult {
+    let a = args.first().unwrap();
+    let b = args.get(1).unwrap();
+    let result = if b.greater_than(&**a, &vm.ctx)? { b } else { a };
+    Ok(Some(result.clone()))
+}
+
+/// Returns Float
+pub fn sqrt(args: Args, vm: &mut VM) -> CallResult {
+    let arg = args.first().unwrap();
+    let value = if let Some(value) = arg.float_val() {
+        value
+    } else if let Some(value) = arg.int_val() {
+        value.to_f64().unwrap()
+    } else {
+        let type_name = arg.type_name();
+        return Err(RuntimeErr::new_type_err(format!(
+            "Expected Int or Float; got {type_name}"
+        )));
+    };
+    if value < 0.0 {
+        return Err(RuntimeErr::new_value_err(format!(
+            "Cannot take square root of negative number: {value}"
+        )));
+    }
+    Ok(Some(vm.ctx.builtins.new_float(value.sqrt())))
+}
+
+/// Raise base to exp. This is equivalent to `base ** exp`.
+/// Returns Int or Float
+pub fn pow(args: Args, vm: &mut VM) -> CallResult {
+    let base = args.first().unwrap();
+    let exp = args.get(1).unwrap();
+    Ok(Some(base.pow(&**exp, &vm.ctx)?))
+}
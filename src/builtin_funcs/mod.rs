@@ -1,9 +1,21 @@
 pub(crate) use specs::get_builtin_func_specs;
 
 // Functions for builtin types (AKA "methods")
+pub mod float;
+pub mod func;
+pub mod int;
+pub mod list;
+pub mod map;
+pub mod seq;
+pub mod set;
+pub mod str;
 pub mod tuple;
 
+mod assert;
 mod file;
+mod input;
+mod math;
 mod print;
 mod specs;
 mod types;
+mod util;
@@ -1,19 +1,56 @@
-use crate::types::{Args, CallResult};
-use crate::vm::VM;
+use std::io::Write;
 
+use crate::types::{Args, CallResult, ObjectRef};
+use crate::vm::{RuntimeErr, VM};
+
+/// Print args separated by spaces, followed by a newline. Each arg is
+/// converted to a string the same way it would be in a format string
+/// (using `$str` if the arg has it).
+///
+/// Returns Nil
+pub fn print(args: Args, vm: &mut VM) -> CallResult {
+    write_items(&args, " ", "\n", vm)
+}
+
+/// Print the items of a Tuple or List separated by `sep`, followed by
+/// `end`. Items are converted to strings the same way as in `print()`.
+/// For example, `print_with((1, 2), ", ", "")` prints `1, 2` without a
+/// newline.
+///
 /// Returns Nil
-pub fn print(args: Args, _vm: &mut VM) -> CallResult {
-    let num_args = args.len();
-    if num_args > 0 {
-        let last = num_args - 1;
-        let mut sep = " ";
-        for (i, arg) in args.iter().enumerate() {
-            if i == last {
-                sep = "";
-            }
-            print!("{arg}{sep}");
+pub fn print_with(args: Args, vm: &mut VM) -> CallResult {
+    let items = args.first().unwrap();
+    let items = if let Some(tuple) = items.as_tuple() {
+        tuple.items().clone()
+    } else if let Some(list) = items.as_list() {
+        list.items().clone()
+    } else {
+        return Err(RuntimeErr::new_type_err("print_with() expected a Tuple or List"));
+    };
+    let sep = get_str_arg(args.get(1).unwrap(), "sep")?;
+    let end = get_str_arg(args.get(2).unwrap(), "end")?;
+    write_items(&items, &sep, &end, vm)
+}
+
+fn get_str_arg(arg: &ObjectRef, name: &str) -> Result<String, RuntimeErr> {
+    arg.str_val().ok_or_else(|| {
+        RuntimeErr::new_type_err(format!("print_with() expected {name} to be a Str"))
+    })
+}
+
+/// Write items to the VM's stdout. The output is built up front so
+/// nothing is written if converting an item to a string fails.
+fn write_items(items: &[ObjectRef], sep: &str, end: &str, vm: &mut VM) -> CallResult {
+    let mut output = String::new();
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            output.push_str(sep);
         }
+        output.push_str(&vm.display_string(item)?);
     }
-    println!();
+    output.push_str(end);
+    vm.stdout
+        .write_all(output.as_bytes())
+        .map_err(|err| RuntimeErr::new_io_err(err.to_string()))?;
     Ok(None)
 }
@@ -0,0 +1,433 @@
+//! Functions shared by sequence types (Tuple and List, and in some
+//! cases Str), plus builtins that operate on arbitrary sequences
+//! (`reversed`, `sorted`, `map`, etc).
+use num_bigint::BigInt;
+use num_traits::{ToPrimitive, Zero};
+
+use crate::types::{Args, CallResult, ObjectRef};
+use crate::vm::{RuntimeErr, VM};
+
+use super::util::{check_callable, clamp_index};
+
+/// Rotate the items of a sequence left by N places. If N is negative,
+/// the items are rotated right instead. N is taken modulo the length
+/// of the sequence, so rotating by the length is the same as not
+/// rotating at all.
+///
+/// Returns a new sequence of the same type
+pub fn rotate(args: Args, vm: &mut VM) -> CallResult {
+    let this = args.first().unwrap();
+    let n = match args.get(1).unwrap().int_val() {
+        Some(n) => n,
+        None => return Err(RuntimeErr::new_type_err("rotate() expected an Int")),
+    };
+    if let Some(tuple) = this.as_tuple() {
+        let items = rotated(tuple.items(), &n);
+        Ok(Some(vm.ctx.builtins.new_tuple(items)))
+    } else if let Some(list) = this.as_list() {
+        let items = rotated(&list.items(), &n);
+        Ok(Some(vm.ctx.builtins.new_list(items)))
+    } else {
+        Err(RuntimeErr::new_type_err("rotate() expected a Tuple or List"))
+    }
+}
+
+/// Split a sequence into two parts at index I, like `(items[..i],
+/// items[i..])`. A negative index counts back from the end of the
+/// sequence. An out-of-range index is clamped to the bounds of the
+/// sequence, so splitting at or beyond the length gives the whole
+/// sequence followed by an empty one, and splitting at or before the
+/// negative length gives an empty sequence followed by the whole
+/// sequence. Str values are split on chars, not bytes.
+///
+/// Returns a 2-tuple of sequences of the same type
+pub fn split_at(args: Args, vm: &mut VM) -> CallResult {
+    let this = args.first().unwrap();
+    let i = match args.get(1).unwrap().int_val() {
+        Some(i) => i,
+        None => return Err(RuntimeErr::new_type_err("split_at() expected an Int")),
+    };
+    let builtins = &vm.ctx.builtins;
+    let (a, b) = if let Some(tuple) = this.as_tuple() {
+        let (a, b) = tuple.items().split_at(clamp_index(&i, tuple.len()));
+        (builtins.new_tuple(a.to_vec()), builtins.new_tuple(b.to_vec()))
+    } else if let Some(list) = this.as_list() {
+        let items = list.items();
+        let (a, b) = items.split_at(clamp_index(&i, items.len()));
+        (builtins.new_list(a.to_vec()), builtins.new_list(b.to_vec()))
+    } else if let Some(string) = this.str_val() {
+        let mid = clamp_index(&i, string.chars().count());
+        let mid = string.char_indices().nth(mid).map_or(string.len(), |(i, _)| i);
+        let (a, b) = string.split_at(mid);
+        (builtins.new_str(a), builtins.new_str(b))
+    } else {
+        return Err(RuntimeErr::new_type_err(
+            "split_at() expected a Tuple, List, or Str",
+        ));
+    };
+    Ok(Some(builtins.new_tuple(vec![a, b])))
+}
+
+/// Call `f(index, item)` for each item in a sequence. The items are
+/// copied before iteration starts, so modifying a list while iterating
+/// over it doesn't affect which items `f` is called with.
+///
+/// Returns nil
+pub fn each_with_index(args: Args, vm: &mut VM) -> CallResult {
+    let items = get_items(&args, "each_with_index")?;
+    let f = args.get(1).unwrap();
+    check_callable(f)?;
+    for (i, item) in items.into_iter().enumerate() {
+        let i = vm.ctx.builtins.new_int(i);
+        vm.call_obj(f, vec![i, item])?;
+    }
+    Ok(None)
+}
+
+/// Find the indices of all the items in a sequence for which
+/// `pred(item)` is truthy. For strings, `pred` is called with each
+/// char (as a Str).
+///
+/// Returns List<Int>
+pub fn positions(args: Args, vm: &mut VM) -> CallResult {
+    let this = args.first().unwrap();
+    let pred = args.get(1).unwrap();
+    let items = if let Some(string) = this.str_val() {
+        string.chars().map(|c| vm.ctx.builtins.new_str(c.to_string())).collect()
+    } else {
+        get_items(&args, "positions")?
+    };
+    check_callable(pred)?;
+    let mut indices = vec![];
+    for (i, item) in items.into_iter().enumerate() {
+        let result = vm.call_obj(pred, vec![item])?;
+        if vm.as_bool(&result)? {
+            indices.push(vm.ctx.builtins.new_int(i));
+        }
+    }
+    Ok(Some(vm.ctx.builtins.new_list(indices)))
+}
+
+/// Add the items of a sequence together, starting from 0. Mixed Int
+/// and Float items are promoted the same way they are by `+`.
+///
+/// Returns Int or Float (0 if the sequence is empty)
+pub fn sum(args: Args, vm: &mut VM) -> CallResult {
+    let items = get_items(&args, "sum")?;
+    let mut result = vm.ctx.builtins.new_int(0);
+    for item in items {
+        result = result.add(&*item, &vm.ctx)?;
+    }
+    Ok(Some(result))
+}
+
+/// Multiply the items of a sequence together, starting from 1. Mixed
+/// Int and Float items are promoted the same way they are by `*`.
+///
+/// Returns Int or Float (1 if the sequence is empty)
+pub fn product(args: Args, vm: &mut VM) -> CallResult {
+    let items = get_items(&args, "product")?;
+    let mut result = vm.ctx.builtins.new_int(1);
+    for item in items {
+        result = result.mul(&*item, &vm.ctx)?;
+    }
+    Ok(Some(result))
+}
+
+/// Get the average of the items in a sequence. The items are added
+/// together with `+` and the total is divided by the number of items.
+///
+/// Returns Float
+pub fn mean(args: Args, vm: &mut VM) -> CallResult {
+    let items = get_items(&args, "mean")?;
+    if items.is_empty() {
+        return Err(RuntimeErr::new_value_err("mean() of an empty sequence"));
+    }
+    let mut total = vm.ctx.builtins.new_int(0);
+    for item in items.iter() {
+        total = total.add(&**item, &vm.ctx)?;
+    }
+    let n = vm.ctx.builtins.new_int(items.len());
+    Ok(Some(total.div(&*n, &vm.ctx)?))
+}
+
+/// Get the middle item of a sequence, after sorting a copy of it. If
+/// the sequence has an even number of items, the average of the two
+/// middle items is returned. The original sequence isn't modified.
+///
+/// Returns the middle item or Float (the average of the middle items)
+pub fn median(args: Args, vm: &mut VM) -> CallResult {
+    let mut items = get_items(&args, "median")?;
+    if items.is_empty() {
+        return Err(RuntimeErr::new_value_err("median() of an empty sequence"));
+    }
+    sort(&mut items, vm)?;
+    let mid = items.len() / 2;
+    if items.len() % 2 == 1 {
+        return Ok(Some(items[mid].clone()));
+    }
+    let total = items[mid - 1].add(&*items[mid], &vm.ctx)?;
+    let two = vm.ctx.builtins.new_int(2);
+    Ok(Some(total.div(&*two, &vm.ctx)?))
+}
+
+/// Make a Set from the items of a sequence. Duplicate items are
+/// dropped; otherwise, the original order is preserved.
+///
+/// Returns Set
+pub fn to_set(args: Args, vm: &mut VM) -> CallResult {
+    let items = get_items(&args, "to_set")?;
+    Ok(Some(vm.ctx.builtins.new_set(items)?))
+}
+
+/// Search a sorted sequence for an item using binary search. Items
+/// are compared using `less_than`. The sequence is assumed to be
+/// sorted in ascending order; this is NOT checked, and the result is
+/// unspecified if the sequence isn't sorted.
+///
+/// Returns Int (index of item) or Nil (if the item isn't found)
+pub fn binary_search(args: Args, vm: &mut VM) -> CallResult {
+    let this = args.first().unwrap();
+    let item = args.get(1).unwrap();
+    let index = if let Some(tuple) = this.as_tuple() {
+        search(tuple.items(), item, vm)?
+    } else if let Some(list) = this.as_list() {
+        search(&list.items(), item, vm)?
+    } else {
+        return Err(RuntimeErr::new_type_err(
+            "binary_search() expected a Tuple or List",
+        ));
+    };
+    Ok(index.map(|index| vm.ctx.builtins.new_int(index)))
+}
+
+/// Make a reversed copy of a sequence. Works for anything that has a
+/// length and supports `get_item` by index (e.g., Tuple, List, Str).
+///
+/// Returns List if the sequence is a List; Tuple otherwise
+pub fn reversed(args: Args, vm: &mut VM) -> CallResult {
+    let this = args.first().unwrap();
+    let mut items = get_seq_items(this, "reversed", vm)?;
+    items.reverse();
+    Ok(Some(new_seq(this, items, vm)))
+}
+
+/// Make a sorted copy of a sequence. Items are compared using
+/// `less_than`, so they all have to be mutually comparable (e.g., all
+/// numbers or all strings). Works for anything that has a length and
+/// supports `get_item` by index (e.g., Tuple, List, Str).
+///
+/// Returns List if the sequence is a List; Tuple otherwise
+pub fn sorted(args: Args, vm: &mut VM) -> CallResult {
+    let this = args.first().unwrap();
+    let mut items = get_seq_items(this, "sorted", vm)?;
+    sort(&mut items, vm)?;
+    Ok(Some(new_seq(this, items, vm)))
+}
+
+/// Call `f(item)` for each item in a sequence and collect the results.
+///
+/// Returns Tuple
+pub fn map(args: Args, vm: &mut VM) -> CallResult {
+    let (f, items) = get_callable_and_items(&args, "map", vm)?;
+    let mut results = Vec::with_capacity(items.len());
+    for item in items {
+        results.push(vm.call_obj(f, vec![item])?);
+    }
+    Ok(Some(vm.ctx.builtins.new_tuple(results)))
+}
+
+/// Collect the items in a sequence for which `pred(item)` is truthy.
+///
+/// Returns Tuple
+pub fn filter(args: Args, vm: &mut VM) -> CallResult {
+    let (pred, items) = get_callable_and_items(&args, "filter", vm)?;
+    let mut results = vec![];
+    for item in items {
+        let result = vm.call_obj(pred, vec![item.clone()])?;
+        if vm.as_bool(&result)? {
+            results.push(item);
+        }
+    }
+    Ok(Some(vm.ctx.builtins.new_tuple(results)))
+}
+
+/// Fold the items of a sequence into a single value by calling
+/// `f(acc, item)` for each item, starting with `acc = init`. `f` must
+/// accept two args.
+///
+/// Returns the final accumulated value (`init` if the sequence is
+/// empty)
+pub fn reduce(args: Args, vm: &mut VM) -> CallResult {
+    let (f, items) = get_callable_and_items(&args, "reduce", vm)?;
+    // Check the arity up front too, using placeholder args, so that
+    // the error doesn't depend on the sequence being non-empty.
+    let nil: ObjectRef = vm.ctx.builtins.nil_obj.clone();
+    let placeholder_args: Args = vec![nil.clone(), nil];
+    if let Some(func) = f.as_func() {
+        let num_defaults = func.defaults.len();
+        let rest = func.rest.as_deref();
+        vm.check_call_args(
+            &func.name,
+            &func.params,
+            num_defaults,
+            rest,
+            &placeholder_args,
+            false,
+        )?;
+    } else if let Some(func) = f.as_builtin_func() {
+        vm.check_call_args(
+            &func.name,
+            &func.params,
+            0,
+            None,
+            &placeholder_args,
+            false,
+        )?;
+    }
+    let mut acc = args.get(2).unwrap().clone();
+    for item in items {
+        acc = vm.call_obj(f, vec![acc, item])?;
+    }
+    Ok(Some(acc))
+}
+
+/// Call `f(item)` for each item in a sequence, discarding the results.
+///
+/// Returns nil
+pub fn each(args: Args, vm: &mut VM) -> CallResult {
+    let (f, items) = get_callable_and_items(&args, "each", vm)?;
+    for item in items {
+        vm.call_obj(f, vec![item])?;
+    }
+    Ok(None)
+}
+
+fn search(
+    items: &[ObjectRef],
+    item: &ObjectRef,
+    vm: &VM,
+) -> Result<Option<usize>, RuntimeErr> {
+    let (mut lo, mut hi) = (0, items.len());
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        let candidate = &items[mid];
+        if candidate.less_than(&**item, &vm.ctx)? {
+            lo = mid + 1;
+        } else if item.less_than(&**candidate, &vm.ctx)? {
+            hi = mid;
+        } else {
+            return Ok(Some(mid));
+        }
+    }
+    Ok(None)
+}
+
+/// Copy the items of a Tuple or List.
+fn get_items(args: &Args, name: &str) -> Result<Vec<ObjectRef>, RuntimeErr> {
+    let this = args.first().unwrap();
+    if let Some(tuple) = this.as_tuple() {
+        Ok(tuple.items().clone())
+    } else if let Some(list) = this.as_list() {
+        Ok(list.items().clone())
+    } else {
+        Err(RuntimeErr::new_type_err(format!("{name}() expected a Tuple or List")))
+    }
+}
+
+/// Copy the items of any object that has a length and supports
+/// `get_item` by index.
+fn get_seq_items(
+    this: &ObjectRef,
+    name: &str,
+    vm: &VM,
+) -> Result<Vec<ObjectRef>, RuntimeErr> {
+    let len = match this.len() {
+        Some(len) => len,
+        None => {
+            return Err(RuntimeErr::new_type_err(format!(
+                "{name}() expected a sequence; got {}",
+                this.class()
+            )))
+        }
+    };
+    let mut items = Vec::with_capacity(len);
+    for i in 0..len {
+        let index = vm.ctx.builtins.new_int(i);
+        items.push(this.get_item(&index, &vm.ctx)?);
+    }
+    Ok(items)
+}
+
+/// Get the callable and a copy of the sequence items for `f(callable,
+/// seq)` style builtins.
+fn get_callable_and_items<'a>(
+    args: &'a Args,
+    name: &str,
+    vm: &VM,
+) -> Result<(&'a ObjectRef, Vec<ObjectRef>), RuntimeErr> {
+    let f = args.first().unwrap();
+    check_callable(f)?;
+    let items = get_seq_items(args.get(1).unwrap(), name, vm)?;
+    Ok((f, items))
+}
+
+/// Make a new List from items if the original sequence is a List;
+/// otherwise, make a new Tuple.
+fn new_seq(this: &ObjectRef, items: Vec<ObjectRef>, vm: &VM) -> ObjectRef {
+    if this.as_list().is_some() {
+        vm.ctx.builtins.new_list(items)
+    } else {
+        vm.ctx.builtins.new_tuple(items)
+    }
+}
+
+/// Sort items in place using `less_than`. If any pair of items can't
+/// be compared, the first error encountered is returned and the order
+/// of the items is unspecified.
+///
+/// This is a merge sort rather than `sort_by` because comparisons can
+/// fail and don't always form a total order (e.g., with NaN), and
+/// `sort_by` may panic in that case.
+fn sort(items: &mut [ObjectRef], vm: &VM) -> Result<(), RuntimeErr> {
+    let len = items.len();
+    if len < 2 {
+        return Ok(());
+    }
+    let mid = len / 2;
+    sort(&mut items[..mid], vm)?;
+    sort(&mut items[mid..], vm)?;
+    let mut merged = Vec::with_capacity(len);
+    let (mut i, mut j) = (0, mid);
+    while i < mid && j < len {
+        // Items from the right half are only taken when they're less
+        // than the next item from the left half so that the sort is
+        // stable.
+        if items[j].less_than(&*items[i], &vm.ctx)? {
+            merged.push(items[j].clone());
+            j += 1;
+        } else {
+            merged.push(items[i].clone());
+            i += 1;
+        }
+    }
+    merged.extend_from_slice(&items[i..mid]);
+    merged.extend_from_slice(&items[j..]);
+    items.clone_from_slice(&merged);
+    Ok(())
+}
+
+/// Copy items, rotating them left by N places (right if N is
+/// negative).
+fn rotated(items: &[ObjectRef], n: &BigInt) -> Vec<ObjectRef> {
+    let mut items = items.to_vec();
+    if !items.is_empty() {
+        let len = BigInt::from(items.len());
+        let mut mid = n % &len;
+        if mid < BigInt::zero() {
+            mid += &len;
+        }
+        items.rotate_left(mid.to_usize().unwrap());
+    }
+    items
+}
@@ -0,0 +1,101 @@
+use crate::types::{Args, CallResult, ObjectRef};
+use crate::vm::{RuntimeErr, VM};
+
+/// Add an item to the set if it's not already present.
+///
+/// Returns Nil
+pub fn add(args: Args, _vm: &mut VM) -> CallResult {
+    let this = args.first().unwrap();
+    if let Some(this) = this.as_set() {
+        this.add(args.get(1).unwrap().clone())?;
+        Ok(None)
+    } else {
+        Err(RuntimeErr::new_type_err("Builtin add function expected a set"))
+    }
+}
+
+/// Remove an item from the set.
+///
+/// Returns Bool (whether the item was present)
+pub fn remove(args: Args, vm: &mut VM) -> CallResult {
+    let this = args.first().unwrap();
+    if let Some(this) = this.as_set() {
+        let removed = this.remove(args.get(1).unwrap())?;
+        Ok(Some(vm.ctx.builtins.bool_obj_from_bool(removed)))
+    } else {
+        Err(RuntimeErr::new_type_err("Builtin remove function expected a set"))
+    }
+}
+
+/// Returns Bool
+pub fn has(args: Args, vm: &mut VM) -> CallResult {
+    let this = args.first().unwrap();
+    if let Some(this) = this.as_set() {
+        let result = this.has(args.get(1).unwrap())?;
+        Ok(Some(vm.ctx.builtins.bool_obj_from_bool(result)))
+    } else {
+        Err(RuntimeErr::new_type_err("Builtin has function expected a set"))
+    }
+}
+
+/// Get the items that are in either set. Items from this set come
+/// first, followed by items only in the other set.
+///
+/// Returns Set
+pub fn union(args: Args, vm: &mut VM) -> CallResult {
+    let (this, other) = get_this_and_other(&args, "union")?;
+    let mut items = this;
+    items.extend(other);
+    Ok(Some(vm.ctx.builtins.new_set(items)?))
+}
+
+/// Get the items in this set that are also in the other set, in this
+/// set's order.
+///
+/// Returns Set
+pub fn intersection(args: Args, vm: &mut VM) -> CallResult {
+    let other = args.get(1).unwrap();
+    let (this, _) = get_this_and_other(&args, "intersection")?;
+    let other = other.as_set().unwrap();
+    let mut items = vec![];
+    for item in this {
+        if other.has(&item)? {
+            items.push(item);
+        }
+    }
+    Ok(Some(vm.ctx.builtins.new_set(items)?))
+}
+
+/// Get the items in this set that are NOT in the other set, in this
+/// set's order.
+///
+/// Returns Set
+pub fn difference(args: Args, vm: &mut VM) -> CallResult {
+    let other = args.get(1).unwrap();
+    let (this, _) = get_this_and_other(&args, "difference")?;
+    let other = other.as_set().unwrap();
+    let mut items = vec![];
+    for item in this {
+        if !other.has(&item)? {
+            items.push(item);
+        }
+    }
+    Ok(Some(vm.ctx.builtins.new_set(items)?))
+}
+
+// Utilities -----------------------------------------------------------
+
+/// Get the items of this set and the other set.
+fn get_this_and_other(
+    args: &Args,
+    name: &str,
+) -> Result<(Vec<ObjectRef>, Vec<ObjectRef>), RuntimeErr> {
+    let this = args.first().unwrap();
+    let other = args.get(1).unwrap();
+    match (this.as_set(), other.as_set()) {
+        (Some(this), Some(other)) => Ok((this.items(), other.items())),
+        _ => Err(RuntimeErr::new_type_err(format!(
+            "Builtin {name} function expected two sets"
+        ))),
+    }
+}
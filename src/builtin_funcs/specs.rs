@@ -1,7 +1,12 @@
 use crate::types::BuiltinFn;
 
+use super::assert::*;
 use super::file::*;
+use super::func::*;
+use super::input::*;
+use super::math::*;
 use super::print::*;
+use super::seq::{each, filter, map, reduce, reversed, sorted};
 use super::types::*;
 
 /// Get the specs for all builtin functions. A spec comprises a name,
@@ -9,13 +14,46 @@ use super::types::*;
 /// that means the function accepts a variable number of args.
 pub fn get_builtin_func_specs<'a>() -> Vec<(&'a str, Option<Vec<&'a str>>, BuiltinFn)> {
     vec![
+        // Assertions
+        ("assert", None, assert),
+        ("assert_raises", Some(vec!["kind_name", "func"]), assert_raises),
+        // Disassembly
+        ("dis", Some(vec!["func"]), dis),
+        // Errors
+        ("error", Some(vec!["kind", "message"]), error),
         // File
         ("read_file", Some(vec!["file_name"]), read_file),
         ("read_file_lines", Some(vec!["file_name"]), read_file_lines),
+        ("write_file", Some(vec!["file_name", "contents"]), write_file),
+        // Input
+        ("input", Some(vec!["prompt"]), input),
+        ("read_line", Some(vec![]), read_line),
+        // Math
+        ("min", Some(vec!["a", "b"]), min),
+        ("max", Some(vec!["a", "b"]), max),
+        ("sqrt", Some(vec!["x"]), sqrt),
+        ("pow", Some(vec!["base", "exp"]), pow),
         // Print
         ("print", None, print),
+        ("print_with", Some(vec!["items", "sep", "end"]), print_with),
+        // Sequences
+        ("reversed", Some(vec!["seq"]), reversed),
+        ("sorted", Some(vec!["seq"]), sorted),
+        ("map", Some(vec!["f", "seq"]), map),
+        ("filter", Some(vec!["pred", "seq"]), filter),
+        ("each", Some(vec!["f", "seq"]), each),
+        ("reduce", Some(vec!["f", "seq", "init"]), reduce),
         // Type
+        ("type", Some(vec!["obj"]), type_name),
         ("type_of", None, type_of),
+        ("type_info", Some(vec!["obj"]), type_info),
         ("obj_id", None, obj_id),
+        ("len", Some(vec!["obj"]), len),
+        // Type conversion
+        ("int", Some(vec!["obj"]), int),
+        ("float", Some(vec!["obj"]), float),
+        ("parse", Some(vec!["string"]), parse),
+        ("str", Some(vec!["obj"]), str),
+        ("set", Some(vec!["items"]), set),
     ]
 }
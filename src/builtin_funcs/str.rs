@@ -0,0 +1,70 @@
+use crate::types::{Args, CallResult};
+use crate::vm::{RuntimeErr, VM};
+
+/// Returns 2-tuple or nil
+pub fn split_once(args: Args, vm: &mut VM) -> CallResult {
+    let (this, sep) = get_this_and_sep(&args, "split_once")?;
+    let result = this.split_once(sep.as_str()).map(|(a, b)| {
+        let items = vec![vm.ctx.builtins.new_str(a), vm.ctx.builtins.new_str(b)];
+        vm.ctx.builtins.new_tuple(items)
+    });
+    Ok(result)
+}
+
+/// Like `split_once` but splits on the *last* occurrence of the
+/// separator.
+///
+/// Returns 2-tuple or nil
+pub fn rsplit(args: Args, vm: &mut VM) -> CallResult {
+    let (this, sep) = get_this_and_sep(&args, "rsplit")?;
+    let result = this.rsplit_once(sep.as_str()).map(|(a, b)| {
+        let items = vec![vm.ctx.builtins.new_str(a), vm.ctx.builtins.new_str(b)];
+        vm.ctx.builtins.new_tuple(items)
+    });
+    Ok(result)
+}
+
+/// Generate a method that checks whether *all* the chars in a string
+/// belong to a particular Unicode character class. An empty string is
+/// never considered a member of any class.
+macro_rules! make_char_class_predicate {
+    ( $name:ident, $pred:ident ) => {
+        /// Returns Bool
+        pub fn $name(args: Args, vm: &mut VM) -> CallResult {
+            let this = get_this(&args, stringify!($name))?;
+            let result = !this.is_empty() && this.chars().all(char::$pred);
+            Ok(Some(vm.ctx.builtins.bool_obj_from_bool(result)))
+        }
+    };
+}
+
+make_char_class_predicate!(is_alpha, is_alphabetic);
+make_char_class_predicate!(is_digit, is_numeric);
+make_char_class_predicate!(is_alnum, is_alphanumeric);
+
+// Utilities -----------------------------------------------------------
+
+fn get_this(args: &Args, name: &str) -> Result<String, RuntimeErr> {
+    let this = args.first().unwrap();
+    if let Some(this) = this.str_val() {
+        Ok(this)
+    } else {
+        Err(RuntimeErr::new_type_err(format!("Builtin {name} function expected a Str")))
+    }
+}
+
+fn get_this_and_sep(args: &Args, name: &str) -> Result<(String, String), RuntimeErr> {
+    let this = get_this(args, name)?;
+    let sep = args.get(1).unwrap();
+    if let Some(sep) = sep.str_val() {
+        if sep.is_empty() {
+            Err(RuntimeErr::new_value_err(format!("Empty separator passed to {name}")))
+        } else {
+            Ok((this, sep))
+        }
+    } else {
+        Err(RuntimeErr::new_type_err(format!(
+            "Builtin {name} function expected a Str separator"
+        )))
+    }
+}
@@ -1,16 +1,49 @@
 use crate::types::{Args, CallResult};
 use crate::vm::{RuntimeErr, VM};
 
+use super::util::clamp_index;
+
 pub fn map(args: Args, vm: &mut VM) -> CallResult {
     let this = args.get(0).unwrap();
     if let Some(this) = this.as_tuple() {
         let map_fn = args.get(1).unwrap();
         for (i, item) in this.items().iter().enumerate() {
             let i = vm.ctx.builtins.new_int(i);
-            map_fn.call(vec![item.clone(), i], vm)?;
+            vm.call_obj(map_fn, vec![item.clone(), i])?;
         }
         Ok(None)
     } else {
         Err(RuntimeErr::new_type_err("Builtin map function expected a tuple"))
     }
 }
+
+/// Get the items from index `start` up to, but not including, index
+/// `end` as a new tuple. Negative indexes count back from the end of
+/// the tuple, as with `split_at`. Indexes are clamped to the bounds of
+/// the tuple, and if `start` is past `end`, the result is empty.
+///
+/// Returns Tuple
+pub fn slice(args: Args, vm: &mut VM) -> CallResult {
+    let this = args.first().unwrap();
+    let this = match this.as_tuple() {
+        Some(this) => this,
+        None => {
+            return Err(RuntimeErr::new_type_err(
+                "Builtin slice function expected a tuple",
+            ))
+        }
+    };
+    let (start, end) =
+        match (args.get(1).unwrap().int_val(), args.get(2).unwrap().int_val()) {
+            (Some(start), Some(end)) => (start, end),
+            _ => {
+                return Err(RuntimeErr::new_type_err(
+                    "slice() expected Int start and end",
+                ))
+            }
+        };
+    let len = this.len();
+    let start = clamp_index(&start, len);
+    let end = clamp_index(&end, len).max(start);
+    Ok(Some(vm.ctx.builtins.new_tuple(this.items()[start..end].to_vec())))
+}
@@ -1,5 +1,10 @@
-use crate::types::{Args, CallResult};
-use crate::vm::VM;
+use num_bigint::BigInt;
+use num_traits::{FromPrimitive, Num, ToPrimitive};
+
+use crate::scanner::{Scanner, Token};
+use crate::types::{Args, CallResult, ObjectRef};
+use crate::util::source_from_text;
+use crate::vm::{RuntimeErr, VM};
 
 /// Returns Type
 pub fn type_of(args: Args, _vm: &mut VM) -> CallResult {
@@ -8,7 +13,187 @@ pub fn type_of(args: Args, _vm: &mut VM) -> CallResult {
 }
 
 /// Returns Str
+pub fn type_name(args: Args, vm: &mut VM) -> CallResult {
+    let arg = args.first().unwrap();
+    Ok(Some(vm.ctx.builtins.new_str(arg.type_name())))
+}
+
+/// Collect introspection info about an object: its `type` name, its
+/// `id`, whether it's `callable`, and its `len` when it has one.
+///
+/// Returns Map
+pub fn type_info(args: Args, vm: &mut VM) -> CallResult {
+    let arg = args.first().unwrap();
+    let builtins = &vm.ctx.builtins;
+    let callable = arg.as_func().is_some() || arg.as_builtin_func().is_some();
+    let mut entries = vec![
+        ("type", builtins.new_str(arg.type_name())),
+        ("id", builtins.new_int(arg.id())),
+        ("callable", builtins.bool_obj_from_bool(callable)),
+    ];
+    if let Some(len) = arg.len() {
+        entries.push(("len", builtins.new_int(len)));
+    }
+    let entries = entries.into_iter().map(|(k, v)| (builtins.new_str(k), v)).collect();
+    Ok(Some(builtins.new_map(entries)?))
+}
+
+/// Returns Int
 pub fn obj_id(args: Args, vm: &mut VM) -> CallResult {
     let arg = args.first().unwrap();
     Ok(Some(vm.ctx.builtins.new_int(arg.id())))
 }
+
+/// Convert Int, Float, or Str to Int. Floats are truncated toward
+/// zero.
+///
+/// Returns Int
+pub fn int(args: Args, vm: &mut VM) -> CallResult {
+    let arg = args.first().unwrap();
+    let value = if arg.is_int() {
+        return Ok(Some(arg.clone()));
+    } else if let Some(value) = arg.float_val() {
+        BigInt::from_f64(value.trunc())
+    } else if let Some(value) = arg.str_val() {
+        BigInt::from_str_radix(value.trim(), 10).ok()
+    } else {
+        return Err(RuntimeErr::new_type_err(format!(
+            "Could not convert {} to Int",
+            arg.class()
+        )));
+    };
+    match value {
+        Some(value) => Ok(Some(vm.ctx.builtins.new_int(value))),
+        None => {
+            Err(RuntimeErr::new_value_err(format!("Could not convert {arg:?} to Int")))
+        }
+    }
+}
+
+/// Convert Int, Float, or Str to Float.
+///
+/// Returns Float
+pub fn float(args: Args, vm: &mut VM) -> CallResult {
+    let arg = args.first().unwrap();
+    let value = if arg.is_float() {
+        return Ok(Some(arg.clone()));
+    } else if let Some(value) = arg.int_val() {
+        value.to_f64()
+    } else if let Some(value) = arg.str_val() {
+        value.trim().parse::<f64>().ok()
+    } else {
+        return Err(RuntimeErr::new_type_err(format!(
+            "Could not convert {} to Float",
+            arg.class()
+        )));
+    };
+    match value {
+        Some(value) => Ok(Some(vm.ctx.builtins.new_float(value))),
+        None => Err(RuntimeErr::new_value_err(format!(
+            "Could not convert {arg:?} to Float"
+        ))),
+    }
+}
+
+/// Make a Set from the items of a Tuple, List, or Set. Duplicate items
+/// are dropped; otherwise, the original order is preserved.
+///
+/// Returns Set
+pub fn set(args: Args, vm: &mut VM) -> CallResult {
+    let arg = args.first().unwrap();
+    let items = if let Some(tuple) = arg.as_tuple() {
+        tuple.items().clone()
+    } else if let Some(list) = arg.as_list() {
+        list.items().clone()
+    } else if let Some(set) = arg.as_set() {
+        set.items()
+    } else {
+        return Err(RuntimeErr::new_type_err(format!(
+            "Could not convert {} to Set",
+            arg.class()
+        )));
+    };
+    Ok(Some(vm.ctx.builtins.new_set(items)?))
+}
+
+/// Make an Error with the specified kind (e.g., "ValueErr") and
+/// message, which can then be raised via `raise`. The kind and message
+/// must both be strings.
+///
+/// Returns Error
+pub fn error(args: Args, vm: &mut VM) -> CallResult {
+    let kind = args.first().unwrap();
+    let message = args.get(1).unwrap();
+    match (kind.str_val(), message.str_val()) {
+        (Some(kind), Some(message)) => {
+            Ok(Some(vm.ctx.builtins.new_error(kind, message)))
+        }
+        _ => Err(RuntimeErr::new_type_err(format!(
+            "error() expected a Str kind and message; got {} and {}",
+            kind.class(),
+            message.class()
+        ))),
+    }
+}
+
+/// Parse a string, inferring its type from its contents. The string
+/// is scanned using the same rules as FeInt source code, so numbers
+/// can be written in any of the supported formats (e.g., `0x10`,
+/// `1_000`, `1.5E+10`). Strings that don't contain a single Int,
+/// Float, Bool, or nil literal are returned as is.
+///
+/// Returns Int, Float, Bool, Nil, or Str
+pub fn parse(args: Args, vm: &mut VM) -> CallResult {
+    let arg = args.first().unwrap();
+    if let Some(string) = arg.str_val() {
+        match parse_literal(string.as_str(), vm) {
+            Some(obj) => Ok(Some(obj)),
+            None => Ok(Some(arg.clone())),
+        }
+    } else {
+        Err(RuntimeErr::new_type_err(format!("Could not parse {}", arg.class())))
+    }
+}
+
+fn parse_literal(string: &str, vm: &mut VM) -> Option<ObjectRef> {
+    let mut source = source_from_text(string.trim());
+    let scanner = Scanner::new(&mut source);
+    let mut tokens = vec![];
+    for token in scanner {
+        match token {
+            Ok(token) if token.token == Token::EndOfStatement => (),
+            Ok(token) => tokens.push(token.token),
+            Err(_) => return None,
+        }
+    }
+    let builtins = &vm.ctx.builtins;
+    let obj = match tokens.as_slice() {
+        [Token::Int(value)] => builtins.new_int(value.clone()),
+        [Token::Minus, Token::Int(value)] => builtins.new_int(-value),
+        [Token::Float(value)] => builtins.new_float(*value),
+        [Token::Minus, Token::Float(value)] => builtins.new_float(-value),
+        [Token::True] => builtins.true_obj.clone(),
+        [Token::False] => builtins.false_obj.clone(),
+        [Token::Nil] => builtins.nil_obj.clone(),
+        _ => return None,
+    };
+    Some(obj)
+}
+
+/// Convert any object to a Str using its `$str` method, if it has one,
+/// or its default display string.
+///
+/// Returns Str
+pub fn str(args: Args, vm: &mut VM) -> CallResult {
+    let arg = args.first().unwrap();
+    Ok(Some(vm.str_obj(arg)?))
+}
+
+/// Returns Int
+pub fn len(args: Args, vm: &mut VM) -> CallResult {
+    let arg = args.first().unwrap();
+    match arg.len() {
+        Some(len) => Ok(Some(vm.ctx.builtins.new_int(len))),
+        None => Err(RuntimeErr::new_type_err(format!("{} has no length", arg.class()))),
+    }
+}
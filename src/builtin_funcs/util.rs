@@ -0,0 +1,30 @@
+//! Utilities shared by builtin functions.
+use num_bigint::BigInt;
+use num_traits::{Signed, ToPrimitive};
+
+use crate::types::ObjectRef;
+use crate::vm::RuntimeErr;
+
+/// Check that an arg that's going to be called, like the function
+/// passed to `map`, is callable. This is checked up front so that a
+/// non-callable is an error even when there's nothing to call it with
+/// (e.g., when the sequence is empty).
+pub(super) fn check_callable(obj: &ObjectRef) -> Result<(), RuntimeErr> {
+    if obj.as_func().is_none() && obj.as_builtin_func().is_none() {
+        return Err(RuntimeErr::new_not_callable(obj.clone()));
+    }
+    Ok(())
+}
+
+/// Convert index I into a sequence of length LEN to an index in the
+/// range 0..=len, for use as a split point or slice bound. A negative
+/// index counts back from the end of the sequence. Indexes that are
+/// still out of range are clamped to the bounds of the sequence.
+pub(super) fn clamp_index(i: &BigInt, len: usize) -> usize {
+    let i = if i.is_negative() { i + len } else { i.clone() };
+    if i.is_negative() {
+        0
+    } else {
+        i.to_usize().map_or(len, |i| i.min(len))
+    }
+}
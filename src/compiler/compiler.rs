@@ -1,16 +1,43 @@
+use num_traits::{ToPrimitive, Zero};
+
 use crate::ast;
+use crate::format::FormatSpec;
 use crate::types::ObjectRef;
-use crate::util::{BinaryOperator, UnaryOperator};
+use crate::util::{BinaryOperator, Location, UnaryOperator};
 use crate::vm::{Chunk, Inst, RuntimeContext, VM};
 
+use super::optimize::optimize;
+use super::resolve::check_names;
 use super::result::{CompErr, CompResult};
 use super::scope::{Scope, ScopeKind, ScopeTree};
 
 // Compiler ------------------------------------------------------------
 
 /// Compile AST to VM instructions.
-pub fn compile(vm: &mut VM, program: ast::Program) -> CompResult {
-    let mut visitor = Visitor::new(&mut vm.ctx);
+#[cfg(test)]
+pub fn compile(vm: &mut VM, program: ast::Program) -> Result<Chunk, CompErr> {
+    let (chunk, _) = compile_with_locations(vm, program)?;
+    Ok(chunk)
+}
+
+/// Compile AST to VM instructions, also returning the source location
+/// of each instruction. This is used to show where runtime errors
+/// occurred.
+pub fn compile_with_locations(vm: &mut VM, program: ast::Program) -> CompResult {
+    check_names(&vm.ctx, &program)?;
+    let mut visitor = Visitor::new(&mut vm.ctx, true);
+    visitor.visit_program(program)?;
+    Ok((visitor.chunk, visitor.locations))
+}
+
+/// Compile AST to VM instructions without applying optimizations.
+#[cfg(test)]
+pub fn compile_unoptimized(
+    vm: &mut VM,
+    program: ast::Program,
+) -> Result<Chunk, CompErr> {
+    check_names(&vm.ctx, &program)?;
+    let mut visitor = Visitor::new(&mut vm.ctx, false);
     visitor.visit_program(program)?;
     Ok(visitor.chunk)
 }
@@ -22,19 +49,50 @@ type VisitResult = Result<(), CompErr>;
 struct Visitor<'a> {
     ctx: &'a mut RuntimeContext,
     chunk: Chunk,
+    // Source location of each instruction in the chunk.
+    locations: Vec<Location>,
+    // Source location of the statement or expression currently being
+    // visited.
+    loc: Location,
     scope_tree: ScopeTree,
     scope_depth: usize,
+    // Labels of the enclosing labeled loops, innermost last.
+    loop_labels: Vec<String>,
+    // Labeled breaks and continues that haven't been resolved yet.
+    // These are resolved when the loop they target is finished.
+    labeled_loop_jumps: Vec<LabeledLoopJump>,
+    // Index of the scope whose declarations might currently be
+    // skipped at runtime (e.g., in a conditional branch condition that
+    // isn't the first).
+    skipped_scope: Option<usize>,
     has_main: bool,
+    optimize: bool,
+}
+
+/// A `break <label>` or `continue <label>` that targets an enclosing
+/// labeled loop.
+struct LabeledLoopJump {
+    label: String,
+    // Address of the placeholder for the jump.
+    addr: usize,
+    scope_depth: usize,
+    is_break: bool,
 }
 
 impl<'a> Visitor<'a> {
-    fn new(ctx: &'a mut RuntimeContext) -> Self {
+    fn new(ctx: &'a mut RuntimeContext, optimize: bool) -> Self {
         Self {
             ctx,
             chunk: Chunk::new(),
+            locations: vec![],
+            loc: Location::new(0, 0),
             scope_tree: ScopeTree::new(),
             scope_depth: 0,
+            loop_labels: vec![],
+            labeled_loop_jumps: vec![],
+            skipped_scope: None,
             has_main: false,
+            optimize,
         }
     }
 
@@ -55,25 +113,45 @@ impl<'a> Visitor<'a> {
             self.add_const(self.ctx.builtins.new_int(10));
 
             self.push(Inst::Call(2));
-            self.push(Inst::Return);
             self.push(Inst::HaltTop);
         } else {
             self.push(Inst::Halt(0));
         }
+        if self.optimize {
+            optimize(&mut self.chunk, &mut self.locations);
+        }
         Ok(())
     }
 
     fn visit_statements(&mut self, statements: Vec<ast::Statement>) -> VisitResult {
-        for statement in statements {
+        let num_statements = statements.len();
+        for (i, statement) in statements.into_iter().enumerate() {
+            // Only the value of the last statement is kept. The values
+            // of other statements are popped so that they don't pile
+            // up on the stack. Note that jump, break, and continue
+            // statements don't leave a value on the stack.
+            let is_last = i + 1 == num_statements;
+            let pop = !is_last
+                && matches!(
+                    statement.kind,
+                    ast::StatementKind::Expr(_) | ast::StatementKind::Label(..)
+                );
             self.visit_statement(statement)?;
+            if pop {
+                self.push(Inst::Pop);
+            }
         }
         Ok(())
     }
 
     fn visit_statement(&mut self, node: ast::Statement) -> VisitResult {
         type Kind = ast::StatementKind;
+        let outer_loc = std::mem::replace(&mut self.loc, node.start);
         match node.kind {
             Kind::Jump(name) => {
+                // Declarations between the jump and its label are
+                // skipped.
+                self.scope_tree.set_unordered();
                 let jump_addr = self.chunk.len();
                 self.push(Inst::Placeholder(
                     0,
@@ -89,22 +167,56 @@ impl<'a> Visitor<'a> {
                     return Err(CompErr::new_duplicate_label_in_scope(name));
                 }
             }
-            Kind::Break(expr) => self.visit_break(expr)?,
-            Kind::Continue => self.visit_continue()?,
+            Kind::Break(label, expr) => self.visit_break(label, expr)?,
+            Kind::Continue(label) => self.visit_continue(label)?,
+            Kind::Raise(expr) => {
+                self.visit_expr(expr, None)?;
+                self.push(Inst::Raise);
+            }
             Kind::Expr(expr) => self.visit_expr(expr, None)?,
         }
+        self.loc = outer_loc;
         Ok(())
     }
 
-    fn visit_break(&mut self, expr: ast::Expr) -> VisitResult {
+    fn visit_break(&mut self, label: Option<String>, expr: ast::Expr) -> VisitResult {
         self.visit_expr(expr, None)?;
-        self.chunk.push(Inst::BreakPlaceholder(self.chunk.len(), self.scope_depth));
+        match label {
+            Some(label) => self.push_labeled_loop_jump(label, true)?,
+            None => {
+                self.push(Inst::BreakPlaceholder(self.chunk.len(), self.scope_depth))
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_continue(&mut self, label: Option<String>) -> VisitResult {
+        self.push(Inst::LoadConst(0));
+        match label {
+            Some(label) => self.push_labeled_loop_jump(label, false)?,
+            None => {
+                self.push(Inst::ContinuePlaceholder(self.chunk.len(), self.scope_depth))
+            }
+        }
         Ok(())
     }
 
-    fn visit_continue(&mut self) -> VisitResult {
-        self.chunk.push(Inst::LoadConst(0));
-        self.chunk.push(Inst::ContinuePlaceholder(self.chunk.len(), self.scope_depth));
+    /// Add a placeholder for a labeled break or continue. The label
+    /// must name an enclosing loop in the current function.
+    fn push_labeled_loop_jump(&mut self, label: String, is_break: bool) -> VisitResult {
+        if !self.loop_labels.contains(&label) {
+            return Err(CompErr::new_label_not_found_in_scope(label));
+        }
+        let addr = self.chunk.len();
+        let kind = if is_break { "Break" } else { "Continue" };
+        self.push(Inst::Placeholder(
+            addr,
+            Box::new(Inst::Jump(0, 0)),
+            format!("{kind} address not set for loop {label}"),
+        ));
+        let jump =
+            LabeledLoopJump { label, addr, scope_depth: self.scope_depth, is_break };
+        self.labeled_loop_jumps.push(jump);
         Ok(())
     }
 
@@ -119,21 +231,31 @@ impl<'a> Visitor<'a> {
     /// used to assign names to functions.
     fn visit_expr(&mut self, node: ast::Expr, name: Option<String>) -> VisitResult {
         type Kind = ast::ExprKind;
+        let outer_loc = std::mem::replace(&mut self.loc, node.start);
         match node.kind {
             Kind::Tuple(items) => self.visit_tuple(items)?,
+            Kind::List(items) => self.visit_list(items)?,
             Kind::Literal(literal) => self.visit_literal(literal)?,
             Kind::FormatString(items) => self.visit_format_string(items)?,
+            Kind::FormatValue(expr, spec) => self.visit_format_value(*expr, spec)?,
             Kind::Ident(ident) => self.visit_ident(ident)?,
             Kind::Block(block) => self.visit_block(block)?,
             Kind::Conditional(branches, default) => {
                 self.visit_conditional(branches, default)?
             }
-            Kind::Loop(expr, block) => self.visit_loop(*expr, block)?,
+            Kind::Loop(label, expr, block) => self.visit_loop(label, *expr, block)?,
+            Kind::Try(block, name, catch_block) => {
+                self.visit_try(block, name, catch_block)?
+            }
             Kind::Func(func) => self.visit_func(func, name)?,
             Kind::Call(call) => self.visit_call(call)?,
             Kind::UnaryOp(op, b) => self.visit_unary_op(op, *b)?,
             Kind::BinaryOp(a, op, b) => self.visit_binary_op(*a, op, *b)?,
+            Kind::MultiAssign(names, value) => {
+                self.visit_multi_assign(names, *value)?
+            }
         }
+        self.loc = outer_loc;
         Ok(())
     }
 
@@ -144,6 +266,13 @@ impl<'a> Visitor<'a> {
         Ok(())
     }
 
+    fn visit_list(&mut self, items: Vec<ast::Expr>) -> VisitResult {
+        let num_items = items.len();
+        self.visit_exprs(items)?;
+        self.push(Inst::MakeList(num_items));
+        Ok(())
+    }
+
     fn visit_literal(&mut self, node: ast::Literal) -> VisitResult {
         type Kind = ast::LiteralKind;
         match node.kind {
@@ -172,33 +301,64 @@ impl<'a> Visitor<'a> {
         Ok(())
     }
 
+    fn visit_format_value(&mut self, expr: ast::Expr, spec: FormatSpec) -> VisitResult {
+        self.visit_expr(expr, None)?;
+        self.push(Inst::FormatValue(spec));
+        Ok(())
+    }
+
     /// Visit identifier as expression (i.e., not as part of an
     /// assignment).
     fn visit_ident(&mut self, node: ast::Ident) -> VisitResult {
         type Kind = ast::IdentKind;
-        match node.kind {
-            Kind::Ident(name) => self.push(Inst::LoadVar(name)),
-            Kind::SpecialIdent(name) => self.push(Inst::LoadVar(name)),
-            Kind::TypeIdent(name) => self.push(Inst::LoadVar(name)),
+        let name = match node.kind {
+            Kind::Ident(name) => name,
+            Kind::SpecialIdent(name) => name,
+            Kind::TypeIdent(name) => name,
+        };
+        match self.scope_tree.resolve_var(&name) {
+            Some((up, slot)) => self.push(Inst::LoadLocal(up, slot)),
+            None => self.push(Inst::LoadVar(name)),
         }
         Ok(())
     }
 
+    /// Assign to an item (`a.0 = v`) or attribute (`a.b = v`). The
+    /// object, index, and value are pushed, in that order, and the value
+    /// is left on the stack.
+    fn visit_set_item(
+        &mut self,
+        obj_expr: ast::Expr,
+        index_expr: ast::Expr,
+        value_expr: ast::Expr,
+    ) -> VisitResult {
+        self.visit_expr(obj_expr, None)?;
+        self.visit_attr_name(index_expr)?;
+        self.visit_expr(value_expr, None)?;
+        self.push(Inst::SetItem);
+        Ok(())
+    }
+
     fn visit_get_attr(
         &mut self,
         obj_expr: ast::Expr,
         name_expr: ast::Expr,
     ) -> VisitResult {
         self.visit_expr(obj_expr, None)?;
+        self.visit_attr_name(name_expr)?;
+        self.push(Inst::BinaryOp(BinaryOperator::Dot));
+        Ok(())
+    }
+
+    /// Push the name (or index) on the RHS of a `.` as a constant.
+    fn visit_attr_name(&mut self, name_expr: ast::Expr) -> VisitResult {
         if let Some(name) = name_expr.is_ident() {
-            self.visit_literal(ast::Literal::new_string(name))?;
+            self.visit_literal(ast::Literal::new_string(name))
         } else if let Some(name) = name_expr.is_type_ident() {
-            self.visit_literal(ast::Literal::new_string(name))?;
+            self.visit_literal(ast::Literal::new_string(name))
         } else {
-            self.visit_expr(name_expr, None)?;
+            self.visit_expr(name_expr, None)
         }
-        self.push(Inst::BinaryOp(BinaryOperator::Dot));
-        Ok(())
     }
 
     fn visit_assignment(
@@ -206,11 +366,18 @@ impl<'a> Visitor<'a> {
         name_expr: ast::Expr,
         value_expr: ast::Expr,
     ) -> VisitResult {
+        let name_expr = match name_expr.kind {
+            ast::ExprKind::BinaryOp(obj_expr, BinaryOperator::Dot, index_expr) => {
+                return self.visit_set_item(*obj_expr, *index_expr, value_expr);
+            }
+            kind => ast::Expr::new(kind, name_expr.start, name_expr.end),
+        };
         let name = if let Some(name) = name_expr.is_ident() {
             name
         } else if let Some(name) = name_expr.is_special_ident() {
             // TODO: Add more name validation.
-            if name == "$main" && self.scope_tree.in_global_scope() {
+            let is_main = name == "$main" && self.scope_tree.in_global_scope();
+            if is_main || name.starts_with(ast::TEMP_PREFIX) {
                 name
             } else {
                 return Err(CompErr::new_cannot_assign_special_ident(name));
@@ -218,16 +385,76 @@ impl<'a> Visitor<'a> {
         } else {
             return Err(CompErr::new_expected_ident());
         };
-        self.push(Inst::DeclareVar(name.clone()));
-        self.visit_expr(value_expr, Some(name.clone()))?;
-        self.push(Inst::AssignVar(name));
+        // The var is declared before the value is evaluated, so if the
+        // value might read the var, the declaration has to come first.
+        // Otherwise, the var can be declared and assigned in one step.
+        let first = !self.scope_tree.current().has_var(&name);
+        if first && !value_expr.may_read_var(&name) {
+            self.visit_expr(value_expr, Some(name.clone()))?;
+            self.push_assign(name);
+        } else {
+            if first {
+                self.add_var(&name);
+            }
+            if first || self.local_slot(&name).is_none() {
+                self.push(Inst::DeclareVar(name.clone()));
+            }
+            self.visit_expr(value_expr, Some(name.clone()))?;
+            self.push_store(name);
+        }
+        Ok(())
+    }
+
+    /// Assign the value at the top of the stack to the var with the
+    /// specified name in the current scope. The first assignment to a
+    /// var in a scope declares and assigns it in one step.
+    fn push_assign(&mut self, name: String) {
+        if self.add_var(&name) {
+            self.push(Inst::DeclareAndAssignVar(name));
+        } else {
+            if self.local_slot(&name).is_none() {
+                self.push(Inst::DeclareVar(name.clone()));
+            }
+            self.push_store(name);
+        }
+    }
+
+    /// Assign the value at the top of the stack to a var that has
+    /// already been declared in the current scope, by slot if possible.
+    fn push_store(&mut self, name: String) {
+        match self.local_slot(&name) {
+            Some(slot) => self.push(Inst::StoreLocal(0, slot)),
+            None => self.push(Inst::AssignVar(name)),
+        }
+    }
+
+    /// Unpack a tuple into multiple names. The tuple is left on the
+    /// stack as the value of the assignment.
+    fn visit_multi_assign(
+        &mut self,
+        names: Vec<String>,
+        value_expr: ast::Expr,
+    ) -> VisitResult {
+        self.visit_expr(value_expr, None)?;
+        self.push(Inst::UnpackTuple(names.len()));
+        for name in names {
+            self.push_assign(name);
+            self.push(Inst::Pop);
+        }
         Ok(())
     }
 
     fn visit_block(&mut self, node: ast::StatementBlock) -> VisitResult {
         self.push(Inst::ScopeStart);
         self.enter_scope(ScopeKind::Block);
-        self.visit_statements(node.statements)?;
+        if node.statements.is_empty() {
+            // An empty block evaluates to nil. This ensures there's
+            // always a value in the block's scope to return when the
+            // scope is exited.
+            self.push(Inst::LoadConst(0));
+        } else {
+            self.visit_statements(node.statements)?;
+        }
         self.push(Inst::ScopeEnd);
         self.exit_scope();
         Ok(())
@@ -245,9 +472,17 @@ impl<'a> Visitor<'a> {
         // until the whole conditional suite is compiled.
         let mut jump_out_addrs: Vec<usize> = vec![];
 
-        for (expr, block) in branches {
-            // Evaluate branch expression.
-            self.visit_expr(expr, None)?;
+        for (i, (expr, block)) in branches.into_iter().enumerate() {
+            // Evaluate branch expression. Only the first branch's
+            // expression is always evaluated, so vars declared in the
+            // others might not be declared at runtime.
+            let skipped_scope = self.skipped_scope;
+            if i > 0 {
+                self.skipped_scope = Some(self.scope_tree.pointer());
+            }
+            let result = self.visit_expr(expr, None);
+            self.skipped_scope = skipped_scope;
+            result?;
 
             // Placeholder for jump depending on result of branch expr.
             let jump_index = self.chunk.len();
@@ -298,6 +533,7 @@ impl<'a> Visitor<'a> {
 
     fn visit_loop(
         &mut self,
+        label: Option<String>,
         expr: ast::Expr,
         block: ast::StatementBlock,
     ) -> VisitResult {
@@ -321,16 +557,30 @@ impl<'a> Visitor<'a> {
             jump_out_index
         };
         // Run the loop body.
-        self.visit_block(block)?;
+        if let Some(label) = &label {
+            self.loop_labels.push(label.clone());
+        }
+        let result = self.visit_block(block);
+        if label.is_some() {
+            self.loop_labels.pop();
+        }
+        result?;
+        // Discard the value of the loop body so values don't pile up
+        // on the stack as the loop runs. `continue` jumps here too,
+        // since it also leaves a value on the stack.
+        let pop_addr = self.chunk.len();
+        self.push(Inst::Pop);
         // Jump to top of loop.
         self.push(Inst::Jump(loop_addr, 0));
-        // Jump-out address.
-        let after_addr = self.chunk.len();
         // Set address of jump-out placeholder (not needed if loop
-        // expression is always true).
+        // expression is always true). When the loop exits because its
+        // condition is false, its value is nil.
         if !true_cond {
-            self.chunk[jump_out_index] = Inst::JumpIfNot(after_addr, 0);
+            self.chunk[jump_out_index] = Inst::JumpIfNot(self.chunk.len(), 0);
+            self.push(Inst::LoadConst(0));
         }
+        // Address after loop, which is where breaks jump to.
+        let after_addr = self.chunk.len();
         // Set address of breaks and continues.
         for addr in loop_addr..after_addr {
             match self.chunk[addr] {
@@ -340,16 +590,88 @@ impl<'a> Visitor<'a> {
                 }
                 Inst::ContinuePlaceholder(continue_addr, depth) => {
                     self.chunk[continue_addr] =
-                        Inst::Jump(loop_addr, depth - loop_scope_depth);
+                        Inst::Jump(pop_addr, depth - loop_scope_depth);
                 }
                 _ => (),
             }
         }
+        // Set address of labeled breaks and continues that target this
+        // loop. Any jumps with this label that target an inner loop with
+        // the same label were already resolved by the inner loop.
+        if let Some(label) = label {
+            let (jumps, rest) = std::mem::take(&mut self.labeled_loop_jumps)
+                .into_iter()
+                .partition(|jump| jump.label == label);
+            self.labeled_loop_jumps = rest;
+            for jump in jumps {
+                let target = if jump.is_break { after_addr } else { pop_addr };
+                self.chunk[jump.addr] =
+                    Inst::Jump(target, jump.scope_depth - loop_scope_depth);
+            }
+        }
+        Ok(())
+    }
+
+    /// The try/catch is wrapped in a scope that the error handler is
+    /// installed in. The handler is removed when the try block's scope
+    /// is exited, whether that's because the block finished or because
+    /// of a jump out of it. When an error is caught, the VM unwinds to
+    /// the wrapper scope and pushes the error, which is then bound to
+    /// the catch name (if there is one) in the wrapper scope.
+    fn visit_try(
+        &mut self,
+        block: ast::StatementBlock,
+        name: Option<String>,
+        catch_block: ast::StatementBlock,
+    ) -> VisitResult {
+        self.push(Inst::ScopeStart);
+        self.enter_scope(ScopeKind::Block);
+        let handler_addr = self.chunk.len();
+        self.push(Inst::Placeholder(
+            handler_addr,
+            Box::new(Inst::PushHandler(0)),
+            "Catch address not set".to_owned(),
+        ));
+        self.visit_block(block)?;
+        let jump_out_addr = self.chunk.len();
+        self.push(Inst::Placeholder(
+            jump_out_addr,
+            Box::new(Inst::Jump(0, 0)),
+            "Try jump out not set".to_owned(),
+        ));
+        let catch_addr = self.chunk.len();
+        self.chunk[handler_addr] = Inst::PushHandler(catch_addr);
+        if let Some(name) = name {
+            self.push_assign(name);
+        }
+        self.push(Inst::Pop);
+        self.visit_block(catch_block)?;
+        let after_addr = self.chunk.len();
+        self.chunk[jump_out_addr] = Inst::Jump(after_addr, 0);
+        self.push(Inst::ScopeEnd);
+        self.exit_scope();
         Ok(())
     }
 
     fn visit_func(&mut self, node: ast::Func, name: Option<String>) -> VisitResult {
-        let mut func_visitor = Visitor::new(&mut self.ctx);
+        let defaults = self.compile_defaults(node.defaults)?;
+        let mut func_visitor = Visitor::new(self.ctx, self.optimize);
+        func_visitor.loc = self.loc;
+        // Args are bound in order when the function is called, followed
+        // by the rest arg. When there are defaults, the defaulted args
+        // are bound last, so the order isn't known until runtime.
+        let param_names = if defaults.is_empty() {
+            match &node.params {
+                Some(params) => {
+                    let names = params.iter().map(|name| name.as_str());
+                    Some(names.chain(node.rest.as_deref()).collect())
+                }
+                None => Some(vec!["$args"]),
+            }
+        } else {
+            None
+        };
+        func_visitor.scope_tree = ScopeTree::new_for_func(param_names);
         let name = if name.is_some() {
             let name = name.unwrap();
             self.has_main = name == "$main" && self.scope_tree.in_global_scope();
@@ -358,6 +680,7 @@ impl<'a> Visitor<'a> {
             "<anonymous>".to_owned()
         };
         let params = node.params;
+        let rest = node.rest;
         let return_nil = if let Some(last) = node.block.statements.last() {
             if let ast::StatementKind::Expr(_) = last.kind {
                 false
@@ -365,7 +688,7 @@ impl<'a> Visitor<'a> {
                 true
             }
         } else {
-            true // XXX: This should never happen
+            true // Empty body
         };
         func_visitor.push(Inst::ScopeStart);
         func_visitor.enter_scope(ScopeKind::Func);
@@ -374,27 +697,71 @@ impl<'a> Visitor<'a> {
         if return_nil {
             func_visitor.push(Inst::LoadConst(0));
         }
-        func_visitor.push(Inst::Return);
         func_visitor.push(Inst::ScopeEnd);
+        func_visitor.push(Inst::Return);
         func_visitor.exit_scope();
         assert_eq!(func_visitor.scope_tree.pointer(), 0);
+        if func_visitor.optimize {
+            optimize(&mut func_visitor.chunk, &mut func_visitor.locations);
+        }
         let chunk = func_visitor.chunk;
-        let func = self.ctx.builtins.new_func(name, params, chunk);
+        let locations = func_visitor.locations;
+        let func =
+            self.ctx.builtins.new_func(name, params, defaults, rest, chunk, locations);
         self.add_const(func);
         Ok(())
     }
 
+    /// Compile each param default expression into its own chunk. When
+    /// the function is called, the chunks for any missing args are
+    /// executed in the function's scope, leaving the default values on
+    /// the stack.
+    fn compile_defaults(
+        &mut self,
+        defaults: Vec<ast::Expr>,
+    ) -> Result<Vec<Chunk>, CompErr> {
+        let mut chunks = vec![];
+        for expr in defaults {
+            let mut visitor = Visitor::new(self.ctx, self.optimize);
+            visitor.loc = self.loc;
+            visitor.visit_expr(expr, None)?;
+            visitor.fix_jumps()?;
+            if visitor.optimize {
+                optimize(&mut visitor.chunk, &mut visitor.locations);
+            }
+            chunks.push(visitor.chunk);
+        }
+        Ok(chunks)
+    }
+
     fn visit_call(&mut self, node: ast::Call) -> VisitResult {
-        let callable = node.callable;
+        let callable = *node.callable;
         let args = node.args;
         let n_args = args.len();
-        self.visit_expr(*callable, None)?;
-        self.visit_exprs(args)?;
-        self.push(Inst::Call(n_args));
+        match callable.kind {
+            ast::ExprKind::BinaryOp(obj_expr, BinaryOperator::Dot, name_expr) => {
+                // Method call: push the receiver, the method name, and
+                // the args.
+                self.visit_expr(*obj_expr, None)?;
+                self.visit_attr_name(*name_expr)?;
+                self.visit_exprs(args)?;
+                self.push(Inst::CallMethod(n_args));
+            }
+            kind => {
+                let callable = ast::Expr::new(kind, callable.start, callable.end);
+                self.visit_expr(callable, None)?;
+                self.visit_exprs(args)?;
+                self.push(Inst::Call(n_args));
+            }
+        }
         Ok(())
     }
 
     fn visit_unary_op(&mut self, op: UnaryOperator, expr: ast::Expr) -> VisitResult {
+        if let Some(obj) = self.fold_unary_op(&op, &expr) {
+            self.add_const(obj);
+            return Ok(());
+        }
         self.visit_expr(expr, None)?;
         self.push(Inst::UnaryOp(op));
         Ok(())
@@ -411,6 +778,10 @@ impl<'a> Visitor<'a> {
             Dot => self.visit_get_attr(expr_a, expr_b),
             Assign => self.visit_assignment(expr_a, expr_b),
             _ => {
+                if let Some(obj) = self.fold_binary_op(&expr_a, &op, &expr_b) {
+                    self.add_const(obj);
+                    return Ok(());
+                }
                 self.visit_expr(expr_a, None)?;
                 self.visit_expr(expr_b, None)?;
                 self.push(Inst::BinaryOp(op));
@@ -419,10 +790,83 @@ impl<'a> Visitor<'a> {
         }
     }
 
+    // Constant folding ------------------------------------------------
+    //
+    // Arithmetic on numeric literals is evaluated at compile time using
+    // the same Int and Float operations used at runtime. When folding
+    // isn't possible, `None` is returned and normal code is emitted.
+
+    /// Evaluate a constant numeric expression.
+    fn fold_const(&self, expr: &ast::Expr) -> Option<ObjectRef> {
+        type Kind = ast::ExprKind;
+        match &expr.kind {
+            Kind::Literal(literal) => match &literal.kind {
+                ast::LiteralKind::Int(value) => {
+                    Some(self.ctx.builtins.new_int(value.clone()))
+                }
+                ast::LiteralKind::Float(value) => {
+                    Some(self.ctx.builtins.new_float(*value))
+                }
+                _ => None,
+            },
+            Kind::UnaryOp(op, expr) => self.fold_unary_op(op, expr),
+            Kind::BinaryOp(a, op, b) => self.fold_binary_op(a, op, b),
+            _ => None,
+        }
+    }
+
+    fn fold_unary_op(&self, op: &UnaryOperator, expr: &ast::Expr) -> Option<ObjectRef> {
+        use UnaryOperator::*;
+        let obj = self.fold_const(expr)?;
+        match op {
+            Plus => Some(obj),
+            Negate => obj.negate(self.ctx).ok(),
+            _ => None,
+        }
+    }
+
+    fn fold_binary_op(
+        &self,
+        expr_a: &ast::Expr,
+        op: &BinaryOperator,
+        expr_b: &ast::Expr,
+    ) -> Option<ObjectRef> {
+        use BinaryOperator::*;
+        if !matches!(op, Pow | Mul | Div | FloorDiv | Mod | Add | Sub) {
+            return None;
+        }
+        let a = self.fold_const(expr_a)?;
+        let b = self.fold_const(expr_b)?;
+        let b_is_zero = match (b.int_val(), b.float_val()) {
+            (Some(value), _) => value.is_zero(),
+            (_, Some(value)) => value == 0.0,
+            _ => false,
+        };
+        let (ctx, b) = (&*self.ctx, &*b);
+        let result = match op {
+            // Leave errors involving the exponent to the runtime.
+            Pow => match b.int_val() {
+                Some(exp) if exp.to_u32().is_none() => return None,
+                _ => a.pow(b, ctx),
+            },
+            Mul => a.mul(b, ctx),
+            // Leave division by zero to the runtime.
+            Div | FloorDiv | Mod if b_is_zero => return None,
+            Div => a.div(b, ctx),
+            FloorDiv => a.floor_div(b, ctx),
+            Mod => a.modulo(b, ctx),
+            Add => a.add(b, ctx),
+            Sub => a.sub(b, ctx),
+            _ => unreachable!(),
+        };
+        result.ok()
+    }
+
     // Utilities -------------------------------------------------------
 
     fn push(&mut self, inst: Inst) {
         self.chunk.push(inst);
+        self.locations.push(self.loc);
     }
 
     fn push_const(&mut self, index: usize) {
@@ -435,6 +879,21 @@ impl<'a> Visitor<'a> {
         index
     }
 
+    /// Record that a var is assigned in the current scope. Returns
+    /// `true` if this is the first assignment to the var in the scope.
+    fn add_var(&mut self, name: &str) -> bool {
+        let maybe_skipped = self.skipped_scope == Some(self.scope_tree.pointer());
+        self.scope_tree.current_mut().add_var(name, maybe_skipped)
+    }
+
+    /// Get the slot of a var in the current scope, if it has one.
+    fn local_slot(&self, name: &str) -> Option<usize> {
+        match self.scope_tree.resolve_var(name) {
+            Some((0, slot)) => Some(slot),
+            _ => None,
+        }
+    }
+
     /// Add nested scope to current scope then make the new scope the
     /// current scope.
     fn enter_scope(&mut self, kind: ScopeKind) {
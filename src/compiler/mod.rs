@@ -1,6 +1,10 @@
-pub(crate) use compiler::compile;
+pub(crate) use compiler::compile_with_locations;
+#[cfg(test)]
+pub(crate) use compiler::{compile, compile_unoptimized};
 pub(crate) use result::{CompErr, CompErrKind};
 
 mod compiler;
+mod optimize;
+mod resolve;
 mod result;
 mod scope;
@@ -0,0 +1,110 @@
+//! Peephole optimizations. These are applied to compiled chunks to
+//! remove instructions that have no effect.
+use std::collections::HashSet;
+
+use crate::util::Location;
+use crate::vm::{Chunk, Inst};
+
+/// Optimize chunk in place, keeping the corresponding instruction
+/// locations in sync. The following instructions are removed,
+/// and jump targets are rewritten to account for removed instructions:
+///
+/// - A `LoadConst` immediately followed by a `Pop`. These are left in
+///   place if something jumps directly to the `Pop`, since that jump
+///   relies on the `Pop` to discard a different value.
+/// - A `Jump` to the immediately following instruction that doesn't
+///   exit any scopes.
+pub fn optimize(chunk: &mut Chunk, locations: &mut Vec<Location>) {
+    while optimize_pass(chunk, locations) {}
+}
+
+/// Run a single optimization pass. Returns `true` if any instructions
+/// were removed.
+fn optimize_pass(chunk: &mut Chunk, locations: &mut Vec<Location>) -> bool {
+    let len = chunk.len();
+    let targets = jump_targets(chunk);
+    let mut remove = vec![false; len];
+    let mut removed_any = false;
+    let mut addr = 0;
+
+    while addr < len {
+        match (&chunk[addr], chunk.get(addr + 1)) {
+            (Inst::LoadConst(_), Some(Inst::Pop)) if !targets.contains(&(addr + 1)) => {
+                remove[addr] = true;
+                remove[addr + 1] = true;
+                removed_any = true;
+                addr += 2;
+                continue;
+            }
+            (Inst::Jump(target, 0), _) if *target == addr + 1 => {
+                remove[addr] = true;
+                removed_any = true;
+            }
+            _ => (),
+        }
+        addr += 1;
+    }
+
+    if !removed_any {
+        return false;
+    }
+
+    // Map old addresses to new addresses. A removed instruction maps to
+    // the next instruction that's kept. The extra entry at the end is
+    // for jumps to the end of the chunk.
+    let mut new_addrs = Vec::with_capacity(len + 1);
+    let mut new_addr = 0;
+    for removed in remove.iter() {
+        new_addrs.push(new_addr);
+        if !removed {
+            new_addr += 1;
+        }
+    }
+    new_addrs.push(new_addr);
+
+    let old_chunk = std::mem::take(chunk);
+    let old_locations = std::mem::take(locations);
+    for ((inst, loc), removed) in old_chunk.into_iter().zip(old_locations).zip(remove) {
+        if !removed {
+            chunk.push(rewrite_jump(inst, &new_addrs));
+            locations.push(loc);
+        }
+    }
+
+    true
+}
+
+/// Get the addresses of all jump targets in the chunk. This includes
+/// the catch addresses of error handlers.
+fn jump_targets(chunk: &Chunk) -> HashSet<usize> {
+    let mut targets = HashSet::new();
+    for inst in chunk.iter() {
+        match inst {
+            Inst::Jump(addr, _)
+            | Inst::JumpIf(addr, _)
+            | Inst::JumpIfNot(addr, _)
+            | Inst::PushHandler(addr) => {
+                targets.insert(*addr);
+            }
+            Inst::JumpIfElse(if_addr, else_addr, _) => {
+                targets.insert(*if_addr);
+                targets.insert(*else_addr);
+            }
+            _ => (),
+        }
+    }
+    targets
+}
+
+fn rewrite_jump(inst: Inst, new_addrs: &[usize]) -> Inst {
+    match inst {
+        Inst::Jump(addr, count) => Inst::Jump(new_addrs[addr], count),
+        Inst::JumpIf(addr, count) => Inst::JumpIf(new_addrs[addr], count),
+        Inst::JumpIfNot(addr, count) => Inst::JumpIfNot(new_addrs[addr], count),
+        Inst::JumpIfElse(if_addr, else_addr, count) => {
+            Inst::JumpIfElse(new_addrs[if_addr], new_addrs[else_addr], count)
+        }
+        Inst::PushHandler(addr) => Inst::PushHandler(new_addrs[addr]),
+        inst => inst,
+    }
+}
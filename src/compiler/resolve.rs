@@ -0,0 +1,216 @@
+//! Name resolution pass. This runs before code is generated and finds
+//! reads of names that can't be defined when they're evaluated so that
+//! they're reported before the program is executed.
+//!
+//! Code outside of functions is checked lexically: a name read there
+//! has to be assigned somewhere in the current scope or one of its
+//! enclosing scopes. Names in function bodies that aren't local are
+//! looked up in the caller's scopes when the function is called, so
+//! they're left for the VM to check. Names that are already defined
+//! when the program is compiled, like builtins, and special names, like
+//! `$args`, are always allowed.
+use std::collections::HashSet;
+
+use crate::ast;
+use crate::util::{closest_name, BinaryOperator};
+use crate::vm::RuntimeContext;
+
+use super::result::CompErr;
+
+/// Check the program for reads of undefined names.
+pub fn check_names(
+    ctx: &RuntimeContext,
+    program: &ast::Program,
+) -> Result<(), CompErr> {
+    let mut defined = HashSet::new();
+    for namespace in ctx.iter_namespaces() {
+        defined.extend(namespace.iter().map(|(name, _)| name.as_str()));
+    }
+    let mut resolver = Resolver { defined, scopes: vec![] };
+    resolver.check_statements(&program.statements)
+}
+
+type CheckResult = Result<(), CompErr>;
+
+struct Resolver<'a> {
+    // Names defined before the program is run
+    defined: HashSet<&'a str>,
+    // Names assigned in each enclosing scope, innermost last
+    scopes: Vec<HashSet<&'a str>>,
+}
+
+impl<'a> Resolver<'a> {
+    fn check_statements(&mut self, statements: &'a [ast::Statement]) -> CheckResult {
+        let mut names = HashSet::new();
+        add_assigned_names(statements, &mut names);
+        self.scopes.push(names);
+        let result = statements.iter().try_for_each(|s| self.check_statement(s));
+        self.scopes.pop();
+        result
+    }
+
+    fn check_statement(&mut self, statement: &'a ast::Statement) -> CheckResult {
+        type Kind = ast::StatementKind;
+        match &statement.kind {
+            Kind::Jump(_) | Kind::Continue(_) => Ok(()),
+            Kind::Label(_, expr)
+            | Kind::Break(_, expr)
+            | Kind::Raise(expr)
+            | Kind::Expr(expr) => self.check_expr(expr),
+        }
+    }
+
+    fn check_exprs(&mut self, exprs: &'a [ast::Expr]) -> CheckResult {
+        exprs.iter().try_for_each(|expr| self.check_expr(expr))
+    }
+
+    fn check_expr(&mut self, expr: &'a ast::Expr) -> CheckResult {
+        type Kind = ast::ExprKind;
+        match &expr.kind {
+            // Function bodies (and param defaults) are evaluated in the
+            // caller's scope, which isn't known until they're called.
+            Kind::Literal(_) | Kind::Func(_) => Ok(()),
+            Kind::Ident(ident) => match &ident.kind {
+                ast::IdentKind::Ident(name) | ast::IdentKind::TypeIdent(name) => {
+                    self.check_read(name)
+                }
+                ast::IdentKind::SpecialIdent(_) => Ok(()),
+            },
+            Kind::Tuple(items) | Kind::List(items) | Kind::FormatString(items) => {
+                self.check_exprs(items)
+            }
+            Kind::FormatValue(expr, _) | Kind::UnaryOp(_, expr) => {
+                self.check_expr(expr)
+            }
+            Kind::Block(block) => self.check_statements(&block.statements),
+            Kind::Conditional(branches, default) => {
+                for (expr, block) in branches {
+                    self.check_expr(expr)?;
+                    self.check_statements(&block.statements)?;
+                }
+                match default {
+                    Some(block) => self.check_statements(&block.statements),
+                    None => Ok(()),
+                }
+            }
+            Kind::Loop(_, expr, block) => {
+                self.check_expr(expr)?;
+                self.check_statements(&block.statements)
+            }
+            Kind::Try(block, name, catch_block) => {
+                // The error is bound to the name in a scope that wraps
+                // the try and catch blocks.
+                self.scopes.push(name.iter().map(|name| name.as_str()).collect());
+                let result = self
+                    .check_statements(&block.statements)
+                    .and_then(|()| self.check_statements(&catch_block.statements));
+                self.scopes.pop();
+                result
+            }
+            Kind::Call(call) => {
+                self.check_expr(&call.callable)?;
+                self.check_exprs(&call.args)
+            }
+            Kind::BinaryOp(a, BinaryOperator::Assign, b) => {
+                // The name being assigned isn't read, but the object an
+                // attribute or item is being set on is.
+                if a.is_ident().is_none() && a.is_special_ident().is_none() {
+                    self.check_expr(a)?;
+                }
+                self.check_expr(b)
+            }
+            Kind::BinaryOp(a, BinaryOperator::Dot, b) => {
+                // Names on the RHS of a dot are attribute names.
+                self.check_expr(a)?;
+                if b.is_ident().is_some() || b.is_type_ident().is_some() {
+                    Ok(())
+                } else {
+                    self.check_expr(b)
+                }
+            }
+            Kind::BinaryOp(a, _, b) => {
+                self.check_expr(a)?;
+                self.check_expr(b)
+            }
+            Kind::MultiAssign(_, value) => self.check_expr(value),
+        }
+    }
+
+    fn check_read(&self, name: &str) -> CheckResult {
+        if self.defined.contains(name)
+            || self.scopes.iter().any(|names| names.contains(name))
+        {
+            return Ok(());
+        }
+        let visible = self.scopes.iter().flatten().chain(self.defined.iter());
+        let suggestion = closest_name(name, visible.copied()).map(str::to_owned);
+        Err(CompErr::new_undefined_name(name.to_owned(), suggestion))
+    }
+}
+
+/// Add the names assigned by the statements in the scope they're in to
+/// `names`. Names assigned in nested scopes aren't added.
+fn add_assigned_names<'a>(
+    statements: &'a [ast::Statement],
+    names: &mut HashSet<&'a str>,
+) {
+    type Kind = ast::StatementKind;
+    for statement in statements {
+        match &statement.kind {
+            Kind::Jump(_) | Kind::Continue(_) => (),
+            Kind::Label(_, expr)
+            | Kind::Break(_, expr)
+            | Kind::Raise(expr)
+            | Kind::Expr(expr) => add_assigned_names_in_expr(expr, names),
+        }
+    }
+}
+
+fn add_assigned_names_in_expr<'a>(expr: &'a ast::Expr, names: &mut HashSet<&'a str>) {
+    type Kind = ast::ExprKind;
+    match &expr.kind {
+        // Blocks, functions, etc have their own scopes
+        Kind::Literal(_)
+        | Kind::Ident(_)
+        | Kind::Block(_)
+        | Kind::Try(..)
+        | Kind::Func(_) => (),
+        Kind::Tuple(items) | Kind::List(items) | Kind::FormatString(items) => {
+            for item in items {
+                add_assigned_names_in_expr(item, names);
+            }
+        }
+        Kind::FormatValue(expr, _) | Kind::UnaryOp(_, expr) => {
+            add_assigned_names_in_expr(expr, names)
+        }
+        Kind::Conditional(branches, _) => {
+            for (expr, _) in branches {
+                add_assigned_names_in_expr(expr, names);
+            }
+        }
+        Kind::Loop(_, expr, _) => add_assigned_names_in_expr(expr, names),
+        Kind::Call(call) => {
+            add_assigned_names_in_expr(&call.callable, names);
+            for arg in &call.args {
+                add_assigned_names_in_expr(arg, names);
+            }
+        }
+        Kind::BinaryOp(a, BinaryOperator::Assign, b) => {
+            match &a.kind {
+                Kind::Ident(ast::Ident { kind: ast::IdentKind::Ident(name) }) => {
+                    names.insert(name.as_str());
+                }
+                _ => add_assigned_names_in_expr(a, names),
+            }
+            add_assigned_names_in_expr(b, names);
+        }
+        Kind::BinaryOp(a, _, b) => {
+            add_assigned_names_in_expr(a, names);
+            add_assigned_names_in_expr(b, names);
+        }
+        Kind::MultiAssign(assigned, value) => {
+            names.extend(assigned.iter().map(|name| name.as_str()));
+            add_assigned_names_in_expr(value, names);
+        }
+    }
+}
@@ -1,7 +1,9 @@
 use crate::util::Location;
 use crate::vm::Chunk;
 
-pub type CompResult = Result<Chunk, CompErr>;
+/// The compiled chunk along with the source location of each of its
+/// instructions.
+pub type CompResult = Result<(Chunk, Vec<Location>), CompErr>;
 
 #[derive(Clone, Debug)]
 pub struct CompErr {
@@ -36,6 +38,10 @@ impl CompErr {
     pub fn new_cannot_assign_special_ident(name: String) -> Self {
         Self { kind: CompErrKind::CannotAssignSpecialIdent(name) }
     }
+
+    pub fn new_undefined_name(name: String, suggestion: Option<String>) -> Self {
+        Self { kind: CompErrKind::UndefinedName(name, suggestion) }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -46,4 +52,7 @@ pub enum CompErrKind {
     DuplicateLabelInScope(String),
     ExpectedIdent,
     CannotAssignSpecialIdent(String),
+    // A name is read but is never assigned where it could be visible
+    // (name, similar name that is visible)
+    UndefinedName(String, Option<String>),
 }
@@ -1,5 +1,7 @@
 //! The scope tree keeps track of nested scopes during compilation.
-//! Currently, it's only used resolve jump targets to labels.
+//! It's used to resolve jump targets to labels and to track which vars
+//! have been assigned in each scope, which is used to resolve vars in
+//! local scopes to slots.
 use std::collections::HashMap;
 
 pub struct ScopeTree {
@@ -13,6 +15,24 @@ impl ScopeTree {
         Self { storage: vec![global_scope], pointer: 0 }
     }
 
+    /// Create a scope tree for a function body. The root scope is the
+    /// scope the function's args are bound in when it's called. When
+    /// the order the args will be bound in is known, `params` contains
+    /// the param names in that order so that they can be accessed by
+    /// slot. Otherwise, params are accessed by name.
+    pub fn new_for_func(params: Option<Vec<&str>>) -> Self {
+        let mut call_scope = Scope::new(ScopeKind::Call, 0, None);
+        match params {
+            Some(params) => {
+                for name in params {
+                    call_scope.add_var(name, false);
+                }
+            }
+            None => call_scope.ordered = false,
+        }
+        Self { storage: vec![call_scope], pointer: 0 }
+    }
+
     pub fn in_global_scope(&self) -> bool {
         self.current().is_global()
     }
@@ -72,6 +92,37 @@ impl ScopeTree {
         self.current_mut().labels.insert(name.to_owned(), addr)
     }
 
+    /// Mark the current scope and its parent scopes as unordered. This
+    /// is used when vars declared in these scopes after this point
+    /// might not be declared at runtime (e.g., due to a jump).
+    pub fn set_unordered(&mut self) {
+        let mut index = Some(self.pointer);
+        while let Some(i) = index {
+            self.storage[i].ordered = false;
+            index = self.storage[i].parent;
+        }
+    }
+
+    /// Resolve a var to its slot in the current scope or one of its
+    /// parent scopes. The returned depth is relative to the current
+    /// scope. `None` is returned for global vars, vars that haven't
+    /// been assigned yet, and vars without a reliable slot, all of
+    /// which have to be accessed by name.
+    pub fn resolve_var(&self, name: &str) -> Option<(usize, usize)> {
+        let mut scope = self.current();
+        let mut up = 0;
+        loop {
+            if scope.is_global() {
+                return None;
+            }
+            if let Some(slot) = scope.vars.get(name) {
+                return slot.map(|slot| (up, slot));
+            }
+            scope = self.get(scope.parent?);
+            up += 1;
+        }
+    }
+
     // -----------------------------------------------------------------
 
     fn get(&self, index: usize) -> &Scope {
@@ -123,11 +174,18 @@ pub struct Scope {
     labels: HashMap<String, usize>,
     /// target label name => jump inst address
     jumps: HashMap<String, usize>,
+    /// Names of vars assigned in this scope so far => slot
+    vars: HashMap<String, Option<usize>>,
+    /// Whether vars declared in this scope from now on will be
+    /// declared at runtime in the order they're compiled
+    ordered: bool,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ScopeKind {
     Global,
+    // The scope a function's args are bound in
+    Call,
     Block,
     Func,
 }
@@ -141,6 +199,8 @@ impl Scope {
             children: vec![],
             labels: HashMap::new(),
             jumps: HashMap::new(),
+            vars: HashMap::new(),
+            ordered: true,
         }
     }
 
@@ -152,6 +212,30 @@ impl Scope {
         self.children.len() == 0
     }
 
+    /// Record that a var is assigned in this scope. Returns `true` if
+    /// this is the first assignment to the var in this scope.
+    ///
+    /// Vars are given slots in the order they're first assigned, which
+    /// is the order they'll be declared at runtime, unless a var's
+    /// declaration might be skipped. In that case, the slots of that
+    /// var and any vars declared after it aren't reliable, so they
+    /// aren't given slots.
+    pub fn add_var(&mut self, name: &str, maybe_skipped: bool) -> bool {
+        if self.vars.contains_key(name) {
+            return false;
+        }
+        if maybe_skipped {
+            self.ordered = false;
+        }
+        let slot = if self.ordered { Some(self.vars.len()) } else { None };
+        self.vars.insert(name.to_owned(), slot);
+        true
+    }
+
+    pub fn has_var(&self, name: &str) -> bool {
+        self.vars.contains_key(name)
+    }
+
     pub fn jumps(&self) -> &HashMap<String, usize> {
         &self.jumps
     }
@@ -1,14 +1,28 @@
 //! Front end for executing code from a source on a VM.
 use std::io::BufRead;
 
-use crate::compiler::{compile, CompErr, CompErrKind};
+use crate::compiler::{compile_with_locations, CompErr, CompErrKind};
 use crate::parser::{ParseErr, ParseErrKind, Parser};
 use crate::result::{ExeErr, ExeErrKind, ExeResult};
 use crate::scanner::{ScanErr, ScanErrKind, Scanner, Token};
+use crate::types::ObjectRef;
 use crate::util::{
     source_from_file, source_from_stdin, source_from_text, Location, Source,
 };
-use crate::vm::{Inst, RuntimeErr, RuntimeErrKind, VM};
+use crate::vm::{Chunk, Inst, RuntimeErr, RuntimeErrKind, VM};
+
+/// Execute text on a new VM and return the value of the last statement
+/// executed. This is intended for embedding FeInt in Rust programs.
+pub fn eval_to_object(text: &str) -> Result<ObjectRef, ExeErr> {
+    let mut vm = VM::default();
+    let mut executor = Executor::new(&mut vm, false, false, false);
+    executor.execute_text(text, None)?;
+    match vm.peek_obj() {
+        Ok(Some(obj)) => Ok(obj),
+        Ok(None) => Ok(vm.ctx.builtins.nil_obj.clone()),
+        Err(err) => Err(ExeErr::new(ExeErrKind::RuntimeErr(err.kind))),
+    }
+}
 
 pub struct Executor<'a> {
     pub vm: &'a mut VM,
@@ -16,11 +30,25 @@ pub struct Executor<'a> {
     dis: bool,
     debug: bool,
     current_file_name: &'a str,
+    /// In incremental mode, the most recently compiled chunk is kept so
+    /// it can be inspected (e.g., by the REPL).
+    pub last_chunk: Option<Chunk>,
+    /// Whether the last statement of the most recently compiled source
+    /// is an assignment (e.g., so the REPL doesn't echo its result).
+    pub last_is_assignment: bool,
 }
 
 impl<'a> Executor<'a> {
     pub fn new(vm: &'a mut VM, incremental: bool, dis: bool, debug: bool) -> Self {
-        Self { vm, incremental, dis, debug, current_file_name: "<none>" }
+        Self {
+            vm,
+            incremental,
+            dis,
+            debug,
+            current_file_name: "<none>",
+            last_chunk: None,
+            last_is_assignment: false,
+        }
     }
 
     /// Execute source from file.
@@ -57,6 +85,42 @@ impl<'a> Executor<'a> {
 
     /// Execute source.
     pub fn execute_source<T: BufRead>(&mut self, source: &mut Source<T>) -> ExeResult {
+        let (chunk, locations) = self.compile_source(source)?;
+        let get_line = |line_no| source.get_line(line_no);
+        let result = self.execute_chunk_with_locations(&chunk, &locations, get_line);
+        if self.incremental {
+            self.last_chunk = Some(chunk);
+        }
+        result
+    }
+
+    /// Scan, parse, and compile text without executing it. Errors are
+    /// reported just as they would be if the text were executed.
+    pub fn compile_only_text(
+        &mut self,
+        text: &str,
+        file_name: Option<&'a str>,
+    ) -> Result<Chunk, ExeErr> {
+        self.current_file_name = file_name.unwrap_or("<text>");
+        let mut source = source_from_text(text);
+        self.compile_only(&mut source)
+    }
+
+    /// Scan, parse, and compile source without executing it. This is
+    /// useful for static checks since it's faster than executing the
+    /// source and has no side effects.
+    pub fn compile_only<T: BufRead>(
+        &mut self,
+        source: &mut Source<T>,
+    ) -> Result<Chunk, ExeErr> {
+        self.compile_source(source).map(|(chunk, _)| chunk)
+    }
+
+    /// Scan, parse, and compile source, reporting any errors.
+    fn compile_source<T: BufRead>(
+        &mut self,
+        source: &mut Source<T>,
+    ) -> Result<(Chunk, Vec<Location>), ExeErr> {
         let scanner = Scanner::new(source);
         let mut parser = Parser::new(scanner.into_iter());
         let program = match parser.parse() {
@@ -65,29 +129,27 @@ impl<'a> Executor<'a> {
                 return match err.kind {
                     ParseErrKind::ScanErr(scan_err) => {
                         if !self.ignore_scan_err(&scan_err) {
-                            self.print_err_line(
-                                source.line_no,
-                                source.get_current_line().unwrap_or("<none>"),
-                            );
-                            self.handle_scan_err(&scan_err);
+                            let line = source.get_current_line().unwrap_or("<none>");
+                            self.print_err_line(source.line_no, line);
+                            self.handle_scan_err(&scan_err, line);
                         }
                         Err(ExeErr::new(ExeErrKind::ScanErr(scan_err.kind)))
                     }
                     _ => {
                         if !self.ignore_parse_err(&err) {
-                            self.print_err_line(
-                                source.line_no,
-                                source.get_current_line().unwrap_or("<none>"),
-                            );
-                            self.handle_parse_err(&err);
+                            let line = source.get_current_line().unwrap_or("<none>");
+                            self.print_err_line(source.line_no, line);
+                            self.handle_parse_err(&err, line);
                         }
                         Err(ExeErr::new(ExeErrKind::ParseErr(err.kind)))
                     }
                 };
             }
         };
-        let chunk = match compile(self.vm, program) {
-            Ok(chunk) => chunk,
+        self.last_is_assignment =
+            program.statements.last().is_some_and(|s| s.is_assignment());
+        match compile_with_locations(self.vm, program) {
+            Ok(result) => Ok(result),
             Err(err) => {
                 if !self.ignore_comp_err(&err) {
                     self.print_err_line(
@@ -96,31 +158,42 @@ impl<'a> Executor<'a> {
                     );
                     self.handle_comp_err(&err);
                 }
-                return Err(ExeErr::new(ExeErrKind::CompErr(err.kind)));
+                Err(ExeErr::new(ExeErrKind::CompErr(err.kind)))
             }
-        };
-        self.execute_chunk(chunk)
+        }
     }
 
     /// Execute a chunk (a list of instructions).
     pub fn execute_chunk(&mut self, chunk: Vec<Inst>) -> ExeResult {
+        self.execute_chunk_with_locations(&chunk, &[], |_| None)
+    }
+
+    /// Execute a chunk, using the source location of each instruction
+    /// to show where runtime errors occurred. `get_line` is used to
+    /// look up the source line for a runtime error.
+    fn execute_chunk_with_locations<'s>(
+        &mut self,
+        chunk: &Chunk,
+        locations: &[Location],
+        get_line: impl Fn(usize) -> Option<&'s str>,
+    ) -> ExeResult {
         let result = if cfg!(debug_assertions) {
             if self.dis {
                 eprintln!("{:=<79}", "INSTRUCTIONS ");
             } else if self.debug {
                 eprintln!("{:=<79}", "OUTPUT ");
             }
-            self.vm.execute(&chunk, self.dis)
+            self.vm.execute(chunk, self.dis)
         } else if self.dis {
             eprintln!("{:=<79}", "INSTRUCTIONS ");
-            let result = self.vm.dis_list(&chunk);
+            let result = self.vm.dis_list(chunk);
             eprintln!("NOTE: Full disassembly is only available in debug builds");
             result
         } else {
             if self.debug {
                 eprintln!("{:=<79}", "OUTPUT ");
             }
-            self.vm.execute(&chunk, false)
+            self.vm.execute(chunk, false)
         };
         let num_funcs = if self.dis {
             eprintln!();
@@ -128,6 +201,13 @@ impl<'a> Executor<'a> {
         } else {
             0
         };
+        if self.vm.profile {
+            if self.dis {
+                eprintln!();
+            }
+            eprintln!("{:=<79}", "PROFILE ");
+            self.vm.display_profile_report();
+        }
         if self.debug {
             if !self.dis || num_funcs > 0 {
                 eprintln!();
@@ -139,8 +219,18 @@ impl<'a> Executor<'a> {
         }
         match result {
             Ok(vm_state) => Ok(vm_state),
-            Err(err) => {
-                self.print_err_line(0, "<line not available (TODO)>");
+            Err(mut err) => {
+                // Fill in the traceback entry for the top level code.
+                if let Some(entry) = err.traceback.last_mut() {
+                    if entry.func_name.is_none() {
+                        entry.loc = locations.get(entry.ip).copied();
+                    }
+                }
+                let loc = err.loc();
+                let line = loc
+                    .and_then(|loc| get_line(loc.line))
+                    .unwrap_or("<line not available>");
+                self.print_err_line(loc.map(|loc| loc.line).unwrap_or(0), line);
                 self.handle_runtime_err(&err);
                 Err(ExeErr::new(ExeErrKind::RuntimeErr(err.kind)))
             }
@@ -153,10 +243,12 @@ impl<'a> Executor<'a> {
         eprintln!("\n  Error in {file_name} on line {line_no}:\n\n    |\n    |{line}");
     }
 
-    fn print_err_message(&self, message: String, loc: Location) {
+    /// Print error message with a marker under the location in the
+    /// line where the error occurred.
+    fn print_err_message(&self, message: String, loc: Location, line: &str) {
         if message.len() > 0 {
-            let marker_loc = if loc.col == 0 { 0 } else { loc.col - 1 };
-            eprintln!("    |{:>marker_loc$}^\n\n  {}\n", "", message);
+            let padding = err_marker_padding(line, loc);
+            eprintln!("    |{padding}^\n\n  {}\n", message);
         }
     }
 
@@ -172,14 +264,26 @@ impl<'a> Executor<'a> {
             }
     }
 
-    fn handle_scan_err(&self, err: &ScanErr) {
+    fn handle_scan_err(&self, err: &ScanErr, line: &str) {
+        let (message, loc) = self.format_scan_err(err);
+        self.print_err_message(message, loc, line);
+    }
+
+    /// Get the error message for a scan error along with the location
+    /// where the error marker should be shown.
+    pub fn format_scan_err(&self, err: &ScanErr) -> (String, Location) {
         use ScanErrKind::*;
         let mut loc = err.location.clone();
         let col = loc.col;
         let message = match &err.kind {
-            UnexpectedChar(c) => {
-                format!("Syntax error: Unexpected character at column {}: '{}'", col, c)
-            }
+            UnexpectedChar(c) => match err.context_str() {
+                Some(context) => format!(
+                    "Syntax error at column {col}: after `{context}`, found unexpected character '{c}'"
+                ),
+                None => {
+                    format!("Syntax error: Unexpected character at column {}: '{}'", col, c)
+                }
+            },
             UnmatchedOpeningBracket(_) => {
                 format!("Unmatched open bracket at {loc}")
             }
@@ -205,28 +309,32 @@ impl<'a> Executor<'a> {
                 use crate::format::FormatStrErr::*;
                 match err {
                     EmptyExpr(pos) => {
-                        loc = Location::new(loc.line, loc.col + pos);
+                        loc = loc.advance(*pos);
                         format!("Syntax error in format string: expected expression")
                     }
                     UnmatchedOpeningBracket(pos) => {
-                        loc = Location::new(loc.line, loc.col + pos);
+                        loc = loc.advance(*pos);
                         format!("Unmatched opening bracket in format string")
                     }
                     UnmatchedClosingBracket(pos) => {
-                        loc = Location::new(loc.line, loc.col + pos);
+                        loc = loc.advance(*pos);
                         format!("Unmatched closing bracket in format string")
                     }
                     ScanErr(_, pos) => {
-                        loc = Location::new(loc.line, loc.col + *pos);
+                        loc = loc.advance(*pos);
                         format!("Error while scanning format string")
                     }
+                    InvalidSpec(spec, pos) => {
+                        loc = loc.advance(*pos);
+                        format!("Invalid format spec in format string: {spec}")
+                    }
                 }
             }
             kind => {
                 format!("Unhandled scan error at {loc}: {kind:?}")
             }
         };
-        self.print_err_message(message, loc);
+        (message, loc)
     }
 
     fn ignore_parse_err(&self, err: &ParseErr) -> bool {
@@ -238,7 +346,7 @@ impl<'a> Executor<'a> {
             }
     }
 
-    fn handle_parse_err(&self, err: &ParseErr) {
+    fn handle_parse_err(&self, err: &ParseErr, line: &str) {
         use ParseErrKind::*;
         let (loc, message) = match &err.kind {
             ScanErr(_) => {
@@ -265,6 +373,16 @@ impl<'a> Executor<'a> {
             ExpectedIdent(loc) => {
                 (loc.clone(), format!("Parse error: expected identifier at {loc}",))
             }
+            ExpectedDefault(loc) => (
+                *loc,
+                format!("Parse error: expected default value for parameter at {loc}"),
+            ),
+            MisplacedRestParam(loc) => (
+                *loc,
+                format!(
+                    "Parse error: unexpected ... at {loc} (only the last function param can collect extra args)"
+                ),
+            ),
             UnexpectedBreak(loc) => (
                 loc.clone(),
                 format!(
@@ -280,7 +398,7 @@ impl<'a> Executor<'a> {
             SyntaxErr(loc) => (loc.clone(), format!("Syntax error at {loc}",)),
             kind => (Location::new(0, 0), format!("Unhandled parse error: {:?}", kind)),
         };
-        self.print_err_message(message, loc);
+        self.print_err_message(message, loc, line);
     }
 
     fn handle_comp_err(&self, err: &CompErr) {
@@ -304,6 +422,12 @@ impl<'a> Executor<'a> {
             CannotAssignSpecialIdent(name) => {
                 format!("cannot assign to special name: {name}")
             }
+            UndefinedName(name, Some(suggestion)) => {
+                format!("name is never defined: {name}; did you mean `{suggestion}`?")
+            }
+            UndefinedName(name, None) => {
+                format!("name is never defined: {name}")
+            }
         };
         eprintln!("    |\n\n  Compilation error: {}", message);
     }
@@ -322,12 +446,97 @@ impl<'a> Executor<'a> {
         let message = match &err.kind {
             NameErr(message) => format!("Name error: {message}"),
             TypeErr(message) => format!("Type error: {message}"),
+            ValueErr(message) => format!("Value error: {message}"),
             AttrDoesNotExist(type_name, name) => {
                 format!("Attribute does not exist on type {type_name}: {name}")
             }
+            AttrCannotBeSet(type_name, name) => {
+                format!("Attribute cannot be set on type {type_name}: {name}")
+            }
+            ItemCannotBeSet(type_name, index) => {
+                format!("Item cannot be set on type {type_name}: {index}")
+            }
+            IndexOutOfBounds(index, len) => {
+                format!("Index out of bounds: {index} (length is {len})")
+            }
+            DivisionByZero => "Division by zero".to_owned(),
+            IoErr(message) => format!("IO error: {message}"),
             NotCallable(obj) => format!("Object is not callable: {obj:?}"),
+            ExitCodeOutOfRange(code) => {
+                format!("Exit code must be in the range 0-255: {code}")
+            }
+            AssertionFailed(message) => format!("Assertion failed: {message}"),
+            Raised(obj) => format!("Uncaught error: {obj}"),
+            RecursionDepthExceeded(max_depth) => {
+                format!("Maximum recursion depth exceeded: {max_depth}")
+            }
+            StackOverflow(max_size) => {
+                format!("Stack overflow: more than {max_size} items on the value stack")
+            }
+            StepLimitExceeded(max_steps) => {
+                format!("Step limit exceeded: {max_steps}")
+            }
+            ScopeUnderflow(message) => format!("Scope underflow: {message}"),
             kind => format!("Unhandled runtime error: {:?}", kind),
         };
         eprintln!("    |\n\n  {}", message);
+        // The top level entry is always present, so only show the
+        // traceback when the error occurred inside a function.
+        if err.traceback.len() > 1 {
+            eprintln!("\n  Traceback (most recent call last):\n");
+            for line in err.format_traceback(self.current_file_name) {
+                eprintln!("    {line}");
+            }
+        }
     }
 }
+
+/// Get the padding that places an error marker under the specified
+/// location in the line. The line is sliced at the location's byte
+/// offset and the padding is sized by display width, so the marker
+/// lines up when the line contains multi-byte and/or wide chars. Tabs
+/// are kept as is so they line up too.
+pub fn err_marker_padding(line: &str, loc: Location) -> String {
+    let num_chars = loc.col.saturating_sub(1);
+    let prefix = match line.get(..loc.offset) {
+        Some(prefix) if prefix.chars().count() == num_chars => prefix,
+        // The offset is derived from the column for some locations, so
+        // it may not be accurate; fall back to the column.
+        _ => match line.char_indices().nth(num_chars) {
+            Some((offset, _)) => &line[..offset],
+            // Line doesn't match location (e.g., it's not available)
+            None => return " ".repeat(num_chars),
+        },
+    };
+    prefix
+        .chars()
+        .map(|c| match c {
+            '\t' => "\t",
+            c if is_wide_char(c) => "  ",
+            _ => " ",
+        })
+        .collect()
+}
+
+/// Check if char is displayed using two columns in a terminal. This
+/// covers the common wide ranges: CJK, Hangul, fullwidth forms, and
+/// emoji.
+fn is_wide_char(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1100..=0x115F
+            | 0x2E80..=0x303E
+            | 0x3041..=0x33FF
+            | 0x3400..=0x4DBF
+            | 0x4E00..=0x9FFF
+            | 0xA000..=0xA4CF
+            | 0xAC00..=0xD7A3
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60
+            | 0xFFE0..=0xFFE6
+            | 0x1F300..=0x1F64F
+            | 0x1F900..=0x1F9FF
+            | 0x20000..=0x3FFFD
+    )
+}
@@ -1,18 +1,294 @@
+use std::fmt;
+
 use crate::scanner::{ScanTokensResult, Scanner, TokenWithLocation};
-use crate::util::source_from_text;
+use crate::types::ObjectRef;
+use crate::util::{source_from_text, Location};
+use crate::vm::{RuntimeErr, VM};
 
+/// The locations of the tokens in an `Expr` are relative to the start
+/// of the format string, including its `$"` prefix. On the first line
+/// of the format string, a token's column is its offset from the `$`;
+/// on subsequent lines, columns are the same as in the source.
 #[derive(Clone, Debug, PartialEq)]
 pub enum FormatStrToken {
     Str(String),
-    Expr(Vec<TokenWithLocation>),
+    Expr(Vec<TokenWithLocation>, Option<FormatSpec>),
 }
 
+/// Length of the `$"` prefix of a format string.
+const PREFIX_LEN: usize = 2;
+
+/// Error positions are offsets from the `$` at the start of the format
+/// string.
 #[derive(Clone, Debug, PartialEq)]
 pub enum FormatStrErr {
     EmptyExpr(usize),
     UnmatchedOpeningBracket(usize),
     UnmatchedClosingBracket(usize),
     ScanErr(usize, usize),
+    InvalidSpec(String, usize),
+}
+
+// Format spec ---------------------------------------------------------
+
+/// A format spec follows an expression in a format string, separated
+/// from it by a colon (e.g., `$"{n:x}"`). The general form of a spec
+/// is:
+///
+/// ```text
+/// [[fill]align][0][width][.precision][base]
+/// ```
+///
+/// - `align` is `<` (left), `>` (right), or `^` (center), and `fill`
+///   is the char used to pad the value to `width` (a space by default)
+/// - `0` pads numbers with zeros after the sign (e.g., `{n:04}`)
+/// - `width` is the minimum width of the formatted value in chars
+/// - `precision` is the number of digits after the decimal point
+/// - `base` is `b` (binary), `o` (octal), `x` (lowercase hex), or `X`
+///   (uppercase hex)
+///
+/// Numbers are right-aligned by default and everything else is
+/// left-aligned.
+///
+/// Number base specs can only be applied to Ints, and precision and
+/// zero padding can only be applied to Ints and Floats. Applying one of
+/// these to any other type of object is a runtime type error.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FormatSpec {
+    pub fill: char,
+    pub align: Option<Align>,
+    pub zero: bool,
+    pub width: Option<usize>,
+    pub precision: Option<usize>,
+    pub base: Option<NumberBase>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NumberBase {
+    Binary,
+    Octal,
+    LowerHex,
+    UpperHex,
+}
+
+impl Align {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            '<' => Some(Self::Left),
+            '>' => Some(Self::Right),
+            '^' => Some(Self::Center),
+            _ => None,
+        }
+    }
+}
+
+impl FormatSpec {
+    /// Parse format spec, returning `None` if the spec is invalid.
+    pub fn parse(spec: &str) -> Option<Self> {
+        use NumberBase::*;
+
+        if spec.is_empty() {
+            return None;
+        }
+
+        let chars: Vec<char> = spec.chars().collect();
+        let mut i = 0;
+
+        let mut fill = ' ';
+        let mut align = None;
+        if let Some(a) = chars.get(1).and_then(|c| Align::from_char(*c)) {
+            fill = chars[0];
+            align = Some(a);
+            i = 2;
+        } else if let Some(a) = Align::from_char(chars[0]) {
+            align = Some(a);
+            i = 1;
+        }
+
+        let zero = chars.get(i) == Some(&'0');
+        if zero {
+            i += 1;
+        }
+
+        let width = Self::parse_number(&chars, &mut i);
+
+        let precision = if chars.get(i) == Some(&'.') {
+            i += 1;
+            Some(Self::parse_number(&chars, &mut i)?)
+        } else {
+            None
+        };
+
+        let base = match chars.get(i) {
+            Some('b') => Some(Binary),
+            Some('o') => Some(Octal),
+            Some('x') => Some(LowerHex),
+            Some('X') => Some(UpperHex),
+            Some(_) => return None,
+            None => None,
+        };
+        if base.is_some() {
+            i += 1;
+        }
+
+        if i < chars.len() {
+            return None;
+        }
+
+        Some(Self { fill, align, zero, width, precision, base })
+    }
+
+    /// Parse a run of digits starting at `i`, advancing `i` past them.
+    fn parse_number(chars: &[char], i: &mut usize) -> Option<usize> {
+        let start = *i;
+        while *i < chars.len() && chars[*i].is_ascii_digit() {
+            *i += 1;
+        }
+        chars[start..*i].iter().collect::<String>().parse().ok()
+    }
+
+    /// Format object according to spec. Objects that aren't formatted
+    /// as numbers are converted to strings via `$str`.
+    pub fn format(&self, obj: &ObjectRef, vm: &mut VM) -> Result<String, RuntimeErr> {
+        use NumberBase::*;
+
+        let (string, is_number) = if let Some(base) = self.base {
+            let value = obj.int_val().ok_or_else(|| self.type_err(obj, "Int"))?;
+            let string = match base {
+                Binary => format!("{value:b}"),
+                Octal => format!("{value:o}"),
+                LowerHex => format!("{value:x}"),
+                UpperHex => format!("{value:X}"),
+            };
+            (string, true)
+        } else if let Some(precision) = self.precision {
+            let value =
+                obj.as_f64().ok_or_else(|| self.type_err(obj, "Int or Float"))?;
+            (format!("{value:.precision$}"), true)
+        } else if obj.int_val().is_some() || obj.float_val().is_some() {
+            (obj.to_string(), true)
+        } else if self.zero {
+            return Err(self.type_err(obj, "Int or Float"));
+        } else {
+            (vm.display_string(obj)?, false)
+        };
+
+        Ok(self.pad(string, is_number))
+    }
+
+    fn type_err(&self, obj: &ObjectRef, expected: &str) -> RuntimeErr {
+        let type_name = obj.type_name();
+        RuntimeErr::new_type_err(format!(
+            "Format spec {self} can only be applied to {expected}; got {type_name}"
+        ))
+    }
+
+    /// Pad string to width, if the spec has a width.
+    fn pad(&self, string: String, is_number: bool) -> String {
+        let width = match self.width {
+            Some(width) => width,
+            None => return string,
+        };
+
+        let len = string.chars().count();
+        if len >= width {
+            return string;
+        }
+        let n = width - len;
+
+        if self.zero && self.align.is_none() {
+            // Zeros go after the sign, if there is one
+            let (sign, digits) = match string.strip_prefix('-') {
+                Some(digits) => ("-", digits),
+                None => ("", string.as_str()),
+            };
+            return format!("{sign}{}{digits}", "0".repeat(n));
+        }
+
+        let fill = if self.zero && self.fill == ' ' { '0' } else { self.fill };
+        let fill = |n: usize| fill.to_string().repeat(n);
+        let default_align = if is_number { Align::Right } else { Align::Left };
+        match self.align.unwrap_or(default_align) {
+            Align::Left => format!("{string}{}", fill(n)),
+            Align::Right => format!("{}{string}", fill(n)),
+            Align::Center => format!("{}{string}{}", fill(n / 2), fill(n - n / 2)),
+        }
+    }
+}
+
+impl fmt::Display for FormatSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use NumberBase::*;
+        if let Some(align) = self.align {
+            if self.fill != ' ' {
+                write!(f, "{}", self.fill)?;
+            }
+            let align = match align {
+                Align::Left => "<",
+                Align::Right => ">",
+                Align::Center => "^",
+            };
+            write!(f, "{align}")?;
+        }
+        if self.zero {
+            write!(f, "0")?;
+        }
+        if let Some(width) = self.width {
+            write!(f, "{width}")?;
+        }
+        if let Some(precision) = self.precision {
+            write!(f, ".{precision}")?;
+        }
+        let base = match self.base {
+            Some(Binary) => "b",
+            Some(Octal) => "o",
+            Some(LowerHex) => "x",
+            Some(UpperHex) => "X",
+            None => "",
+        };
+        write!(f, "{base}")
+    }
+}
+
+/// Split format string expression into the expression and its spec,
+/// if it has one. The spec follows the last colon that isn't inside
+/// a string or brackets. The char position of the spec in the expression
+/// is returned along with the spec.
+fn split_spec(expr: &str) -> (&str, Option<(usize, &str)>) {
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    let mut depth = 0;
+    let mut colon = None;
+    for (pos, (i, c)) in expr.char_indices().enumerate() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' => quote = Some(c),
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            ':' if depth == 0 => colon = Some((pos, i)),
+            _ => (),
+        }
+    }
+    match colon {
+        Some((pos, i)) => (&expr[..i], Some((pos + 1, &expr[i + 1..]))),
+        None => (expr, None),
+    }
 }
 
 pub fn scan_format_string(string: &str) -> Result<Vec<FormatStrToken>, FormatStrErr> {
@@ -20,33 +296,44 @@ pub fn scan_format_string(string: &str) -> Result<Vec<FormatStrToken>, FormatStr
     use FormatStrToken::*;
 
     let mut tokens: Vec<FormatStrToken> = Vec::new();
-    let mut chars = string.chars();
-    let mut peek_chars = string.chars();
-    let mut stack = vec![];
+    let mut chars = string.char_indices().peekable();
+
+    // Stack of opening brackets, each with its char position and byte
+    // index in the string
+    let mut stack: Vec<(usize, usize)> = vec![];
 
-    // Current position in string
+    // Line (starting from 0) and line start position of the outermost
+    // opening bracket
+    let mut expr_line = (0usize, 0usize);
+
+    // Current char position in string
     let mut pos = 0usize;
 
+    // Current line in string (starting from 0) and the char position
+    // where it starts
+    let mut line = 0usize;
+    let mut line_start = 0usize;
+
     // Accumulator for current string/non-expression part. This is
     // needed in order to skip over backslashes used to escape format
     // string brackets.
     let mut str = String::with_capacity(32);
 
-    peek_chars.next();
-
-    while let Some(c) = chars.next() {
-        let d = peek_chars.next();
+    while let Some((byte_pos, c)) = chars.next() {
+        let d = chars.peek().map(|(_, d)| *d);
         match (c, d) {
             ('\\', Some(d @ ('{' | '}'))) => {
                 // Escaped brackets are handled as literals
                 str.push(d);
                 chars.next();
-                peek_chars.next();
                 pos += 1;
             }
             ('{', _) => {
                 // Start of expression
-                stack.push(pos);
+                if stack.is_empty() {
+                    expr_line = (line, line_start);
+                }
+                stack.push((pos, byte_pos));
                 if str.len() > 0 {
                     tokens.push(Str(str.clone()));
                     str.clear();
@@ -54,22 +341,67 @@ pub fn scan_format_string(string: &str) -> Result<Vec<FormatStrToken>, FormatStr
             }
             ('}', _) => {
                 // End of expression
-                if let Some(i) = stack.pop() {
+                if let Some((i, byte_i)) = stack.pop() {
                     if stack.len() == 0 {
-                        let expr = string[i + 1..pos].trim();
+                        let (expr, spec) = split_spec(&string[byte_i + 1..byte_pos]);
+                        let (mut line, mut line_start) = expr_line;
+                        let mut expr_pos = i + 1;
+                        for c in expr.chars().take_while(|c| c.is_whitespace()) {
+                            expr_pos += 1;
+                            if c == '\n' {
+                                line += 1;
+                                line_start = expr_pos;
+                            }
+                        }
+                        let expr = expr.trim();
                         if expr.len() == 0 {
-                            return Err(EmptyExpr(i));
+                            return Err(EmptyExpr(PREFIX_LEN + i));
                         }
+                        let spec = match spec {
+                            Some((spec_pos, spec)) => match FormatSpec::parse(spec) {
+                                Some(spec) => Some(spec),
+                                None => {
+                                    // Skip the opening bracket
+                                    let spec_pos = PREFIX_LEN + i + 1 + spec_pos;
+                                    return Err(InvalidSpec(spec.to_owned(), spec_pos));
+                                }
+                            },
+                            None => None,
+                        };
                         let mut source = source_from_text(expr);
                         let scanner = Scanner::new(&mut source);
                         let result: ScanTokensResult = scanner.collect();
                         match result {
-                            Ok(expr_tokens) => tokens.push(Expr(expr_tokens)),
-                            Err(_) => return Err(ScanErr(i + 2, pos + 2)),
+                            Ok(expr_tokens) => {
+                                // Make token locations relative to the
+                                // start of the format string.
+                                let (line_offset, col_offset) = if line == 0 {
+                                    (0, expr_pos + 1)
+                                } else {
+                                    (line, expr_pos - line_start)
+                                };
+                                let expr_tokens = expr_tokens
+                                    .into_iter()
+                                    .map(|t| {
+                                        let start = offset_loc(
+                                            t.start,
+                                            line_offset,
+                                            col_offset,
+                                        );
+                                        let end =
+                                            offset_loc(t.end, line_offset, col_offset);
+                                        TokenWithLocation::new(t.token, start, end)
+                                    })
+                                    .collect();
+                                tokens.push(Expr(expr_tokens, spec))
+                            }
+                            Err(_) => {
+                                return Err(ScanErr(PREFIX_LEN + i, PREFIX_LEN + pos))
+                            }
                         }
                     }
                 } else {
-                    return Err(UnmatchedClosingBracket(pos + 2));
+                    return Err(UnmatchedClosingBracket(PREFIX_LEN + pos));
                 }
             }
             _ => {
@@ -79,10 +411,15 @@ pub fn scan_format_string(string: &str) -> Result<Vec<FormatStrToken>, FormatStr
             }
         }
         pos += 1;
+        if c == '\n' {
+            line += 1;
+            line_start = pos;
+        }
     }
 
     if stack.len() > 0 {
-        return Err(UnmatchedOpeningBracket(stack.pop().unwrap() + 2));
+        let (i, _) = stack.pop().unwrap();
+        return Err(UnmatchedOpeningBracket(PREFIX_LEN + i));
     }
 
     if str.len() > 0 {
@@ -91,3 +428,15 @@ pub fn scan_format_string(string: &str) -> Result<Vec<FormatStrToken>, FormatStr
 
     Ok(tokens)
 }
+
+/// Offset a location in a format string expression, which is relative
+/// to the start of the expression, by the position of the expression in
+/// the format string. Columns are only offset on the first line of the
+/// expression.
+fn offset_loc(loc: Location, line_offset: usize, col_offset: usize) -> Location {
+    if loc.line == 1 {
+        Location::new(loc.line + line_offset, loc.col + col_offset)
+    } else {
+        Location::new(loc.line + line_offset, loc.col)
+    }
+}
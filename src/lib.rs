@@ -6,6 +6,10 @@ pub mod exe;
 pub mod repl;
 pub mod run;
 
+pub use exe::eval_to_object;
+pub use result::{ExeErr, ExeErrKind};
+pub use types::{Object, ObjectExt, ObjectRef};
+
 mod ast;
 mod builtin_funcs;
 mod compiler;
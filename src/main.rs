@@ -40,6 +40,13 @@ fn main() -> ExitCode {
                 .takes_value(false)
                 .help("Disable REPL history?"),
         )
+        .arg(
+            Arg::new("no_echo")
+                .long("no-echo")
+                .required(false)
+                .takes_value(false)
+                .help("Don't show expression results in REPL?"),
+        )
         .arg(
             Arg::new("dis")
                 .short('i')
@@ -55,6 +62,14 @@ fn main() -> ExitCode {
                 .required(false)
                 .takes_value(false)
                 .help("Enable debug mode?"),
+        )
+        .arg(
+            Arg::new("profile")
+                .short('p')
+                .long("profile")
+                .required(false)
+                .takes_value(false)
+                .help("Show instruction counts and timings?"),
         );
 
     let matches = app.get_matches();
@@ -62,16 +77,18 @@ fn main() -> ExitCode {
     let code = matches.value_of("code");
     let history_path = matches.value_of("history_path");
     let save_repl_history = !matches.is_present("no_history");
+    let echo = !matches.is_present("no_echo");
     let dis = matches.is_present("dis");
     let debug = matches.is_present("debug");
+    let profile = matches.is_present("profile");
 
     let result = if let Some(code) = code {
-        run::run_text(code, dis, debug)
+        run::run_text(code, dis, debug, profile)
     } else if let Some(file_name) = file_name {
         if file_name == "-" {
-            run::run_stdin(dis, debug)
+            run::run_stdin(dis, debug, profile)
         } else {
-            run::run_file(file_name, dis, debug)
+            run::run_file(file_name, dis, debug, profile)
         }
     } else {
         match save_repl_history {
@@ -80,9 +97,9 @@ fn main() -> ExitCode {
                     Some(path) => PathBuf::from(path),
                     None => default_history_path(),
                 };
-                repl::run(Some(history_path.as_path()), dis, debug)
+                repl::run(Some(history_path.as_path()), echo, dis, debug)
             }
-            false => repl::run(None, dis, debug),
+            false => repl::run(None, echo, dis, debug),
         }
     };
 
@@ -6,7 +6,7 @@ use crate::ast;
 use crate::format::FormatStrToken;
 use crate::parser::result::{MaybeExprResult, StatementResult};
 use crate::scanner::{ScanErr, ScanTokenResult, Token, TokenWithLocation};
-use crate::util::Location;
+use crate::util::{BinaryOperator, Location};
 
 use super::precedence::{
     get_binary_precedence, get_unary_precedence, is_right_associative,
@@ -29,6 +29,15 @@ pub struct Parser<I: Iterator<Item = ScanTokenResult>> {
     token_stream: Peekable<I>,
     lookahead_queue: VecDeque<TokenWithLocation>,
     loop_level: u8,
+    // Labels of the enclosing labeled loops, innermost last.
+    loop_labels: Vec<String>,
+    // Number of temporary vars generated so far. This is used to give
+    // each temporary var a unique name.
+    temp_count: usize,
+    // Set when parsing the condition of an `if` or `loop`, where the
+    // `->` after the condition starts the block rather than an inline
+    // conditional.
+    in_cond_header: bool,
 }
 
 impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
@@ -38,6 +47,9 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
             token_stream: token_iter.peekable(),
             lookahead_queue: VecDeque::new(),
             loop_level: 0,
+            loop_labels: vec![],
+            temp_count: 0,
+            in_cond_header: false,
         }
     }
 
@@ -71,7 +83,7 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
     /// certain cases, multiple statements may be returned (e.g.,
     /// loops).
     fn statement(&mut self) -> StatementResult {
-        use Token::{Break, Continue, EndOfStatement, Jump, Label};
+        use Token::{Break, Continue, EndOfStatement, Jump, Label, Raise};
         let token = self.expect_next_token()?;
         let start = token.start;
         let statement = match token.token {
@@ -79,9 +91,15 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
             Label(name) => self.label(name, start)?,
             Break => self.break_(start)?,
             Continue => self.continue_(start, token.end)?,
+            Raise => self.raise(start)?,
             _ => {
                 self.lookahead_queue.push_front(token);
                 let expr = self.expr(0)?;
+                let expr = if self.peek_token_is(&Token::Comma)? {
+                    self.multi_assign(expr)?
+                } else {
+                    expr
+                };
                 let end = expr.end;
                 ast::Statement::new_expr(expr, start, end)
             }
@@ -90,6 +108,39 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
         Ok(statement)
     }
 
+    /// Handle assignment to multiple names, like `a, b = t`. The first
+    /// name has already been parsed. Each subsequent name is parsed as
+    /// an expression so that the last one will be parsed along with the
+    /// value as a regular assignment.
+    fn multi_assign(&mut self, first: ast::Expr) -> ExprResult {
+        let start = first.start;
+        let mut names = vec![];
+        let mut target = first;
+        loop {
+            match target.is_ident() {
+                Some(name) => names.push(name),
+                None => return Err(self.err(ParseErrKind::ExpectedIdent(target.start))),
+            }
+            self.expect_token(&Token::Comma)?;
+            let expr = self.expr(0)?;
+            if let ast::ExprKind::BinaryOp(name_expr, BinaryOperator::Assign, value) =
+                expr.kind
+            {
+                match name_expr.is_ident() {
+                    Some(name) => names.push(name),
+                    None => {
+                        return Err(
+                            self.err(ParseErrKind::ExpectedIdent(name_expr.start))
+                        )
+                    }
+                }
+                let end = value.end;
+                return Ok(ast::Expr::new_multi_assign(names, *value, start, end));
+            }
+            target = expr;
+        }
+    }
+
     /// Handle jump statement.
     fn jump(&mut self, start: Location) -> StatementResult {
         if let Some(ident_token) = self.next_token()? {
@@ -103,8 +154,15 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
         }
     }
 
-    /// Handle label statement.
+    /// Handle label statement. A label on a loop (`outer: loop -> ...`)
+    /// names the loop so that `break` and `continue` in nested loops
+    /// can target it.
     fn label(&mut self, name: String, start: Location) -> StatementResult {
+        if self.next_token_is(&Token::Loop)? {
+            let expr = self.loop_(Some(name), start)?;
+            let end = expr.end;
+            return Ok(ast::Statement::new_expr(expr, start, end));
+        }
         let expr = match self.peek_token()? {
             Some(TokenWithLocation { token: Token::EndOfStatement, .. }) | None => {
                 ast::Expr::new_nil(start, start)
@@ -116,10 +174,22 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
     }
 
     /// Handle `break`, ensuring it's contained in a `loop`.
+    ///
+    /// `break <label>` exits the enclosing loop with that label. Since
+    /// `break <expr>` is also allowed, an ident is only treated as a
+    /// label when it names an enclosing loop.
     fn break_(&mut self, start: Location) -> StatementResult {
         if self.loop_level == 0 {
             return Err(self.err(ParseErrKind::UnexpectedBreak(start)));
         }
+        let labels = self.loop_labels.clone();
+        let label = match self.next_token_if(|t| match t {
+            Token::Ident(name) => labels.contains(name),
+            _ => false,
+        })? {
+            Some(TokenWithLocation { token: Token::Ident(name), .. }) => Some(name),
+            _ => None,
+        };
         let expr = match self.peek_token()? {
             Some(TokenWithLocation { token: Token::EndOfStatement, .. }) | None => {
                 ast::Expr::new_nil(start, start)
@@ -127,26 +197,46 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
             _ => self.expr(0)?,
         };
         let end = expr.end;
-        Ok(ast::Statement::new_break(expr, start, end))
+        Ok(ast::Statement::new_break(label, expr, start, end))
     }
 
     /// Handle `continue`, ensuring it's contained in a `loop`.
+    /// `continue <label>` continues the enclosing loop with that label.
     fn continue_(&mut self, start: Location, end: Location) -> StatementResult {
         if self.loop_level == 0 {
             return Err(self.err(ParseErrKind::UnexpectedContinue(start)));
         }
-        Ok(ast::Statement::new_continue(start, end))
+        match self.next_token_if(|t| matches!(t, Token::Ident(_)))? {
+            Some(TokenWithLocation { token: Token::Ident(name), end, .. }) => {
+                Ok(ast::Statement::new_continue(Some(name), start, end))
+            }
+            _ => Ok(ast::Statement::new_continue(None, start, end)),
+        }
+    }
+
+    /// Handle `raise <expr>`.
+    fn raise(&mut self, start: Location) -> StatementResult {
+        if let Some(TokenWithLocation { token: Token::EndOfStatement, .. }) | None =
+            self.peek_token()?
+        {
+            return Err(self.err(ParseErrKind::ExpectedExpr(self.next_loc())));
+        }
+        let expr = self.expr(0)?;
+        let end = expr.end;
+        Ok(ast::Statement::new_raise(expr, start, end))
     }
 
     /// Get the next expression, possibly recurring to handle nested
     /// expressions, unary & binary expressions, blocks, functions, etc.
     fn expr(&mut self, prec: u8) -> ExprResult {
         use Token::*;
+        let in_cond_header = std::mem::take(&mut self.in_cond_header);
         let token = self.expect_next_token()?;
         let start = token.start;
         let end = token.end; // Default end location for simple expressions
         let expr = match token.token {
-            LParen => self.parenthesized(start)?,
+            LParen => self.parenthesized(start, true)?,
+            LBracket => self.list(start)?,
             Nil => ast::Expr::new_nil(start, end),
             True => ast::Expr::new_true(start, end),
             False => ast::Expr::new_false(start, end),
@@ -161,7 +251,8 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
                 ast::Expr::new_block(block, start, end)
             }
             If => self.conditional(start)?,
-            Loop => self.loop_(start)?,
+            Loop => self.loop_(None, start)?,
+            Try => self.try_(start)?,
             Ident(name) => {
                 ast::Expr::new_ident(ast::Ident::new_ident(name), start, end)
             }
@@ -180,22 +271,40 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
         // expression will be parsed and returned. Otherwise, the
         // expression will be returned as is.
         let expr = self.maybe_binary_expr(prec, expr)?;
+        // If the expression is followed by an inline block, it's the
+        // condition of an inline conditional.
+        if prec == 0 && !in_cond_header && self.peek_token_is(&InlineScopeStart)? {
+            return self.inline_conditional(expr);
+        }
         Ok(expr)
     }
 
+    /// Parse the condition of an `if` or `loop`.
+    fn cond_header(&mut self) -> ExprResult {
+        self.in_cond_header = true;
+        self.expr(0)
+    }
+
     /// Handle parenthesized expressions. Cases:
     ///
     /// 1. A grouped expression such as `(1)` or `(1 + 2)`.
     /// 2. A tuple such as `(1,)` or `(1, 2)`.
     /// 3. One of the above followed by `->`, indicating that the
-    ///    parenthesized expression is a function parameter list.
-    fn parenthesized(&mut self, start: Location) -> ExprResult {
+    ///    parenthesized expression is a function parameter list. This
+    ///    only applies if `allow_func` is set and the items can be
+    ///    params. Otherwise, the parenthesized expression is the
+    ///    condition of an inline conditional, like `(x > 0) -> ...`.
+    fn parenthesized(&mut self, start: Location, allow_func: bool) -> ExprResult {
         use Token::{Comma, RParen};
+        // Location of a `...` rest marker following the last item. This
+        // is only valid in function params.
+        let mut rest_loc = None;
         let expr = if self.next_token_is(&RParen)? {
             // () is parsed as a tuple with 0 items
             ast::Expr::new_tuple(vec![], start, self.loc())
         } else {
             let first_item = self.expr(0)?;
+            self.rest_marker(&mut rest_loc)?;
             if self.peek_token_is(&Comma)? {
                 let mut items = vec![];
                 items.push(first_item);
@@ -207,7 +316,11 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
                     if self.next_token_is(&RParen)? {
                         break;
                     }
+                    if let Some(loc) = rest_loc {
+                        return Err(self.err(ParseErrKind::MisplacedRestParam(loc)));
+                    }
                     let item = self.expr(0)?;
+                    self.rest_marker(&mut rest_loc)?;
                     items.push(item);
                 }
                 ast::Expr::new_tuple(items, start, self.loc())
@@ -216,13 +329,42 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
                 first_item
             }
         };
-        if self.peek_token_is_scope_start()? {
-            self.func(expr, start)
+        if allow_func && is_param_list(&expr) && self.peek_token_is_scope_start()? {
+            self.func(expr, rest_loc.is_some(), start)
+        } else if let Some(loc) = rest_loc {
+            Err(self.err(ParseErrKind::MisplacedRestParam(loc)))
         } else {
             Ok(expr)
         }
     }
 
+    /// Check for a `...` rest marker after an item in parens and, if
+    /// one is present, save its location.
+    fn rest_marker(&mut self, rest_loc: &mut Option<Location>) -> Result<(), ParseErr> {
+        if self.next_token_is(&Token::Ellipsis)? {
+            *rest_loc = Some(self.loc());
+        }
+        Ok(())
+    }
+
+    /// Handle list literals such as `[]`, `[1]`, or `[1, 2,]`.
+    fn list(&mut self, start: Location) -> ExprResult {
+        use Token::{Comma, RBracket};
+        let mut items = vec![];
+        loop {
+            if self.next_token_is(&RBracket)? {
+                break;
+            }
+            let item = self.expr(0)?;
+            items.push(item);
+            if self.next_token_is(&RBracket)? {
+                break;
+            }
+            self.expect_token(&Comma)?;
+        }
+        Ok(ast::Expr::new_list(items, start, self.loc()))
+    }
+
     /// Handle format strings (AKA $ strings).
     fn format_string(
         &mut self,
@@ -238,20 +380,42 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
                     //       matter for string parts.
                     items.push(ast::Expr::new_string(value, start, end));
                 }
-                FormatStrToken::Expr(tokens) => {
-                    let mut adjusted_tokens = vec![];
-                    for t in tokens.iter() {
-                        let (s, e) = (t.start, t.end);
-                        adjusted_tokens.push(TokenWithLocation::new(
-                            t.token.clone(),
-                            Location::new(start.line + s.line, start.col + s.col),
-                            Location::new(end.line + s.line, end.col + e.col),
-                        ));
-                    }
+                FormatStrToken::Expr(tokens, spec) => {
+                    // Token locations are relative to the start of the
+                    // format string; make them absolute.
+                    let adjust = |loc: Location| {
+                        if loc.line == 1 {
+                            start.advance(loc.col)
+                        } else {
+                            Location::new(start.line + loc.line - 1, loc.col)
+                        }
+                    };
+                    let adjusted_tokens = tokens
+                        .into_iter()
+                        .map(|t| {
+                            TokenWithLocation::new(
+                                t.token,
+                                adjust(t.start),
+                                adjust(t.end),
+                            )
+                        })
+                        .collect();
                     let program = parse_tokens(adjusted_tokens)?;
                     for statement in program.statements {
                         if let ast::StatementKind::Expr(expr) = statement.kind {
-                            items.push(expr)
+                            let item = match &spec {
+                                Some(spec) => {
+                                    let (start, end) = (expr.start, expr.end);
+                                    ast::Expr::new_format_value(
+                                        expr,
+                                        spec.clone(),
+                                        start,
+                                        end,
+                                    )
+                                }
+                                None => expr,
+                            };
+                            items.push(item)
                         } else {
                             return Err(
                                 self.err(ParseErrKind::ExpectedExpr(statement.start))
@@ -293,14 +457,14 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
         use Token::{Else, EndOfStatement, If};
         let mut branches = vec![];
         let mut end;
-        let cond = self.expr(0)?;
+        let cond = self.cond_header()?;
         let block = self.block()?;
         end = block.end;
         branches.push((cond, block));
         loop {
             match self.next_tokens_are(vec![&EndOfStatement, &Else, &If])? {
                 true => {
-                    let cond = self.expr(0)?;
+                    let cond = self.cond_header()?;
                     let block = self.block()?;
                     end = block.end;
                     branches.push((cond, block))
@@ -319,22 +483,66 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
         Ok(ast::Expr::new_conditional(branches, default, start, end))
     }
 
+    /// Handle inline conditionals like `<cond> -> <expr> else -> <expr>`.
+    /// Unlike `if`, the `else` branch is required.
+    fn inline_conditional(&mut self, cond: ast::Expr) -> ExprResult {
+        use Token::{Else, EndOfStatement};
+        let start = cond.start;
+        let block = self.block()?;
+        if !self.next_tokens_are(vec![&EndOfStatement, &Else])? {
+            return Err(self.err(ParseErrKind::ExpectedToken(self.next_loc(), Else)));
+        }
+        let default = self.block()?;
+        let end = default.end;
+        Ok(ast::Expr::new_conditional(vec![(cond, block)], Some(default), start, end))
+    }
+
     /// Handle `loop -> ...` and `loop <cond> -> ...` (`while` loops).
     /// TODO: Handle `for` loops.
-    fn loop_(&mut self, start: Location) -> ExprResult {
+    fn loop_(&mut self, label: Option<String>, start: Location) -> ExprResult {
         self.loop_level += 1;
         let cond = match self.peek_token_is_scope_start()? {
             true => ast::Expr::new_true(self.next_loc(), self.next_loc()),
-            false => self.expr(0)?,
+            false => self.cond_header()?,
         };
-        let block = self.block()?;
+        if let Some(label) = &label {
+            self.loop_labels.push(label.clone());
+        }
+        let block = self.block();
+        if label.is_some() {
+            self.loop_labels.pop();
+        }
+        let block = block?;
         let end = block.end;
         self.loop_level -= 1;
-        Ok(ast::Expr::new_loop(cond, block, start, end))
+        Ok(ast::Expr::new_loop(label, cond, block, start, end))
+    }
+
+    /// Handle `try -> ... catch <name> -> ...`. The name is optional;
+    /// if it's present, the error is bound to it in the catch block.
+    fn try_(&mut self, start: Location) -> ExprResult {
+        use Token::{Catch, EndOfStatement};
+        let block = self.block()?;
+        if !self.next_tokens_are(vec![&EndOfStatement, &Catch])? {
+            return Err(self.err(ParseErrKind::ExpectedToken(self.next_loc(), Catch)));
+        }
+        let name = match self.next_token_if(|t| matches!(t, Token::Ident(_)))? {
+            Some(TokenWithLocation { token: Token::Ident(name), .. }) => Some(name),
+            _ => None,
+        };
+        let catch_block = self.block()?;
+        let end = catch_block.end;
+        Ok(ast::Expr::new_try(block, name, catch_block, start, end))
     }
 
-    /// Handle function definition.
-    fn func(&mut self, params_expr: ast::Expr, start: Location) -> ExprResult {
+    /// Handle function definition. If `has_rest` is set, the last param
+    /// collects any extra args.
+    fn func(
+        &mut self,
+        params_expr: ast::Expr,
+        has_rest: bool,
+        start: Location,
+    ) -> ExprResult {
         let params_opt = match params_expr.kind {
             ast::ExprKind::Tuple(items) => Some(items),
             _ => match params_expr.is_ellipsis() {
@@ -342,17 +550,51 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
                 false => Some(vec![params_expr]),
             },
         };
-        let params = if let Some(items) = params_opt {
-            let mut params = vec![];
-            // Ensure all items are identifiers
-            for item in items.iter() {
+        let mut defaults = vec![];
+        let mut rest = None;
+        let params = if let Some(mut items) = params_opt {
+            // The rest param must be a plain identifier. It can't have
+            // a default since it's always bound (to an empty tuple when
+            // there are no extra args).
+            if has_rest {
+                let item = items.pop().unwrap();
                 match item.is_ident() {
-                    Some(name) => params.push(name),
+                    Some(name) => rest = Some(name),
                     None => {
                         return Err(self.err(ParseErrKind::ExpectedIdent(item.start)))
                     }
                 }
             }
+            let mut params = vec![];
+            // Ensure all items are identifiers or `name = default`
+            // assignments. Once a param has a default, all the params
+            // after it must have defaults too.
+            for item in items {
+                let item_start = item.start;
+                if let Some(name) = item.is_ident() {
+                    if !defaults.is_empty() {
+                        return Err(self.err(ParseErrKind::ExpectedDefault(item_start)));
+                    }
+                    params.push(name);
+                } else if let ast::ExprKind::BinaryOp(
+                    name_expr,
+                    BinaryOperator::Assign,
+                    default,
+                ) = item.kind
+                {
+                    match name_expr.is_ident() {
+                        Some(name) => params.push(name),
+                        None => {
+                            return Err(
+                                self.err(ParseErrKind::ExpectedIdent(item_start))
+                            )
+                        }
+                    }
+                    defaults.push(*default);
+                } else {
+                    return Err(self.err(ParseErrKind::ExpectedIdent(item_start)));
+                }
+            }
             Some(params)
         } else {
             None
@@ -363,7 +605,7 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
         //       is assigned to a var. Since this is done at compile
         //       time, the function will retain its initial name even
         //       if reassigned.
-        Ok(ast::Expr::new_func(params, block, start, def_end))
+        Ok(ast::Expr::new_func(params, defaults, rest, block, start, def_end))
     }
 
     /// Handle function call.
@@ -383,7 +625,7 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
     /// `f()()()` syntax.
     fn maybe_call(&mut self, expr: ast::Expr) -> MaybeExprResult {
         if self.next_token_is(&Token::LParen)? {
-            let args = self.parenthesized(expr.start)?;
+            let args = self.parenthesized(expr.start, false)?;
             let call_expr = self.call(expr, args)?;
             self.maybe_call(call_expr)
         } else {
@@ -391,6 +633,34 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
         }
     }
 
+    /// Handle the name (or index) on the right hand side of a `.`.
+    fn attr_name(&mut self) -> ExprResult {
+        use Token::*;
+        let token = self.expect_next_token()?;
+        let (start, end) = (token.start, token.end);
+        let expr = match token.token {
+            Ident(name) => {
+                ast::Expr::new_ident(ast::Ident::new_ident(name), start, end)
+            }
+            SpecialIdent(name) => {
+                ast::Expr::new_ident(ast::Ident::new_special_ident(name), start, end)
+            }
+            TypeIdent(name) => {
+                ast::Expr::new_ident(ast::Ident::new_type_ident(name), start, end)
+            }
+            Int(value) => ast::Expr::new_int(value, start, end),
+            // Negative index, e.g. `a.-1`
+            Minus => match self.next_token_if(|t| matches!(t, Int(_)))? {
+                Some(TokenWithLocation { token: Int(value), end, .. }) => {
+                    ast::Expr::new_int(-value, start, end)
+                }
+                _ => return Err(self.err(ParseErrKind::UnexpectedToken(token))),
+            },
+            _ => return Err(self.err(ParseErrKind::UnexpectedToken(token))),
+        };
+        Ok(expr)
+    }
+
     /// The current token should represent a unary operator and should
     /// be followed by an expression.
     fn expect_unary_expr(&mut self, op_token: &TokenWithLocation) -> ExprResult {
@@ -413,6 +683,14 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
     fn maybe_binary_expr(&mut self, prec: u8, mut lhs: ast::Expr) -> ExprResult {
         let start = lhs.start;
         loop {
+            if self.next_is_not_in(prec)? {
+                // `a !in b` and `a not in b` are parsed as `!(a in b)`
+                let rhs = self.expr(get_binary_precedence(&Token::In))?;
+                let end = rhs.end;
+                let in_expr = ast::Expr::new_binary_op(lhs, "in", rhs, start, end);
+                lhs = ast::Expr::new_unary_op("!", in_expr, start, end);
+                continue;
+            }
             let next = self.next_infix_token(prec)?;
             if let Some((infix_token, mut infix_prec)) = next {
                 if !self.has_tokens()? {
@@ -426,7 +704,23 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
                     infix_prec -= 1;
                 }
                 let op = infix_token.as_str();
+                if infix_token.token == Token::Dot {
+                    // The attribute name is parsed on its own so that
+                    // `a.b(c)` is parsed as a call of `a.b` rather than
+                    // an attribute lookup of `b(c)`.
+                    let rhs = self.attr_name()?;
+                    let end = rhs.end;
+                    lhs = ast::Expr::new_binary_op(lhs, op, rhs, start, end);
+                    lhs = self.maybe_call(lhs)?.1;
+                    continue;
+                }
                 let rhs = self.expr(infix_prec)?;
+                if is_chainable_comparison(&infix_token.token)
+                    && self.peek_token_if(is_chainable_comparison)?.is_some()
+                {
+                    lhs = self.comparison_chain(lhs, op, rhs)?;
+                    continue;
+                }
                 let end = rhs.end;
                 lhs = ast::Expr::new_binary_op(lhs, op, rhs, start, end);
             } else {
@@ -435,6 +729,47 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
         }
     }
 
+    /// Handle chained comparisons like `a < b < c`, which are lowered
+    /// to `a < b && b < c`. Each middle operand is assigned to a temp
+    /// var so that it's only evaluated once. The lowered comparisons
+    /// are wrapped in a block so that the temp vars are removed when
+    /// the chain has been evaluated. `lhs op rhs` is the first
+    /// comparison in the chain.
+    fn comparison_chain(
+        &mut self,
+        lhs: ast::Expr,
+        op: &str,
+        rhs: ast::Expr,
+    ) -> ExprResult {
+        let start = lhs.start;
+        let mut comparisons = vec![];
+        let (mut a, mut op, mut b) = (lhs, op.to_owned(), rhs);
+        while let Some(next_token) = self.next_token_if(is_chainable_comparison)? {
+            let next_op = next_token.as_str().to_owned();
+            let next_b = self.expr(get_binary_precedence(&next_token.token))?;
+            // b = $tmp.N = b, with the next comparison using $tmp.N as
+            // its left operand
+            let temp = ast::Ident::new_temp_ident(self.temp_count);
+            self.temp_count += 1;
+            let (b_start, b_end) = (b.start, b.end);
+            let temp_expr = ast::Expr::new_ident(temp, b_start, b_end);
+            let b_assign =
+                ast::Expr::new_binary_op(temp_expr.clone(), "=", b, b_start, b_end);
+            comparisons.push(ast::Expr::new_binary_op(a, &op, b_assign, start, b_end));
+            (a, op, b) = (temp_expr, next_op, next_b);
+        }
+        let end = b.end;
+        comparisons.push(ast::Expr::new_binary_op(a, &op, b, start, end));
+        let mut comparisons = comparisons.into_iter();
+        let mut expr = comparisons.next().unwrap();
+        for comparison in comparisons {
+            expr = ast::Expr::new_binary_op(expr, "&&", comparison, start, end);
+        }
+        let statement = ast::Statement::new_expr(expr, start, end);
+        let block = ast::StatementBlock::new(vec![statement], start, end);
+        Ok(ast::Expr::new_block(block, start, end))
+    }
+
     // Errors ----------------------------------------------------------
 
     /// Make creating errors a little less tedious.
@@ -547,6 +882,18 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
         Ok(None)
     }
 
+    /// Consume the next two tokens and return true *if* they're `!in`
+    /// or `not in` and `in` has a higher precedence than the current
+    /// precedence level.
+    fn next_is_not_in(&mut self, current_prec: u8) -> BoolResult {
+        if get_binary_precedence(&Token::In) <= current_prec {
+            return Ok(false);
+        }
+        let not = Token::Ident("not".to_owned());
+        Ok(self.next_tokens_are(vec![&Token::Bang, &Token::In])?
+            || self.next_tokens_are(vec![&not, &Token::In])?)
+    }
+
     /// Return the next token without consuming it. If no tokens are
     /// left, return `None`.
     fn peek_token(&mut self) -> PeekTokenResult {
@@ -606,10 +953,40 @@ impl<I: Iterator<Item = ScanTokenResult>> Parser<I> {
         if let Some(t) = &self.current_token {
             match t.token {
                 Token::EndOfStatement => Location::new(t.end.line + 1, 1),
-                _ => Location::new(t.end.line, t.end.col + 1),
+                _ => t.end.advance(1),
             }
         } else {
             Location::new(0, 0)
         }
     }
 }
+
+/// Comparison operators that can be chained, like `a < b < c`.
+fn is_chainable_comparison(token: &Token) -> bool {
+    use Token::*;
+    matches!(
+        token,
+        LessThan
+            | LessThanOrEqual
+            | GreaterThan
+            | GreaterThanOrEqual
+            | EqualEqual
+            | NotEqual
+    )
+}
+
+/// Can the parenthesized expression be a function param list? Each item
+/// has to be a name or a `name = default` assignment. A lone `...`
+/// means the function takes any args.
+fn is_param_list(expr: &ast::Expr) -> bool {
+    let is_param = |item: &ast::Expr| match &item.kind {
+        ast::ExprKind::BinaryOp(name_expr, BinaryOperator::Assign, _) => {
+            name_expr.is_ident().is_some()
+        }
+        _ => item.is_ident().is_some(),
+    };
+    match &expr.kind {
+        ast::ExprKind::Tuple(items) => items.iter().all(is_param),
+        _ => expr.is_ellipsis() || is_param(expr),
+    }
+}
@@ -13,8 +13,8 @@ pub fn get_binary_precedence(token: &Token) -> u8 {
 /// Return true if the token represents a right-associate operator.
 pub fn is_right_associative(token: &Token) -> bool {
     match token {
-        Token::Caret => true, // a ^ b (exponentiation)
-        Token::Equal => true, // a = b = c (assignment)
+        Token::DoubleStar => true, // a ** b (exponentiation)
+        Token::Equal => true,      // a = b = c (assignment)
         _ => false,
     }
 }
@@ -45,22 +45,27 @@ pub fn get_operator_precedence(token: &Token) -> (u8, u8) {
         | LessThan                       // a < b
         | LessThanOrEqual                // a <= b
         | GreaterThan                    // a > b
+        | In                             // a in b
         | GreaterThanOrEqual => (0, 5),  // a >= b
+
+        | Pipe               => (0, 6),  // a | b       (bitwise or)
+        | Caret              => (0, 7),  // a ^ b       (bitwise xor)
+        | Ampersand          => (0, 8),  // a & b       (bitwise and)
         
         | Plus                           // +a, a + b
-        | Minus              => (9, 6),  // -a, a - b
+        | Minus              => (12, 9), // -a, a - b
         
         | Star                           // a * b
         | Slash                          // a / b       (floating point div)
         | DoubleSlash                    // a // b      (floor div)
-        | Percent            => (0, 7),  // a % b
+        | Percent            => (0, 10), // a % b
        
-        | Caret              => (0, 8),  // a ^ b       (exponentiation)
+        | DoubleStar         => (0, 11), // a ** b      (exponentiation)
 
         | BangBang                       // !!a         (as bool)
-        | Bang               => (9, 0),  // !a          (logical not)
+        | Bang               => (12, 0), // !a          (logical not)
 
-        | Dot                => (0, 10), // x.y
+        | Dot                => (0, 13), // x.y
         
         _                    => (0, 0),  // not an operator
     }
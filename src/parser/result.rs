@@ -36,6 +36,10 @@ pub enum ParseErrKind {
     ExpectedBlock(Location),
     ExpectedExpr(Location),
     ExpectedIdent(Location),
+    /// A param without a default follows a param with a default
+    ExpectedDefault(Location),
+    /// A `...` rest marker that isn't on the last function param
+    MisplacedRestParam(Location),
     ExpectedOperand(Location),
     ExpectedToken(Location, Token),
 
@@ -8,13 +8,20 @@ use crate::exe::Executor;
 use crate::parser::ParseErrKind;
 use crate::result::{ExeErr, ExeErrKind, ExitResult};
 use crate::scanner::ScanErrKind;
+use crate::types::ObjectRef;
 use crate::vm::{Inst, VMState, VM};
 
 /// Run FeInt REPL until user exits.
-pub fn run(history_path: Option<&Path>, dis: bool, debug: bool) -> ExitResult {
+pub fn run(
+    history_path: Option<&Path>,
+    echo: bool,
+    dis: bool,
+    debug: bool,
+) -> ExitResult {
     let mut vm = VM::default();
     let executor = Executor::new(&mut vm, true, dis, debug);
     let mut repl = Repl::new(history_path, executor);
+    repl.set_echo(echo);
     repl.run()
 }
 
@@ -22,6 +29,13 @@ pub(crate) struct Repl<'a> {
     reader: rustyline::Editor<()>,
     history_path: Option<&'a Path>,
     executor: Executor<'a>,
+    /// When set, the result of each expression entered is shown.
+    echo: bool,
+    /// The text that was shown for the result of the last entry.
+    pub(crate) last_echo: Option<String>,
+    /// Lines of an incomplete entry (e.g., a block or an unterminated
+    /// string) that are waiting for more input.
+    pending: Option<String>,
 }
 
 impl<'a> Repl<'a> {
@@ -30,7 +44,14 @@ impl<'a> Repl<'a> {
             rustyline::Editor::<()>::new().expect("Could initialize readline");
         reader.set_indent_size(4);
         reader.set_tab_stop(4);
-        Repl { reader, history_path, executor }
+        Repl {
+            reader,
+            history_path,
+            executor,
+            echo: true,
+            last_echo: None,
+            pending: None,
+        }
     }
 
     fn run(&mut self) -> ExitResult {
@@ -40,16 +61,13 @@ impl<'a> Repl<'a> {
         println!("Type .exit or .quit to exit");
 
         loop {
-            match self.read_line("→ ", true) {
-                Ok(None) => {
-                    // Blank or all-whitespace line.
-                    ()
-                }
-                Ok(Some(input)) => {
+            let prompt = if self.pending.is_some() { "+ " } else { "→ " };
+            match self.reader.readline(prompt) {
+                Ok(input) => {
                     // Evaluate the input. If eval returns a result of
                     // any kind (ok or err), exit the loop and shut down
                     // the REPL.
-                    match self.eval(input.as_str(), false) {
+                    match self.eval_line(input.as_str()) {
                         Some(result) => {
                             self.executor.vm.halt();
                             break result;
@@ -72,27 +90,45 @@ impl<'a> Repl<'a> {
         }
     }
 
-    /// Get a line of input from the user. If the line comprises only
-    /// whitespace *and* ``trim_blank`` is set, the line will be trimmed
-    /// and ``None`` will be returned.
-    fn read_line(
-        &mut self,
-        prompt: &str,
-        trim_blank: bool,
-    ) -> Result<Option<String>, ReadlineError> {
-        match self.reader.readline(prompt) {
-            Ok(input) if trim_blank && input.trim().len() == 0 => Ok(None),
-            Ok(input) => Ok(Some(input)),
-            Err(err) => Err(err),
+    /// Evaluate a line of input. If the line starts an entry that's
+    /// incomplete, like a block or an unterminated string, it's held
+    /// until more lines are entered.
+    ///
+    /// While an entry is pending, indented lines are added to it
+    /// without evaluating it, since they're probably part of a block.
+    /// Other lines are added and then the entry is evaluated again. A
+    /// blank line ends the entry.
+    ///
+    /// Returns None to indicate to the main loop to continue reading
+    /// and evaluating input. Returns some result to indicate to the
+    /// main loop to exit.
+    pub(crate) fn eval_line(&mut self, line: &str) -> Option<ExitResult> {
+        self.add_history_entry(line);
+        match self.pending.take() {
+            Some(text) if line.trim().is_empty() => self.eval(text.as_str(), true),
+            Some(mut text) => {
+                text.push('\n');
+                text.push_str(line);
+                if line.starts_with(char::is_whitespace) {
+                    self.pending = Some(text);
+                    None
+                } else {
+                    self.eval(text.as_str(), false)
+                }
+            }
+            None if line.trim().is_empty() => None,
+            None => self.eval(line, false),
         }
     }
 
-    /// Evaluate text. Returns None to indicate to the main loop to
-    /// continue reading and evaluating input. Returns some result to
-    /// indicate to the main loop to exit.
+    /// Evaluate text. If `no_continue` isn't set and the text is
+    /// incomplete, it's held as the pending entry so that more lines
+    /// can be added to it (see `eval_line`).
+    ///
+    /// Returns None to indicate to the main loop to continue reading
+    /// and evaluating input. Returns some result to indicate to the
+    /// main loop to exit.
     pub(crate) fn eval(&mut self, text: &str, no_continue: bool) -> Option<ExitResult> {
-        self.add_history_entry(text);
-
         let result = match text.trim() {
             "?" | ".help" => {
                 eprintln!("{:=>72}", "");
@@ -102,6 +138,10 @@ impl<'a> Repl<'a> {
                 eprintln!(".exit  -> exit");
                 eprintln!(".stack -> show VM stack (top first)");
                 eprintln!(".constants -> show constants");
+                eprintln!(".vars  -> show vars in all scopes");
+                eprintln!(".echo  -> toggle showing expression results");
+                eprintln!(".dis   -> disassemble last entry");
+                eprintln!(".dis <code> -> disassemble code without running it");
                 eprintln!(".emacs -> switch to emacs-style input (default)");
                 eprintln!(".vi    -> switch to vi-style input");
                 eprintln!("{:=>72}", "");
@@ -124,6 +164,19 @@ impl<'a> Repl<'a> {
                 self.executor.vm.display_vars();
                 return None;
             }
+            ".echo" => {
+                self.echo = !self.echo;
+                eprintln!("Echo {}", if self.echo { "on" } else { "off" });
+                return None;
+            }
+            text if text == ".dis" || text.starts_with(".dis ") => {
+                if let Some(lines) = self.dis_lines(&text[4..]) {
+                    for line in lines {
+                        eprintln!("{line}");
+                    }
+                }
+                return None;
+            }
             ".emacs" => {
                 self.reader.set_edit_mode(rustyline::config::EditMode::Emacs);
                 return None;
@@ -136,15 +189,16 @@ impl<'a> Repl<'a> {
         };
 
         if let Ok(vm_state) = result {
+            self.last_echo = None;
             // Assign _ to value at top of stack
             let var = "_";
             let mut chunk = vec![Inst::DeclareVar(var.to_owned())];
             match self.executor.vm.peek_obj() {
                 Ok(Some(val)) => {
                     chunk.push(Inst::AssignVar(var.to_owned()));
-                    // Print the result if it's not nil
-                    if !val.is_nil() {
-                        eprintln!("{val:?}");
+                    self.last_echo = self.echo_text(&val);
+                    if let Some(text) = &self.last_echo {
+                        eprintln!("{text}");
                     }
                 }
                 Ok(None) => {
@@ -167,36 +221,51 @@ impl<'a> Repl<'a> {
 
         let err = result.unwrap_err();
 
-        if no_continue {
-            None
-        } else if self.continue_on_err(err) {
-            // Keep adding input until 2 successive blank lines are
-            // entered.
-            let mut input = text.to_owned();
-            let mut blank_line_count = 0;
-            loop {
-                match self.read_line("+ ", false) {
-                    Ok(None) => unreachable!(),
-                    Ok(Some(new_input)) if new_input == "" => {
-                        input.push('\n');
-                        if blank_line_count > 0 {
-                            break self.eval(input.as_str(), true);
-                        }
-                        blank_line_count += 1;
-                    }
-                    Ok(Some(new_input)) => {
-                        input.push('\n');
-                        input.push_str(new_input.as_str());
-                        if blank_line_count > 0 {
-                            break self.eval(input.as_str(), true);
-                        }
-                        blank_line_count = 0;
-                    }
-                    Err(err) => break Some(Err((2, Some(format!("{}", err))))),
+        if self.continue_on_err(err) {
+            if no_continue {
+                // The executor doesn't report these errors in
+                // incremental mode.
+                eprintln!("Syntax error: incomplete input");
+            } else {
+                self.pending = Some(text.to_owned());
+            }
+        }
+        None
+    }
+
+    /// Get the text to show for the result of the last entry, if any.
+    /// Nothing is shown when echo is off, when the result is nil, or
+    /// when the entry ended with an assignment.
+    fn echo_text(&self, val: &ObjectRef) -> Option<String> {
+        if !self.echo || val.is_nil() {
+            return None;
+        }
+        if self.executor.last_is_assignment {
+            return None;
+        }
+        Some(val.repr())
+    }
+
+    pub(crate) fn set_echo(&mut self, echo: bool) {
+        self.echo = echo;
+    }
+
+    /// Disassemble code without running it or, if no code is
+    /// specified, the last entry that was run.
+    pub(crate) fn dis_lines(&mut self, text: &str) -> Option<Vec<String>> {
+        let text = text.trim();
+        if text.is_empty() {
+            match &self.executor.last_chunk {
+                Some(chunk) => Some(self.executor.vm.format_chunk(chunk)),
+                None => {
+                    eprintln!("Nothing to disassemble");
+                    None
                 }
             }
         } else {
-            None
+            // Errors are reported by the executor.
+            let chunk = self.executor.compile_only_text(text, Some("<repl>")).ok()?;
+            Some(self.executor.vm.format_chunk(&chunk))
         }
     }
 
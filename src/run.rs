@@ -4,24 +4,27 @@ use crate::result::{ExeErrKind, ExeResult, ExitResult};
 use crate::vm::{VMState, VM};
 
 /// Run source from file.
-pub fn run_file(file_path: &str, dis: bool, debug: bool) -> ExitResult {
+pub fn run_file(file_path: &str, dis: bool, debug: bool, profile: bool) -> ExitResult {
     let mut vm = VM::default();
+    vm.profile = profile;
     let mut executor = Executor::new(&mut vm, false, dis, debug);
     let result = executor.execute_file(file_path);
     exit(result)
 }
 
 /// Read and run source from stdin.
-pub fn run_stdin(dis: bool, debug: bool) -> ExitResult {
+pub fn run_stdin(dis: bool, debug: bool, profile: bool) -> ExitResult {
     let mut vm = VM::default();
+    vm.profile = profile;
     let mut executor = Executor::new(&mut vm, false, dis, debug);
     let result = executor.execute_stdin();
     exit(result)
 }
 
 /// Run text source.
-pub fn run_text(text: &str, dis: bool, debug: bool) -> ExitResult {
+pub fn run_text(text: &str, dis: bool, debug: bool, profile: bool) -> ExitResult {
     let mut vm = VM::default();
+    vm.profile = profile;
     let mut executor = Executor::new(&mut vm, false, dis, debug);
     let result = executor.execute_text(text, None);
     exit(result)
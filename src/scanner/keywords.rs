@@ -24,6 +24,10 @@ lazy_static! {
         ("break", Break),
         ("continue", Continue),
         ("jump", Jump),
+        ("try", Try),
+        ("catch", Catch),
+        ("raise", Raise),
+        ("in", In),
     ]
     .iter()
     .cloned()
@@ -16,11 +16,29 @@ pub type ScanTokensResult = Result<Vec<TokenWithLocation>, ScanErr>;
 pub struct ScanErr {
     pub kind: ScanErrKind,
     pub location: Location,
+    /// The tokens scanned successfully just before the error occurred
+    /// in the current statement, oldest first.
+    pub context: Vec<Token>,
 }
 
 impl ScanErr {
     pub fn new(kind: ScanErrKind, location: Location) -> Self {
-        Self { kind, location }
+        Self { kind, location, context: vec![] }
+    }
+
+    pub fn with_context(mut self, context: Vec<Token>) -> Self {
+        self.context = context;
+        self
+    }
+
+    /// Format context tokens for display in error messages (e.g.,
+    /// `x +`). Returns `None` if there's no context.
+    pub fn context_str(&self) -> Option<String> {
+        if self.context.is_empty() {
+            return None;
+        }
+        let tokens: Vec<String> = self.context.iter().map(|t| t.to_string()).collect();
+        Some(tokens.join(" "))
     }
 }
 
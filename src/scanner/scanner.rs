@@ -17,6 +17,37 @@ type NextOption<'a> = Option<(char, Option<&'a char>, Option<&'a char>)>;
 type NextTwoOption<'a> = Option<(char, char, Option<&'a char>)>;
 type NextThreeOption = Option<(char, char, char)>;
 
+/// Max number of recently scanned tokens to keep for error context.
+const MAX_RECENT_TOKENS: usize = 3;
+
+/// What an inline block is attached to. When an `else` or `catch` is
+/// encountered, inline blocks are exited back to the block for the
+/// matching condition or `try`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum InlineScopeKind {
+    /// `if <cond> -> ...`, inline conditional `<cond> -> ...`, or
+    /// `try -> ...`
+    Cond,
+    /// `else -> ...` or `catch <name> -> ...`
+    Else,
+    /// Function, `block`, or `loop` body
+    Other,
+}
+
+/// What a parenthesized group is. This determines whether an inline
+/// block after the group's closing paren is a function body or the
+/// block for a condition that ends with the group.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ParenKind {
+    /// Call args, e.g. `f(x)`
+    Args,
+    /// Group whose contents so far could be function params. The flag
+    /// is set while scanning a param's default value.
+    Params(bool),
+    /// Grouped expression or tuple that can't be function params
+    Group,
+}
+
 pub struct Scanner<'a, T: BufRead> {
     /// This is the source code that's being scanned. T can be anything
     /// that implements the BufRead trait (e.g., a Cursor wrapping some
@@ -36,15 +67,23 @@ pub struct Scanner<'a, T: BufRead> {
     bracket_stack: Stack<(char, Location)>,
     /// Stack to keep track of inline blocks (e.g., `block -> true`
     /// where there's no newline after the `->`).
-    inline_scope_stack: Stack<Location>,
-    /// Keep track of where `if`s are encountered. This is used with
-    /// exit from certain inline blocks.
-    /// TODO: Not sure this is the best way to handle this. Currently,
-    ///       any `if` without an `else` will never be removed from this
-    ///       stack.
-    if_stack: Stack<Location>,
+    inline_scope_stack: Stack<(Location, InlineScopeKind)>,
+    /// The kind of each open paren group, innermost last.
+    paren_stack: Stack<ParenKind>,
+    /// The kind of the most recently closed paren group.
+    last_paren_kind: Option<ParenKind>,
+    /// Set when an `if` is encountered and cleared at the `->` after
+    /// its condition. This is used to determine which inline blocks
+    /// an `else` exits.
+    pending_if: bool,
+    /// Set when a `catch` is encountered and cleared at the `->` after
+    /// the optional name for the error.
+    pending_catch: bool,
     /// The last token that was popped from the queue.
     last_token_from_queue: Token,
+    /// The last few tokens popped from the queue in the current
+    /// statement. These are attached to scan errors as context.
+    recent_tokens: VecDeque<Token>,
 }
 
 impl<'a, T: BufRead> Scanner<'a, T> {
@@ -55,8 +94,12 @@ impl<'a, T: BufRead> Scanner<'a, T> {
             indent_level: 0,
             bracket_stack: Stack::new(),
             inline_scope_stack: Stack::new(),
-            if_stack: Stack::new(),
+            paren_stack: Stack::new(),
+            last_paren_kind: None,
+            pending_if: false,
+            pending_catch: false,
             last_token_from_queue: Token::EndOfStatement,
+            recent_tokens: VecDeque::with_capacity(MAX_RECENT_TOKENS),
         }
     }
 
@@ -66,13 +109,29 @@ impl<'a, T: BufRead> Scanner<'a, T> {
     /// returned.
     fn next_token_from_queue(&mut self) -> ScanTokenResult {
         while self.queue.is_empty() {
-            self.add_tokens_to_queue()?;
+            self.add_tokens_to_queue().map_err(|err| {
+                err.with_context(self.recent_tokens.iter().cloned().collect())
+            })?;
         }
         let token = self.queue.pop_front().unwrap();
         self.last_token_from_queue = token.token.clone();
+        self.add_recent_token(&token.token);
         Ok(token)
     }
 
+    /// Remember a token for error context. Context is reset at the
+    /// end of each statement.
+    fn add_recent_token(&mut self, token: &Token) {
+        if token == &Token::EndOfStatement {
+            self.recent_tokens.clear();
+            return;
+        }
+        if self.recent_tokens.len() == MAX_RECENT_TOKENS {
+            self.recent_tokens.pop_front();
+        }
+        self.recent_tokens.push_back(token.clone());
+    }
+
     /// Get the last token. If there are pending tokens in the queue,
     /// the last pending token will be returned. Otherwise, the last
     /// processed token will be returned.
@@ -89,8 +148,7 @@ impl<'a, T: BufRead> Scanner<'a, T> {
         use ErrKind::*;
         use Token::*;
 
-        let loc = self.source.loc();
-        let start = Location::new(loc.line, loc.col + 1);
+        let start = self.source.next_loc();
 
         let token = match self.next_char() {
             Some((quote @ ('"' | '\''), _, _)) => self.handle_string(quote, start)?,
@@ -105,12 +163,20 @@ impl<'a, T: BufRead> Scanner<'a, T> {
                 Comma
             }
             Some(('(', _, _)) => {
+                self.update_paren_kind(&LParen);
+                let kind = match is_expr_end(self.last_token()) {
+                    true => ParenKind::Args,
+                    false => ParenKind::Params(false),
+                };
                 self.bracket_stack.push(('(', start));
+                self.paren_stack.push(kind);
                 LParen
             }
             Some((c @ ')', _, _)) => {
                 self.maybe_exit_inline_scope(start, false);
-                self.pop_bracket_and_return_token(c, start, RParen)?
+                let token = self.pop_bracket_and_return_token(c, start, RParen)?;
+                self.last_paren_kind = self.paren_stack.pop();
+                token
             }
             Some(('[', _, _)) => {
                 self.bracket_stack.push(('[', start));
@@ -135,9 +201,9 @@ impl<'a, T: BufRead> Scanner<'a, T> {
             Some(('=', Some('='), _)) => self.consume_char_and_return_token(EqualEqual),
             Some(('=', _, _)) => Equal,
             Some(('&', Some('&'), _)) => self.consume_char_and_return_token(And),
-            Some(('&', _, _)) => self.consume_char_and_return_token(Ampersand),
+            Some(('&', _, _)) => Ampersand,
             Some(('|', Some('|'), _)) => self.consume_char_and_return_token(Or),
-            Some(('|', _, _)) => self.consume_char_and_return_token(Pipe),
+            Some(('|', _, _)) => Pipe,
             Some(('*', Some('*'), _)) => self.consume_char_and_return_token(DoubleStar),
             Some(('*', Some('='), _)) => self.consume_char_and_return_token(MulEqual),
             Some(('*', _, _)) => Star,
@@ -181,7 +247,11 @@ impl<'a, T: BufRead> Scanner<'a, T> {
             None => return self.handle_end_of_input(start),
         };
 
+        if token != LParen && token != RParen {
+            self.update_paren_kind(&token);
+        }
         let end = self.source.loc();
+        let after_end = self.source.next_loc();
         self.add_token_to_queue(token, start, end);
         self.consume_whitespace();
 
@@ -192,11 +262,9 @@ impl<'a, T: BufRead> Scanner<'a, T> {
             self.consume_comment();
         }
         if self.next_char_is('\n') {
-            let loc = Location::new(end.line, end.col + 1);
-            self.handle_newline(loc)?;
+            self.handle_newline(after_end)?;
         } else if self.source.peek().is_none() {
-            let loc = Location::new(end.line, end.col + 1);
-            self.handle_end_of_input(loc)?;
+            self.handle_end_of_input(after_end)?;
         }
 
         Ok(())
@@ -265,7 +333,7 @@ impl<'a, T: BufRead> Scanner<'a, T> {
 
     fn handle_ident(&mut self, first_char: char, start: Location) -> AddTokenResult {
         use Token::{
-            Else, EndOfStatement, Ident, If, InlineScopeStart, Label, ScopeStart,
+            Catch, Else, EndOfStatement, Ident, If, InlineScopeStart, Label, ScopeStart,
         };
         // Special case for underscore placeholder vars.
         if first_char == '_' {
@@ -287,12 +355,14 @@ impl<'a, T: BufRead> Scanner<'a, T> {
         // Keyword
         if let Some(token) = KEYWORDS.get(ident.as_str()) {
             if token == &If {
-                self.if_stack.push(start);
-            } else if token == &Else {
-                if self.maybe_exit_inline_scope(start, true) {
-                    self.add_token_to_queue(EndOfStatement, start, start);
-                }
-                self.if_stack.pop();
+                self.pending_if = true;
+            } else if token == &Catch {
+                self.pending_catch = true;
+            }
+            if matches!(token, Else | Catch)
+                && self.maybe_exit_inline_scope(start, true)
+            {
+                self.add_token_to_queue(EndOfStatement, start, start);
             }
             return Ok(token.clone());
         }
@@ -301,7 +371,30 @@ impl<'a, T: BufRead> Scanner<'a, T> {
     }
 
     fn handle_scope_start(&mut self, start: Location) -> AddTokensResult {
-        let end = Location::new(start.line, start.col + 1);
+        use Token::{Block, Else, Loop, RParen, Try};
+        self.update_paren_kind(&Token::InlineScopeStart);
+        let end = start.advance(1);
+        let kind = if std::mem::take(&mut self.pending_if) {
+            InlineScopeKind::Cond
+        } else if std::mem::take(&mut self.pending_catch) {
+            InlineScopeKind::Else
+        } else {
+            match self.last_token() {
+                Else => InlineScopeKind::Else,
+                Try => InlineScopeKind::Cond,
+                // Function body, unless the parens are call args or a
+                // grouped expression, like `f(x) -> ...` or
+                // `(x > 0) -> ...`, in which case they end a condition
+                RParen => match self.last_paren_kind {
+                    Some(ParenKind::Params(_)) => InlineScopeKind::Other,
+                    _ => InlineScopeKind::Cond,
+                },
+                // `block` and `loop` bodies
+                Block | Loop => InlineScopeKind::Other,
+                // Inline conditional (or `loop <cond>`)
+                _ => InlineScopeKind::Cond,
+            }
+        };
         self.source.next(); // consume >
         self.consume_whitespace();
         if self.source.peek() == Some(&'#') {
@@ -316,9 +409,9 @@ impl<'a, T: BufRead> Scanner<'a, T> {
             self.expect_indent()?;
         } else {
             // Inline block
-            let end = Location::new(start.line, start.col + 1);
+            let end = start.advance(1);
             self.add_token_to_queue(Token::InlineScopeStart, start, end);
-            self.inline_scope_stack.push(start);
+            self.inline_scope_stack.push((start, kind));
         }
         return Ok(());
     }
@@ -457,30 +550,27 @@ impl<'a, T: BufRead> Scanner<'a, T> {
     /// tokens is encountered: comma, closing bracket, newline, end of
     /// input.
     ///
-    /// If exiting because an `else` was encountered, exit back to the
-    /// matching `if`. If inside a bracket group, exit only as far back
-    /// as the start of the group. Otherwise, all inline scopes are
-    /// exited.
+    /// If exiting because an `else` or `catch` was encountered, exit
+    /// back to (and including) the block for the matching condition or
+    /// `try`. If inside a bracket group, exit only as far back as the
+    /// start of the group. Otherwise, all inline scopes are exited.
     fn exit_inline_scope(&mut self, loc: Location, is_else: bool) -> bool {
         let bracket_loc = match self.bracket_stack.peek() {
             Some((_, bracket_loc)) => (bracket_loc.line, bracket_loc.col),
             None => (0, 0),
         };
-        let if_loc = match self.if_stack.peek() {
-            Some(if_loc) => (if_loc.line, if_loc.col),
-            None => (0, 0),
-        };
         let mut count = 0;
-        while let Some(scope_start) = self.inline_scope_stack.peek() {
+        while let Some((scope_start, kind)) = self.inline_scope_stack.peek() {
             let scope_loc = (scope_start.line, scope_start.col);
             if scope_loc > bracket_loc {
-                if is_else && scope_loc < if_loc {
-                    break;
-                }
+                let is_cond = *kind == InlineScopeKind::Cond;
                 self.inline_scope_stack.pop();
                 self.add_token_to_queue(Token::EndOfStatement, loc, loc);
                 self.add_token_to_queue(Token::InlineScopeEnd, loc, loc);
                 count += 1;
+                if is_else && is_cond {
+                    break;
+                }
             } else {
                 break;
             }
@@ -495,6 +585,27 @@ impl<'a, T: BufRead> Scanner<'a, T> {
         false
     }
 
+    /// Update the kind of the innermost paren group, if the token is
+    /// directly inside one, based on whether the token can appear in a
+    /// function param list. Anything can appear in a default value.
+    fn update_paren_kind(&mut self, token: &Token) {
+        use Token::{Comma, Ellipsis, Equal, Ident};
+        if !matches!(self.bracket_stack.peek(), Some(('(', _))) {
+            return;
+        }
+        if let Some(&ParenKind::Params(in_default)) = self.paren_stack.peek() {
+            let kind = match token {
+                Comma => ParenKind::Params(false),
+                Equal => ParenKind::Params(true),
+                Ident(_) | Ellipsis => ParenKind::Params(in_default),
+                _ if in_default => ParenKind::Params(true),
+                _ => ParenKind::Group,
+            };
+            self.paren_stack.pop();
+            self.paren_stack.push(kind);
+        }
+    }
+
     // Utilities -------------------------------------------------------
 
     /// Consume and return the next character. The following two
@@ -834,3 +945,25 @@ impl<'a, T: BufRead> Iterator for Scanner<'a, T> {
         }
     }
 }
+
+/// Can the token be the end of an expression? A paren following one of
+/// these starts call args.
+fn is_expr_end(token: &Token) -> bool {
+    use Token::*;
+    matches!(
+        token,
+        RParen
+            | RBracket
+            | Nil
+            | True
+            | False
+            | Int(_)
+            | Float(_)
+            | Str(_)
+            | FormatStr(_)
+            | Ident(_)
+            | TypeIdent(_)
+            | TypeFuncIdent(_)
+            | SpecialIdent(_)
+    )
+}
@@ -82,6 +82,10 @@ pub enum Token {
     Break,         // break
     Continue,      // continue
     Jump,          // jump label
+    Try,           // try
+    Catch,         // catch
+    Raise,         // raise <expr>
+    In,            // a in b
     Label(String), // label:
 
     // Identifiers
@@ -107,6 +111,7 @@ impl Token {
 
             Self::Caret => "^",
             Self::Star => "*",
+            Self::DoubleStar => "**",
             Self::Slash => "/",
             Self::Percent => "%",
             Self::Plus => "+",
@@ -128,6 +133,14 @@ impl Token {
             Self::GreaterThanOrEqual => ">=",
             Self::And => "&&",
             Self::Or => "||",
+            Self::Ampersand => "&",
+            Self::Pipe => "|",
+            Self::LoopFeed => "<-",
+            Self::DotDot => "..",
+            Self::Ellipsis => "...",
+
+            Self::MulEqual => "*=",
+            Self::DivEqual => "/=",
 
             Self::ScopeStart => "->",
             Self::ScopeEnd => "<scope end>",
@@ -136,11 +149,27 @@ impl Token {
             Self::InlineScopeEnd => "<inline scope end>",
 
             // Keywords
+            Self::Nil => "nil",
+            Self::True => "true",
+            Self::False => "false",
+            Self::Import => "import",
+            Self::From => "from",
+            Self::Package => "package",
+            Self::Export => "export",
+            Self::As => "as",
+            Self::Let => "let",
             Self::Block => "block",
             Self::If => "if",
             Self::Else => "else",
             Self::Match => "match",
+            Self::Loop => "loop",
+            Self::Break => "break",
+            Self::Continue => "continue",
             Self::Jump => "jump",
+            Self::Try => "try",
+            Self::Catch => "catch",
+            Self::Raise => "raise",
+            Self::In => "in",
             Self::Label(_name) => "label",
 
             // Identifiers
@@ -159,7 +188,14 @@ impl Token {
 
 impl fmt::Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.as_str())
+        match self {
+            Self::Float(value) => write!(f, "{value:?}"),
+            Self::Int(value) => write!(f, "{value}"),
+            Self::Str(value) => write!(f, "{value:?}"),
+            Self::FormatStr(_) => write!(f, "$\"...\""),
+            Self::Label(name) => write!(f, "{name}:"),
+            _ => write!(f, "{}", self.as_str()),
+        }
     }
 }
 
@@ -0,0 +1,600 @@
+use std::cell::RefCell;
+use std::io::{self, Cursor, Write};
+use std::rc::Rc;
+
+use num_bigint::BigInt;
+
+use crate::result::{ExeErr, ExeErrKind};
+use crate::types::{Args, CallResult, ObjectRef};
+use crate::vm::{RuntimeErrKind, VM};
+
+use super::exe::eval_text;
+
+/// Execute the text and return the result, panicking on error.
+fn eval_ok(text: &str) -> ObjectRef {
+    let mut vm = VM::default();
+    eval_text(&mut vm, text).unwrap_or_else(|err| panic!("{text}: {err:?}"))
+}
+
+/// Execute the text and return the runtime error kind, panicking if
+/// the text executes successfully or some other kind of error occurs.
+fn eval_runtime_err(text: &str) -> RuntimeErrKind {
+    let mut vm = VM::default();
+    match eval_text(&mut vm, text) {
+        Err(ExeErr { kind: ExeErrKind::RuntimeErr(kind) }) => kind,
+        result => panic!("{text}: expected runtime error; got {result:?}"),
+    }
+}
+
+#[test]
+fn test_int() {
+    assert_eq!(eval_ok("int(\"42\")").int_val(), Some(BigInt::from(42)));
+    assert_eq!(eval_ok("int(\"-42\")").int_val(), Some(BigInt::from(-42)));
+    assert_eq!(eval_ok("int(3.9)").int_val(), Some(BigInt::from(3)));
+    assert_eq!(eval_ok("int(-3.9)").int_val(), Some(BigInt::from(-3)));
+    assert_eq!(eval_ok("int(7)").int_val(), Some(BigInt::from(7)));
+}
+
+#[test]
+fn test_int_with_invalid_input() {
+    assert!(matches!(eval_runtime_err("int(\"abc\")"), RuntimeErrKind::ValueErr(_)));
+    assert!(matches!(eval_runtime_err("int(\"1.5\")"), RuntimeErrKind::ValueErr(_)));
+    assert!(matches!(eval_runtime_err("int(nil)"), RuntimeErrKind::TypeErr(_)));
+}
+
+#[test]
+fn test_float() {
+    let result = eval_ok("float(3)");
+    assert_eq!(result.float_val(), Some(3.0));
+    assert_eq!(result.to_string(), "3.0");
+    assert_eq!(eval_ok("float(\"1.5\")").float_val(), Some(1.5));
+    assert_eq!(eval_ok("float(2.5)").float_val(), Some(2.5));
+}
+
+#[test]
+fn test_float_with_invalid_input() {
+    assert!(matches!(eval_runtime_err("float(\"abc\")"), RuntimeErrKind::ValueErr(_)));
+    assert!(matches!(eval_runtime_err("float(())"), RuntimeErrKind::TypeErr(_)));
+}
+
+#[test]
+fn test_parse_numbers() {
+    assert_eq!(eval_ok("parse(\"42\")").int_val(), Some(BigInt::from(42)));
+    assert_eq!(eval_ok("parse(\"-42\")").int_val(), Some(BigInt::from(-42)));
+    assert_eq!(eval_ok("parse(\"0x10\")").int_val(), Some(BigInt::from(16)));
+    assert_eq!(eval_ok("parse(\"0b101\")").int_val(), Some(BigInt::from(5)));
+    assert_eq!(eval_ok("parse(\"1_000\")").int_val(), Some(BigInt::from(1000)));
+    assert_eq!(eval_ok("parse(\"1.5\")").float_val(), Some(1.5));
+    assert_eq!(eval_ok("parse(\" -1.5 \")").float_val(), Some(-1.5));
+}
+
+#[test]
+fn test_parse_bool_and_nil() {
+    assert_eq!(eval_ok("parse(\"true\")").bool_val(), Some(true));
+    assert_eq!(eval_ok("parse(\"false\")").bool_val(), Some(false));
+    assert!(eval_ok("parse(\"nil\")").is_nil());
+}
+
+#[test]
+fn test_parse_falls_back_to_str() {
+    for string in ["abc", "1 2", "1 + 2", "\"1\"", "1.5.1", ""] {
+        let text = format!("parse({string:?})");
+        let result = eval_ok(text.as_str());
+        assert_eq!(result.str_val(), Some(string.to_owned()), "{text}");
+    }
+}
+
+#[test]
+fn test_str() {
+    assert_eq!(eval_ok("str(1)").str_val(), Some("1".to_owned()));
+    assert_eq!(eval_ok("str(1.0)").str_val(), Some("1.0".to_owned()));
+    assert_eq!(eval_ok("str(nil)").str_val(), Some("nil".to_owned()));
+    assert_eq!(eval_ok("str(\"abc\")").str_val(), Some("abc".to_owned()));
+    let result = eval_ok("t = (1, \"a\", nil)\nstr(t)");
+    assert_eq!(result.str_val(), Some("(1, \"a\", nil)".to_owned()));
+}
+
+#[test]
+fn test_str_with_custom_str_method() {
+    fn custom_str(_args: Args, vm: &mut VM) -> CallResult {
+        Ok(Some(vm.ctx.builtins.new_str("<custom>")))
+    }
+
+    let mut vm = VM::default();
+    let class = vm.ctx.builtins.new_type("test", "Custom");
+    let obj = vm.ctx.builtins.new_custom_instance(class);
+    let str_method =
+        vm.ctx.builtins.new_builtin_func("$str", Some(vec!["this"]), custom_str);
+    obj.set_attr("$str", str_method, &vm.ctx).unwrap();
+    let result = vm.str_obj(&obj).unwrap();
+    assert_eq!(result.str_val(), Some("<custom>".to_owned()));
+}
+
+#[test]
+fn test_len() {
+    assert_eq!(eval_ok("len(\"\")").int_val(), Some(BigInt::from(0)));
+    assert_eq!(eval_ok("len(\"abc\")").int_val(), Some(BigInt::from(3)));
+    // Length is the number of chars, not bytes
+    assert_eq!(eval_ok("len(\"héllo 🙂\")").int_val(), Some(BigInt::from(7)));
+    assert_eq!(eval_ok("t = ()\nlen(t)").int_val(), Some(BigInt::from(0)));
+    assert_eq!(eval_ok("t = (1, 2, 3)\nlen(t)").int_val(), Some(BigInt::from(3)));
+}
+
+#[test]
+fn test_len_of_object_with_no_length() {
+    assert!(matches!(eval_runtime_err("len(1)"), RuntimeErrKind::TypeErr(_)));
+    assert!(matches!(eval_runtime_err("len(nil)"), RuntimeErrKind::TypeErr(_)));
+}
+
+#[test]
+fn test_type() {
+    assert_eq!(eval_ok("type(1)").str_val(), Some("Int".to_owned()));
+    assert_eq!(eval_ok("type(1.0)").str_val(), Some("Float".to_owned()));
+    assert_eq!(eval_ok("type(nil)").str_val(), Some("Nil".to_owned()));
+    assert_eq!(eval_ok("type(\"a\")").str_val(), Some("Str".to_owned()));
+    assert_eq!(eval_ok("type(type)").str_val(), Some("BuiltinFunc".to_owned()));
+}
+
+#[test]
+fn test_type_with_wrong_number_of_args() {
+    assert!(matches!(eval_runtime_err("type()"), RuntimeErrKind::TypeErr(_)));
+    assert!(matches!(eval_runtime_err("type(1, 2)"), RuntimeErrKind::TypeErr(_)));
+}
+
+/// Get the named entry from the Map returned by `type_info()`.
+fn get_info(vm: &VM, info: &ObjectRef, name: &str) -> Option<ObjectRef> {
+    assert!(info.is_map());
+    let key = vm.ctx.builtins.new_str(name);
+    info.get_item(&key, &vm.ctx).ok()
+}
+
+#[test]
+fn test_type_info_for_sequence() {
+    let mut vm = VM::default();
+    let info = eval_text(&mut vm, "type_info([1, 2, 3])").unwrap();
+    let get = |name| get_info(&vm, &info, name);
+    assert_eq!(get("type").unwrap().str_val(), Some("List".to_owned()));
+    assert!(get("id").unwrap().is_int());
+    assert_eq!(get("len").unwrap().int_val(), Some(BigInt::from(3)));
+    assert_eq!(get("callable").unwrap().bool_val(), Some(false));
+}
+
+#[test]
+fn test_type_info_for_func() {
+    let mut vm = VM::default();
+    let info = eval_text(&mut vm, "f = (x) -> x\ntype_info(f)").unwrap();
+    let get = |name| get_info(&vm, &info, name);
+    assert_eq!(get("type").unwrap().str_val(), Some("Func".to_owned()));
+    assert_eq!(get("callable").unwrap().bool_val(), Some(true));
+    assert!(get("len").is_none());
+    let info = eval_text(&mut vm, "type_info(print)").unwrap();
+    assert_eq!(get_info(&vm, &info, "callable").unwrap().bool_val(), Some(true));
+}
+
+#[test]
+fn test_assert() {
+    assert!(eval_ok("assert(true)").is_nil());
+    assert!(eval_ok("assert(1 == 1, \"one is one\")").is_nil());
+}
+
+#[test]
+fn test_assert_failure() {
+    match eval_runtime_err("assert(false)") {
+        RuntimeErrKind::AssertionFailed(message) => {
+            assert_eq!(message, "assertion failed")
+        }
+        kind => panic!("Unexpected error: {kind:?}"),
+    }
+    match eval_runtime_err("assert(nil, \"expected a value\")") {
+        RuntimeErrKind::AssertionFailed(message) => {
+            assert_eq!(message, "expected a value")
+        }
+        kind => panic!("Unexpected error: {kind:?}"),
+    }
+}
+
+#[test]
+fn test_assert_with_wrong_number_of_args() {
+    assert!(matches!(eval_runtime_err("assert()"), RuntimeErrKind::TypeErr(_)));
+    assert!(matches!(
+        eval_runtime_err("assert(true, \"a\", \"b\")"),
+        RuntimeErrKind::TypeErr(_)
+    ));
+}
+
+#[test]
+fn test_assert_raises() {
+    assert!(eval_ok("assert_raises(\"TypeErr\", () -> 1 + nil)").is_nil());
+    assert!(eval_ok("assert_raises(\"NameErr\", () -> x)").is_nil());
+    assert!(eval_ok("assert_raises(\"DivisionByZero\", () -> 1 / 0)").is_nil());
+}
+
+#[test]
+fn test_assert_raises_failure() {
+    // No error
+    match eval_runtime_err("assert_raises(\"TypeErr\", () -> 1 + 1)") {
+        RuntimeErrKind::AssertionFailed(message) => {
+            assert_eq!(message, "expected TypeErr; no error was raised")
+        }
+        kind => panic!("Unexpected error: {kind:?}"),
+    }
+    // Different kind of error
+    assert!(matches!(
+        eval_runtime_err("assert_raises(\"ValueErr\", () -> 1 + nil)"),
+        RuntimeErrKind::AssertionFailed(_)
+    ));
+}
+
+#[test]
+fn test_read_line_and_input() {
+    let mut vm = VM::default();
+    vm.stdin = Box::new(Cursor::new("first\nsecond\r\nthird"));
+    let result = eval_text(&mut vm, "read_line()").unwrap();
+    assert_eq!(result.str_val(), Some("first".to_owned()));
+    let result = eval_text(&mut vm, "input(\"> \")").unwrap();
+    assert_eq!(result.str_val(), Some("second".to_owned()));
+    // The last line doesn't have a trailing newline.
+    let result = eval_text(&mut vm, "read_line()").unwrap();
+    assert_eq!(result.str_val(), Some("third".to_owned()));
+    // EOF
+    let result = eval_text(&mut vm, "read_line()").unwrap();
+    assert!(result.is_nil());
+    let result = eval_text(&mut vm, "input(\"> \")").unwrap();
+    assert!(result.is_nil());
+}
+
+/// Output buffer that can be given to the VM as its stdout and then
+/// read after the VM writes to it.
+#[derive(Clone, Default)]
+struct Output(Rc<RefCell<Vec<u8>>>);
+
+impl Output {
+    fn take(&self) -> String {
+        String::from_utf8(self.0.take()).unwrap()
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Execute the text and return what it writes to stdout.
+fn eval_output(text: &str) -> String {
+    let mut vm = VM::default();
+    let output = Output::default();
+    vm.stdout = Box::new(output.clone());
+    eval_text(&mut vm, text).unwrap_or_else(|err| panic!("{text}: {err:?}"));
+    output.take()
+}
+
+#[test]
+fn test_print() {
+    assert_eq!(eval_output("print()"), "\n");
+    assert_eq!(eval_output("print(1)"), "1\n");
+    assert_eq!(eval_output("print(1, \"a\", (2, 3), nil)"), "1 a (2, 3) nil\n");
+}
+
+#[test]
+fn test_print_map_with_str_key() {
+    // Only custom objects can have a $str method. A map item with the
+    // same key isn't one.
+    let mut vm = VM::default();
+    let output = Output::default();
+    vm.stdout = Box::new(output.clone());
+    let key = vm.ctx.builtins.new_str("$str");
+    let value = vm.ctx.builtins.new_int(1);
+    let map = vm.ctx.builtins.new_map(vec![(key, value)]).unwrap();
+    let expected = map.to_string();
+    vm.ctx.declare_and_assign_var("m", map).unwrap();
+    eval_text(&mut vm, "print(m)\nprint($\"<{m}>\")").unwrap();
+    assert_eq!(output.take(), format!("{expected}\n<{expected}>\n"));
+}
+
+#[test]
+fn test_print_with() {
+    assert_eq!(eval_output("print_with((1, 2, 3), \", \", \"\\n\")"), "1, 2, 3\n");
+    assert_eq!(eval_output("print_with([\"a\", \"b\"], \"\", \"\")"), "ab");
+    assert_eq!(eval_output("print_with((), \" \", \"!\")"), "!");
+    assert_eq!(
+        eval_output("print_with((1,), \"\", \"\")\nprint_with((2,), \"\", \"\\n\")"),
+        "12\n"
+    );
+}
+
+#[test]
+fn test_print_with_bad_args() {
+    assert!(matches!(
+        eval_runtime_err("print_with(1, \" \", \"\")"),
+        RuntimeErrKind::TypeErr(_)
+    ));
+    assert!(matches!(
+        eval_runtime_err("print_with((1,), 1, \"\")"),
+        RuntimeErrKind::TypeErr(_)
+    ));
+    assert!(matches!(
+        eval_runtime_err("print_with((1,), \" \", nil)"),
+        RuntimeErrKind::TypeErr(_)
+    ));
+}
+
+#[test]
+fn test_input_prompt_is_written_to_stdout() {
+    let mut vm = VM::default();
+    let output = Output::default();
+    vm.stdin = Box::new(Cursor::new("line\n"));
+    vm.stdout = Box::new(output.clone());
+    let result = eval_text(&mut vm, "input(\"> \")").unwrap();
+    assert_eq!(result.str_val(), Some("line".to_owned()));
+    assert_eq!(output.take(), "> ");
+}
+
+#[test]
+fn test_input_with_non_str_prompt() {
+    let mut vm = VM::default();
+    vm.stdin = Box::new(Cursor::new("line\n"));
+    let result = eval_text(&mut vm, "input(1)");
+    assert!(
+        matches!(
+            result,
+            Err(ExeErr { kind: ExeErrKind::RuntimeErr(RuntimeErrKind::TypeErr(_)) })
+        ),
+        "{result:?}"
+    );
+}
+
+#[test]
+fn test_dis() {
+    let mut vm = VM::default();
+    let text = "f = (x) -> x + 1\nf.dis()";
+    let result = eval_text(&mut vm, text).unwrap();
+    let dis = result.str_val().unwrap();
+    assert!(dis.contains("LOAD_LOCAL"), "{dis}");
+    assert!(dis.contains("BINARY_OP"), "{dis}");
+    assert!(dis.contains("RETURN"), "{dis}");
+    // The global function returns the same disassembly.
+    let result = eval_text(&mut vm, "dis(f)").unwrap();
+    assert_eq!(result.str_val().unwrap(), dis);
+}
+
+#[test]
+fn test_dis_non_func() {
+    assert!(matches!(eval_runtime_err("dis(1)"), RuntimeErrKind::TypeErr(_)));
+    assert!(matches!(eval_runtime_err("dis(print)"), RuntimeErrKind::TypeErr(_)));
+}
+
+/// Get a path in the system temp directory that's unique to the test.
+fn temp_file_path(name: &str) -> String {
+    let name = format!("feint-test-{}-{name}", std::process::id());
+    std::env::temp_dir().join(name).to_str().unwrap().to_owned()
+}
+
+#[test]
+fn test_write_and_read_file() {
+    let path = temp_file_path("write-and-read");
+    let mut vm = VM::default();
+    let text = format!("p = {path:?}\nwrite_file(p, \"one\\ntwo\\n\")");
+    let result = eval_text(&mut vm, text.as_str()).unwrap();
+    assert!(result.is_nil());
+    let result = eval_text(&mut vm, "read_file(p)").unwrap();
+    assert_eq!(result.str_val(), Some("one\ntwo\n".to_owned()));
+    let result = eval_text(&mut vm, "read_file_lines(p)").unwrap();
+    assert_eq!(result.to_string(), "(\"one\", \"two\")");
+    // Writing again replaces the contents.
+    eval_text(&mut vm, "write_file(p, \"three\")").unwrap();
+    let result = eval_text(&mut vm, "read_file(p)").unwrap();
+    assert_eq!(result.str_val(), Some("three".to_owned()));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_file_io_errors() {
+    let path = temp_file_path("does-not-exist");
+    for func in ["read_file", "read_file_lines"] {
+        let text = format!("{func}({path:?})");
+        assert!(matches!(eval_runtime_err(text.as_str()), RuntimeErrKind::IoErr(_)));
+    }
+    let path = temp_file_path("no-such-dir/file");
+    let text = format!("write_file({path:?}, \"contents\")");
+    assert!(matches!(eval_runtime_err(text.as_str()), RuntimeErrKind::IoErr(_)));
+    let text = format!("write_file({path:?}, 1)");
+    assert!(matches!(eval_runtime_err(text.as_str()), RuntimeErrKind::TypeErr(_)));
+}
+
+#[test]
+fn test_min_and_max() {
+    let cases = [
+        ("min(1, 2)", "1"),
+        ("min(2, 1)", "1"),
+        ("max(1, 2)", "2"),
+        ("max(2, 1)", "2"),
+        ("min(1, 1.5)", "1"),
+        ("min(1.5, 1)", "1"),
+        ("max(1, 1.5)", "1.5"),
+        ("max(-1.5, -2)", "-1.5"),
+        // Ties return the first arg
+        ("min(1, 1.0)", "1"),
+        ("max(1.0, 1)", "1.0"),
+    ];
+    for (text, expected) in cases {
+        assert_eq!(eval_ok(text).to_string(), expected, "{text}");
+    }
+    assert!(matches!(eval_runtime_err("min(1, nil)"), RuntimeErrKind::TypeErr(_)));
+}
+
+#[test]
+fn test_sqrt_and_pow() {
+    let cases = [
+        ("sqrt(4)", "2.0"),
+        ("sqrt(2.25)", "1.5"),
+        ("sqrt(0)", "0.0"),
+        ("pow(2, 10)", "1024"),
+        ("pow(2, 0.5) == sqrt(2)", "true"),
+        ("pow(2.0, 3)", "8.0"),
+        ("pow(4, 0.5)", "2.0"),
+    ];
+    for (text, expected) in cases {
+        assert_eq!(eval_ok(text).to_string(), expected, "{text}");
+    }
+    assert!(matches!(eval_runtime_err("sqrt(-1)"), RuntimeErrKind::ValueErr(_)));
+    assert!(matches!(eval_runtime_err("sqrt(-0.5)"), RuntimeErrKind::ValueErr(_)));
+    assert!(matches!(eval_runtime_err("sqrt(\"4\")"), RuntimeErrKind::TypeErr(_)));
+    assert!(matches!(eval_runtime_err("pow(\"2\", 2)"), RuntimeErrKind::TypeErr(_)));
+}
+
+#[test]
+fn test_register_builtin_func() {
+    let mut vm = VM::default();
+    let factor = 2;
+    vm.ctx
+        .register_builtin_func("double", Some(vec!["x"]), move |args, vm| {
+            let x = args.first().unwrap();
+            let factor = vm.ctx.builtins.new_int(factor);
+            Ok(Some(x.mul(&*factor, &vm.ctx)?))
+        })
+        .unwrap();
+    let result = eval_text(&mut vm, "double(21)").unwrap();
+    assert_eq!(result.int_val(), Some(BigInt::from(42)));
+    // It's also available via the builtins namespace.
+    let result = eval_text(&mut vm, "builtins.double(2.5)").unwrap();
+    assert_eq!(result.float_val(), Some(5.0));
+    let result = eval_text(&mut vm, "builtins.len(\"abc\")").unwrap();
+    assert_eq!(result.int_val(), Some(BigInt::from(3)));
+    assert!(matches!(
+        eval_text(&mut vm, "double(1, 2)"),
+        Err(ExeErr { kind: ExeErrKind::RuntimeErr(RuntimeErrKind::TypeErr(_)) })
+    ));
+}
+
+#[test]
+fn test_reversed() {
+    let result = eval_ok("t = (1, 2, 3)\nreversed(t)");
+    let items = result.as_tuple().unwrap().items().clone();
+    let items: Vec<_> = items.iter().map(|i| i.int_val().unwrap()).collect();
+    assert_eq!(items, vec![BigInt::from(3), BigInt::from(2), BigInt::from(1)]);
+    assert!(eval_ok("reversed([1, 2])").as_list().is_some());
+    assert_eq!(eval_ok("reversed(\"abc\").0").str_val(), Some("c".to_owned()));
+    assert!(matches!(eval_runtime_err("reversed(1)"), RuntimeErrKind::TypeErr(_)));
+}
+
+#[test]
+fn test_sorted_ints() {
+    let result = eval_ok("sorted([3, -1, 2, 0])");
+    let items = result.as_list().unwrap().items();
+    let items: Vec<_> = items.iter().map(|i| i.int_val().unwrap()).collect();
+    let expected: Vec<BigInt> =
+        vec![-1, 0, 2, 3].into_iter().map(BigInt::from).collect();
+    assert_eq!(items, expected);
+    // The original sequence isn't modified
+    assert_eq!(eval_ok("l = [2, 1]\nsorted(l)\nl.0").int_val(), Some(BigInt::from(2)));
+}
+
+#[test]
+fn test_sorted_strings() {
+    let result = eval_ok("t = (\"pear\", \"apple\", \"fig\")\nsorted(t)");
+    let items = result.as_tuple().unwrap().items().clone();
+    let items: Vec<_> = items.iter().map(|i| i.str_val().unwrap()).collect();
+    assert_eq!(items, vec!["apple", "fig", "pear"]);
+}
+
+#[test]
+fn test_sorted_mixed_types() {
+    assert!(matches!(
+        eval_runtime_err("t = (1, \"a\")\nsorted(t)"),
+        RuntimeErrKind::TypeErr(_)
+    ));
+    assert!(matches!(eval_runtime_err("sorted(nil)"), RuntimeErrKind::TypeErr(_)));
+}
+
+#[test]
+fn test_sorted_large_sequences_with_incomparable_items() {
+    // Sequences this long are sorted differently than short ones by the
+    // standard library, which could panic when comparisons failed.
+    let mut items: Vec<String> = (0..25).map(|i| (i * 7919 % 25).to_string()).collect();
+    items[5] = "\"a\"".to_owned();
+    let text = format!("sorted([{}])", items.join(", "));
+    assert!(matches!(eval_runtime_err(&text), RuntimeErrKind::TypeErr(_)), "{text}");
+    // NaN can't be ordered, but that isn't an error.
+    items[5] = "float(\"nan\")".to_owned();
+    let text = format!("sorted([{}])", items.join(", "));
+    assert_eq!(eval_ok(&text).len(), Some(25));
+}
+
+/// Convert a sequence of Ints to a Vec of i64s.
+fn int_items(obj: &ObjectRef) -> Vec<i64> {
+    let items = match obj.as_tuple() {
+        Some(tuple) => tuple.items().clone(),
+        None => obj.as_list().unwrap().items().clone(),
+    };
+    items.iter().map(|i| i64::try_from(i.int_val().unwrap()).unwrap()).collect()
+}
+
+#[test]
+fn test_map() {
+    let result = eval_ok("double = (x) -> x * 2\nt = (1, 2, 3)\nmap(double, t)");
+    assert_eq!(int_items(&result), vec![2, 4, 6]);
+    let result = eval_ok("map((x) -> x + 1, [])");
+    assert_eq!(int_items(&result), Vec::<i64>::new());
+}
+
+#[test]
+fn test_filter() {
+    let result =
+        eval_ok("is_even = (x) -> x % 2 == 0\nfilter(is_even, [1, 2, 3, 4, 6])");
+    assert_eq!(int_items(&result), vec![2, 4, 6]);
+}
+
+#[test]
+fn test_each() {
+    let result = eval_ok("total = 0\neach((x) -> total += x, [1, 2, 3])\ntotal");
+    assert_eq!(result.int_val(), Some(BigInt::from(6)));
+    assert!(eval_ok("each((x) -> x, [1])").is_nil());
+}
+
+#[test]
+fn test_higher_order_funcs_errors() {
+    assert!(matches!(eval_runtime_err("map(1, [])"), RuntimeErrKind::NotCallable(_)));
+    assert!(matches!(
+        eval_runtime_err("filter((x) -> x, 1)"),
+        RuntimeErrKind::TypeErr(_)
+    ));
+    // Errors from the callback are propagated
+    assert!(matches!(
+        eval_runtime_err("map((x) -> x + \"a\", [1])"),
+        RuntimeErrKind::TypeErr(_)
+    ));
+}
+
+#[test]
+fn test_reduce() {
+    let result = eval_ok("t = (1, 2, 3, 4)\nreduce((acc, x) -> acc + x, t, 0)");
+    assert_eq!(result.int_val(), Some(BigInt::from(10)));
+    let result =
+        eval_ok("t = (\"a\", \"b\", \"c\")\nreduce((acc, s) -> acc + s, t, \"\")");
+    assert_eq!(result.str_val(), Some("abc".to_owned()));
+    // An empty sequence gives the initial value
+    let result = eval_ok("reduce((acc, x) -> acc + x, [], 42)");
+    assert_eq!(result.int_val(), Some(BigInt::from(42)));
+}
+
+#[test]
+fn test_reduce_errors() {
+    assert!(matches!(
+        eval_runtime_err("reduce(1, [], 0)"),
+        RuntimeErrKind::NotCallable(_)
+    ));
+    // The callable must accept two args, even if the sequence is empty
+    assert!(matches!(
+        eval_runtime_err("reduce((x) -> x, [], 0)"),
+        RuntimeErrKind::TypeErr(_)
+    ));
+    assert!(matches!(
+        eval_runtime_err("reduce((a, b, c) -> a, [1], 0)"),
+        RuntimeErrKind::TypeErr(_)
+    ));
+}
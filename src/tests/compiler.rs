@@ -0,0 +1,382 @@
+use num_bigint::BigInt;
+
+use crate::ast;
+use crate::compiler::{
+    compile, compile_unoptimized, compile_with_locations, CompErrKind,
+};
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::types::ObjectRef;
+use crate::util::{source_from_text, BinaryOperator, Location};
+use crate::vm::{Chunk, Inst, VMState, VM};
+
+use super::exe::eval_text;
+
+/// Scan, parse, and compile the text, returning the chunk.
+pub fn compile_text(vm: &mut VM, text: &str) -> Chunk {
+    compile_text_with(vm, text, true)
+}
+
+/// Scan, parse, and compile the text, returning the chunk and the
+/// source location of each instruction.
+pub fn compile_text_with_locations(vm: &mut VM, text: &str) -> (Chunk, Vec<Location>) {
+    let mut source = source_from_text(text);
+    let scanner = Scanner::new(&mut source);
+    let mut parser = Parser::new(scanner.into_iter());
+    let program = parser.parse().expect("Could not parse program");
+    compile_with_locations(vm, program).expect("Could not compile program")
+}
+
+fn compile_text_with(vm: &mut VM, text: &str, optimize: bool) -> Chunk {
+    let mut source = source_from_text(text);
+    let scanner = Scanner::new(&mut source);
+    let mut parser = Parser::new(scanner.into_iter());
+    let program = parser.parse().expect("Could not parse program");
+    let result =
+        if optimize { compile(vm, program) } else { compile_unoptimized(vm, program) };
+    result.expect("Could not compile program")
+}
+
+/// Compile the text, check that it was folded to a single constant,
+/// and return the constant.
+fn compile_folded(vm: &mut VM, text: &str) -> ObjectRef {
+    let chunk = compile_text(vm, text);
+    assert_eq!(chunk.len(), 2, "{text}: {chunk:?}");
+    match chunk[0] {
+        Inst::LoadConst(index) => vm.ctx.get_const(index).unwrap().clone(),
+        _ => panic!("{text}: Expected LOAD_CONST: {chunk:?}"),
+    }
+}
+
+fn count_binary_ops(chunk: &Chunk) -> usize {
+    chunk.iter().filter(|inst| matches!(inst, Inst::BinaryOp(_))).count()
+}
+
+#[test]
+fn fold_int_arithmetic() {
+    let mut vm = VM::default();
+    let obj = compile_folded(&mut vm, "2 + 3 * 4");
+    assert_eq!(obj.int_val(), Some(BigInt::from(14)));
+    let obj = compile_folded(&mut vm, "-(2 + 3)");
+    assert_eq!(obj.int_val(), Some(BigInt::from(-5)));
+}
+
+#[test]
+fn fold_float_arithmetic() {
+    let mut vm = VM::default();
+    let obj = compile_folded(&mut vm, "1.5 * 2");
+    assert_eq!(obj.float_val(), Some(3.0));
+    let obj = compile_folded(&mut vm, "1 / 4");
+    assert_eq!(obj.float_val(), Some(0.25));
+}
+
+#[test]
+fn fold_preserves_big_ints() {
+    let mut vm = VM::default();
+    let obj = compile_folded(&mut vm, "2 ** 100 + 1");
+    let expected = BigInt::from(2).pow(100) + 1;
+    assert_eq!(obj.int_val(), Some(expected));
+}
+
+#[test]
+fn fold_reduces_instruction_count() {
+    let mut vm = VM::default();
+    // Partially constant: only `3 * 4` can be folded.
+    let chunk = compile_text(&mut vm, "x = 2\nx + 3 * 4");
+    assert_eq!(count_binary_ops(&chunk), 1);
+    assert!(chunk.contains(&Inst::BinaryOp(BinaryOperator::Add)));
+    // Fully constant
+    let chunk = compile_text(&mut vm, "2 + 3 * 4");
+    assert_eq!(count_binary_ops(&chunk), 0);
+}
+
+#[test]
+fn do_not_fold_division_by_zero() {
+    let mut vm = VM::default();
+    for text in ["1 / 0", "1 // 0", "1 % 0", "1.0 / 0.0"] {
+        let chunk = compile_text(&mut vm, text);
+        assert_eq!(count_binary_ops(&chunk), 1, "{text}");
+    }
+}
+
+#[test]
+fn do_not_fold_non_numeric_operands() {
+    let mut vm = VM::default();
+    let chunk = compile_text(&mut vm, "\"a\" + \"b\"");
+    assert_eq!(count_binary_ops(&chunk), 1);
+    let chunk = compile_text(&mut vm, "1 == 1");
+    assert_eq!(count_binary_ops(&chunk), 1);
+}
+
+// Peephole optimization -----------------------------------------------
+
+const OPTIMIZER_PROGRAMS: &[&str] = &[
+    "1\n2\n3",
+    "x = 1\nnil\nx + 1",
+    "i = 0\nloop i < 5 ->\n    nil\n    i += 1\ni",
+    "x = if false -> 1 else -> 2\nx",
+    "x = if false -> 1\nx",
+    "f = (n) ->\n    1\n    if n > 0 -> f(n - 1) else -> n\nf(3)",
+    "i = 0\nloop ->\n    i += 1\n    if i > 3 ->\n        break i\n    nil",
+];
+
+/// Execute the chunk and return the VM state and the string
+/// representation of the object at the top of the stack.
+fn execute(vm: &mut VM, chunk: &Chunk) -> (VMState, String) {
+    let state = vm.execute(chunk, false).expect("Could not execute chunk");
+    let obj = vm.peek_obj().expect("Could not get top of stack");
+    (state, obj.expect("Stack is empty").to_string())
+}
+
+#[test]
+fn optimized_programs_produce_same_results() {
+    for text in OPTIMIZER_PROGRAMS {
+        let mut vm = VM::default();
+        let chunk = compile_text_with(&mut vm, text, false);
+        let expected = execute(&mut vm, &chunk);
+
+        let mut vm = VM::default();
+        let optimized_chunk = compile_text_with(&mut vm, text, true);
+        assert!(optimized_chunk.len() <= chunk.len(), "{text}");
+        let actual = execute(&mut vm, &optimized_chunk);
+
+        assert_eq!(actual, expected, "{text}");
+    }
+}
+
+#[test]
+fn optimize_removes_dead_loads() {
+    let mut vm = VM::default();
+    let text = "1\n2\n3";
+    let chunk = compile_text_with(&mut vm, text, false);
+    assert_eq!(chunk.iter().filter(|inst| matches!(inst, Inst::Pop)).count(), 2);
+    let chunk = compile_text_with(&mut vm, text, true);
+    assert!(matches!(chunk[..], [Inst::LoadConst(_), Inst::Halt(0)]), "{chunk:?}");
+}
+
+#[test]
+fn optimize_rewrites_jump_targets() {
+    let mut vm = VM::default();
+    let text = "1\ni = 0\nloop i < 5 ->\n    i += 1\ni";
+    let chunk = compile_text_with(&mut vm, text, false);
+    let chunk_len = chunk.len();
+    let chunk = compile_text_with(&mut vm, text, true);
+    // The leading `1` and the nil the loop evaluates to are both loaded
+    // and then immediately popped.
+    assert_eq!(chunk.len(), chunk_len - 4);
+    // All jumps must target addresses inside the chunk.
+    for inst in chunk.iter() {
+        if let Inst::Jump(addr, _) | Inst::JumpIfNot(addr, _) = inst {
+            assert!(*addr < chunk.len(), "{chunk:?}");
+        }
+    }
+    assert_eq!(execute(&mut vm, &chunk).1, "5");
+}
+
+// Item assignment -----------------------------------------------------
+
+#[test]
+fn compile_set_item() {
+    let mut vm = VM::default();
+    let chunk = compile_text(&mut vm, "l = [1, 2]\nl.0 = 3");
+    // The list, index, and value are pushed before SET_ITEM.
+    let i = chunk.iter().position(|inst| matches!(inst, Inst::SetItem));
+    let i = i.expect("SET_ITEM not found");
+    assert!(
+        matches!(
+            chunk[i - 3..i],
+            [Inst::LoadVar(_), Inst::LoadConst(_), Inst::LoadConst(_)]
+        ),
+        "{chunk:?}"
+    );
+}
+
+fn count_insts(chunk: &Chunk, name: &str) -> usize {
+    chunk.iter().filter(|inst| inst.name() == name).count()
+}
+
+#[test]
+fn compile_first_assignment_as_declare_and_assign() {
+    let mut vm = VM::default();
+    let chunk = compile_text(&mut vm, "x = 1\ny, z = (1, 2)\nf = () -> f");
+    assert_eq!(count_insts(&chunk, "DECLARE_ASSIGN"), 4);
+    assert_eq!(count_insts(&chunk, "DECLARE_VAR"), 0);
+    assert_eq!(count_insts(&chunk, "ASSIGN_VAR"), 0);
+}
+
+#[test]
+fn compile_reassignment_as_separate_insts() {
+    let mut vm = VM::default();
+    let chunk = compile_text(&mut vm, "x = 1\nx = 2");
+    assert_eq!(count_insts(&chunk, "DECLARE_ASSIGN"), 1);
+    assert_eq!(count_insts(&chunk, "DECLARE_VAR"), 1);
+    assert_eq!(count_insts(&chunk, "ASSIGN_VAR"), 1);
+}
+
+#[test]
+fn compile_assignment_that_reads_var_declares_first() {
+    // The var is declared before the value is evaluated, so the value
+    // sees the new, nil var rather than the outer var.
+    let mut vm = VM::default();
+    let chunk = compile_text(&mut vm, "x = 1\nblock ->\n    x = (x, 2)");
+    assert_eq!(count_insts(&chunk, "DECLARE_ASSIGN"), 1);
+    assert_eq!(count_insts(&chunk, "DECLARE_VAR"), 1);
+    let mut vm = VM::default();
+    let result = eval_text(&mut vm, "x = 1\nblock ->\n    x = (x, 2)").unwrap();
+    assert_eq!(result.to_string(), "(nil, 2)");
+}
+
+/// Get the most recently compiled function from the VM's constants.
+fn last_func(vm: &VM) -> ObjectRef {
+    let mut funcs = vm.ctx.iter_constants().filter(|obj| obj.as_func().is_some());
+    funcs.next_back().expect("No functions compiled").clone()
+}
+
+#[test]
+fn compile_local_vars_as_slots() {
+    let mut vm = VM::default();
+    compile_text(&mut vm, "f = (a, b) ->\n    c = a\n    block ->\n        c + b");
+    let func = last_func(&vm);
+    let chunk = &func.as_func().unwrap().chunk;
+    // Params are in the call scope, which is above the function's scope.
+    assert!(chunk.contains(&Inst::LoadLocal(1, 0)), "{chunk:?}");
+    assert!(chunk.contains(&Inst::LoadLocal(2, 1)), "{chunk:?}");
+    assert_eq!(count_insts(chunk, "LOAD_LOCAL"), 3);
+    assert_eq!(count_insts(chunk, "LOAD_VAR"), 0);
+}
+
+#[test]
+fn compile_global_vars_by_name() {
+    let mut vm = VM::default();
+    let chunk = compile_text(&mut vm, "x = 1\nblock ->\n    x");
+    assert_eq!(count_insts(&chunk, "LOAD_VAR"), 1);
+    assert_eq!(count_insts(&chunk, "LOAD_LOCAL"), 0);
+}
+
+#[test]
+fn compile_local_reassignment_as_store_local() {
+    let mut vm = VM::default();
+    let chunk = compile_text(&mut vm, "block ->\n    x = 1\n    x = x + 1");
+    assert!(chunk.contains(&Inst::StoreLocal(0, 0)), "{chunk:?}");
+    assert_eq!(count_insts(&chunk, "DECLARE_VAR"), 0);
+    assert_eq!(count_insts(&chunk, "ASSIGN_VAR"), 0);
+}
+
+#[test]
+fn compile_vars_that_might_not_be_declared_by_name() {
+    // The second branch condition might not be evaluated, so `b` might
+    // not be declared, which would shift the slots of later vars.
+    let text = "\
+block ->
+    a = 1
+    if a == 1 -> nil
+    else if (b = 2) == 2 -> nil
+    c = 3
+    (a, c)";
+    let mut vm = VM::default();
+    let chunk = compile_text(&mut vm, text);
+    assert!(chunk.contains(&Inst::LoadLocal(0, 0)), "{chunk:?}");
+    assert!(chunk.contains(&Inst::LoadVar("c".to_owned())), "{chunk:?}");
+    let mut vm = VM::default();
+    assert_eq!(eval_text(&mut vm, text).unwrap().to_string(), "(1, 3)");
+
+    // Vars declared after a jump might be skipped.
+    let text = "block ->\n    jump end\n    a = 1\n    end: b = 2\n    b";
+    let mut vm = VM::default();
+    let chunk = compile_text(&mut vm, text);
+    assert!(chunk.contains(&Inst::LoadVar("b".to_owned())), "{chunk:?}");
+    let mut vm = VM::default();
+    assert_eq!(eval_text(&mut vm, text).unwrap().to_string(), "2");
+}
+
+#[test]
+fn compile_params_with_defaults_by_name() {
+    // Defaulted args are bound after the others, so the order params
+    // are bound in isn't known until the function is called.
+    let mut vm = VM::default();
+    compile_text(&mut vm, "f = (a, b=1) -> a");
+    let func = last_func(&vm);
+    let chunk = &func.as_func().unwrap().chunk;
+    assert!(chunk.contains(&Inst::LoadVar("a".to_owned())), "{chunk:?}");
+}
+
+/// Scan, parse, and compile the text, returning the name reported as
+/// undefined, if any, along with the suggested name, if any.
+fn undefined_name(vm: &mut VM, text: &str) -> Option<(String, Option<String>)> {
+    let mut source = source_from_text(text);
+    let scanner = Scanner::new(&mut source);
+    let mut parser = Parser::new(scanner.into_iter());
+    let program = parser.parse().expect("Could not parse program");
+    match compile_with_locations(vm, program) {
+        Ok(_) => None,
+        Err(err) => match err.kind {
+            CompErrKind::UndefinedName(name, suggestion) => Some((name, suggestion)),
+            kind => panic!("Unexpected compile error: {kind:?}"),
+        },
+    }
+}
+
+#[test]
+fn compile_read_of_undefined_name() {
+    let mut vm = VM::default();
+    assert_eq!(undefined_name(&mut vm, "x + 1"), Some(("x".to_owned(), None)));
+    // Vars assigned in a block aren't visible outside of it.
+    let text = "block ->\n    x = 1\nx";
+    assert_eq!(undefined_name(&mut vm, text), Some(("x".to_owned(), None)));
+}
+
+#[test]
+fn compile_read_of_defined_name() {
+    let mut vm = VM::default();
+    assert_eq!(undefined_name(&mut vm, "x = 1\nblock ->\n    x + 1"), None);
+    assert_eq!(undefined_name(&mut vm, "f = (a, b) -> a + b"), None);
+    assert_eq!(undefined_name(&mut vm, "try -> 1 catch e -> e.kind"), None);
+    // Names read in functions are looked up when they're called.
+    assert_eq!(undefined_name(&mut vm, "f = () -> x\nx = 1\nf()"), None);
+    assert_eq!(undefined_name(&mut vm, "f = () -> y"), None);
+    // Attribute names aren't vars.
+    assert_eq!(undefined_name(&mut vm, "x = (1, 2)\nx.length"), None);
+}
+
+#[test]
+fn compile_read_of_builtin_and_special_names() {
+    let mut vm = VM::default();
+    assert_eq!(undefined_name(&mut vm, "print(Int)"), None);
+    assert_eq!(undefined_name(&mut vm, "f = () -> $args\nf()"), None);
+    assert_eq!(undefined_name(&mut vm, "$main"), None);
+}
+
+#[test]
+fn compile_read_of_undefined_name_suggests_similar_name() {
+    let mut vm = VM::default();
+    let result = undefined_name(&mut vm, "total_count = 1\nblock ->\n    total_cuont");
+    assert_eq!(
+        result,
+        Some(("total_cuont".to_owned(), Some("total_count".to_owned())))
+    );
+    // Builtins are suggested too.
+    let result = undefined_name(&mut vm, "prnt(1)");
+    assert_eq!(result, Some(("prnt".to_owned(), Some("print".to_owned()))));
+    // Names that aren't visible aren't suggested.
+    let result = undefined_name(&mut vm, "block ->\n    total_count = 1\ntotal_cuont");
+    assert_eq!(result, Some(("total_cuont".to_owned(), None)));
+}
+
+#[test]
+fn compile_empty_block_as_nil() {
+    // The parser doesn't produce empty blocks, so the AST is built
+    // directly here: `(block -> <nothing>, 1)`
+    let loc = Location::new(1, 1);
+    let empty_block = ast::StatementBlock::new(vec![], loc, loc);
+    let items = vec![
+        ast::Expr::new_block(empty_block, loc, loc),
+        ast::Expr::new_int(BigInt::from(1), loc, loc),
+    ];
+    let expr = ast::Expr::new_tuple(items, loc, loc);
+    let program = ast::Program::new(vec![ast::Statement::new_expr(expr, loc, loc)]);
+    let mut vm = VM::default();
+    let chunk = compile(&mut vm, program).expect("Could not compile program");
+    let (_, result) = execute(&mut vm, &chunk);
+    assert_eq!(result, "(nil, 1)");
+    assert_eq!(vm.value_stack.size(), 1);
+}
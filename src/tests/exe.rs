@@ -0,0 +1,634 @@
+use num_bigint::BigInt;
+
+use crate::compiler::CompErrKind;
+use crate::eval_to_object;
+use crate::exe::{err_marker_padding, Executor};
+use crate::result::{ExeErr, ExeErrKind};
+use crate::scanner::{ScanErr, Scanner};
+use crate::types::ObjectRef;
+use crate::util::{source_from_text, Location};
+use crate::vm::{RuntimeErrKind, VM};
+
+/// Execute the text and return the object at the top of the stack,
+/// which will be the value of the last statement executed.
+pub fn eval_text(vm: &mut VM, text: &str) -> Result<ObjectRef, ExeErr> {
+    let mut executor = Executor::new(vm, false, false, false);
+    executor.execute_text(text, None)?;
+    let obj = vm.peek_obj().expect("Could not get top of stack");
+    Ok(obj.expect("Stack is empty"))
+}
+
+/// Execute the text and check that the result is the specified Int.
+fn assert_int_result(text: &str, expected: i64) {
+    let mut vm = VM::default();
+    let result = eval_text(&mut vm, text);
+    let obj = result.unwrap_or_else(|err| panic!("{text}: {err:?}"));
+    assert_eq!(obj.int_val(), Some(BigInt::from(expected)), "{text}");
+}
+
+#[test]
+fn eval_bitwise_ops() {
+    assert_int_result("6 & 3", 2);
+    assert_int_result("6 | 3", 7);
+    assert_int_result("6 ^ 3", 5);
+}
+
+#[test]
+fn eval_bitwise_precedence() {
+    // Bitwise operators bind more loosely than arithmetic operators
+    // and | < ^ < &, as in C and Python.
+    assert_int_result("4 + 4 & 3", 0);
+    assert_int_result("1 | 2 ^ 3 & 4", 3);
+}
+
+#[test]
+fn eval_pow_and_xor() {
+    // ** is exponentiation; ^ is bitwise XOR
+    assert_int_result("2 ** 10", 1024);
+    assert_int_result("2 ** 3 ** 2", 512);
+    assert_int_result("2 ^ 10", 8);
+}
+
+#[test]
+fn eval_bitwise_op_on_float() {
+    for text in ["1.0 & 1", "1 | 1.0", "1.0 ^ 1.0"] {
+        let mut vm = VM::default();
+        let result = eval_text(&mut vm, text);
+        assert!(
+            matches!(
+                result,
+                Err(ExeErr {
+                    kind: ExeErrKind::RuntimeErr(RuntimeErrKind::TypeErr(_))
+                })
+            ),
+            "{text}"
+        );
+    }
+}
+
+#[test]
+fn eval_many_statements_keeps_stack_bounded() {
+    let mut vm = VM::default();
+    let mut text = "x = 0\n".repeat(100);
+    text.push_str(&"x + 1\n".repeat(100));
+    text.push_str("x + 2");
+    let result = eval_text(&mut vm, text.as_str()).unwrap();
+    // Only the value of the last statement should be left on the stack
+    assert_eq!(vm.value_stack.size(), 1);
+    assert_eq!(result.int_val(), Some(BigInt::from(2)));
+}
+
+#[test]
+fn eval_block_keeps_only_last_value() {
+    let mut vm = VM::default();
+    let result = eval_text(&mut vm, "block ->\n    1\n    2\n    3").unwrap();
+    assert_eq!(vm.value_stack.size(), 1);
+    assert_eq!(result.int_val(), Some(BigInt::from(3)));
+}
+
+#[test]
+fn eval_jump_out_of_block() {
+    let mut vm = VM::default();
+    let text = "block ->\n    1\n    jump out\n    2\nout: 3";
+    let result = eval_text(&mut vm, text).unwrap();
+    assert_eq!(vm.value_stack.size(), 1);
+    assert_eq!(result.int_val(), Some(BigInt::from(3)));
+}
+
+#[test]
+fn eval_jump_out_of_nested_blocks() {
+    let mut vm = VM::default();
+    let text = "block ->\n    block ->\n        jump out\n        1\n    2\nout: 3";
+    let result = eval_text(&mut vm, text).unwrap();
+    assert_eq!(vm.value_stack.size(), 1);
+    assert_eq!(result.int_val(), Some(BigInt::from(3)));
+}
+
+/// Evaluate the text and return the compilation error.
+fn eval_comp_err(text: &str) -> CompErrKind {
+    let mut vm = VM::default();
+    match eval_text(&mut vm, text) {
+        Err(ExeErr { kind: ExeErrKind::CompErr(kind) }) => kind,
+        result => panic!("{text}: expected compilation error; got {result:?}"),
+    }
+}
+
+#[test]
+fn eval_jump_errors() {
+    // Backward jumps aren't allowed, so the label isn't found.
+    assert!(matches!(
+        eval_comp_err("back: 1\njump back"),
+        CompErrKind::LabelNotFoundInScope(name) if name == "back"
+    ));
+    assert!(matches!(
+        eval_comp_err("jump nowhere"),
+        CompErrKind::LabelNotFoundInScope(name) if name == "nowhere"
+    ));
+    // Labels in inner scopes aren't visible to outer scopes.
+    assert!(matches!(
+        eval_comp_err("jump inner\nblock ->\n    inner: 1"),
+        CompErrKind::LabelNotFoundInScope(name) if name == "inner"
+    ));
+    assert!(matches!(
+        eval_comp_err("a: 1\na: 2"),
+        CompErrKind::DuplicateLabelInScope(name) if name == "a"
+    ));
+    assert!(matches!(
+        eval_comp_err("f = () ->\n    jump out\nout: 1"),
+        CompErrKind::CannotJumpOutOfFunc(name) if name == "out"
+    ));
+}
+
+#[test]
+fn format_scan_err_with_context() {
+    let mut vm = VM::default();
+    let executor = Executor::new(&mut vm, false, false, false);
+    let mut source = source_from_text("x + @");
+    let err: ScanErr = Scanner::new(&mut source).find_map(|r| r.err()).unwrap();
+    let (message, loc) = executor.format_scan_err(&err);
+    assert_eq!(
+        message,
+        "Syntax error at column 5: after `x +`, found unexpected character '@'"
+    );
+    assert_eq!(loc.col, 5);
+}
+
+#[test]
+fn err_marker_after_emoji() {
+    // The emoji is two columns wide when displayed
+    let mut source = source_from_text("s = \"😀\" @");
+    let err: ScanErr = Scanner::new(&mut source).find_map(|r| r.err()).unwrap();
+    assert_eq!(err.location.col, 9);
+    assert_eq!(err.location.offset, 11);
+    let line = source.get_line(1).unwrap();
+    assert_eq!(err_marker_padding(line, err.location), " ".repeat(9));
+}
+
+#[test]
+fn err_marker_with_inexact_offset() {
+    // Offset derived from column is wrong for non-ASCII lines, so the
+    // column is used instead.
+    let padding = err_marker_padding("é😀 x", Location::new(1, 4));
+    assert_eq!(padding, "    ");
+    // Line not available
+    let padding = err_marker_padding("<line not available>", Location::new(1, 40));
+    assert_eq!(padding, " ".repeat(39));
+}
+
+#[test]
+fn eval_method_call() {
+    let mut vm = VM::default();
+    let result = eval_text(&mut vm, "\"a,b\".split_once(\",\")").unwrap();
+    let result = result.as_tuple().expect("Expected tuple");
+    assert_eq!(result.items()[0].str_val(), Some("a".to_owned()));
+    assert_eq!(result.items()[1].str_val(), Some("b".to_owned()));
+}
+
+#[test]
+fn eval_chained_method_calls() {
+    let mut vm = VM::default();
+    let result = eval_text(&mut vm, "x = 4\nx.abs().is_even()").unwrap();
+    assert_eq!(result.bool_val(), Some(true));
+}
+
+#[test]
+fn eval_call_of_non_method_attr() {
+    // Attributes that don't take `this` are called without the
+    // receiver.
+    assert_int_result("f = (x) -> x + 1\nt = (f, 2)\nt.0(41)", 42);
+}
+
+#[test]
+fn eval_list_literal() {
+    let mut vm = VM::default();
+    let result = eval_text(&mut vm, "[1, 2 + 3, \"a\",]").unwrap();
+    assert_eq!(result.to_string(), "[1, 5, \"a\"]");
+    let result = eval_text(&mut vm, "[]").unwrap();
+    assert_eq!(result.to_string(), "[]");
+    let result = eval_text(&mut vm, "[1, 2, 3, 4].rotate(1)").unwrap();
+    assert_eq!(result.to_string(), "[2, 3, 4, 1]");
+}
+
+const COUNT_DOWN: &str = "\
+count = (n) ->
+    if n == 0 -> 0
+    else -> 1 + count(n - 1)
+";
+
+#[test]
+fn eval_recursive_factorial() {
+    let source = "\
+fact = (n) ->
+    if n <= 1 -> 1
+    else -> n * fact(n - 1)
+fact(10)
+";
+    assert_int_result(source, 3_628_800);
+}
+
+#[test]
+fn eval_deep_recursion() {
+    assert_int_result(&format!("{COUNT_DOWN}count(100)"), 100);
+}
+
+#[test]
+fn eval_recursion_limit() {
+    let mut vm = VM::default();
+    vm.max_call_depth = 10;
+    let source = format!("{COUNT_DOWN}count(10)");
+    match eval_text(&mut vm, &source) {
+        Err(ExeErr { kind: ExeErrKind::RuntimeErr(kind) }) => {
+            assert!(matches!(kind, RuntimeErrKind::RecursionDepthExceeded(10)))
+        }
+        result => panic!("Expected recursion limit error; got {result:?}"),
+    }
+    // The call stack is unwound after the error.
+    assert_eq!(vm.frame_stack.size(), 0);
+    assert_eq!(
+        eval_text(&mut vm, "count(9)").unwrap().int_val(),
+        Some(BigInt::from(9))
+    );
+}
+
+#[test]
+fn compile_only_reports_errors() {
+    let mut vm = VM::default();
+    let mut executor = Executor::new(&mut vm, false, false, false);
+    let result = executor.compile_only_text("$x = 1", None);
+    assert!(
+        matches!(
+            result,
+            Err(ExeErr {
+                kind: ExeErrKind::CompErr(CompErrKind::CannotAssignSpecialIdent(_))
+            })
+        ),
+        "{result:?}"
+    );
+    let result = executor.compile_only_text("x = (1", None);
+    assert!(
+        matches!(result, Err(ExeErr { kind: ExeErrKind::ScanErr(_) })),
+        "{result:?}"
+    );
+}
+
+#[test]
+fn compile_only_does_not_execute() {
+    let mut vm = VM::default();
+    let mut executor = Executor::new(&mut vm, false, false, false);
+    let result = executor.compile_only_text("x = 1\nassert(false)", None);
+    assert!(result.is_ok(), "{result:?}");
+    // Nothing was executed, so the var wasn't assigned.
+    let result = eval_text(&mut vm, "x");
+    assert!(
+        matches!(
+            result,
+            Err(ExeErr { kind: ExeErrKind::CompErr(CompErrKind::UndefinedName(..)) })
+        ),
+        "{result:?}"
+    );
+}
+
+#[test]
+fn name_err_suggests_similar_name() {
+    let mut vm = VM::default();
+    let result = eval_text(&mut vm, "total_count = 1\ntotal_cuont + 1");
+    match result {
+        Err(ExeErr {
+            kind: ExeErrKind::CompErr(CompErrKind::UndefinedName(name, suggestion)),
+        }) => {
+            assert_eq!(name, "total_cuont");
+            assert_eq!(suggestion.as_deref(), Some("total_count"));
+        }
+        result => panic!("Expected undefined name error; got {result:?}"),
+    }
+    // Names that aren't close to any var don't get a suggestion.
+    match eval_text(&mut vm, "zzzzzz") {
+        Err(ExeErr {
+            kind: ExeErrKind::CompErr(CompErrKind::UndefinedName(name, suggestion)),
+        }) => {
+            assert_eq!(name, "zzzzzz");
+            assert_eq!(suggestion, None);
+        }
+        result => panic!("Expected undefined name error; got {result:?}"),
+    }
+}
+
+#[test]
+fn name_err_in_func_suggests_similar_name() {
+    // Names read in functions are looked up when the function is
+    // called, so misspellings in functions are caught at run time.
+    let mut vm = VM::default();
+    let result = eval_text(&mut vm, "total_count = 1\nf = () -> total_cuont + 1\nf()");
+    match result {
+        Err(ExeErr {
+            kind: ExeErrKind::RuntimeErr(RuntimeErrKind::NameErr(message)),
+        }) => {
+            assert!(message.contains("did you mean `total_count`?"), "{message}")
+        }
+        result => panic!("Expected name error; got {result:?}"),
+    }
+    match eval_text(&mut vm, "f = () -> zzzzzz\nf()") {
+        Err(ExeErr {
+            kind: ExeErrKind::RuntimeErr(RuntimeErrKind::NameErr(message)),
+        }) => {
+            assert_eq!(message, "Name not found: zzzzzz")
+        }
+        result => panic!("Expected name error; got {result:?}"),
+    }
+}
+
+#[test]
+fn eval_to_object_and_convert() {
+    let result = eval_to_object("6 * 7").unwrap();
+    assert_eq!(result.as_i64(), Some(42));
+    assert_eq!(result.as_f64(), Some(42.0));
+    assert_eq!(result.as_string(), None);
+    let result = eval_to_object("\"hi\"").unwrap();
+    assert_eq!(result.as_string(), Some("hi".to_owned()));
+    assert_eq!(result.as_i64(), None);
+    let result = eval_to_object("x = 1.5\nx * 2").unwrap();
+    assert_eq!(result.as_f64(), Some(3.0));
+    assert_eq!(result.as_i64(), None);
+    // Ints that don't fit in an i64 can't be converted.
+    let result = eval_to_object("2 ** 64").unwrap();
+    assert_eq!(result.as_i64(), None);
+}
+
+#[test]
+fn eval_to_object_with_error() {
+    let result = eval_to_object("1 + nil");
+    assert!(
+        matches!(
+            result,
+            Err(ExeErr { kind: ExeErrKind::RuntimeErr(RuntimeErrKind::TypeErr(_)) })
+        ),
+        "{result:?}"
+    );
+}
+
+#[test]
+fn eval_call_with_default_param() {
+    let source = "\
+f = (x, y = 10) -> x + y
+";
+    assert_int_result(&format!("{source}f(1)"), 11);
+    assert_int_result(&format!("{source}f(1, 2)"), 3);
+}
+
+#[test]
+fn eval_default_param_refers_to_earlier_param() {
+    let source = "\
+f = (x, y = x * 2, z = y + 1) -> x + y + z
+";
+    assert_int_result(&format!("{source}f(1)"), 6);
+    assert_int_result(&format!("{source}f(1, 1)"), 4);
+    assert_int_result(&format!("{source}f(1, 1, 1)"), 3);
+}
+
+#[test]
+fn eval_call_missing_required_arg() {
+    let mut vm = VM::default();
+    let source = "\
+f = (x, y = 10) -> x + y
+f()
+";
+    match eval_text(&mut vm, source) {
+        Err(ExeErr {
+            kind: ExeErrKind::RuntimeErr(RuntimeErrKind::TypeErr(message)),
+        }) => {
+            assert_eq!(message, "f() expected 2 args; got 0")
+        }
+        result => panic!("Expected arity error; got {result:?}"),
+    }
+    let mut vm = VM::default();
+    let source = "\
+f = (x, y = 10) -> x + y
+f(1, 2, 3)
+";
+    assert!(eval_text(&mut vm, source).is_err());
+}
+
+const SUM_REST: &str = "\
+f = (first, rest...) -> first + rest.sum()
+";
+
+#[test]
+fn eval_call_with_rest_param() {
+    assert_int_result(&format!("{SUM_REST}f(1)"), 1);
+    assert_int_result(&format!("{SUM_REST}f(1, 2)"), 3);
+    assert_int_result(&format!("{SUM_REST}f(1, 2, 3, 4)"), 10);
+}
+
+#[test]
+fn eval_rest_param_is_tuple() {
+    let source = "\
+f = (first, rest...) -> rest
+";
+    let mut vm = VM::default();
+    let result = eval_text(&mut vm, &format!("{source}f(1)")).unwrap();
+    assert_eq!(result.type_name(), "Tuple");
+    assert_eq!(result.len(), Some(0));
+    let result = eval_text(&mut vm, &format!("{source}f(1, 2, 3)")).unwrap();
+    assert_eq!(result.len(), Some(2));
+    assert_eq!(
+        result.get_item(&vm.ctx.builtins.new_int(1), &vm.ctx).unwrap().int_val(),
+        Some(BigInt::from(3))
+    );
+}
+
+#[test]
+fn eval_call_with_rest_param_missing_required_arg() {
+    let mut vm = VM::default();
+    match eval_text(&mut vm, &format!("{SUM_REST}f()")) {
+        Err(ExeErr {
+            kind: ExeErrKind::RuntimeErr(RuntimeErrKind::TypeErr(message)),
+        }) => {
+            assert_eq!(message, "f() expected at least 1 arg; got 0")
+        }
+        result => panic!("Expected arity error; got {result:?}"),
+    }
+}
+
+// Labeled loops -------------------------------------------------------
+
+#[test]
+fn eval_break_out_of_labeled_loop() {
+    let mut vm = VM::default();
+    let text = "\
+i = 0
+outer: loop ->
+    i += 1
+    j = 0
+    loop j < 10 ->
+        j += 1
+        if i == 2 && j == 3 -> break outer i * 10 + j
+    nil";
+    let result = eval_text(&mut vm, text).unwrap();
+    assert_eq!(result.int_val(), Some(BigInt::from(23)));
+    assert_eq!(vm.value_stack.size(), 1);
+    // The inner `break outer` exits both loops.
+    assert_eq!(eval_text(&mut vm, "i").unwrap().int_val(), Some(BigInt::from(2)));
+}
+
+#[test]
+fn eval_continue_labeled_loop() {
+    let mut vm = VM::default();
+    let text = "\
+i = 0
+n = [0]
+outer: loop i < 3 ->
+    i += 1
+    j = 0
+    loop ->
+        j += 1
+        if j == 2 -> continue outer
+        n.0 = n.0 + 1
+n.0";
+    let result = eval_text(&mut vm, text).unwrap();
+    assert_eq!(result.int_val(), Some(BigInt::from(3)));
+    assert_eq!(vm.value_stack.size(), 1);
+}
+
+#[test]
+fn eval_break_with_value_named_like_no_label() {
+    // An ident that doesn't name an enclosing loop is a break value.
+    assert_int_result("x = 4\nloop ->\n    break x", 4);
+}
+
+#[test]
+fn eval_labeled_continue_to_unknown_loop() {
+    let mut vm = VM::default();
+    for text in [
+        "loop ->\n    continue outer",
+        "outer: loop ->\n    f = () ->\n        loop ->\n            continue outer\n    break",
+    ] {
+        match eval_text(&mut vm, text) {
+            Err(ExeErr {
+                kind: ExeErrKind::CompErr(CompErrKind::LabelNotFoundInScope(name)),
+            }) => assert_eq!(name, "outer"),
+            result => panic!("Expected label not found error; got {result:?}"),
+        }
+    }
+}
+
+// Errors --------------------------------------------------------------
+
+/// Execute the text and return the value of the last statement as a
+/// string, panicking on error.
+fn eval_str(vm: &mut VM, text: &str) -> String {
+    let result = eval_text(vm, text).unwrap_or_else(|err| panic!("{text}: {err:?}"));
+    result.str_val().unwrap_or_else(|| panic!("{text}: expected Str; got {result:?}"))
+}
+
+#[test]
+fn eval_catch_raised_error() {
+    let mut vm = VM::default();
+    let text = "\
+try ->
+    raise error(\"ValueErr\", \"bad value\")
+    1
+catch e ->
+    $\"{e.kind} {e.message}\"";
+    assert_eq!(eval_str(&mut vm, text), "ValueErr bad value");
+    assert_eq!(vm.value_stack.size(), 1);
+    assert!(vm.handler_stack.is_empty());
+}
+
+#[test]
+fn eval_catch_raised_non_error() {
+    let mut vm = VM::default();
+    let text = "try -> raise \"oops\" catch e -> e.kind + \" \" + e.message";
+    assert_eq!(eval_str(&mut vm, text), "Error oops");
+    // The error doesn't have to be bound to a name.
+    assert_eq!(eval_str(&mut vm, "try -> raise 1 catch -> \"caught\""), "caught");
+}
+
+#[test]
+fn eval_catch_runtime_error() {
+    let mut vm = VM::default();
+    assert_eq!(eval_str(&mut vm, "try -> 1 + \"a\" catch e -> e.kind"), "TypeErr");
+    assert_eq!(vm.value_stack.size(), 1);
+    assert_eq!(eval_str(&mut vm, "try -> 1 / 0 catch e -> e.kind"), "DivisionByZero");
+}
+
+#[test]
+fn eval_try_without_error() {
+    assert_int_result("try -> 1 catch e -> 2", 1);
+    // The catch name isn't bound when no error occurs.
+    let mut vm = VM::default();
+    let text = "x = try -> 1 catch e -> 2\ne";
+    assert!(matches!(
+        eval_text(&mut vm, text),
+        Err(ExeErr { kind: ExeErrKind::CompErr(CompErrKind::UndefinedName(..)) })
+    ));
+}
+
+#[test]
+fn eval_catch_error_from_nested_call() {
+    let mut vm = VM::default();
+    let text = "\
+f = (x) -> raise $\"bad {x}\"
+g = (x) -> f(x) + 1
+try ->
+    g(1)
+catch e ->
+    e.message";
+    assert_eq!(eval_str(&mut vm, text), "bad 1");
+    assert_eq!(vm.value_stack.size(), 1);
+    assert_eq!(vm.frame_stack.size(), 0);
+    assert!(vm.handler_stack.is_empty());
+}
+
+#[test]
+fn eval_nested_try() {
+    let mut vm = VM::default();
+    // An error in a catch block is caught by the outer handler.
+    let text = "\
+try ->
+    try ->
+        raise \"inner\"
+    catch e ->
+        raise e.message + \" again\"
+catch e ->
+    e.message";
+    assert_eq!(eval_str(&mut vm, text), "inner again");
+    // An error after the inner try is caught by the outer handler.
+    let text = "\
+try ->
+    try -> 1 catch -> \"wrong\"
+    raise \"outer\"
+catch e ->
+    e.message";
+    assert_eq!(eval_str(&mut vm, text), "outer");
+}
+
+#[test]
+fn eval_break_out_of_try_removes_handler() {
+    let mut vm = VM::default();
+    let text = "\
+loop ->
+    try ->
+        break 1
+    catch ->
+        2";
+    let result = eval_text(&mut vm, text).unwrap();
+    assert_eq!(result.int_val(), Some(BigInt::from(1)));
+    assert!(vm.handler_stack.is_empty());
+    // A later error isn't caught by the stale handler.
+    assert!(matches!(
+        eval_text(&mut vm, "raise \"later\""),
+        Err(ExeErr { kind: ExeErrKind::RuntimeErr(RuntimeErrKind::Raised(_)) })
+    ));
+}
+
+#[test]
+fn eval_uncaught_error_propagates() {
+    let mut vm = VM::default();
+    let text = "f = () -> raise error(\"ValueErr\", \"bad\")\nf()";
+    match eval_text(&mut vm, text) {
+        Err(ExeErr { kind: ExeErrKind::RuntimeErr(RuntimeErrKind::Raised(obj)) }) => {
+            assert_eq!(obj.to_string(), "ValueErr: bad");
+        }
+        result => panic!("Expected raised error; got {result:?}"),
+    }
+}
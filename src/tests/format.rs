@@ -3,8 +3,15 @@ use num_bigint::BigInt;
 use crate::format::FormatStrErr::*;
 use crate::format::FormatStrToken::*;
 use crate::format::*;
+use crate::parser::{ParseErr, ParseErrKind};
+use crate::scanner::ScanErrKind;
 use crate::scanner::{Token, TokenWithLocation};
+use crate::types::{Args, CallResult};
 use crate::util::Location;
+use crate::vm::{RuntimeErrKind, VM};
+
+use super::exe::eval_text;
+use super::parser::parse_text;
 
 fn scan_ok(string: &str, expected_num_tokens: usize) -> Vec<FormatStrToken> {
     let result = scan_format_string(string);
@@ -18,18 +25,21 @@ fn scan_ok(string: &str, expected_num_tokens: usize) -> Vec<FormatStrToken> {
 fn scan_simple() {
     let tokens = scan_ok("{1}", 1);
     let token = tokens.first().unwrap();
-    let expected = Expr(vec![
-        TokenWithLocation::new(
-            Token::Int(BigInt::from(1)),
-            Location::new(1, 1),
-            Location::new(1, 1),
-        ),
-        TokenWithLocation::new(
-            Token::EndOfStatement,
-            Location::new(1, 2),
-            Location::new(1, 2),
-        ),
-    ]);
+    let expected = Expr(
+        vec![
+            TokenWithLocation::new(
+                Token::Int(BigInt::from(1)),
+                Location::new(1, 3),
+                Location::new(1, 3),
+            ),
+            TokenWithLocation::new(
+                Token::EndOfStatement,
+                Location::new(1, 4),
+                Location::new(1, 4),
+            ),
+        ],
+        None,
+    );
     assert_eq!(token, &expected);
 }
 
@@ -43,18 +53,21 @@ fn scan_two_expr() {
     assert_eq!(token, &expected);
 
     token = tokens.get(1).unwrap();
-    let expected = Expr(vec![
-        TokenWithLocation::new(
-            Token::Int(BigInt::from(1)),
-            Location::new(1, 1),
-            Location::new(1, 1),
-        ),
-        TokenWithLocation::new(
-            Token::EndOfStatement,
-            Location::new(1, 2),
-            Location::new(1, 2),
-        ),
-    ]);
+    let expected = Expr(
+        vec![
+            TokenWithLocation::new(
+                Token::Int(BigInt::from(1)),
+                Location::new(1, 4),
+                Location::new(1, 4),
+            ),
+            TokenWithLocation::new(
+                Token::EndOfStatement,
+                Location::new(1, 5),
+                Location::new(1, 5),
+            ),
+        ],
+        None,
+    );
     assert_eq!(token, &expected);
 
     token = tokens.get(2).unwrap();
@@ -62,18 +75,21 @@ fn scan_two_expr() {
     assert_eq!(token, &expected);
 
     token = tokens.get(3).unwrap();
-    let expected = Expr(vec![
-        TokenWithLocation::new(
-            Token::Str("2".to_owned()),
-            Location::new(1, 1),
-            Location::new(1, 3),
-        ),
-        TokenWithLocation::new(
-            Token::EndOfStatement,
-            Location::new(1, 4),
-            Location::new(1, 4),
-        ),
-    ]);
+    let expected = Expr(
+        vec![
+            TokenWithLocation::new(
+                Token::Str("2".to_owned()),
+                Location::new(1, 8),
+                Location::new(1, 10),
+            ),
+            TokenWithLocation::new(
+                Token::EndOfStatement,
+                Location::new(1, 11),
+                Location::new(1, 11),
+            ),
+        ],
+        None,
+    );
     assert_eq!(token, &expected);
 
     token = tokens.get(4).unwrap();
@@ -110,7 +126,7 @@ fn scan_no_expr() {
 #[test]
 fn scan_empty_expr() {
     let result = scan_format_string("{}");
-    assert_eq!(result, Err(EmptyExpr(0)));
+    assert_eq!(result, Err(EmptyExpr(2)));
 }
 
 #[test]
@@ -128,3 +144,227 @@ fn scan_unmatched_closing_bracket() {
     let result = scan_format_string("a1}");
     assert_eq!(result, Err(UnmatchedClosingBracket(4)));
 }
+
+#[test]
+fn scan_spec() {
+    let tokens = scan_ok("{n:x}", 1);
+    match tokens.first().unwrap() {
+        Expr(tokens, Some(spec)) => {
+            assert_eq!(tokens[0].token, Token::Ident("n".to_owned()));
+            assert_eq!(spec.base, Some(NumberBase::LowerHex));
+        }
+        token => panic!("Unexpected token: {token:?}"),
+    }
+}
+
+#[test]
+fn scan_colon_in_string_is_not_spec() {
+    let tokens = scan_ok("{\"a:b\"}", 1);
+    assert!(matches!(tokens.first().unwrap(), Expr(_, None)));
+}
+
+#[test]
+fn scan_invalid_spec() {
+    // The position is that of the spec in $"{n:z}"
+    let result = scan_format_string("{n:z}");
+    assert_eq!(result, Err(InvalidSpec("z".to_owned(), 5)));
+    let result = scan_format_string("ab {x + 1:zz}");
+    assert_eq!(result, Err(InvalidSpec("zz".to_owned(), 12)));
+}
+
+#[test]
+fn scan_non_ascii() {
+    let tokens = scan_ok("é{1}ü", 3);
+    assert_eq!(tokens.first().unwrap(), &Str("é".to_owned()));
+    assert_eq!(tokens.last().unwrap(), &Str("ü".to_owned()));
+}
+
+#[test]
+fn parse_valid_expr() {
+    let result = parse_text("$\"a {1 + 2} b\"");
+    assert!(result.is_ok());
+}
+
+#[test]
+fn parse_empty_expr() {
+    match parse_text("x = $\"{}\"") {
+        Err(ParseErr { kind: ParseErrKind::ScanErr(err) }) => match err.kind {
+            // The error position is relative to the $ at column 5
+            ScanErrKind::FormatStrErr(EmptyExpr(pos)) => {
+                assert_eq!(err.location.col, 5);
+                assert_eq!(err.location.col + pos, 7);
+            }
+            kind => panic!("Unexpected scan error: {kind:?}"),
+        },
+        result => panic!("Expected scan error; got {result:?}"),
+    }
+}
+
+#[test]
+fn parse_invalid_expr() {
+    // The { is at column 11 and the } is at column 15
+    let text = "y = $\"abc {1 +} def\"";
+    let loc = match parse_text(text) {
+        Err(ParseErr { kind: ParseErrKind::UnexpectedToken(token) }) => token.start,
+        result => panic!("Expected unexpected token error; got {result:?}"),
+    };
+    assert_eq!(loc.line, 1);
+    assert!(loc.col > 11 && loc.col <= 15, "{loc:?}");
+}
+
+#[test]
+fn parse_invalid_expr_on_later_line() {
+    let text = "x = 1\ny = $\"a\nb {1 +} def\"";
+    let loc = match parse_text(text) {
+        Err(ParseErr { kind: ParseErrKind::UnexpectedToken(token) }) => token.start,
+        result => panic!("Expected unexpected token error; got {result:?}"),
+    };
+    assert_eq!(loc.line, 3);
+    assert!(loc.col > 3 && loc.col <= 7, "{loc:?}");
+}
+
+fn eval_format_string(text: &str) -> String {
+    let mut vm = VM::default();
+    let obj = eval_text(&mut vm, text).unwrap_or_else(|err| panic!("{text}: {err:?}"));
+    obj.str_val().unwrap()
+}
+
+#[test]
+fn format_embedded_objects() {
+    assert_eq!(eval_format_string("n = 42\n$\"n = {n}\""), "n = 42");
+    assert_eq!(eval_format_string("x = 1.5\n$\"x = {x}\""), "x = 1.5");
+    assert_eq!(eval_format_string("x = 2.0\n$\"x = {x}\""), "x = 2.0");
+    assert_eq!(eval_format_string("$\"x = {nil}\""), "x = nil");
+    assert_eq!(
+        eval_format_string("t = (1, \"a\", nil)\n$\"t = {t}\""),
+        "t = (1, \"a\", nil)"
+    );
+    // Embedded strings are included as is
+    assert_eq!(eval_format_string("s = \"a\"\n$\"s = {s}\""), "s = a");
+}
+
+#[test]
+fn format_embedded_objects_agree_with_str() {
+    for expr in ["42", "1.5", "nil", "true", "(1, \"a\")", "[1.0, nil]", "\"a\""] {
+        let text = format!("x = {expr}\n$\"{{x}}\" == str(x)");
+        let mut vm = VM::default();
+        let result = eval_text(&mut vm, &text).unwrap();
+        assert_eq!(result.bool_val(), Some(true), "{expr}");
+    }
+}
+
+#[test]
+fn format_embedded_object_with_custom_str_method() {
+    fn custom_str(_args: Args, vm: &mut VM) -> CallResult {
+        Ok(Some(vm.ctx.builtins.new_str("<custom>")))
+    }
+
+    let mut vm = VM::default();
+    let class = vm.ctx.builtins.new_type("test", "Custom");
+    let obj = vm.ctx.builtins.new_custom_instance(class);
+    let str_method =
+        vm.ctx.builtins.new_builtin_func("$str", Some(vec!["this"]), custom_str);
+    obj.set_attr("$str", str_method, &vm.ctx).unwrap();
+    vm.ctx.declare_and_assign_var("obj", obj).unwrap();
+    let result = eval_text(&mut vm, "$\"obj = {obj}\"").unwrap();
+    assert_eq!(result.str_val(), Some("obj = <custom>".to_owned()));
+}
+
+#[test]
+fn format_hex() {
+    assert_eq!(eval_format_string("n = 255\n$\"{n:x}\""), "ff");
+    assert_eq!(eval_format_string("n = 255\n$\"0x{n:X}\""), "0xFF");
+}
+
+#[test]
+fn format_binary() {
+    assert_eq!(eval_format_string("$\"{5:b}\""), "101");
+    assert_eq!(eval_format_string("$\"{2 ** 3:b}\""), "1000");
+}
+
+#[test]
+fn format_octal() {
+    assert_eq!(eval_format_string("$\"{8:o} {64:o}\""), "10 100");
+}
+
+#[test]
+fn format_base_requires_int() {
+    let mut vm = VM::default();
+    let result = eval_text(&mut vm, "$\"{1.5:x}\"");
+    let err = result.unwrap_err();
+    assert!(
+        matches!(
+            err.kind,
+            crate::result::ExeErrKind::RuntimeErr(RuntimeErrKind::TypeErr(_))
+        ),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn parse_spec() {
+    let spec = FormatSpec::parse("*^010.3x").unwrap();
+    assert_eq!(spec.fill, '*');
+    assert_eq!(spec.align, Some(Align::Center));
+    assert!(spec.zero);
+    assert_eq!(spec.width, Some(10));
+    assert_eq!(spec.precision, Some(3));
+    assert_eq!(spec.base, Some(NumberBase::LowerHex));
+    for spec in ["", ".", "5.", "<<<", "x5", "10z", "+10"] {
+        assert!(FormatSpec::parse(spec).is_none(), "{spec}");
+    }
+}
+
+#[test]
+fn spec_display_round_trips() {
+    for spec in [">10", "*<5", "04", "^8.2", ".3", "08x", "X"] {
+        let parsed = FormatSpec::parse(spec).unwrap();
+        assert_eq!(parsed.to_string(), spec);
+    }
+}
+
+#[test]
+fn format_right_align() {
+    assert_eq!(eval_format_string("s = 'ab'\n$\"[{s:>5}]\""), "[   ab]");
+    assert_eq!(eval_format_string("$\"[{42:>5}]\""), "[   42]");
+    assert_eq!(eval_format_string("$\"[{42:*>5}]\""), "[***42]");
+}
+
+#[test]
+fn format_default_align() {
+    // Strings are left-aligned and numbers are right-aligned
+    assert_eq!(eval_format_string("$\"[{'ab':5}]\""), "[ab   ]");
+    assert_eq!(eval_format_string("$\"[{42:5}]\""), "[   42]");
+    assert_eq!(eval_format_string("$\"[{'ab':-^6}]\""), "[--ab--]");
+}
+
+#[test]
+fn format_zero_padded_int() {
+    assert_eq!(eval_format_string("n = 7\n$\"{n:04}\""), "0007");
+    assert_eq!(eval_format_string("n = -7\n$\"{n:04}\""), "-007");
+    assert_eq!(eval_format_string("n = 12345\n$\"{n:04}\""), "12345");
+    assert_eq!(eval_format_string("$\"{255:08x}\""), "000000ff");
+}
+
+#[test]
+fn format_float_precision() {
+    assert_eq!(eval_format_string("x = 3.14159\n$\"{x:.2}\""), "3.14");
+    assert_eq!(eval_format_string("x = 2.5\n$\"{x:.0}\""), "2");
+    assert_eq!(eval_format_string("x = 1.5\n$\"{x:08.3}\""), "0001.500");
+    assert_eq!(eval_format_string("$\"{2:.1}\""), "2.0");
+}
+
+#[test]
+fn format_spec_type_errors() {
+    for text in ["$\"{'a':.2}\"", "$\"{'a':05}\"", "$\"{1.5:x}\""] {
+        let mut vm = VM::default();
+        let err = eval_text(&mut vm, text).unwrap_err();
+        assert!(
+            matches!(
+                err.kind,
+                crate::result::ExeErrKind::RuntimeErr(RuntimeErrKind::TypeErr(_))
+            ),
+            "{text}: {err:?}"
+        );
+    }
+}
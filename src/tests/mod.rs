@@ -1,4 +1,5 @@
 mod ast;
+mod builtin_funcs;
 mod compiler;
 mod exe;
 mod format;
@@ -1,7 +1,7 @@
 use num_bigint::BigInt;
 
 use crate::ast;
-use crate::scanner::Scanner;
+use crate::scanner::{Scanner, Token};
 use crate::util::{source_from_text, BinaryOperator, Location};
 
 use crate::parser::*;
@@ -253,3 +253,326 @@ sub(1, 2, 3)
         assert!(false, "Function def failed to parse: {:?}", err);
     }
 }
+
+#[test]
+fn parse_func_with_defaults() {
+    let program = parse_text("(x, y = 1, z = y) -> x").expect("Failed to parse");
+    let statement = program.statements.into_iter().next().unwrap();
+    let func = match statement.kind {
+        ast::StatementKind::Expr(ast::Expr {
+            kind: ast::ExprKind::Func(func), ..
+        }) => func,
+        kind => panic!("Expected func; got {kind:?}"),
+    };
+    assert_eq!(func.params, Some(vec!["x".to_owned(), "y".to_owned(), "z".to_owned()]));
+    assert_eq!(func.defaults.len(), 2);
+    assert_eq!(func.defaults[1].is_ident(), Some("y".to_owned()));
+}
+
+#[test]
+fn parse_func_with_required_param_after_default() {
+    match parse_text("(x = 1, y) -> x") {
+        Err(ParseErr { kind: ParseErrKind::ExpectedDefault(_) }) => (),
+        result => panic!("Expected missing default error; got {result:?}"),
+    }
+}
+
+#[test]
+fn parse_func_with_rest_param() {
+    let program = parse_text("(x, rest...) -> x").expect("Failed to parse");
+    let statement = program.statements.into_iter().next().unwrap();
+    let func = match statement.kind {
+        ast::StatementKind::Expr(ast::Expr {
+            kind: ast::ExprKind::Func(func), ..
+        }) => func,
+        kind => panic!("Expected func; got {kind:?}"),
+    };
+    assert_eq!(func.params, Some(vec!["x".to_owned()]));
+    assert_eq!(func.rest, Some("rest".to_owned()));
+}
+
+#[test]
+fn parse_misplaced_rest_param() {
+    for source in ["(rest..., x) -> x", "(x, rest...)", "f(x...)"] {
+        match parse_text(source) {
+            Err(ParseErr { kind: ParseErrKind::MisplacedRestParam(_) }) => (),
+            result => panic!("{source}: expected misplaced rest error; got {result:?}"),
+        }
+    }
+}
+
+#[test]
+fn parse_multi_assign() {
+    let program = parse_text("a, b, c = t").expect("Failed to parse");
+    let statement = program.statements.into_iter().next().unwrap();
+    match statement.kind {
+        ast::StatementKind::Expr(ast::Expr {
+            kind: ast::ExprKind::MultiAssign(names, value),
+            ..
+        }) => {
+            assert_eq!(names, vec!["a", "b", "c"]);
+            assert_eq!(value.is_ident(), Some("t".to_owned()));
+        }
+        kind => panic!("Expected multiple assignment; got {kind:?}"),
+    }
+}
+
+#[test]
+fn parse_multi_assign_with_non_ident_target() {
+    for source in ["a, 1 = t", "1, a = t", "a.0, b = t"] {
+        match parse_text(source) {
+            Err(ParseErr { kind: ParseErrKind::ExpectedIdent(_) }) => (),
+            result => panic!("{source}: expected ident error; got {result:?}"),
+        }
+    }
+}
+
+#[test]
+fn parse_comparison_chain() {
+    let program = parse_text("1 < x <= 10").expect("Failed to parse");
+    let statement = program.statements.into_iter().next().unwrap();
+    // The comparisons are wrapped in a block that scopes the temp vars.
+    let block = match statement.kind {
+        ast::StatementKind::Expr(ast::Expr {
+            kind: ast::ExprKind::Block(block),
+            ..
+        }) => block,
+        kind => panic!("Expected block; got {kind:?}"),
+    };
+    assert_eq!(block.statements.len(), 1);
+    let statement = block.statements.into_iter().next().unwrap();
+    let (a, b) = match statement.kind {
+        ast::StatementKind::Expr(ast::Expr {
+            kind: ast::ExprKind::BinaryOp(a, BinaryOperator::And, b),
+            ..
+        }) => (a, b),
+        kind => panic!("Expected && of comparisons; got {kind:?}"),
+    };
+    // 1 < ($tmp.0 = x)
+    match a.kind {
+        ast::ExprKind::BinaryOp(_, BinaryOperator::LessThan, x) => match x.kind {
+            ast::ExprKind::BinaryOp(temp, BinaryOperator::Assign, x) => {
+                assert_eq!(temp.is_special_ident(), Some("$tmp.0".to_owned()));
+                assert_eq!(x.is_ident(), Some("x".to_owned()));
+            }
+            kind => panic!("Expected temp assignment; got {kind:?}"),
+        },
+        kind => panic!("Expected < comparison; got {kind:?}"),
+    }
+    // $tmp.0 <= 10
+    match b.kind {
+        ast::ExprKind::BinaryOp(temp, BinaryOperator::LessThanOrEqual, _) => {
+            assert_eq!(temp.is_special_ident(), Some("$tmp.0".to_owned()));
+        }
+        kind => panic!("Expected <= comparison; got {kind:?}"),
+    }
+}
+
+#[test]
+fn parse_comparison_not_chained() {
+    // Parenthesized comparisons and non-relational operators aren't
+    // chained.
+    for source in ["(1 < 2) < 3", "1 < (2 < 3)", "1 < 2 && 2 < 3", "1 === 1 == true"] {
+        let program = parse_text(source).expect("Failed to parse");
+        let statement = program.statements.into_iter().next().unwrap();
+        match statement.kind {
+            ast::StatementKind::Expr(ast::Expr {
+                kind: ast::ExprKind::BinaryOp(_, op, _),
+                ..
+            }) => {
+                assert_ne!(op, BinaryOperator::Assign, "{source}");
+                if source.contains("&&") {
+                    assert_eq!(op, BinaryOperator::And, "{source}");
+                } else {
+                    assert_ne!(op, BinaryOperator::And, "{source}");
+                }
+            }
+            kind => panic!("{source}: expected binary op; got {kind:?}"),
+        }
+    }
+}
+
+/// Parse the text, which should be a single expression, and return
+/// the expression.
+fn parse_expr(text: &str) -> ast::Expr {
+    let program = parse_text(text).unwrap_or_else(|err| panic!("{text}: {err:?}"));
+    assert_eq!(program.statements.len(), 1, "{text}");
+    match program.statements.into_iter().next().unwrap().kind {
+        ast::StatementKind::Expr(expr) => expr,
+        kind => panic!("{text}: expected expression; got {kind:?}"),
+    }
+}
+
+#[test]
+fn parse_inline_conditional() {
+    let expr = parse_expr("a -> 1 else -> 2");
+    match expr.kind {
+        ast::ExprKind::Conditional(branches, Some(default)) => {
+            assert_eq!(branches.len(), 1);
+            assert_eq!(branches[0].0.is_ident(), Some("a".to_owned()));
+            assert_eq!(branches[0].1.statements.len(), 1);
+            assert_eq!(default.statements.len(), 1);
+        }
+        kind => panic!("Expected conditional; got {kind:?}"),
+    }
+}
+
+#[test]
+fn parse_inline_conditional_as_sub_expression() {
+    let expr = parse_expr("x = a -> 1 else -> 2");
+    match expr.kind {
+        ast::ExprKind::BinaryOp(_, BinaryOperator::Assign, value) => {
+            assert!(matches!(value.kind, ast::ExprKind::Conditional(..)));
+        }
+        kind => panic!("Expected assignment; got {kind:?}"),
+    }
+}
+
+#[test]
+fn parse_nested_inline_conditional() {
+    let expr = parse_expr("a -> 1 else -> b -> 2 else -> 3");
+    let default = match expr.kind {
+        ast::ExprKind::Conditional(_, Some(default)) => default,
+        kind => panic!("Expected conditional; got {kind:?}"),
+    };
+    let statement = default.statements.into_iter().next().unwrap();
+    match statement.kind {
+        ast::StatementKind::Expr(ast::Expr {
+            kind: ast::ExprKind::Conditional(branches, Some(_)),
+            ..
+        }) => {
+            assert_eq!(branches[0].0.is_ident(), Some("b".to_owned()));
+        }
+        kind => panic!("Expected nested conditional; got {kind:?}"),
+    }
+}
+
+#[test]
+fn parse_inline_conditional_requires_else() {
+    match parse_text("x = a -> 1") {
+        Err(ParseErr { kind: ParseErrKind::ExpectedToken(_, Token::Else) }) => (),
+        result => panic!("Expected missing else error; got {result:?}"),
+    }
+}
+
+#[test]
+fn parse_labeled_loop() {
+    let program = parse_text(
+        "outer: loop ->\n    loop ->\n        break outer 1\n        continue outer\n    break outer",
+    )
+    .unwrap();
+    assert_eq!(program.statements.len(), 1);
+    let (label, block) = match &program.statements[0].kind {
+        ast::StatementKind::Expr(ast::Expr {
+            kind: ast::ExprKind::Loop(label, _, block),
+            ..
+        }) => (label, block),
+        kind => panic!("Expected loop; got {kind:?}"),
+    };
+    assert_eq!(label.as_deref(), Some("outer"));
+    assert!(matches!(
+        &block.statements[1].kind,
+        ast::StatementKind::Break(Some(label), _) if label == "outer"
+    ));
+    let inner = match &block.statements[0].kind {
+        ast::StatementKind::Expr(ast::Expr {
+            kind: ast::ExprKind::Loop(None, _, inner),
+            ..
+        }) => inner,
+        kind => panic!("Expected inner loop; got {kind:?}"),
+    };
+    match &inner.statements[0].kind {
+        ast::StatementKind::Break(Some(label), value) => {
+            assert_eq!(label, "outer");
+            assert!(matches!(value.kind, ast::ExprKind::Literal(_)));
+        }
+        kind => panic!("Expected labeled break; got {kind:?}"),
+    }
+    assert!(matches!(
+        &inner.statements[1].kind,
+        ast::StatementKind::Continue(Some(label)) if label == "outer"
+    ));
+}
+
+#[test]
+fn parse_break_with_ident_value() {
+    // `x` doesn't name an enclosing loop, so it's the break value.
+    let program = parse_text("loop ->\n    break x").unwrap();
+    let block = match &program.statements[0].kind {
+        ast::StatementKind::Expr(ast::Expr {
+            kind: ast::ExprKind::Loop(_, _, block),
+            ..
+        }) => block,
+        kind => panic!("Expected loop; got {kind:?}"),
+    };
+    match &block.statements[0].kind {
+        ast::StatementKind::Break(None, value) => {
+            assert_eq!(value.is_ident(), Some("x".to_owned()));
+        }
+        kind => panic!("Expected break; got {kind:?}"),
+    }
+}
+
+#[test]
+fn parse_method_call() {
+    let program = parse_text("a.b(c)").expect("Method call failed to parse");
+    assert_eq!(program.statements.len(), 1);
+    let statement = program.statements.into_iter().next().unwrap();
+    let call = match statement.kind {
+        ast::StatementKind::Expr(ast::Expr {
+            kind: ast::ExprKind::Call(call), ..
+        }) => call,
+        kind => panic!("Expected call; got {kind:?}"),
+    };
+    match call.callable.kind {
+        ast::ExprKind::BinaryOp(a, BinaryOperator::Dot, b) => {
+            assert_eq!(a.is_ident(), Some("a".to_owned()));
+            assert_eq!(b.is_ident(), Some("b".to_owned()));
+        }
+        kind => panic!("Expected attribute access; got {kind:?}"),
+    }
+    assert_eq!(call.args.len(), 1);
+    assert_eq!(call.args[0].is_ident(), Some("c".to_owned()));
+}
+
+#[test]
+fn parse_try() {
+    for text in ["try -> a catch e -> b", "try ->\n    a\ncatch e ->\n    b"] {
+        match parse_expr(text).kind {
+            ast::ExprKind::Try(block, Some(name), catch_block) => {
+                assert_eq!(block.statements.len(), 1, "{text}");
+                assert_eq!(name, "e", "{text}");
+                assert_eq!(catch_block.statements.len(), 1, "{text}");
+            }
+            kind => panic!("{text}: expected try; got {kind:?}"),
+        }
+    }
+    assert!(matches!(
+        parse_expr("try -> a catch -> b").kind,
+        ast::ExprKind::Try(_, None, _)
+    ));
+}
+
+#[test]
+fn parse_try_requires_catch() {
+    match parse_text("try -> a") {
+        Err(ParseErr { kind: ParseErrKind::ExpectedToken(_, Token::Catch) }) => (),
+        result => panic!("Expected missing catch error; got {result:?}"),
+    }
+}
+
+#[test]
+fn parse_raise() {
+    let program = parse_text("raise a").unwrap();
+    let statement = program.statements.into_iter().next().unwrap();
+    match statement.kind {
+        ast::StatementKind::Raise(expr) => {
+            assert_eq!(expr.is_ident(), Some("a".to_owned()));
+        }
+        kind => panic!("Expected raise; got {kind:?}"),
+    }
+    assert!(matches!(
+        parse_text("raise"),
+        Err(ParseErr { kind: ParseErrKind::ExpectedExpr(_) })
+    ));
+}
@@ -1,7 +1,11 @@
+use num_bigint::BigInt;
+
 use crate::exe::Executor;
 use crate::repl::Repl;
 use crate::vm::VM;
 
+use super::exe::eval_text;
+
 #[test]
 fn eval_empty() {
     eval("");
@@ -34,6 +38,134 @@ fn eval_if_with_no_block() {
     eval("if true ->");
 }
 
+#[test]
+fn eval_lines_sharing_var() {
+    let mut vm = VM::default();
+    let executor = Executor::new(&mut vm, true, false, false);
+    let mut repl = Repl::new(None, executor);
+    assert!(repl.eval("x = 2", true).is_none());
+    assert!(repl.eval("y = x * 3", true).is_none());
+    drop(repl);
+    let result = eval_text(&mut vm, "y").unwrap();
+    assert_eq!(result.int_val(), Some(BigInt::from(6)));
+}
+
+#[test]
+fn eval_expression_using_var_from_previous_line() {
+    let mut vm = VM::default();
+    let executor = Executor::new(&mut vm, true, false, false);
+    let mut repl = Repl::new(None, executor);
+    assert!(repl.eval("x = 1", true).is_none());
+    assert!(repl.eval("x + 1", true).is_none());
+    assert!(repl.eval(".vars", true).is_none());
+    drop(repl);
+    // The result of the last expression is assigned to _.
+    let result = eval_text(&mut vm, "_").unwrap();
+    assert_eq!(result.int_val(), Some(BigInt::from(2)));
+}
+
+#[test]
+fn echo_expression_result() {
+    let mut vm = VM::default();
+    let executor = Executor::new(&mut vm, true, false, false);
+    let mut repl = Repl::new(None, executor);
+    let cases = [
+        ("40 + 2", Some("42")),
+        ("x = 40 + 2", None),
+        ("x", Some("42")),
+        ("l = [1]", None),
+        ("l.0 = 2", None),
+        ("a, b = (1, 2)", None),
+        ("b", Some("2")),
+        ("nil", None),
+        ("print(1)", None),
+    ];
+    for (text, expected) in cases {
+        assert!(repl.eval(text, true).is_none(), "{text}");
+        assert_eq!(repl.last_echo.as_deref(), expected, "{text}");
+    }
+    repl.set_echo(false);
+    assert!(repl.eval("40 + 2", true).is_none());
+    assert_eq!(repl.last_echo, None);
+}
+
+#[test]
+fn echo_shows_repr() {
+    let mut vm = VM::default();
+    let executor = Executor::new(&mut vm, true, false, false);
+    let mut repl = Repl::new(None, executor);
+    // The REPL echoes strings quoted with special chars escaped...
+    assert!(repl.eval("s = \"a\\nb\"", true).is_none());
+    assert!(repl.eval("s", true).is_none());
+    assert_eq!(repl.last_echo.as_deref(), Some("\"a\\nb\""));
+    assert!(repl.eval("(s, [\"\\\"\"])", true).is_none());
+    assert_eq!(repl.last_echo.as_deref(), Some("(\"a\\nb\", [\"\\\"\"])"));
+    drop(repl);
+    // ...whereas print shows the raw text.
+    let result = eval_text(&mut vm, "s").unwrap();
+    assert_eq!(result.to_string(), "a\nb");
+}
+
+#[test]
+fn dis_last_entry() {
+    let mut vm = VM::default();
+    let executor = Executor::new(&mut vm, true, false, false);
+    let mut repl = Repl::new(None, executor);
+    assert!(repl.dis_lines("").is_none());
+    // A var is used so that the addition isn't constant folded.
+    assert!(repl.eval("a = 1", true).is_none());
+    assert!(repl.eval("a + 2", true).is_none());
+    assert!(repl.eval(".dis", true).is_none());
+    let dis = repl.dis_lines("").unwrap().join("\n");
+    assert!(dis.contains("LOAD_CONST"), "{dis}");
+    assert!(dis.contains("BINARY_OP"), "{dis}");
+}
+
+#[test]
+fn dis_code_without_running_it() {
+    let mut vm = VM::default();
+    let executor = Executor::new(&mut vm, true, false, false);
+    let mut repl = Repl::new(None, executor);
+    let dis = repl.dis_lines("b = 1").unwrap().join("\n");
+    assert!(dis.contains("DECLARE_ASSIGN"), "{dis}");
+    assert!(repl.eval(".dis b = 2", true).is_none());
+    drop(repl);
+    assert!(eval_text(&mut vm, "b").is_err());
+}
+
+#[test]
+fn eval_lines_with_block() {
+    let mut vm = VM::default();
+    let executor = Executor::new(&mut vm, true, false, false);
+    let mut repl = Repl::new(None, executor);
+    // The function isn't defined until the block is ended with a blank
+    // line.
+    for line in ["f = (x) ->", "    y = x + 1", "    y * 2"] {
+        assert!(repl.eval_line(line).is_none(), "{line}");
+        assert_eq!(repl.last_echo, None, "{line}");
+    }
+    assert!(repl.eval_line("").is_none());
+    assert!(repl.eval_line("f(2)").is_none());
+    assert_eq!(repl.last_echo.as_deref(), Some("6"));
+}
+
+#[test]
+fn eval_lines_with_unmatched_bracket() {
+    let mut vm = VM::default();
+    let executor = Executor::new(&mut vm, true, false, false);
+    let mut repl = Repl::new(None, executor);
+    // The entry is evaluated as soon as it's complete.
+    assert!(repl.eval_line("(1,").is_none());
+    assert_eq!(repl.last_echo, None);
+    assert!(repl.eval_line("2)").is_none());
+    assert_eq!(repl.last_echo.as_deref(), Some("(1, 2)"));
+    // An entry that's still incomplete when it's ended is dropped.
+    assert!(repl.eval_line("(3,").is_none());
+    assert!(repl.eval_line("").is_none());
+    assert!(repl.eval_line("4").is_none());
+    assert_eq!(repl.last_echo.as_deref(), Some("4"));
+}
+
 // Utilities -----------------------------------------------------------
 fn eval(input: &str) {
     let mut vm = VM::default();
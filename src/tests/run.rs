@@ -3,6 +3,6 @@ use crate::run::*;
 #[test]
 fn test_run_text() {
     let source = "1 + 2";
-    let result = run_text(source, false, true);
+    let result = run_text(source, false, true, false);
     assert!(result.is_ok(), "{:?}", result.err());
 }
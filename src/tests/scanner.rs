@@ -143,7 +143,11 @@ fn scan_string_unclosed() {
     for (source, expected, l1, c1, l2, c2) in items {
         match scan_text(source) {
             Err(err) => match err {
-                ScanErr { kind: ScanErrKind::UnterminatedStr(string), location } => {
+                ScanErr {
+                    kind: ScanErrKind::UnterminatedStr(string),
+                    location,
+                    ..
+                } => {
                     assert_eq!(string, source.to_string());
                     assert_eq!(location, Location::new(1, 1));
                     let new_source = source.to_string() + "\"";
@@ -209,7 +213,7 @@ fn scan_unexpected_indent_on_first_line() {
     let result = scan_text(source);
     assert!(result.is_err());
     match result.unwrap_err() {
-        ScanErr { kind: ScanErrKind::UnexpectedIndent(1), location } => {
+        ScanErr { kind: ScanErrKind::UnexpectedIndent(1), location, .. } => {
             assert_eq!(location.line, 1);
             assert_eq!(location.col, 1);
         }
@@ -254,7 +258,7 @@ fn scan_unknown() {
     match scan_text(source) {
         Ok(_tokens) => assert!(false),
         Err(err) => match err {
-            ScanErr { kind: ScanErrKind::UnexpectedChar(c), location } => {
+            ScanErr { kind: ScanErrKind::UnexpectedChar(c), location, .. } => {
                 assert_eq!(c, '{');
                 assert_eq!(location.line, 1);
                 assert_eq!(location.col, 1);
@@ -264,6 +268,23 @@ fn scan_unknown() {
     }
 }
 
+#[test]
+fn scan_unexpected_char_with_context() {
+    match scan_text("y = 1\nx + 1 * @") {
+        Err(err) => {
+            assert!(matches!(err.kind, ScanErrKind::UnexpectedChar('@')));
+            // Only the most recent tokens in the current statement are
+            // kept as context.
+            assert_eq!(
+                err.context,
+                vec![Token::Plus, Token::Int(BigInt::from(1)), Token::Star]
+            );
+            assert_eq!(err.context_str(), Some("+ 1 *".to_owned()));
+        }
+        Ok(tokens) => assert!(false, "Unexpected tokens: {:?}", tokens),
+    }
+}
+
 #[test]
 fn scan_inline_block_simple() {
     use Token::*;
@@ -438,6 +459,86 @@ fn scan_inline_block_5() {
     );
 }
 
+#[test]
+fn scan_inline_block_with_trailing_comment() {
+    use Token::*;
+    let tokens = scan_optimistic("x -> 1  # note\ny");
+    let mut tokens = tokens.iter();
+    check_token(tokens.next(), Ident("x".to_owned()), 1, 1, 1, 1);
+    check_token(tokens.next(), InlineScopeStart, 1, 3, 1, 4);
+    check_token(tokens.next(), Int(BigInt::from(1)), 1, 6, 1, 6);
+    // The inline scope is closed at the end of the expression, not
+    // after the comment.
+    check_token(tokens.next(), EndOfStatement, 1, 7, 1, 7);
+    check_token(tokens.next(), InlineScopeEnd, 1, 7, 1, 7);
+    check_token(tokens.next(), EndOfStatement, 1, 7, 1, 7);
+    check_token(tokens.next(), Ident("y".to_owned()), 2, 1, 2, 1);
+    check_token(tokens.next(), EndOfStatement, 2, 2, 2, 2);
+    assert!(tokens.next().is_none());
+}
+
+#[test]
+fn scan_inline_block_with_trailing_comment_at_end_of_input() {
+    use Token::*;
+    let tokens = scan_to_tokens("block -> true  # note");
+    assert_eq!(
+        tokens,
+        vec![
+            Block,
+            InlineScopeStart,
+            True,
+            EndOfStatement,
+            InlineScopeEnd,
+            EndOfStatement
+        ]
+    );
+}
+
+#[test]
+fn scan_inline_block_if_else_with_trailing_comments() {
+    use Token::*;
+    let tokens = scan_to_tokens("if true -> 1  # a\nelse -> 2  # b\n");
+    assert_eq!(
+        tokens,
+        vec![
+            If,
+            True,
+            InlineScopeStart,
+            Int(BigInt::from(1)),
+            EndOfStatement,
+            InlineScopeEnd,
+            EndOfStatement,
+            Else,
+            InlineScopeStart,
+            Int(BigInt::from(2)),
+            EndOfStatement,
+            InlineScopeEnd,
+            EndOfStatement,
+        ]
+    );
+}
+
+#[test]
+fn scan_inline_block_in_parens_with_trailing_comment() {
+    use Token::*;
+    // Newlines in brackets don't close inline scopes, so the scope is
+    // closed by the closing paren on the next line.
+    let tokens = scan_to_tokens("(block -> 1  # note\n)");
+    assert_eq!(
+        tokens,
+        vec![
+            LParen,
+            Block,
+            InlineScopeStart,
+            Int(BigInt::from(1)),
+            EndOfStatement,
+            InlineScopeEnd,
+            RParen,
+            EndOfStatement,
+        ]
+    );
+}
+
 // Utilities -------------------------------------------------------
 
 /// Check token returned by scanner against expected token.
@@ -466,8 +567,8 @@ fn check_string_token(
     match actual {
         Some(TokenWithLocation {
             token: Token::Str(actual_string),
-            start: Location { line: actual_start_line, col: actual_start_col },
-            end: Location { line: actual_end_line, col: actual_end_col },
+            start: Location { line: actual_start_line, col: actual_start_col, .. },
+            end: Location { line: actual_end_line, col: actual_end_col, .. },
         }) => {
             assert_eq!(actual_string, expected_string);
             assert_eq!(actual_start_line, &expected_start_line);
@@ -478,3 +579,13 @@ fn check_string_token(
         _ => assert!(false),
     }
 }
+
+#[test]
+fn scan_location_after_multi_byte_chars() {
+    // The emoji is one char but four bytes
+    let tokens = scan_optimistic("s = \"😀\" + 1");
+    let plus = tokens.iter().find(|t| t.token == Token::Plus).unwrap();
+    assert_eq!(plus.start, Location::new_with_offset(1, 9, 11));
+    let one = tokens.iter().find(|t| matches!(t.token, Token::Int(_))).unwrap();
+    assert_eq!(one.start, Location::new_with_offset(1, 11, 13));
+}
@@ -1,5 +1,26 @@
-use crate::types::ObjectExt;
-use crate::vm::RuntimeContext;
+use crate::result::{ExeErr, ExeErrKind};
+use crate::types::{Args, CallResult, ObjectExt, ObjectRef};
+use crate::vm::{RuntimeContext, RuntimeErr, RuntimeErrKind, VM};
+
+use super::exe::eval_text;
+
+/// Look up the method `name` on `obj` and call it with `obj` as the
+/// first arg, returning the result (or nil if the method returned
+/// nothing).
+fn call_method(
+    vm: &mut VM,
+    obj: ObjectRef,
+    name: &str,
+    args: Vec<ObjectRef>,
+) -> ObjectRef {
+    let method = obj.get_attr(name, &vm.ctx).expect("Method not found");
+    let mut method_args = vec![obj];
+    method_args.extend(args);
+    match method.call(method_args, vm).expect("Method call failed") {
+        Some(result) => result,
+        None => vm.ctx.builtins.nil_obj.clone(),
+    }
+}
 
 #[test]
 fn test_float() {
@@ -23,6 +44,91 @@ fn test_float() {
     assert_ne!(float2.id(), float3.id());
 }
 
+#[test]
+fn test_float_display() {
+    let ctx = RuntimeContext::default();
+    assert_eq!(ctx.builtins.new_float(2.0).to_string(), "2.0");
+    assert_eq!(ctx.builtins.new_float(-2.0).to_string(), "-2.0");
+    assert_eq!(ctx.builtins.new_float(2.5).to_string(), "2.5");
+    assert_eq!(ctx.builtins.new_float(1.0 / 3.0).to_string(), "0.3333333333333333");
+    assert_eq!(ctx.builtins.new_int(2u8).to_string(), "2");
+    let mut vm = VM::default();
+    let result = eval_text(&mut vm, "float(2)").unwrap();
+    assert_eq!(result.to_string(), "2.0");
+    let result = eval_text(&mut vm, "1.0 == 1").unwrap();
+    assert!(result.bool_val().unwrap());
+}
+
+#[test]
+fn test_float_nan() {
+    let ctx = RuntimeContext::default();
+    let nan = ctx.builtins.new_float(f64::NAN);
+    let one = ctx.builtins.new_float(1.0);
+    let int = ctx.builtins.new_int(1u8);
+    assert!(!nan.is_equal(&*nan, &ctx));
+    assert!(!nan.is_equal(&*one, &ctx));
+    assert!(!nan.is_equal(&*int, &ctx));
+    assert!(!int.is_equal(&*nan, &ctx));
+    for (a, b) in [(&nan, &one), (&one, &nan), (&nan, &int), (&int, &nan)] {
+        assert!(!a.less_than(&**b, &ctx).unwrap());
+        assert!(!a.greater_than(&**b, &ctx).unwrap());
+    }
+    let mut vm = VM::default();
+    let result =
+        eval_text(&mut vm, "n = float(\"nan\")\n(n == n, n != n, n < 1, n >= 1)");
+    assert_eq!(result.unwrap().to_string(), "(false, true, false, false)");
+}
+
+#[test]
+fn test_float_infinity() {
+    let ctx = RuntimeContext::default();
+    let inf = ctx.builtins.new_float(f64::INFINITY);
+    let neg_inf = ctx.builtins.new_float(f64::NEG_INFINITY);
+    let one = ctx.builtins.new_float(1.0);
+    let int = ctx.builtins.new_int(1u8);
+    assert!(inf.is_equal(&*inf, &ctx));
+    assert!(!inf.is_equal(&*neg_inf, &ctx));
+    assert!(!inf.is_equal(&*int, &ctx));
+    assert!(!int.is_equal(&*inf, &ctx));
+    assert!(inf.greater_than(&*one, &ctx).unwrap());
+    assert!(neg_inf.less_than(&*one, &ctx).unwrap());
+    assert!(inf.greater_than(&*int, &ctx).unwrap());
+    assert!(neg_inf.less_than(&*int, &ctx).unwrap());
+    assert!(int.less_than(&*inf, &ctx).unwrap());
+    assert!(int.greater_than(&*neg_inf, &ctx).unwrap());
+    // Ints too big to be represented as a Float are still less than
+    // infinity.
+    let mut vm = VM::default();
+    let result = eval_text(
+        &mut vm,
+        "i = float(\"inf\")\nn = 10 ** 400\n(n < i, i > n, -i < -n, n < 1.0e308)",
+    );
+    assert_eq!(result.unwrap().to_string(), "(true, true, true, false)");
+}
+
+#[test]
+fn test_small_ints_are_interned() {
+    let ctx = RuntimeContext::default();
+    for value in [-5, 0, 1, 256] {
+        let a = ctx.builtins.new_int(value);
+        let b = ctx.builtins.new_int(value);
+        assert!(a.is(&*b), "{value}");
+        assert!(a.is_equal(&*b, &ctx), "{value}");
+    }
+    for value in [-6, 257, 1_000_000] {
+        let a = ctx.builtins.new_int(value);
+        let b = ctx.builtins.new_int(value);
+        assert!(!a.is(&*b), "{value}");
+        assert!(a.is_equal(&*b, &ctx), "{value}");
+    }
+    let mut vm = VM::default();
+    let result = eval_text(
+        &mut vm,
+        "(1 === 1, 256 === (255 + 1), 257 === (256 + 1), 257 == (256 + 1), nil === nil, true === (1 < 2))",
+    );
+    assert_eq!(result.unwrap().to_string(), "(true, true, false, true, true, true)");
+}
+
 #[test]
 fn test_compare_float_to_int() {
     let ctx = RuntimeContext::default();
@@ -30,6 +136,51 @@ fn test_compare_float_to_int() {
     let int = ctx.builtins.new_int(1u8);
     assert!(float.is_equal(&*int, &ctx));
     assert!(int.is_equal(&*float, &ctx));
+    let float = ctx.builtins.new_float(1.5);
+    assert!(float.greater_than(&*int, &ctx).unwrap());
+    assert!(!float.less_than(&*int, &ctx).unwrap());
+    assert!(int.less_than(&*float, &ctx).unwrap());
+    assert!(!int.greater_than(&*float, &ctx).unwrap());
+}
+
+/// Evaluate the text and check that the result is the Bool `true`.
+fn assert_true(vm: &mut VM, text: &str) {
+    let result = eval_text(vm, text).unwrap_or_else(|err| panic!("{text}: {err:?}"));
+    assert_eq!(result.bool_val(), Some(true), "{text}");
+}
+
+#[test]
+fn test_singletons_are_identical() {
+    let mut vm = VM::default();
+    assert_true(&mut vm, "nil === nil");
+    assert_true(&mut vm, "true === true");
+    assert_true(&mut vm, "false === false");
+    assert_true(&mut vm, "(1 == 1) === true");
+    assert_true(&mut vm, "(1 == 2) === false");
+    assert_true(&mut vm, "!(true === false)");
+    assert_true(&mut vm, "!(nil === false)");
+}
+
+#[test]
+fn test_singletons_are_shared() {
+    let mut vm = VM::default();
+    eval_text(&mut vm, "a = nil\nb = true\nc = true\nd = 1 < 2\ne = false")
+        .expect("Could not evaluate text");
+    let nil_id = (vm.ctx.builtins.nil_obj.clone() as ObjectRef).id();
+    let true_id = (vm.ctx.builtins.true_obj.clone() as ObjectRef).id();
+    let false_id = (vm.ctx.builtins.false_obj.clone() as ObjectRef).id();
+    let mut id_of = |name: &str| {
+        let obj = vm.ctx.get_var_in_current_namespace(name).expect("Var not found");
+        obj.id()
+    };
+    assert_eq!(id_of("a"), nil_id);
+    assert_eq!(id_of("b"), true_id);
+    assert_eq!(id_of("c"), true_id);
+    assert_eq!(id_of("d"), true_id);
+    assert_eq!(id_of("e"), false_id);
+    // The singletons themselves are distinct objects.
+    assert_ne!(true_id, false_id);
+    assert_ne!(nil_id, false_id);
 }
 
 #[test]
@@ -63,3 +214,1201 @@ fn test_custom() {
     // regardless of attributes.
     assert!(!t1_obj1.is_equal(&*t2_obj1, &ctx));
 }
+
+#[test]
+fn test_int_checked_conversions_in_range() {
+    let mut vm = VM::default();
+    let int = vm.ctx.builtins.new_int(255);
+    let result = call_method(&mut vm, int.clone(), "to_u8", vec![]);
+    assert_eq!(result.int_val(), Some(255.into()));
+    let result = call_method(&mut vm, int, "to_i32", vec![]);
+    assert_eq!(result.int_val(), Some(255.into()));
+    let int = vm.ctx.builtins.new_int(-128);
+    let result = call_method(&mut vm, int, "to_i8", vec![]);
+    assert_eq!(result.int_val(), Some((-128).into()));
+}
+
+#[test]
+fn test_int_checked_conversions_out_of_range() {
+    let mut vm = VM::default();
+    let int = vm.ctx.builtins.new_int(256);
+    let result = call_method(&mut vm, int, "to_u8", vec![]);
+    assert!(result.is_nil());
+    let int = vm.ctx.builtins.new_int(-1);
+    let result = call_method(&mut vm, int, "to_u64", vec![]);
+    assert!(result.is_nil());
+    let int = vm.ctx.builtins.new_int(i64::MAX);
+    let result = call_method(&mut vm, int.clone(), "to_i32", vec![]);
+    assert!(result.is_nil());
+    let result = call_method(&mut vm, int, "to_i64", vec![]);
+    assert_eq!(result.int_val(), Some(i64::MAX.into()));
+}
+
+#[test]
+fn test_int_abs() {
+    let mut vm = VM::default();
+    for (value, expected) in [(-5, 5), (0, 0), (5, 5)] {
+        let int = vm.ctx.builtins.new_int(value);
+        let result = call_method(&mut vm, int, "abs", vec![]);
+        assert_eq!(result.int_val(), Some(expected.into()));
+    }
+}
+
+#[test]
+fn test_int_parity() {
+    let mut vm = VM::default();
+    for (value, even) in [(-3, false), (-2, true), (0, true), (1, false), (4, true)] {
+        let int = vm.ctx.builtins.new_int(value);
+        let is_even = call_method(&mut vm, int.clone(), "is_even", vec![]);
+        let is_odd = call_method(&mut vm, int, "is_odd", vec![]);
+        assert_eq!(is_even.bool_val(), Some(even), "{value}");
+        assert_eq!(is_odd.bool_val(), Some(!even), "{value}");
+    }
+}
+
+#[test]
+fn test_rounding_methods_preserve_type() {
+    let mut vm = VM::default();
+    for name in ["abs", "floor", "ceil", "round", "trunc"] {
+        let int = vm.ctx.builtins.new_int(-2);
+        let result = call_method(&mut vm, int, name, vec![]);
+        assert!(result.is_int(), "Int.{name} returned {}", result.class());
+        assert_eq!(result.int_val(), Some(if name == "abs" { 2 } else { -2 }.into()));
+
+        let float = vm.ctx.builtins.new_float(-2.5);
+        let result = call_method(&mut vm, float, name, vec![]);
+        assert!(result.is_float(), "Float.{name} returned {}", result.class());
+        let expected = match name {
+            "abs" => 2.5,
+            "floor" | "round" => -3.0,
+            _ => -2.0,
+        };
+        assert_eq!(result.float_val(), Some(expected), "Float.{name}");
+    }
+}
+
+/// Call a Str method with Str args.
+fn call_str_method(vm: &mut VM, string: &str, name: &str, args: &[&str]) -> ObjectRef {
+    let this = vm.ctx.builtins.new_str(string);
+    let args = args.iter().map(|arg| vm.ctx.builtins.new_str(*arg)).collect();
+    call_method(vm, this, name, args)
+}
+
+#[test]
+fn test_str_split_once() {
+    let mut vm = VM::default();
+    let result = call_str_method(&mut vm, "a=b", "split_once", &["="]);
+    assert_eq!(result.to_string(), "(\"a\", \"b\")");
+    let result = call_str_method(&mut vm, "a=b=c", "split_once", &["="]);
+    assert_eq!(result.to_string(), "(\"a\", \"b=c\")");
+    let result = call_str_method(&mut vm, "abc", "split_once", &["="]);
+    assert!(result.is_nil());
+}
+
+#[test]
+fn test_str_rsplit() {
+    let mut vm = VM::default();
+    let result = call_str_method(&mut vm, "a=b", "rsplit", &["="]);
+    assert_eq!(result.to_string(), "(\"a\", \"b\")");
+    let result = call_str_method(&mut vm, "a=b=c", "rsplit", &["="]);
+    assert_eq!(result.to_string(), "(\"a=b\", \"c\")");
+    let result = call_str_method(&mut vm, "abc", "rsplit", &["="]);
+    assert!(result.is_nil());
+}
+
+#[test]
+fn test_str_split_with_empty_separator() {
+    let mut vm = VM::default();
+    for name in ["split_once", "rsplit"] {
+        let this = vm.ctx.builtins.new_str("abc");
+        let method = this.get_attr(name, &vm.ctx).unwrap();
+        let args = vec![this, vm.ctx.builtins.new_str("")];
+        let result = method.call(args, &mut vm);
+        assert!(
+            matches!(result, Err(RuntimeErr { kind: RuntimeErrKind::ValueErr(_), .. })),
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn test_str_char_class_predicates() {
+    let mut vm = VM::default();
+    let cases = [
+        ("abc", [true, false, true]),
+        ("éß", [true, false, true]),
+        ("123", [false, true, true]),
+        ("a1", [false, false, true]),
+        ("a 1", [false, false, false]),
+        ("", [false, false, false]),
+    ];
+    for (string, expected) in cases {
+        for (name, expected) in
+            ["is_alpha", "is_digit", "is_alnum"].iter().zip(expected)
+        {
+            let result = call_str_method(&mut vm, string, name, &[]);
+            assert_eq!(result.bool_val(), Some(expected), "{string:?}.{name}");
+        }
+    }
+}
+
+/// Make a List of Ints.
+fn int_list(vm: &VM, values: &[i32]) -> ObjectRef {
+    let items = values.iter().map(|v| vm.ctx.builtins.new_int(*v)).collect();
+    vm.ctx.builtins.new_list(items)
+}
+
+#[test]
+fn test_list_rotate() {
+    let mut vm = VM::default();
+    let cases: [(i32, &[i32]); 6] = [
+        (1, &[2, 3, 4, 1]),
+        (-1, &[4, 1, 2, 3]),
+        (0, &[1, 2, 3, 4]),
+        (4, &[1, 2, 3, 4]),
+        (6, &[3, 4, 1, 2]),
+        (-7, &[2, 3, 4, 1]),
+    ];
+    for (n, expected) in cases {
+        let list = int_list(&vm, &[1, 2, 3, 4]);
+        let n = vm.ctx.builtins.new_int(n);
+        let result = call_method(&mut vm, list.clone(), "rotate", vec![n]);
+        assert!(result.is_list());
+        assert!(result.is_equal(&*int_list(&vm, expected), &vm.ctx));
+        // The original list is not modified.
+        assert!(list.is_equal(&*int_list(&vm, &[1, 2, 3, 4]), &vm.ctx));
+    }
+}
+
+#[test]
+fn test_tuple_rotate() {
+    let mut vm = VM::default();
+    let items = (1..=3).map(|i| vm.ctx.builtins.new_int(i)).collect();
+    let tuple = vm.ctx.builtins.new_tuple(items);
+    let n = vm.ctx.builtins.new_int(-1);
+    let result = call_method(&mut vm, tuple, "rotate", vec![n]);
+    assert_eq!(result.to_string(), "(3, 1, 2)");
+}
+
+#[test]
+fn test_rotate_empty() {
+    let mut vm = VM::default();
+    let list = int_list(&vm, &[]);
+    let n = vm.ctx.builtins.new_int(3);
+    let result = call_method(&mut vm, list, "rotate", vec![n]);
+    assert!(result.is_list());
+    assert_eq!(result.len(), Some(0));
+}
+
+#[test]
+fn test_map_insert_and_overwrite() {
+    let ctx = RuntimeContext::default();
+    let map = ctx.builtins.new_map(vec![]).unwrap();
+    let key_a = ctx.builtins.new_str("a");
+    let key_1 = ctx.builtins.new_int(1);
+
+    map.set_item(&key_a, ctx.builtins.new_int(1), &ctx).unwrap();
+    map.set_item(&key_1, ctx.builtins.new_str("one"), &ctx).unwrap();
+    assert_eq!(map.len(), Some(2));
+    assert_eq!(map.get_item(&key_a, &ctx).unwrap().int_val(), Some(1.into()));
+    assert_eq!(map.get_item(&key_1, &ctx).unwrap().str_val(), Some("one".to_owned()));
+
+    // Keys are compared by value, not identity.
+    map.set_item(&ctx.builtins.new_str("a"), ctx.builtins.new_int(2), &ctx).unwrap();
+    assert_eq!(map.len(), Some(2));
+    assert_eq!(map.get_item(&key_a, &ctx).unwrap().int_val(), Some(2.into()));
+
+    let map = map.as_map().unwrap();
+    assert_eq!(map.keys().len(), 2);
+    assert!(map.keys()[0].is_equal(&*key_a, &ctx));
+    assert!(map.values()[1].is_equal(&*ctx.builtins.new_str("one"), &ctx));
+}
+
+#[test]
+fn test_map_missing_key() {
+    let ctx = RuntimeContext::default();
+    let map = ctx.builtins.new_map(vec![]).unwrap();
+    let result = map.get_item(&ctx.builtins.new_str("a"), &ctx);
+    assert!(matches!(
+        result,
+        Err(RuntimeErr { kind: RuntimeErrKind::ItemDoesNotExist(_, _), .. })
+    ));
+}
+
+#[test]
+fn test_map_unhashable_key() {
+    let ctx = RuntimeContext::default();
+    let map = ctx.builtins.new_map(vec![]).unwrap();
+    let key = ctx.builtins.new_float(1.0);
+    let result = map.set_item(&key, ctx.builtins.nil_obj.clone(), &ctx);
+    assert!(matches!(result, Err(RuntimeErr { kind: RuntimeErrKind::TypeErr(_), .. })));
+}
+
+#[test]
+fn test_map_equality() {
+    let ctx = RuntimeContext::default();
+    let (a, b) = (ctx.builtins.new_str("a"), ctx.builtins.new_str("b"));
+    let (one, two) = (ctx.builtins.new_int(1), ctx.builtins.new_int(2));
+    let map1 = ctx
+        .builtins
+        .new_map(vec![(a.clone(), one.clone()), (b.clone(), two.clone())])
+        .unwrap();
+    let map2 = ctx
+        .builtins
+        .new_map(vec![(b.clone(), two.clone()), (a.clone(), one.clone())])
+        .unwrap();
+    let map3 =
+        ctx.builtins.new_map(vec![(a, one), (b, ctx.builtins.new_int(3))]).unwrap();
+    assert!(map1.is_equal(&*map2, &ctx));
+    assert!(!map1.is_equal(&*map3, &ctx));
+}
+
+#[test]
+fn test_map_to_namespace() {
+    let mut vm = VM::default();
+    let (a, b) = (vm.ctx.builtins.new_str("a"), vm.ctx.builtins.new_str("b"));
+    let (one, two) = (vm.ctx.builtins.new_int(1), vm.ctx.builtins.new_int(2));
+    let map = vm.ctx.builtins.new_map(vec![(a, one), (b, two)]).unwrap();
+    vm.ctx.declare_and_assign_var("m", map).unwrap();
+    let result = eval_text(&mut vm, "ns = m.to_namespace()\nns.a + ns.b").unwrap();
+    assert_eq!(result.int_val(), Some(3.into()));
+}
+
+#[test]
+fn test_map_get_and_set_via_vm() {
+    let mut vm = VM::default();
+    let map = vm.ctx.builtins.new_map(vec![]).unwrap();
+    vm.ctx.declare_and_assign_var("m", map).unwrap();
+    let result = eval_text(&mut vm, "m.a = 1\nm.a").unwrap();
+    assert_eq!(result.int_val(), Some(1.into()));
+    let result =
+        eval_text(&mut vm, "m.a = m.a + 1\nm.1 = \"one\"\n(m.a, m.1, m.length)");
+    assert_eq!(result.unwrap().to_string(), "(2, \"one\", 2)");
+    // Missing keys are errors.
+    let result = eval_text(&mut vm, "m.b");
+    assert!(matches!(
+        result,
+        Err(ExeErr {
+            kind: ExeErrKind::RuntimeErr(RuntimeErrKind::ItemDoesNotExist(_, _))
+        })
+    ));
+    // Keys can't shadow the map's attributes.
+    let result = eval_text(&mut vm, "m.length = 1");
+    assert!(matches!(
+        result,
+        Err(ExeErr {
+            kind: ExeErrKind::RuntimeErr(RuntimeErrKind::AttrCannotBeSet(_, _))
+        })
+    ));
+}
+
+#[test]
+fn test_map_to_namespace_with_non_str_key() {
+    let mut vm = VM::default();
+    let (a, one) = (vm.ctx.builtins.new_str("a"), vm.ctx.builtins.new_int(1));
+    let map =
+        vm.ctx.builtins.new_map(vec![(a, one.clone()), (one.clone(), one)]).unwrap();
+    let method = map.get_attr("to_namespace", &vm.ctx).unwrap();
+    let result = method.call(vec![map], &mut vm);
+    assert!(matches!(result, Err(RuntimeErr { kind: RuntimeErrKind::TypeErr(_), .. })));
+}
+
+#[test]
+fn test_map_keys_where() {
+    let mut vm = VM::default();
+    let entries = [("a", 1), ("b", 5), ("c", 2), ("d", 7)]
+        .iter()
+        .map(|(k, v)| (vm.ctx.builtins.new_str(*k), vm.ctx.builtins.new_int(*v)))
+        .collect();
+    let map = vm.ctx.builtins.new_map(entries).unwrap();
+    vm.ctx.declare_and_assign_var("m", map).unwrap();
+    let result = eval_text(&mut vm, "m.keys_where((v) -> v > 2)").unwrap();
+    let b = vm.ctx.builtins.new_str("b");
+    let d = vm.ctx.builtins.new_str("d");
+    let expected = vm.ctx.builtins.new_list(vec![b, d]);
+    assert!(result.is_equal(&*expected, &vm.ctx), "{result}");
+}
+
+#[test]
+fn test_map_keys_where_not_callable() {
+    let mut vm = VM::default();
+    let map = vm.ctx.builtins.new_map(vec![]).unwrap();
+    let method = map.get_attr("keys_where", &vm.ctx).unwrap();
+    let pred = vm.ctx.builtins.new_int(1);
+    let result = method.call(vec![map, pred], &mut vm);
+    assert!(matches!(
+        result,
+        Err(RuntimeErr { kind: RuntimeErrKind::NotCallable(_), .. })
+    ));
+}
+
+#[test]
+fn test_list_fill() {
+    let mut vm = VM::default();
+    let list = int_list(&vm, &[1, 2, 3]);
+    // Mutations are visible through other references to the list.
+    let other_ref = list.clone();
+    let zero = vm.ctx.builtins.new_int(0);
+    let result = call_method(&mut vm, list, "fill", vec![zero]);
+    assert!(result.is_nil());
+    assert!(other_ref.is_equal(&*int_list(&vm, &[0, 0, 0]), &vm.ctx));
+}
+
+#[test]
+fn test_list_resize() {
+    let mut vm = VM::default();
+    let list = int_list(&vm, &[1, 2, 3]);
+    let (n, zero) = (vm.ctx.builtins.new_int(5), vm.ctx.builtins.new_int(0));
+    call_method(&mut vm, list.clone(), "resize", vec![n, zero.clone()]);
+    assert!(list.is_equal(&*int_list(&vm, &[1, 2, 3, 0, 0]), &vm.ctx));
+    let n = vm.ctx.builtins.new_int(2);
+    call_method(&mut vm, list.clone(), "resize", vec![n, zero]);
+    assert!(list.is_equal(&*int_list(&vm, &[1, 2]), &vm.ctx));
+}
+
+#[test]
+fn test_list_reverse() {
+    let mut vm = VM::default();
+    let result = eval_text(&mut vm, "a = [1, 2, 3]\nb = a\na.reverse()").unwrap();
+    assert!(result.is_nil());
+    // The list is reversed in place, so other references see it.
+    let result = eval_text(&mut vm, "b").unwrap();
+    assert!(result.is_equal(&*int_list(&vm, &[3, 2, 1]), &vm.ctx), "{result}");
+    let empty = int_list(&vm, &[]);
+    call_method(&mut vm, empty.clone(), "reverse", vec![]);
+    assert_eq!(empty.len(), Some(0));
+}
+
+#[test]
+fn test_list_resize_negative() {
+    let mut vm = VM::default();
+    let list = int_list(&vm, &[1, 2, 3]);
+    let method = list.get_attr("resize", &vm.ctx).unwrap();
+    let (n, zero) = (vm.ctx.builtins.new_int(-1), vm.ctx.builtins.new_int(0));
+    let result = method.call(vec![list.clone(), n, zero], &mut vm);
+    assert!(matches!(
+        result,
+        Err(RuntimeErr { kind: RuntimeErrKind::ValueErr(_), .. })
+    ));
+    assert_eq!(list.len(), Some(3));
+}
+
+#[test]
+fn test_binary_search() {
+    let mut vm = VM::default();
+    let values = [1, 3, 5, 7, 9, 11];
+    // Found, including the first and last items
+    for (i, value) in values.iter().enumerate() {
+        let list = int_list(&vm, &values);
+        let item = vm.ctx.builtins.new_int(*value);
+        let result = call_method(&mut vm, list, "binary_search", vec![item]);
+        assert_eq!(result.int_val(), Some(i.into()), "{value}");
+    }
+    // Not found, including below the first and above the last items
+    for value in [0, 4, 12] {
+        let list = int_list(&vm, &values);
+        let item = vm.ctx.builtins.new_int(value);
+        let result = call_method(&mut vm, list, "binary_search", vec![item]);
+        assert!(result.is_nil(), "{value}");
+    }
+    // Empty
+    let list = int_list(&vm, &[]);
+    let item = vm.ctx.builtins.new_int(1);
+    assert!(call_method(&mut vm, list, "binary_search", vec![item]).is_nil());
+}
+
+#[test]
+fn test_tuple_binary_search() {
+    let mut vm = VM::default();
+    let items = ["a", "c", "e"].iter().map(|s| vm.ctx.builtins.new_str(*s)).collect();
+    let tuple = vm.ctx.builtins.new_tuple(items);
+    let item = vm.ctx.builtins.new_str("e");
+    let result = call_method(&mut vm, tuple, "binary_search", vec![item]);
+    assert_eq!(result.int_val(), Some(2.into()));
+}
+
+#[test]
+fn test_list_split_at() {
+    let mut vm = VM::default();
+    let cases = [
+        (1, "([1], [2, 3])"),
+        (0, "([], [1, 2, 3])"),
+        (3, "([1, 2, 3], [])"),
+        (100, "([1, 2, 3], [])"),
+        (-1, "([1, 2], [3])"),
+        (-3, "([], [1, 2, 3])"),
+        (-100, "([], [1, 2, 3])"),
+    ];
+    for (i, expected) in cases {
+        let list = int_list(&vm, &[1, 2, 3]);
+        let i = vm.ctx.builtins.new_int(i);
+        let result = call_method(&mut vm, list, "split_at", vec![i]);
+        assert!(result.is_tuple());
+        assert_eq!(result.to_string(), expected);
+    }
+}
+
+#[test]
+fn test_tuple_split_at() {
+    let mut vm = VM::default();
+    let items = (1..=3).map(|i| vm.ctx.builtins.new_int(i)).collect();
+    let tuple = vm.ctx.builtins.new_tuple(items);
+    let i = vm.ctx.builtins.new_int(2);
+    let result = call_method(&mut vm, tuple, "split_at", vec![i]);
+    assert_eq!(result.to_string(), "((1, 2), (3,))");
+}
+
+#[test]
+fn test_str_split_at() {
+    let mut vm = VM::default();
+    let cases = [
+        (2, "(\"ab\", \"çd\")"),
+        (0, "(\"\", \"abçd\")"),
+        (4, "(\"abçd\", \"\")"),
+        (100, "(\"abçd\", \"\")"),
+    ];
+    for (i, expected) in cases {
+        let string = vm.ctx.builtins.new_str("abçd");
+        let i = vm.ctx.builtins.new_int(i);
+        let result = call_method(&mut vm, string, "split_at", vec![i]);
+        assert_eq!(result.to_string(), expected);
+    }
+}
+
+#[test]
+fn test_each_with_index() {
+    let mut vm = VM::default();
+    let text = "\
+total = 0
+items = [1, 2, 3]
+items.each_with_index((i, item) -> total += i * item)
+total";
+    let result = eval_text(&mut vm, text).unwrap();
+    assert_eq!(result.int_val(), Some(8.into()));
+    let text = "\
+total = 0
+(4, 5).each_with_index((i, item) -> total += i * item)
+total";
+    let result = eval_text(&mut vm, text).unwrap();
+    assert_eq!(result.int_val(), Some(5.into()));
+}
+
+#[test]
+fn test_each_with_index_uses_items_at_start() {
+    let mut vm = VM::default();
+    // Growing the list while iterating doesn't add iterations.
+    let text = "\
+count = 0
+items = [1, 2]
+f = (i, item) ->
+    items.resize(4, 0)
+    count += 1
+items.each_with_index(f)
+count";
+    let result = eval_text(&mut vm, text).unwrap();
+    assert_eq!(result.int_val(), Some(2.into()));
+}
+
+#[test]
+fn test_positions() {
+    let mut vm = VM::default();
+    let cases = [
+        ("[1, 2, 3, 4, 6].positions((x) -> x % 2 == 0)", "[1, 3, 4]"),
+        ("(2, 1, 8).positions((x) -> x % 2 == 0)", "[0, 2]"),
+        ("[1, 3].positions((x) -> x % 2 == 0)", "[]"),
+        ("[].positions((x) -> x % 2 == 0)", "[]"),
+        ("\"banana\".positions((c) -> c == \"a\")", "[1, 3, 5]"),
+    ];
+    for (text, expected) in cases {
+        let result =
+            eval_text(&mut vm, text).unwrap_or_else(|err| panic!("{text}: {err:?}"));
+        assert!(result.is_list(), "{text}");
+        assert_eq!(result.to_string(), expected, "{text}");
+    }
+}
+
+#[test]
+fn test_positions_not_callable() {
+    for text in ["[].positions(1)", "(1, 2).positions(nil)", "\"abc\".positions(1)"] {
+        let mut vm = VM::default();
+        let result = eval_text(&mut vm, text);
+        assert!(
+            matches!(
+                result,
+                Err(ExeErr {
+                    kind: ExeErrKind::RuntimeErr(RuntimeErrKind::NotCallable(_))
+                })
+            ),
+            "{text}: {result:?}"
+        );
+    }
+}
+
+#[test]
+fn test_each_with_index_not_callable() {
+    let mut vm = VM::default();
+    let list = int_list(&vm, &[]);
+    let method = list.get_attr("each_with_index", &vm.ctx).unwrap();
+    let f = vm.ctx.builtins.new_int(1);
+    let result = method.call(vec![list, f], &mut vm);
+    assert!(matches!(
+        result,
+        Err(RuntimeErr { kind: RuntimeErrKind::NotCallable(_), .. })
+    ));
+}
+
+#[test]
+fn test_sum_and_product() {
+    let mut vm = VM::default();
+    let cases = [
+        ("[1, 2, 3, 4].sum()", "10"),
+        ("[1, 2, 3, 4].product()", "24"),
+        ("(1, 2.5).sum()", "3.5"),
+        ("(2, 2.5).product()", "5.0"),
+        ("[].sum()", "0"),
+        ("[].product()", "1"),
+        ("().sum()", "0"),
+    ];
+    for (text, expected) in cases {
+        let result = eval_text(&mut vm, text).unwrap();
+        assert_eq!(result.to_string(), expected, "{text}");
+    }
+    let result = eval_text(&mut vm, "[1, 2.5].sum()").unwrap();
+    assert!(result.is_float());
+}
+
+#[test]
+fn test_sum_non_numeric() {
+    let mut vm = VM::default();
+    let list = vm.ctx.builtins.new_list(vec![vm.ctx.builtins.new_str("a")]);
+    let method = list.get_attr("sum", &vm.ctx).unwrap();
+    let result = method.call(vec![list], &mut vm);
+    assert!(matches!(result, Err(RuntimeErr { kind: RuntimeErrKind::TypeErr(_), .. })));
+}
+
+#[test]
+fn test_bool_equality() {
+    let mut vm = VM::default();
+    assert_true(&mut vm, "true == true");
+    assert_true(&mut vm, "false == false");
+    assert_true(&mut vm, "true != false");
+    // Bools are NOT equal to Ints (or anything else that isn't a Bool).
+    assert_true(&mut vm, "!(true == 1)");
+    assert_true(&mut vm, "!(1 == true)");
+    assert_true(&mut vm, "!(false == 0)");
+    assert_true(&mut vm, "!(false == nil)");
+}
+
+#[test]
+fn test_bool_logic() {
+    let mut vm = VM::default();
+    assert_true(&mut vm, "true && true");
+    assert_true(&mut vm, "!(true && false)");
+    assert_true(&mut vm, "false || true");
+    assert_true(&mut vm, "!(false || false)");
+    assert_true(&mut vm, "!false");
+    assert_true(&mut vm, "!!true");
+}
+
+#[test]
+fn test_bool_type_errors() {
+    for text in ["-true", "true && 1", "false || 0"] {
+        let mut vm = VM::default();
+        let result = eval_text(&mut vm, text);
+        assert!(
+            matches!(
+                result,
+                Err(ExeErr {
+                    kind: ExeErrKind::RuntimeErr(RuntimeErrKind::TypeErr(_))
+                })
+            ),
+            "{text}: {result:?}"
+        );
+    }
+}
+
+#[test]
+fn test_bool_special_method() {
+    fn custom_bool(_args: Args, vm: &mut VM) -> CallResult {
+        Ok(Some(vm.ctx.builtins.false_obj.clone()))
+    }
+
+    fn custom_bool_wrong_type(_args: Args, vm: &mut VM) -> CallResult {
+        Ok(Some(vm.ctx.builtins.new_int(0)))
+    }
+
+    let mut vm = VM::default();
+    let class = vm.ctx.builtins.new_type("test", "Custom");
+    let obj = vm.ctx.builtins.new_custom_instance(class);
+    let bool_method =
+        vm.ctx.builtins.new_builtin_func("$bool", Some(vec!["this"]), custom_bool);
+    obj.set_attr("$bool", bool_method, &vm.ctx).unwrap();
+    assert!(!vm.as_bool(&obj).unwrap());
+    vm.ctx.declare_and_assign_var("obj", obj.clone()).unwrap();
+    let result = eval_text(&mut vm, "if obj -> 1 else -> 2").unwrap();
+    assert_eq!(result.int_val(), Some(2.into()));
+    assert_true(&mut vm, "!obj");
+    assert_true(&mut vm, "!!obj == false");
+
+    let bool_method = vm.ctx.builtins.new_builtin_func(
+        "$bool",
+        Some(vec!["this"]),
+        custom_bool_wrong_type,
+    );
+    obj.set_attr("$bool", bool_method.clone(), &vm.ctx).unwrap();
+    assert!(matches!(
+        vm.as_bool(&obj),
+        Err(RuntimeErr { kind: RuntimeErrKind::TypeErr(_), .. })
+    ));
+
+    // Only custom objects can have a $bool method. A map item with the
+    // same key isn't one.
+    let key = vm.ctx.builtins.new_str("$bool");
+    let map = vm.ctx.builtins.new_map(vec![(key, bool_method)]).unwrap();
+    match vm.as_bool(&map) {
+        Err(RuntimeErr { kind: RuntimeErrKind::TypeErr(message), .. }) => {
+            assert!(message.contains("not implemented"), "{message}")
+        }
+        result => panic!("Expected type error; got {result:?}"),
+    }
+}
+
+#[test]
+fn test_mean_and_median() {
+    let mut vm = VM::default();
+    let cases = [
+        ("[1, 2, 3, 4].mean()", "2.5"),
+        ("(1, 2.5, 3).mean()", "2.1666666666666665"),
+        ("[5].mean()", "5.0"),
+        ("[3, 1, 2].median()", "2"),
+        ("[4, 1, 3, 2].median()", "2.5"),
+        ("(1.5, 0.5).median()", "1.0"),
+        ("[5].median()", "5"),
+    ];
+    for (text, expected) in cases {
+        let result = eval_text(&mut vm, text).unwrap();
+        assert_eq!(result.to_string(), expected, "{text}");
+    }
+    let result = eval_text(&mut vm, "[1, 2].mean()").unwrap();
+    assert!(result.is_float());
+}
+
+#[test]
+fn test_median_does_not_modify_original() {
+    let mut vm = VM::default();
+    let list = int_list(&vm, &[3, 1, 2]);
+    call_method(&mut vm, list.clone(), "median", vec![]);
+    assert!(list.is_equal(&*int_list(&vm, &[3, 1, 2]), &vm.ctx));
+}
+
+#[test]
+fn test_median_with_incomparable_items() {
+    let mut vm = VM::default();
+    // NaN isn't ordered relative to other floats, so the items don't
+    // have a total order. This used to cause a panic when sorting.
+    let mut items: Vec<ObjectRef> =
+        (0..25).map(|i| vm.ctx.builtins.new_float(f64::from(i * 7919 % 25))).collect();
+    items[5] = vm.ctx.builtins.new_float(f64::NAN);
+    let list = vm.ctx.builtins.new_list(items);
+    let method = list.get_attr("median", &vm.ctx).unwrap();
+    assert!(method.call(vec![list], &mut vm).is_ok());
+    // Items of different types can't be compared.
+    let mut items: Vec<ObjectRef> =
+        (0..30).map(|i| vm.ctx.builtins.new_int(i)).collect();
+    items.insert(13, vm.ctx.builtins.new_str("a"));
+    let list = vm.ctx.builtins.new_list(items);
+    let method = list.get_attr("median", &vm.ctx).unwrap();
+    let result = method.call(vec![list], &mut vm);
+    assert!(matches!(result, Err(RuntimeErr { kind: RuntimeErrKind::TypeErr(_), .. })));
+}
+
+#[test]
+fn test_mean_and_median_of_empty_sequence() {
+    for name in ["mean", "median"] {
+        let mut vm = VM::default();
+        let list = int_list(&vm, &[]);
+        let method = list.get_attr(name, &vm.ctx).unwrap();
+        let result = method.call(vec![list], &mut vm);
+        assert!(
+            matches!(result, Err(RuntimeErr { kind: RuntimeErrKind::ValueErr(_), .. })),
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn test_int_and_float_as_bool() {
+    let mut vm = VM::default();
+    assert_true(&mut vm, "!0");
+    assert_true(&mut vm, "!!1");
+    assert_true(&mut vm, "!!-1");
+    assert_true(&mut vm, "!0.0");
+    assert_true(&mut vm, "!!0.5");
+    assert_true(&mut vm, "!!-1.0");
+    let result = eval_text(&mut vm, "if 0 -> 1 else -> 2").unwrap();
+    assert_eq!(result.int_val(), Some(2.into()));
+    let result = eval_text(&mut vm, "if 3 -> 1 else -> 2").unwrap();
+    assert_eq!(result.int_val(), Some(1.into()));
+}
+
+#[test]
+fn test_set_construction() {
+    let mut vm = VM::default();
+    let cases = [
+        ("set([1, 2, 1, 3, 2])", "{1, 2, 3}"),
+        ("t = (\"b\", \"a\", \"b\")\nset(t)", "{\"b\", \"a\"}"),
+        ("set([])", "{}"),
+        ("[3, 1, 3].to_set()", "{3, 1}"),
+        ("(1, 1).to_set()", "{1}"),
+        ("set(set([1, 2]))", "{1, 2}"),
+    ];
+    for (text, expected) in cases {
+        let result = eval_text(&mut vm, text).unwrap();
+        assert!(result.is_set(), "{text}");
+        assert_eq!(result.to_string(), expected, "{text}");
+    }
+}
+
+#[test]
+fn test_set_membership() {
+    let mut vm = VM::default();
+    eval_text(&mut vm, "s = set([1, 2, 3])").unwrap();
+    assert_true(&mut vm, "s.has(2)");
+    assert_true(&mut vm, "!s.has(4)");
+    assert_true(&mut vm, "!s.has(\"1\")");
+    eval_text(&mut vm, "s.add(4)\ns.add(1)").unwrap();
+    assert_true(&mut vm, "s.has(4)");
+    assert_true(&mut vm, "s.remove(2)");
+    assert_true(&mut vm, "!s.remove(2)");
+    assert_true(&mut vm, "!s.has(2)");
+    let result = eval_text(&mut vm, "s").unwrap();
+    assert_eq!(result.to_string(), "{1, 3, 4}");
+    assert_eq!(result.len(), Some(3));
+}
+
+#[test]
+fn test_set_operations() {
+    let mut vm = VM::default();
+    eval_text(&mut vm, "a = set([1, 2, 3])\nb = set([4, 3, 2])").unwrap();
+    let cases = [
+        ("a.union(b)", "{1, 2, 3, 4}"),
+        ("a.intersection(b)", "{2, 3}"),
+        ("a.difference(b)", "{1}"),
+        ("b.difference(a)", "{4}"),
+        ("a.union(set([]))", "{1, 2, 3}"),
+        ("a.intersection(set([]))", "{}"),
+    ];
+    for (text, expected) in cases {
+        let result = eval_text(&mut vm, text).unwrap();
+        assert_eq!(result.to_string(), expected, "{text}");
+    }
+    // Order doesn't matter for equality.
+    assert_true(&mut vm, "a == set([3, 2, 1])");
+    assert_true(&mut vm, "a != b");
+    // The original sets aren't modified.
+    assert_true(&mut vm, "a == set([1, 2, 3])");
+}
+
+#[test]
+fn test_set_unhashable_item() {
+    let mut vm = VM::default();
+    let result = eval_text(&mut vm, "set([1, [2]])");
+    assert!(
+        matches!(
+            result,
+            Err(ExeErr { kind: ExeErrKind::RuntimeErr(RuntimeErrKind::TypeErr(_)) })
+        ),
+        "{result:?}"
+    );
+}
+
+#[test]
+fn test_set_in() {
+    let mut vm = VM::default();
+    eval_text(&mut vm, "s = set([1, \"a\"])").unwrap();
+    assert_true(&mut vm, "1 in s");
+    assert_true(&mut vm, "\"a\" in s");
+    assert_true(&mut vm, "!(2 in s)");
+    assert_true(&mut vm, "!(\"1\" in s)");
+    assert_true(&mut vm, "(1 in s) && (\"a\" in s)");
+}
+
+#[test]
+fn test_set_subset_and_superset() {
+    let mut vm = VM::default();
+    eval_text(&mut vm, "a = set([1, 2])\nb = set([2, 1, 3])\nc = set([3, 4])").unwrap();
+    // Subset
+    assert_true(&mut vm, "a <= b");
+    assert_true(&mut vm, "a <= a");
+    assert_true(&mut vm, "a <= set([2, 1])");
+    assert_true(&mut vm, "set([]) <= a");
+    assert_true(&mut vm, "!(b <= a)");
+    assert_true(&mut vm, "!(a <= c)");
+    assert_true(&mut vm, "a < b");
+    assert_true(&mut vm, "!(a < a)");
+    // Superset
+    assert_true(&mut vm, "b >= a");
+    assert_true(&mut vm, "b >= b");
+    assert_true(&mut vm, "a >= set([])");
+    assert_true(&mut vm, "!(a >= b)");
+    assert_true(&mut vm, "!(c >= a)");
+    assert_true(&mut vm, "b > a");
+    assert_true(&mut vm, "!(b > b)");
+}
+
+#[test]
+fn test_set_comparison_type_errors() {
+    for text in [
+        "set([1]) <= [1]",
+        "set([1]) >= (1,)",
+        "set([1]) < 1",
+        "1 in 2",
+        "[1] in set([1])",
+    ] {
+        let mut vm = VM::default();
+        let result = eval_text(&mut vm, text);
+        assert!(
+            matches!(
+                result,
+                Err(ExeErr {
+                    kind: ExeErrKind::RuntimeErr(RuntimeErrKind::TypeErr(_))
+                })
+            ),
+            "{text}: {result:?}"
+        );
+    }
+}
+
+#[test]
+fn test_tuple_and_list_in() {
+    let mut vm = VM::default();
+    eval_text(&mut vm, "t = (1, \"a\", [2])\nl = [1, \"a\", (2,)]").unwrap();
+    for name in ["t", "l"] {
+        assert_true(&mut vm, &format!("1 in {name}"));
+        assert_true(&mut vm, &format!("\"a\" in {name}"));
+        assert_true(&mut vm, &format!("!(2 in {name})"));
+        assert_true(&mut vm, &format!("!(\"1\" in {name})"));
+    }
+    // Items are compared by equality
+    assert_true(&mut vm, "[2] in t");
+    assert_true(&mut vm, "(2,) in l");
+    assert_true(&mut vm, "!(1 in ())");
+    assert_true(&mut vm, "!(1 in [])");
+}
+
+#[test]
+fn test_str_in() {
+    let mut vm = VM::default();
+    assert_true(&mut vm, "\"b\" in \"abc\"");
+    assert_true(&mut vm, "\"bc\" in \"abc\"");
+    assert_true(&mut vm, "\"\" in \"abc\"");
+    assert_true(&mut vm, "!(\"ac\" in \"abc\")");
+    let result = eval_text(&mut vm, "1 in \"abc\"");
+    assert!(
+        matches!(
+            result,
+            Err(ExeErr { kind: ExeErrKind::RuntimeErr(RuntimeErrKind::TypeErr(_)) })
+        ),
+        "{result:?}"
+    );
+}
+
+#[test]
+fn test_seq_get_item() {
+    let ctx = RuntimeContext::default();
+    let items = vec![
+        ctx.builtins.new_str("a"),
+        ctx.builtins.new_str("b"),
+        ctx.builtins.new_str("c"),
+    ];
+    let seqs = [
+        ctx.builtins.new_tuple(items.clone()),
+        ctx.builtins.new_list(items),
+        ctx.builtins.new_str("abc"),
+    ];
+    for seq in seqs {
+        for (index, expected) in [(0, "a"), (2, "c"), (-1, "c"), (-3, "a")] {
+            let index_obj = ctx.builtins.new_int(index);
+            let item = seq.get_item(&index_obj, &ctx).unwrap();
+            assert_eq!(item.str_val().unwrap(), expected, "{seq}.{index}");
+        }
+        for index in [3, -4] {
+            let index_obj = ctx.builtins.new_int(index);
+            match seq.get_item(&index_obj, &ctx) {
+                Err(RuntimeErr {
+                    kind: RuntimeErrKind::IndexOutOfBounds(i, 3),
+                    ..
+                }) => {
+                    assert_eq!(i, index.into())
+                }
+                result => panic!("{seq}.{index}: expected index error; got {result:?}"),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_tuple_concat() {
+    let mut vm = VM::default();
+    let cases = [
+        ("(1, 2) + (3, 4)", "(1, 2, 3, 4)"),
+        ("() + (1,)", "(1,)"),
+        ("(1,) + ()", "(1,)"),
+        ("() + ()", "()"),
+    ];
+    for (text, expected) in cases {
+        let result =
+            eval_text(&mut vm, text).unwrap_or_else(|err| panic!("{text}: {err:?}"));
+        assert_eq!(result.to_string(), expected, "{text}");
+    }
+    for text in ["(1, 2) + [3]", "(1, 2) + 3", "(1, 2) + \"3\""] {
+        let result = eval_text(&mut vm, text);
+        assert!(
+            matches!(
+                result,
+                Err(ExeErr {
+                    kind: ExeErrKind::RuntimeErr(RuntimeErrKind::TypeErr(_))
+                })
+            ),
+            "{text}: {result:?}"
+        );
+    }
+}
+
+#[test]
+fn test_tuple_slice() {
+    let mut vm = VM::default();
+    eval_text(&mut vm, "t = (1, 2, 3, 4)").unwrap();
+    let cases = [
+        ("t.slice(1, 3)", "(2, 3)"),
+        ("t.slice(0, 4)", "(1, 2, 3, 4)"),
+        ("t.slice(0, 0)", "()"),
+        ("t.slice(-2, 4)", "(3, 4)"),
+        ("t.slice(1, -1)", "(2, 3)"),
+        // Out of range indexes are clamped
+        ("t.slice(-10, 2)", "(1, 2)"),
+        ("t.slice(2, 10)", "(3, 4)"),
+        ("t.slice(10, 20)", "()"),
+        // Start after end
+        ("t.slice(3, 1)", "()"),
+        ("().slice(0, 1)", "()"),
+    ];
+    for (text, expected) in cases {
+        let result =
+            eval_text(&mut vm, text).unwrap_or_else(|err| panic!("{text}: {err:?}"));
+        assert_eq!(result.to_string(), expected, "{text}");
+    }
+    let result = eval_text(&mut vm, "t.slice(0, nil)");
+    assert!(
+        matches!(
+            result,
+            Err(ExeErr { kind: ExeErrKind::RuntimeErr(RuntimeErrKind::TypeErr(_)) })
+        ),
+        "{result:?}"
+    );
+}
+
+#[test]
+fn test_negative_index_syntax() {
+    let mut vm = VM::default();
+    let result =
+        eval_text(&mut vm, "l = [1, 2, 3]\nl.-1 = 4\n(l.-1, (1, 2).-2, \"héllo\".-4)");
+    assert_eq!(result.unwrap().to_string(), "(4, 1, \"é\")");
+}
+
+#[test]
+fn test_str_concat_and_repeat() {
+    let mut vm = VM::default();
+    let cases = [
+        ("\"a\" + \"b\"", "ab"),
+        ("\"a\" + \"\"", "a"),
+        ("\"ab\" * 3", "ababab"),
+        ("\"x\" * 1", "x"),
+        ("\"x\" * 0", ""),
+        ("\"\" * 5", ""),
+    ];
+    for (text, expected) in cases {
+        let result =
+            eval_text(&mut vm, text).unwrap_or_else(|err| panic!("{text}: {err:?}"));
+        assert_eq!(result.str_val().unwrap(), expected, "{text}");
+    }
+    for text in ["\"a\" + 1", "\"a\" * 1.0", "\"a\" * \"b\""] {
+        let result = eval_text(&mut vm, text);
+        assert!(
+            matches!(
+                result,
+                Err(ExeErr {
+                    kind: ExeErrKind::RuntimeErr(RuntimeErrKind::TypeErr(_))
+                })
+            ),
+            "{text}: {result:?}"
+        );
+    }
+    // Negative counts and results too large to allocate are errors
+    for text in ["\"a\" * -1", "\"a\" * 10 ** 15", "\"ab\" * (2 ** 29 + 1)"] {
+        let result = eval_text(&mut vm, text);
+        assert!(
+            matches!(
+                result,
+                Err(ExeErr {
+                    kind: ExeErrKind::RuntimeErr(RuntimeErrKind::ValueErr(_))
+                })
+            ),
+            "{text}: {result:?}"
+        );
+    }
+}
+
+#[test]
+fn test_str_repr() {
+    let ctx = RuntimeContext::default();
+    let cases = [
+        ("abc", "abc", "\"abc\""),
+        ("a\nb", "a\nb", "\"a\\nb\""),
+        ("\t\r\0", "\t\r\0", "\"\\t\\r\\0\""),
+        ("say \"hi\"", "say \"hi\"", "\"say \\\"hi\\\"\""),
+        ("a\\b", "a\\b", "\"a\\\\b\""),
+    ];
+    for (value, display, repr) in cases {
+        let obj = ctx.builtins.new_str(value);
+        assert_eq!(obj.to_string(), display);
+        assert_eq!(obj.repr(), repr);
+        assert_eq!(format!("{obj:?}"), repr);
+    }
+    // Other types use their Display output by default.
+    assert_eq!(ctx.builtins.new_int(1).repr(), "1");
+    assert_eq!(ctx.builtins.new_float(1.0).repr(), "1.0");
+    // Strings nested in collections are shown with their repr.
+    let s = ctx.builtins.new_str("a\nb");
+    let tuple = ctx.builtins.new_tuple(vec![s.clone(), ctx.builtins.new_int(1)]);
+    assert_eq!(tuple.to_string(), "(\"a\\nb\", 1)");
+    let list = ctx.builtins.new_list(vec![s]);
+    assert_eq!(list.to_string(), "[\"a\\nb\"]");
+}
+
+#[test]
+fn test_map_in() {
+    let mut vm = VM::default();
+    let (a, one) = (vm.ctx.builtins.new_str("a"), vm.ctx.builtins.new_int(1));
+    let (two, b) = (vm.ctx.builtins.new_int(2), vm.ctx.builtins.new_str("b"));
+    let map = vm.ctx.builtins.new_map(vec![(a, one), (two, b)]).unwrap();
+    vm.ctx.declare_and_assign_var("m", map).unwrap();
+    assert_true(&mut vm, "\"a\" in m");
+    assert_true(&mut vm, "2 in m");
+    // Values aren't checked
+    assert_true(&mut vm, "!(1 in m)");
+    assert_true(&mut vm, "!(\"b\" in m)");
+}
+
+#[test]
+fn test_not_in() {
+    let mut vm = VM::default();
+    eval_text(&mut vm, "l = [1, 2]").unwrap();
+    assert_true(&mut vm, "3 !in l");
+    assert_true(&mut vm, "3 not in l");
+    assert_true(&mut vm, "!(1 !in l)");
+    assert_true(&mut vm, "!(1 not in l)");
+    assert_true(&mut vm, "\"d\" !in \"abc\"");
+    assert_true(&mut vm, "\"x\" not in (\"a\", 1)");
+    // `!in` has the same precedence as `in`
+    assert_true(&mut vm, "1 + 2 !in l");
+    assert_true(&mut vm, "3 !in l && 1 in l");
+}
+
+#[test]
+fn test_floor_div_and_modulo() {
+    let mut vm = VM::default();
+    // Floor division and modulo round toward negative infinity, so
+    // the sign of the modulo matches the sign of the divisor.
+    let cases = [
+        ("7 // 2", "3"),
+        ("-7 // 2", "-4"),
+        ("7 // -2", "-4"),
+        ("-7 // -2", "3"),
+        ("7 % 3", "1"),
+        ("7 % -3", "-2"),
+        ("-7 % 3", "2"),
+        ("-7 % -3", "-1"),
+        ("-7.0 // 2", "-4.0"),
+        ("7.5 % -2", "-0.5"),
+        ("-7 % 2.0", "1.0"),
+        ("7 // 2.0", "3"),
+        // Large Ints don't lose precision
+        ("(10 ** 30 + 1) // 1", "1000000000000000000000000000001"),
+        ("(10 ** 30 + 1) % 10", "1"),
+    ];
+    for (text, expected) in cases {
+        let result =
+            eval_text(&mut vm, text).unwrap_or_else(|err| panic!("{text}: {err:?}"));
+        assert_eq!(result.to_string(), expected, "{text}");
+    }
+}
+
+#[test]
+fn test_int_pow() {
+    let mut vm = VM::default();
+    let cases = [
+        ("2 ** 0", "1"),
+        ("2 ** 10", "1024"),
+        ("-2 ** 3", "-8"),
+        // A negative exponent produces a Float
+        ("2 ** -1", "0.5"),
+        ("-2 ** -2", "0.25"),
+        ("2 ** 0.5", "1.4142135623730951"),
+    ];
+    for (text, expected) in cases {
+        let result =
+            eval_text(&mut vm, text).unwrap_or_else(|err| panic!("{text}: {err:?}"));
+        assert_eq!(result.to_string(), expected, "{text}");
+    }
+}
+
+#[test]
+fn test_int_pow_errors() {
+    let mut vm = VM::default();
+    let result = eval_text(&mut vm, "2 ** (2 ** 32)");
+    assert!(
+        matches!(
+            result,
+            Err(ExeErr { kind: ExeErrKind::RuntimeErr(RuntimeErrKind::ValueErr(_)) })
+        ),
+        "{result:?}"
+    );
+    let result = eval_text(&mut vm, "0 ** -1");
+    assert!(
+        matches!(
+            result,
+            Err(ExeErr {
+                kind: ExeErrKind::RuntimeErr(RuntimeErrKind::DivisionByZero)
+            })
+        ),
+        "{result:?}"
+    );
+}
+
+#[test]
+fn test_division_by_zero() {
+    for text in [
+        "5 / 0",
+        "5 // 0",
+        "5 % 0",
+        "5 / 0.0",
+        "5.0 / 0",
+        "5.0 // 0.0",
+        "5.0 % 0",
+        // Zero divided by zero is also an error rather than NaN
+        "0.0 / 0.0",
+    ] {
+        let mut vm = VM::default();
+        let result = eval_text(&mut vm, text);
+        assert!(
+            matches!(
+                result,
+                Err(ExeErr {
+                    kind: ExeErrKind::RuntimeErr(RuntimeErrKind::DivisionByZero)
+                })
+            ),
+            "{text}: {result:?}"
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use crate::util::Stack;
+use crate::util::{closest_name, source_from_text, Stack};
 
 #[test]
 fn new_stack_is_empty() {
@@ -58,3 +58,82 @@ fn clear() {
     stack.clear();
     assert_eq!(stack.size(), 0);
 }
+
+#[test]
+fn source_get_earlier_line() {
+    let mut source = source_from_text("a = 1\nb = 2\r\nc = 3");
+    let text: String = source.by_ref().collect();
+    assert_eq!(text, "\na = 1\nb = 2\nc = 3\n");
+    assert_eq!(source.line_no, 3);
+    assert_eq!(source.get_line(1), Some("a = 1"));
+    assert_eq!(source.get_line(2), Some("b = 2"));
+    assert_eq!(source.get_line(3), Some("c = 3"));
+    assert_eq!(source.get_line(0), None);
+    assert_eq!(source.get_line(4), None);
+}
+
+#[test]
+fn source_get_line_before_reading() {
+    let source = source_from_text("a = 1");
+    assert_eq!(source.get_line(1), None);
+}
+
+#[test]
+fn source_max_cached_lines() {
+    let mut source = source_from_text("1\n2\n3\n4");
+    source.set_max_cached_lines(Some(2));
+    let _: String = source.by_ref().collect();
+    assert_eq!(source.get_line(1), None);
+    assert_eq!(source.get_line(2), None);
+    assert_eq!(source.get_line(3), Some("3"));
+    assert_eq!(source.get_line(4), Some("4"));
+    source.set_max_cached_lines(Some(1));
+    assert_eq!(source.get_line(3), None);
+    assert_eq!(source.get_line(4), Some("4"));
+}
+
+#[test]
+fn push_checked() {
+    let mut stack: Stack<usize> = Stack::with_max_size(2);
+    assert_eq!(stack.push_checked(1), Ok(()));
+    assert_eq!(stack.push_checked(2), Ok(()));
+    assert_eq!(stack.push_checked(3), Err(3));
+    assert_eq!(stack.size(), 2);
+    stack.pop();
+    assert_eq!(stack.push_checked(3), Ok(()));
+    stack.set_max_size(None);
+    assert_eq!(stack.push_checked(4), Ok(()));
+    assert_eq!(stack.size(), 3);
+}
+
+#[test]
+fn peek_n() {
+    let mut stack: Stack<usize> = Stack::new();
+    assert_eq!(stack.peek_n(1), None);
+    stack.push(1);
+    stack.push(2);
+    stack.push(3);
+    assert_eq!(stack.peek_n(2), Some(&[2, 3][..]));
+    assert_eq!(stack.peek_n(3), Some(&[1, 2, 3][..]));
+    assert_eq!(stack.peek_n(4), None);
+    assert_eq!(stack.size(), 3);
+}
+
+#[test]
+fn closest_name_for_typos() {
+    let names = ["value", "values", "print", "x"];
+    assert_eq!(closest_name("valu", names), Some("value"));
+    assert_eq!(closest_name("prin", names), Some("print"));
+    assert_eq!(closest_name("valuess", names), Some("values"));
+    // Ties are broken alphabetically.
+    assert_eq!(closest_name("y", ["z", "x"]), Some("x"));
+}
+
+#[test]
+fn closest_name_requires_small_distance() {
+    let names = ["value", "print"];
+    assert_eq!(closest_name("total", names), None);
+    assert_eq!(closest_name("ab", ["xy"]), None);
+    // A name isn't suggested for itself.
+    assert_eq!(closest_name("value", ["value"]), None);
+}
@@ -1,7 +1,11 @@
+use crate::result::{ExeErr, ExeErrKind};
 use crate::types::ObjectExt;
 use crate::util::BinaryOperator;
 use crate::vm::*;
 
+use super::compiler::{compile_text, compile_text_with_locations};
+use super::exe::eval_text;
+
 #[test]
 fn execute_simple_program() {
     let mut vm = VM::default();
@@ -18,6 +22,81 @@ fn execute_simple_program() {
     }
 }
 
+#[test]
+fn step_through_program() {
+    let mut vm = VM::default();
+    let i = vm.ctx.add_const(vm.ctx.builtins.new_int(1));
+    let j = vm.ctx.add_const(vm.ctx.builtins.new_int(2));
+    let k = vm.ctx.add_const(vm.ctx.builtins.new_int(3));
+    let chunk: Chunk = vec![
+        Inst::LoadConst(i),
+        Inst::LoadConst(j),
+        Inst::BinaryOp(BinaryOperator::Add),
+        Inst::LoadConst(k),
+        Inst::BinaryOp(BinaryOperator::Mul),
+        Inst::Halt(0),
+    ];
+    // Expected stack size and top of stack after each instruction
+    let expected = [
+        (1, Some("1")),
+        (2, Some("2")),
+        (1, Some("3")),
+        (2, Some("3")),
+        (1, Some("9")),
+    ];
+    let mut ip = 0;
+    for (size, top) in expected {
+        let (next_ip, state) = vm.step(&chunk, ip).expect("Could not step");
+        assert_eq!(next_ip, ip + 1);
+        assert_eq!(state, VMState::Idle);
+        assert_eq!(vm.value_stack.size(), size, "ip = {ip}");
+        let obj = vm.peek_obj().expect("Could not get top of stack");
+        assert_eq!(obj.map(|obj| obj.to_string()).as_deref(), top, "ip = {ip}");
+        ip = next_ip;
+    }
+    let (_, state) = vm.step(&chunk, ip).expect("Could not step");
+    assert_eq!(state, VMState::Halted(0));
+}
+
+#[test]
+fn step_jump_returns_target_ip() {
+    let mut vm = VM::default();
+    let chunk: Chunk = vec![Inst::Jump(2, 0), Inst::NoOp, Inst::NoOp];
+    let (next_ip, state) = vm.step(&chunk, 0).expect("Could not step");
+    assert_eq!(next_ip, 2);
+    assert_eq!(state, VMState::Idle);
+}
+
+/// Execute a chunk that halts with the specified value as its exit
+/// code.
+fn halt_with(value: i64) -> Result<VMState, RuntimeErr> {
+    let mut vm = VM::default();
+    let i = vm.ctx.add_const(vm.ctx.builtins.new_int(value));
+    let chunk: Chunk = vec![Inst::LoadConst(i), Inst::HaltTop];
+    vm.execute(&chunk, false)
+}
+
+#[test]
+fn halt_top_with_valid_exit_codes() {
+    assert_eq!(halt_with(0).unwrap(), VMState::Halted(0));
+    assert_eq!(halt_with(255).unwrap(), VMState::Halted(255));
+}
+
+#[test]
+fn halt_top_with_out_of_range_exit_codes() {
+    // Exit codes that don't fit in a u8 are errors and are NOT clamped
+    for value in [256, -1] {
+        let result = halt_with(value);
+        assert!(
+            matches!(
+                result,
+                Err(RuntimeErr { kind: RuntimeErrKind::ExitCodeOutOfRange(_), .. })
+            ),
+            "{value}: {result:?}"
+        );
+    }
+}
+
 #[test]
 fn test_add_retrieve() {
     let mut ctx = RuntimeContext::default();
@@ -30,3 +109,704 @@ fn test_add_retrieve() {
     assert!(retrieved.is_equal(&*int_copy, &ctx));
     assert_eq!(retrieved.id(), int_copy.id());
 }
+
+#[test]
+fn format_vars_shows_each_scope() {
+    let mut vm = VM::default();
+    eval_text(&mut vm, "a = 1\nb = \"x\"").expect("Could not evaluate text");
+    vm.ctx.enter_scope();
+    let c = vm.ctx.builtins.new_int(2);
+    vm.ctx.declare_and_assign_var("c", c).expect("Could not assign var");
+    let lines = vm.format_vars();
+    // The current scope comes first.
+    assert_eq!(lines[0], "DEPTH 1");
+    assert_eq!(lines[1], "    c = 2 <Int>");
+    assert_eq!(lines[2], "DEPTH 0");
+    assert!(lines.contains(&"    a = 1 <Int>".to_owned()), "{lines:?}");
+    assert!(lines.contains(&"    b = \"x\" <Str>".to_owned()), "{lines:?}");
+}
+
+// Item assignment -----------------------------------------------------
+
+#[test]
+fn set_item_on_list() {
+    let mut vm = VM::default();
+    let result = eval_text(&mut vm, "l = [1, 2, 3]\nl.1 = 5").unwrap();
+    assert_eq!(result.to_string(), "5");
+    let result = eval_text(&mut vm, "l").unwrap();
+    assert_eq!(result.to_string(), "[1, 5, 3]");
+    // The list is shared, so the update is visible via other names.
+    let result = eval_text(&mut vm, "m = l\nm.0 = 4\nl").unwrap();
+    assert_eq!(result.to_string(), "[4, 5, 3]");
+}
+
+#[test]
+fn set_item_on_immutable_types() {
+    for text in ["t = (1, 2)\nt.0 = 3", "s = \"ab\"\ns.0 = \"c\""] {
+        let mut vm = VM::default();
+        let result = eval_text(&mut vm, text);
+        assert!(
+            matches!(
+                result,
+                Err(ExeErr {
+                    kind: ExeErrKind::RuntimeErr(RuntimeErrKind::ItemCannotBeSet(..))
+                })
+            ),
+            "{text}: {result:?}"
+        );
+    }
+}
+
+#[test]
+fn set_item_out_of_bounds() {
+    let mut vm = VM::default();
+    let result = eval_text(&mut vm, "l = [1]\nl.1 = 2");
+    assert!(
+        matches!(
+            result,
+            Err(ExeErr {
+                kind: ExeErrKind::RuntimeErr(RuntimeErrKind::IndexOutOfBounds(ref i, 1))
+            }) if *i == 1.into()
+        ),
+        "{result:?}"
+    );
+}
+
+#[test]
+fn unpack_tuple() {
+    let mut vm = VM::default();
+    let result = eval_text(&mut vm, "a, b = (1, 2)\n(b, a)").unwrap();
+    assert_eq!(result.to_string(), "(2, 1)");
+    let result = eval_text(&mut vm, "t = (3, 4, 5)\nx, y, z = t\n(x, y, z)").unwrap();
+    assert_eq!(result.to_string(), "(3, 4, 5)");
+    // The value of the assignment is the tuple
+    let result = eval_text(&mut vm, "c, d = t2 = (6, 7)").unwrap();
+    assert_eq!(result.to_string(), "(6, 7)");
+}
+
+#[test]
+fn unpack_tuple_length_mismatch() {
+    for text in ["a, b = (1, 2, 3)", "a, b, c = (1, 2)"] {
+        let mut vm = VM::default();
+        let result = eval_text(&mut vm, text);
+        assert!(
+            matches!(
+                result,
+                Err(ExeErr {
+                    kind: ExeErrKind::RuntimeErr(RuntimeErrKind::ValueErr(_))
+                })
+            ),
+            "{text}: {result:?}"
+        );
+    }
+}
+
+#[test]
+fn unpack_non_tuple() {
+    let mut vm = VM::default();
+    let result = eval_text(&mut vm, "a, b = [1, 2]");
+    assert!(
+        matches!(
+            result,
+            Err(ExeErr { kind: ExeErrKind::RuntimeErr(RuntimeErrKind::TypeErr(_)) })
+        ),
+        "{result:?}"
+    );
+}
+
+#[test]
+fn comparison_chain() {
+    let mut vm = VM::default();
+    for (text, expected) in [
+        ("1 < 2 < 3", true),
+        ("1 < 3 < 2", false),
+        ("3 < 2 < 1", false),
+        ("1 <= 1 < 2 <= 2", true),
+        ("1 < 2 < 3 < 3", false),
+        ("3 > 2 >= 2 > 1", true),
+        ("1 < 3 > 2", true),
+        ("1 == 1 != 2", true),
+        ("x = 5\n1 < x < 10", true),
+        ("x = 10\n1 < x < 10", false),
+    ] {
+        let result =
+            eval_text(&mut vm, text).unwrap_or_else(|err| panic!("{text}: {err:?}"));
+        assert_eq!(result.bool_val(), Some(expected), "{text}");
+    }
+}
+
+#[test]
+fn comparison_chain_evaluates_middle_once() {
+    let mut vm = VM::default();
+    let text = "\
+calls = [0]
+f = () ->
+    calls.0 = calls.0 + 1
+    5
+(1 < f() < 10, calls.0)
+";
+    let result = eval_text(&mut vm, text).unwrap();
+    assert_eq!(result.to_string(), "(true, 1)");
+}
+
+#[test]
+fn comparison_chain_leaves_no_temp_vars() {
+    let mut vm = VM::default();
+    let result = eval_text(&mut vm, "x = 5\ny = 1 < x < 10 < 20").unwrap();
+    assert_eq!(result.bool_val(), Some(true));
+    assert_eq!(vm.ctx.depth(), 0);
+    let globals = vm.ctx.iter_namespaces().next().unwrap();
+    let names: Vec<_> = globals.iter().map(|(name, _)| name.as_str()).collect();
+    assert!(!names.iter().any(|name| name.starts_with("$tmp")), "{names:?}");
+    assert!(names.contains(&"y"), "{names:?}");
+}
+
+#[test]
+fn inline_conditional() {
+    let mut vm = VM::default();
+    for (text, expected) in [
+        ("true -> 1 else -> 2", "1"),
+        ("false -> 1 else -> 2", "2"),
+        ("x = 2\nx == 1 -> \"one\" else -> x == 2 -> \"two\" else -> \"many\"", "two"),
+        ("x = 3\nx == 1 -> \"one\" else -> x == 2 -> \"two\" else -> \"many\"", "many"),
+        ("y = 1 < 2 -> \"yes\" else -> \"no\"\ny", "yes"),
+        ("1 + (false -> 10 else -> 20)", "21"),
+        (
+            "f = (n) -> n > 0 -> \"pos\" else -> \"neg\"\n(f(1), f(-1))",
+            "(\"pos\", \"neg\")",
+        ),
+        ("true -> if false -> 1 else -> 2 else -> 3", "2"),
+    ] {
+        let result =
+            eval_text(&mut vm, text).unwrap_or_else(|err| panic!("{text}: {err:?}"));
+        assert_eq!(result.to_string(), expected, "{text}");
+    }
+}
+
+#[test]
+fn inline_conditional_ending_with_parens_in_func() {
+    // Conditions that end with a call or a parenthesized expression
+    // aren't mistaken for function params.
+    let mut vm = VM::default();
+    eval_text(&mut vm, "ok = (n) -> n > 0").unwrap();
+    for (text, expected) in [
+        ("f = (n) -> ok(n) -> 1 else -> 2\n(f(1), f(-1))", "(1, 2)"),
+        ("f = (n) -> (n > 0) -> 1 else -> 2\n(f(1), f(-1))", "(1, 2)"),
+        ("f = (n) -> (n, 1) == (1, 1) -> 1 else -> 2\n(f(1), f(-1))", "(1, 2)"),
+        ("f = (n, m = ok(n)) -> m -> 1 else -> 2\n(f(1), f(-1))", "(1, 2)"),
+        ("x = 1\n(x > 0) -> 1 else -> 2", "1"),
+        ("g = (n) -> (n) -> n + 1\ng(1)(2)", "3"),
+    ] {
+        let result =
+            eval_text(&mut vm, text).unwrap_or_else(|err| panic!("{text}: {err:?}"));
+        assert_eq!(result.to_string(), expected, "{text}");
+    }
+}
+
+// Serialization -------------------------------------------------------
+
+/// Execute the chunk and return the string representation of the
+/// object at the top of the stack.
+fn execute_to_string(vm: &mut VM, chunk: &Chunk) -> String {
+    vm.execute(chunk, false).expect("Could not execute chunk");
+    let obj = vm.peek_obj().expect("Could not get top of stack");
+    obj.expect("Stack is empty").to_string()
+}
+
+#[test]
+fn serialize_round_trip() {
+    let text = "\
+f = (n, s) ->
+    if n == 0 ->
+        s
+    else ->
+        f(n - 1, $\"{s}.\")
+x = 1.5 * 2
+y, z = (x, 4)
+(f(3, \"a\"), y, z, nil, true, !false)";
+
+    let mut vm = VM::default();
+    let chunk = compile_text(&mut vm, text);
+    let chunk_bytes = chunk.to_bytes();
+    let const_bytes = vm.ctx.serialize_constants().unwrap();
+    let expected = execute_to_string(&mut vm, &chunk);
+
+    let mut new_vm = VM::default();
+    new_vm.ctx.deserialize_constants(&const_bytes).unwrap();
+    let new_chunk = Chunk::from_bytes(&chunk_bytes).unwrap();
+    assert_eq!(new_chunk, chunk);
+    assert_eq!(new_vm.ctx.iter_constants().count(), vm.ctx.iter_constants().count());
+    let actual = execute_to_string(&mut new_vm, &new_chunk);
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn serialize_func_locations() {
+    // The multi-byte char puts the function body's byte offset past
+    // its column.
+    let text = "t = (\"é\", () -> 1)";
+    let mut vm = VM::default();
+    compile_text(&mut vm, text);
+    let const_bytes = vm.ctx.serialize_constants().unwrap();
+    let mut new_vm = VM::default();
+    new_vm.ctx.deserialize_constants(&const_bytes).unwrap();
+    let locations = |vm: &VM| {
+        let mut consts = vm.ctx.iter_constants();
+        let locations =
+            consts.find_map(|obj| obj.as_func().map(|f| f.locations.clone()));
+        locations.expect("Expected a func constant")
+    };
+    let expected = locations(&vm);
+    assert!(expected.iter().any(|loc| loc.offset > loc.col - 1), "{expected:?}");
+    assert_eq!(locations(&new_vm), expected);
+}
+
+#[test]
+fn deserialize_bad_data() {
+    let chunk: Chunk = vec![Inst::LoadConst(0), Inst::Return];
+    let bytes = chunk.to_bytes();
+
+    let mut bad_magic = bytes.clone();
+    bad_magic[0] = b'X';
+    assert_eq!(Chunk::from_bytes(&bad_magic), Err(SerializeErr::BadMagic));
+
+    let mut bad_version = bytes.clone();
+    bad_version[4] = 99;
+    assert_eq!(
+        Chunk::from_bytes(&bad_version),
+        Err(SerializeErr::UnsupportedVersion(99))
+    );
+
+    let truncated = &bytes[..bytes.len() - 1];
+    assert_eq!(Chunk::from_bytes(truncated), Err(SerializeErr::UnexpectedEnd));
+
+    // Chunk data can't be loaded as constants
+    let mut ctx = RuntimeContext::default();
+    assert!(matches!(
+        ctx.deserialize_constants(&bytes),
+        Err(SerializeErr::UnexpectedSection(_))
+    ));
+}
+
+#[test]
+fn deserialize_crafted_lengths() {
+    // Header and length for a chunk with a single instruction
+    let chunk: Chunk = vec![Inst::Pop];
+    let mut bytes = chunk.to_bytes();
+    bytes.pop();
+
+    // DeclareVar with a name length that overflows when added to the
+    // current position
+    let mut bad_len = bytes.clone();
+    bad_len.push(6);
+    bad_len.extend_from_slice(&u64::MAX.to_le_bytes());
+    assert_eq!(Chunk::from_bytes(&bad_len), Err(SerializeErr::UnexpectedEnd));
+
+    // A huge constant count shouldn't be used to preallocate
+    let mut ctx = RuntimeContext::default();
+    let mut bad_count = ctx.serialize_constants().unwrap();
+    bad_count.truncate(7); // Header only
+    bad_count.extend_from_slice(&u64::MAX.to_le_bytes());
+    assert_eq!(ctx.deserialize_constants(&bad_count), Err(SerializeErr::UnexpectedEnd));
+}
+
+// Loops ---------------------------------------------------------------
+
+#[test]
+fn loop_body_values_are_discarded() {
+    let mut vm = VM::default();
+    let text =
+        "i = 0\nloop i < 1000 ->\n    i += 1\n    if i % 2 == 0 -> continue\n    i * 2";
+    let chunk = compile_text(&mut vm, text);
+    vm.execute(&chunk, false).unwrap();
+    // Only the value of the loop itself is left on the stack.
+    assert_eq!(vm.value_stack.size(), 1);
+    assert!(vm.peek_obj().unwrap().unwrap().is_nil());
+}
+
+#[test]
+fn loop_value() {
+    let mut vm = VM::default();
+    for (text, expected) in [
+        // Loops that exit because their condition is false are nil
+        ("loop false -> 1", "nil"),
+        ("i = 0\nloop i < 3 ->\n    i += 1", "nil"),
+        // Loops that exit via break have the break value
+        ("i = 0\nloop ->\n    i += 1\n    if i == 3 -> break i * 2\n    i", "6"),
+        ("i = 0\nloop i < 10 ->\n    i += 1\n    if i == 3 -> break i\n    nil", "3"),
+        ("x = loop false -> 1\nloop false -> 2\nx", "nil"),
+    ] {
+        let result =
+            eval_text(&mut vm, text).unwrap_or_else(|err| panic!("{text}: {err:?}"));
+        assert_eq!(result.to_string(), expected, "{text}");
+    }
+}
+
+// Profiling -----------------------------------------------------------
+
+#[test]
+fn profile_counts_binary_ops() {
+    let mut vm = VM::default();
+    // The loop condition is evaluated 11 times and the loop body is
+    // executed 10 times, for a total of 21 binary operations.
+    let chunk = compile_text(&mut vm, "i = 0\nloop i < 10 ->\n    i += 1");
+    vm.profile = true;
+    vm.execute(&chunk, false).unwrap();
+    let report = vm.profile_report();
+    let (_, count, _) = report.iter().find(|(name, ..)| name == "BINARY_OP").unwrap();
+    assert_eq!(*count, 21);
+}
+
+#[test]
+fn profile_disabled_by_default() {
+    let mut vm = VM::default();
+    let chunk = compile_text(&mut vm, "1 + 2");
+    vm.execute(&chunk, false).unwrap();
+    assert!(vm.profile_report().is_empty());
+}
+
+#[test]
+fn unbalanced_scope_end_is_an_error() {
+    let mut vm = VM::default();
+    let chunk: Chunk =
+        vec![Inst::ScopeStart, Inst::ScopeEnd, Inst::ScopeEnd, Inst::Halt(0)];
+    let err = vm.execute(&chunk, false).unwrap_err();
+    match err.kind {
+        RuntimeErrKind::ScopeUnderflow(message) => {
+            assert!(message.contains("Cannot exit 1 scope(s)"), "{message}");
+            assert!(message.contains("scope stack size: 0"), "{message}");
+        }
+        kind => panic!("Expected scope underflow; got {kind:?}"),
+    }
+}
+
+#[test]
+fn jump_out_of_too_many_scopes_is_an_error() {
+    let mut vm = VM::default();
+    let chunk: Chunk = vec![Inst::ScopeStart, Inst::Jump(2, 2), Inst::Halt(0)];
+    let err = vm.execute(&chunk, false).unwrap_err();
+    assert!(matches!(err.kind, RuntimeErrKind::ScopeUnderflow(_)), "{err:?}");
+}
+
+// Error locations -----------------------------------------------------
+
+#[test]
+fn runtime_err_in_func_has_location_and_traceback() {
+    let mut vm = VM::default();
+    let text = "f = (x) ->\n    y = 1\n    x + nil\n\nf(1)";
+    let (chunk, locations) = compile_text_with_locations(&mut vm, text);
+    let err = vm.execute(&chunk, false).unwrap_err();
+    assert!(matches!(err.kind, RuntimeErrKind::TypeErr(_)), "{err:?}");
+    // The error occurred on line 3, inside the function...
+    assert_eq!(err.loc().map(|loc| loc.line), Some(3));
+    assert_eq!(err.traceback.len(), 2);
+    assert_eq!(err.traceback[0].func_name.as_deref(), Some("f"));
+    // ...which was called from the top level on line 5.
+    let top = &err.traceback[1];
+    assert!(top.func_name.is_none());
+    assert!(matches!(chunk[top.ip], Inst::Call(1)));
+    assert_eq!(locations[top.ip].line, 5);
+}
+
+#[test]
+fn runtime_err_at_top_level_has_traceback_entry() {
+    let mut vm = VM::default();
+    let (chunk, locations) = compile_text_with_locations(&mut vm, "x = 1\nx + nil");
+    let err = vm.execute(&chunk, false).unwrap_err();
+    assert_eq!(err.traceback.len(), 1);
+    assert_eq!(locations[err.traceback[0].ip].line, 2);
+}
+
+#[test]
+fn runtime_err_kind_names() {
+    let vm = VM::default();
+    let nil = vm.ctx.builtins.nil_obj.clone();
+    for (kind, name) in [
+        (RuntimeErrKind::TypeErr("message".to_owned()), "TypeErr"),
+        (RuntimeErrKind::DivisionByZero, "DivisionByZero"),
+        (RuntimeErrKind::IndexOutOfBounds(1.into(), 0), "IndexOutOfBounds"),
+        (RuntimeErrKind::NotCallable(nil.clone()), "NotCallable"),
+        (RuntimeErrKind::Raised(nil), "Raised"),
+    ] {
+        assert_eq!(kind.name(), name);
+    }
+}
+
+#[test]
+fn runtime_err_traceback_two_calls_deep() {
+    let mut vm = VM::default();
+    let text = "\
+g = (x) ->
+    x + nil
+f = (x) ->
+    y = 1
+    g(x)
+
+f(1)";
+    let (chunk, locations) = compile_text_with_locations(&mut vm, text);
+    let mut err = vm.execute(&chunk, false).unwrap_err();
+    assert_eq!(err.traceback.len(), 3);
+    // The executor fills in the location of the top level entry.
+    let top = err.traceback.last_mut().unwrap();
+    top.loc = locations.get(top.ip).copied();
+    assert_eq!(
+        err.format_traceback("<text>"),
+        vec!["<text>, line 7", "f, line 5", "g, line 2"]
+    );
+}
+
+// Step limit ----------------------------------------------------------
+
+#[test]
+fn execute_with_limit_stops_infinite_loop() {
+    let mut vm = VM::default();
+    let chunk = compile_text(&mut vm, "i = 0\nloop ->\n    i += 1");
+    let err = vm.execute_with_limit(&chunk, 1000, false).unwrap_err();
+    assert!(matches!(err.kind, RuntimeErrKind::StepLimitExceeded(1000)), "{err:?}");
+}
+
+#[test]
+fn execute_with_limit_counts_steps_in_funcs() {
+    let mut vm = VM::default();
+    let chunk = compile_text(&mut vm, "f = () ->\n    loop -> nil\n\nf()");
+    let err = vm.execute_with_limit(&chunk, 1000, false).unwrap_err();
+    assert!(matches!(err.kind, RuntimeErrKind::StepLimitExceeded(1000)), "{err:?}");
+}
+
+#[test]
+fn execute_with_limit_is_not_caught() {
+    let mut vm = VM::default();
+    let chunk = compile_text(&mut vm, "try ->\n    loop -> nil\ncatch -> 1");
+    let err = vm.execute_with_limit(&chunk, 1000, false).unwrap_err();
+    assert!(matches!(err.kind, RuntimeErrKind::StepLimitExceeded(1000)), "{err:?}");
+    assert!(vm.handler_stack.is_empty());
+}
+
+#[test]
+fn execute_with_limit_within_budget() {
+    let mut vm = VM::default();
+    let chunk = compile_text(&mut vm, "i = 0\nloop i < 10 ->\n    i += 1\ni");
+    vm.execute_with_limit(&chunk, 1000, false).unwrap();
+    assert_eq!(vm.peek_obj().unwrap().unwrap().int_val(), Some(10.into()));
+    // The limit only applies to the chunk it was set for.
+    let mut vm = VM::default();
+    let chunk = compile_text(&mut vm, "i = 0\nloop i < 10 ->\n    i += 1\ni");
+    assert!(vm.execute_with_limit(&chunk, 10, false).is_err());
+    vm.execute(&chunk, false).unwrap();
+}
+
+#[test]
+fn value_stack_overflow_in_expression() {
+    let mut vm = VM::default();
+    vm.set_max_stack_size(Some(20));
+    let items: Vec<String> = (0..50).map(|i| i.to_string()).collect();
+    let text = format!("({})", items.join(", "));
+    match eval_text(&mut vm, &text) {
+        Err(ExeErr {
+            kind: ExeErrKind::RuntimeErr(RuntimeErrKind::StackOverflow(20)),
+        }) => (),
+        result => panic!("Expected stack overflow; got {result:?}"),
+    }
+    // The VM is still usable after the error.
+    assert_eq!(eval_text(&mut vm, "1 + 2").unwrap().int_val(), Some(3.into()));
+}
+
+#[test]
+fn value_stack_overflow_in_recursion() {
+    let mut vm = VM::default();
+    vm.max_call_depth = 1000;
+    vm.set_max_stack_size(Some(100));
+    let text = "f = (n) -> if n == 0 -> 0 else -> 1 + f(n - 1)\nf(500)";
+    match eval_text(&mut vm, text) {
+        Err(ExeErr {
+            kind: ExeErrKind::RuntimeErr(RuntimeErrKind::StackOverflow(100)),
+        }) => (),
+        result => panic!("Expected stack overflow; got {result:?}"),
+    }
+    // The call stack is unwound after the error.
+    assert_eq!(vm.frame_stack.size(), 0);
+    assert_eq!(eval_text(&mut vm, "f(5)").unwrap().int_val(), Some(5.into()));
+}
+
+#[test]
+fn value_stack_overflow_can_be_caught() {
+    let mut vm = VM::default();
+    vm.set_max_stack_size(Some(100));
+    let text = "\
+f = (n) -> if n == 0 -> 0 else -> 1 + f(n - 1)
+try -> f(200)
+catch err -> err.kind";
+    let result = eval_text(&mut vm, text).unwrap();
+    assert_eq!(result.str_val(), Some("StackOverflow".to_owned()));
+}
+
+#[test]
+fn uncaught_err_unwinds_value_stack() {
+    let mut vm = VM::default();
+    vm.set_max_stack_size(Some(20));
+    // Each failed op would otherwise leave its operands on the stack.
+    for _ in 0..20 {
+        assert!(eval_text(&mut vm, "1 + nil").is_err());
+        assert_eq!(vm.value_stack.size(), 0);
+    }
+    // Scopes entered before the error are exited too.
+    assert!(eval_text(&mut vm, "block ->\n    x = 1\n    x + nil").is_err());
+    assert_eq!(vm.value_stack.size(), 0);
+    assert_eq!(vm.ctx.depth(), 0);
+    assert_eq!(eval_text(&mut vm, "1 + 2").unwrap().int_val(), Some(3.into()));
+}
+
+#[test]
+fn binary_op_matches_direct_object_ops() {
+    use BinaryOperator::*;
+    let mut vm = VM::default();
+    let builtins = &vm.ctx.builtins;
+    let operands = [
+        (builtins.new_int(7), builtins.new_int(2)),
+        (builtins.new_int(-7), builtins.new_int(3)),
+        (builtins.new_float(7.5), builtins.new_float(2.5)),
+        (builtins.new_int(7), builtins.new_float(0.5)),
+        (builtins.new_float(-1.5), builtins.new_int(4)),
+    ];
+    for (a, b) in operands.iter() {
+        for op in [Add, Sub, Mul, Div] {
+            let expected = match op {
+                Add => a.add(&**b, &vm.ctx),
+                Sub => a.sub(&**b, &vm.ctx),
+                Mul => a.mul(&**b, &vm.ctx),
+                Div => a.div(&**b, &vm.ctx),
+                _ => unreachable!(),
+            }
+            .unwrap();
+            let i = vm.ctx.add_const(a.clone());
+            let j = vm.ctx.add_const(b.clone());
+            let chunk = vec![
+                Inst::LoadConst(i),
+                Inst::LoadConst(j),
+                Inst::BinaryOp(op.clone()),
+            ];
+            vm.execute(&chunk, false).unwrap();
+            // The operands are replaced by the result.
+            assert_eq!(vm.value_stack.size(), 1);
+            let result = vm.pop_obj().unwrap();
+            assert!(result.is_equal(&*expected, &vm.ctx), "{a} {op} {b}: {result}");
+            assert!(result.class().is(&expected.class()), "{a} {op} {b}");
+        }
+    }
+}
+
+#[test]
+fn binary_op_in_place_update() {
+    let mut vm = VM::default();
+    let result = eval_text(&mut vm, "x = 1\nx += 2\nx -= 5\nx").unwrap();
+    assert_eq!(result.int_val(), Some((-2).into()));
+}
+
+#[test]
+fn binary_op_requires_two_operands() {
+    let mut vm = VM::default();
+    let i = vm.ctx.add_const(vm.ctx.builtins.new_int(1));
+    let chunk = vec![Inst::LoadConst(i), Inst::BinaryOp(BinaryOperator::Add)];
+    let err = vm.execute(&chunk, false).unwrap_err();
+    assert!(matches!(err.kind, RuntimeErrKind::NotEnoughValuesOnStack(2)));
+}
+
+/// Run chunk that assigns 2 to `x` inside a nested scope, using the
+/// specified assignment instructions, and return the values of `x` in
+/// the nested and outer scopes along with the value stack size.
+fn run_assignment(assign: Vec<Inst>, redeclare: bool) -> (String, String, usize) {
+    let mut vm = VM::default();
+    let one = vm.ctx.add_const(vm.ctx.builtins.new_int(1));
+    let two = vm.ctx.add_const(vm.ctx.builtins.new_int(2));
+    let mut chunk = vec![
+        Inst::LoadConst(one),
+        Inst::DeclareVar("x".to_owned()),
+        Inst::AssignVar("x".to_owned()),
+        Inst::Pop,
+        Inst::ScopeStart,
+    ];
+    if redeclare {
+        chunk.extend([
+            Inst::LoadConst(one),
+            Inst::DeclareAndAssignVar("x".to_owned()),
+            Inst::Pop,
+        ]);
+    }
+    chunk.push(Inst::LoadConst(two));
+    chunk.extend(assign);
+    vm.execute(&chunk, false).unwrap();
+    let inner = vm.ctx.get_var_in_current_namespace("x").unwrap().to_string();
+    let size = vm.value_stack.size();
+    vm.execute(&vec![Inst::Pop, Inst::ScopeEnd], false).unwrap();
+    let outer = vm.ctx.get_var_in_current_namespace("x").unwrap().to_string();
+    (inner, outer, size)
+}
+
+#[test]
+fn declare_and_assign_var_matches_separate_insts() {
+    for redeclare in [false, true] {
+        let separate = run_assignment(
+            vec![Inst::DeclareVar("x".to_owned()), Inst::AssignVar("x".to_owned())],
+            redeclare,
+        );
+        let fused =
+            run_assignment(vec![Inst::DeclareAndAssignVar("x".to_owned())], redeclare);
+        assert_eq!(fused, separate);
+        // The var in the nested scope shadows the outer var.
+        assert_eq!(fused, ("2".to_owned(), "1".to_owned(), 1));
+    }
+}
+
+#[test]
+fn load_and_store_local() {
+    let mut vm = VM::default();
+    let one = vm.ctx.add_const(vm.ctx.builtins.new_int(1));
+    let two = vm.ctx.add_const(vm.ctx.builtins.new_int(2));
+    let chunk = vec![
+        Inst::ScopeStart,
+        Inst::LoadConst(one),
+        Inst::DeclareAndAssignVar("x".to_owned()),
+        Inst::Pop,
+        Inst::ScopeStart,
+        Inst::LoadConst(two),
+        Inst::StoreLocal(1, 0),
+        Inst::Pop,
+        Inst::LoadLocal(1, 0),
+    ];
+    vm.execute(&chunk, false).unwrap();
+    assert_eq!(vm.peek_obj().unwrap().unwrap().to_string(), "2");
+    // The var is also updated by name.
+    let depth = vm.ctx.depth() - 1;
+    assert_eq!(vm.ctx.get_var_at_depth(depth, "x").unwrap().to_string(), "2");
+    // Slots that don't exist are errors.
+    let result = vm.execute(&vec![Inst::LoadLocal(1, 1)], false);
+    assert!(matches!(result, Err(RuntimeErr { kind: RuntimeErrKind::NameErr(_), .. })));
+}
+
+#[test]
+fn locals_match_globals() {
+    // Vars at the top level are accessed by name and vars in blocks
+    // and functions are accessed by slot, so each program is evaluated
+    // both ways.
+    let programs = [
+        "x = 1\nx += 2\nx -= 1\nx = x * 10\nx",
+        "a, b = (1, 2)\na, b = (b, a)\n(a, b)",
+        "i = 0\ntotal = 0\nloop i < 5 ->\n    total += i\n    i += 1\n(i, total)",
+        "x = 1\ny = if x == 1 ->\n    x += 1\n    x\nelse -> 0\n(x, y)",
+        "try ->\n    raise \"oops\"\ncatch err ->\n    m = err.message\n    $\"{m}!\"",
+        "f = (a, rest...) ->\n    b = a\n    b += 1\n    (b, rest)\nf(1, 2, 3)",
+    ];
+    for program in programs {
+        let indented: Vec<_> =
+            program.lines().map(|line| format!("    {line}")).collect();
+        let in_block = format!("block ->\n{}", indented.join("\n"));
+        let in_func = format!("f = () ->\n{}\nf()", indented.join("\n"));
+        let mut vm = VM::default();
+        let expected = eval_text(&mut vm, program).unwrap().to_string();
+        for text in [in_block, in_func] {
+            let mut vm = VM::default();
+            let result = eval_text(&mut vm, &text).unwrap().to_string();
+            assert_eq!(result, expected, "{text}");
+        }
+    }
+}
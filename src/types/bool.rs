@@ -39,6 +39,8 @@ impl Object for Bool {
 
     // Binary operations -----------------------------------------------
 
+    /// Bools are only equal to other Bools. In particular, they're NOT
+    /// equal to Ints, so `true == 1` is false.
     fn is_equal(&self, rhs: &dyn Object, _ctx: &RuntimeContext) -> bool {
         if let Some(rhs) = rhs.as_any().downcast_ref::<Self>() {
             self.is(rhs) || self.value() == rhs.value()
@@ -11,15 +11,42 @@ use super::result::{Args, CallResult, Params};
 
 pub type BuiltinFn = fn(Args, &mut VM) -> CallResult;
 
+/// Builtin functions can also be implemented by closures, which allows
+/// host code to register functions that capture state.
+pub type BuiltinClosure = Box<dyn Fn(Args, &mut VM) -> CallResult + Send + Sync>;
+
+/// Most builtin functions are plain functions, which don't need to be
+/// boxed. Closures are only used for functions registered by host code.
+pub enum BuiltinFuncImpl {
+    Fn(BuiltinFn),
+    Closure(BuiltinClosure),
+}
+
 pub struct BuiltinFunc {
     pub name: String,
     pub params: Params,
     pub arity: Option<usize>,
-    pub func: BuiltinFn,
+    pub func: BuiltinFuncImpl,
 }
 
 impl BuiltinFunc {
     pub fn new<S: Into<String>>(name: S, params: Params, func: BuiltinFn) -> Self {
+        Self::with_impl(name, params, BuiltinFuncImpl::Fn(func))
+    }
+
+    pub fn new_closure<S, F>(name: S, params: Params, func: F) -> Self
+    where
+        S: Into<String>,
+        F: Fn(Args, &mut VM) -> CallResult + Send + Sync + 'static,
+    {
+        Self::with_impl(name, params, BuiltinFuncImpl::Closure(Box::new(func)))
+    }
+
+    fn with_impl<S: Into<String>>(
+        name: S,
+        params: Params,
+        func: BuiltinFuncImpl,
+    ) -> Self {
         let arity = if let Some(params) = &params { Some(params.len()) } else { None };
         Self { name: name.into(), params, arity, func }
     }
@@ -35,7 +62,10 @@ impl Object for BuiltinFunc {
     }
 
     fn call(&self, args: Args, vm: &mut VM) -> CallResult {
-        (self.func)(args, vm)
+        match &self.func {
+            BuiltinFuncImpl::Fn(func) => func(args, vm),
+            BuiltinFuncImpl::Closure(func) => func(args, vm),
+        }
     }
 }
 
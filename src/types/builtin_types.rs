@@ -10,11 +10,15 @@ lazy_static! {
     pub static ref BUILTIN_TYPES: HashMap<&'static str, TypeRef> = [
         ("Bool", Arc::new(Type::new("builtins", "Bool"))),
         ("BuiltinFunc", Arc::new(Type::new("builtins", "BuiltinFunc"))),
+        ("Error", Arc::new(Type::new("builtins", "Error"))),
         ("Float", Arc::new(Type::new("builtins", "Float"))),
         ("Func", Arc::new(Type::new("builtins", "Func"))),
         ("Int", Arc::new(Type::new("builtins", "Int"))),
+        ("List", Arc::new(Type::new("builtins", "List"))),
+        ("Map", Arc::new(Type::new("builtins", "Map"))),
         ("Namespace", Arc::new(Type::new("builtins", "Namespace"))),
         ("Nil", Arc::new(Type::new("builtins", "Nil"))),
+        ("Set", Arc::new(Type::new("builtins", "Set"))),
         ("Str", Arc::new(Type::new("builtins", "Str"))),
         ("Tuple", Arc::new(Type::new("builtins", "Tuple"))),
         ("Type", Arc::new(Type::new("builtins", "Type"))),
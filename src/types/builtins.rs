@@ -1,15 +1,22 @@
 use std::sync::Arc;
 
 use num_bigint::BigInt;
-use num_traits::Num;
+use num_traits::{Num, ToPrimitive};
 
-use crate::vm::Chunk;
+use crate::util::Location;
+use crate::vm::{Chunk, RuntimeErr, VM};
 
 use super::builtin_func::BuiltinFn;
 use super::class::{Type, TypeRef};
 use super::custom::Custom;
 use super::object::ObjectRef;
-use super::result::Params;
+use super::result::{Args, CallResult, Params};
+
+/// Range of small Ints that are interned. Creating an Int in this range
+/// always returns the same object, so `1 is 1` is true, whereas larger
+/// Ints are distinct objects even when their values are equal.
+const SMALL_INT_MIN: i32 = -5;
+const SMALL_INT_MAX: i32 = 256;
 
 pub struct Builtins {
     // Singletons
@@ -17,6 +24,8 @@ pub struct Builtins {
     pub true_obj: Arc<super::bool::Bool>,
     pub false_obj: Arc<super::bool::Bool>,
     pub empty_tuple: Arc<super::tuple::Tuple>,
+    // Interned Ints from SMALL_INT_MIN to SMALL_INT_MAX
+    small_ints: Vec<ObjectRef>,
 }
 
 impl Builtins {
@@ -26,7 +35,10 @@ impl Builtins {
         let true_obj = Arc::new(super::bool::Bool::new(true));
         let false_obj = Arc::new(super::bool::Bool::new(false));
         let empty_tuple = Arc::new(super::tuple::Tuple::new(vec![]));
-        Self { nil_obj, true_obj, false_obj, empty_tuple }
+        let small_ints = (SMALL_INT_MIN..=SMALL_INT_MAX)
+            .map(|i| Arc::new(super::int::Int::new(BigInt::from(i))) as ObjectRef)
+            .collect();
+        Self { nil_obj, true_obj, false_obj, empty_tuple, small_ints }
     }
 
     pub fn new_type(&self, module: &str, name: &str) -> TypeRef {
@@ -46,6 +58,24 @@ impl Builtins {
         Arc::new(super::builtin_func::BuiltinFunc::new(name, params, func))
     }
 
+    pub fn new_builtin_closure<S, F>(
+        &self,
+        name: S,
+        params: Option<Vec<S>>,
+        func: F,
+    ) -> ObjectRef
+    where
+        S: Into<String>,
+        F: Fn(Args, &mut VM) -> CallResult + Send + Sync + 'static,
+    {
+        let params = self.collect_params(params);
+        Arc::new(super::builtin_func::BuiltinFunc::new_closure(name, params, func))
+    }
+
+    pub fn new_error<S: Into<String>>(&self, kind: S, message: S) -> ObjectRef {
+        Arc::new(super::error::Error::new(kind, message))
+    }
+
     pub fn new_float<F: Into<f64>>(&self, value: F) -> ObjectRef {
         let value = value.into();
         Arc::new(super::float::Float::new(value))
@@ -61,22 +91,62 @@ impl Builtins {
         &self,
         name: S,
         params: Option<Vec<S>>,
+        defaults: Vec<Chunk>,
+        rest: Option<String>,
         chunk: Chunk,
+        locations: Vec<Location>,
     ) -> ObjectRef {
         let params = self.collect_params(params);
-        Arc::new(super::func::Func::new(name, params, chunk))
+        Arc::new(super::func::Func::new(name, params, defaults, rest, chunk, locations))
     }
 
     pub fn new_int<I: Into<BigInt>>(&self, value: I) -> ObjectRef {
         let value = value.into();
+        if let Some(i) = value.to_i32() {
+            if (SMALL_INT_MIN..=SMALL_INT_MAX).contains(&i) {
+                return self.small_ints[(i - SMALL_INT_MIN) as usize].clone();
+            }
+        }
         Arc::new(super::int::Int::new(value))
     }
 
+    pub fn new_list(&self, items: Vec<ObjectRef>) -> ObjectRef {
+        Arc::new(super::list::List::new(items))
+    }
+
+    pub fn new_map(
+        &self,
+        entries: Vec<(ObjectRef, ObjectRef)>,
+    ) -> Result<ObjectRef, RuntimeErr> {
+        let map = super::map::Map::new();
+        for (key, value) in entries {
+            map.insert(key, value)?;
+        }
+        Ok(Arc::new(map))
+    }
+
+    // Duplicate items are dropped.
+    pub fn new_set(&self, items: Vec<ObjectRef>) -> Result<ObjectRef, RuntimeErr> {
+        let set = super::set::Set::new();
+        for item in items {
+            set.add(item)?;
+        }
+        Ok(Arc::new(set))
+    }
+
     pub fn new_namespace(&self) -> ObjectRef {
         let ns = super::namespace::Namespace::new(self.nil_obj.clone());
         Arc::new(ns)
     }
 
+    pub fn new_namespace_from_vars(&self, vars: Vec<(String, ObjectRef)>) -> ObjectRef {
+        let mut ns = super::namespace::Namespace::new(self.nil_obj.clone());
+        for (name, obj) in vars {
+            ns.add_and_set_var(&name, obj);
+        }
+        Arc::new(ns)
+    }
+
     pub fn new_int_from_string<S: Into<String>>(&self, value: S) -> ObjectRef {
         let value = value.into();
         let value = BigInt::from_str_radix(value.as_ref(), 10).unwrap();
@@ -2,9 +2,9 @@
 //! attributes. This is opposed to fundamental/builtin types, like
 //! `Bool` and `Float` that wrap Rust primitives.
 use std::any::Any;
-use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt;
+use std::sync::RwLock;
 
 use crate::vm::RuntimeContext;
 
@@ -12,7 +12,7 @@ use super::class::TypeRef;
 use super::object::{Object, ObjectExt, ObjectRef};
 use super::result::{GetAttrResult, SetAttrResult};
 
-pub type Attrs = RefCell<HashMap<String, ObjectRef>>;
+pub type Attrs = RwLock<HashMap<String, ObjectRef>>;
 
 pub struct Custom {
     class: TypeRef,
@@ -21,7 +21,12 @@ pub struct Custom {
 
 impl Custom {
     pub fn new(class: TypeRef) -> Self {
-        Self { class, attrs: RefCell::new(HashMap::new()) }
+        Self { class, attrs: RwLock::new(HashMap::new()) }
+    }
+
+    /// Get the attribute with the specified name, if it's set.
+    pub fn attr(&self, name: &str) -> Option<ObjectRef> {
+        self.attrs.read().unwrap().get(name).cloned()
     }
 }
 
@@ -45,10 +50,7 @@ impl Object for Custom {
     }
 
     fn get_attr(&self, name: &str, _ctx: &RuntimeContext) -> GetAttrResult {
-        if let Some(value) = self.attrs.borrow().get(name) {
-            return Ok(value.clone());
-        }
-        Err(self.attr_does_not_exist(name))
+        self.attr(name).ok_or_else(|| self.attr_does_not_exist(name))
     }
 
     fn set_attr(
@@ -57,7 +59,7 @@ impl Object for Custom {
         value: ObjectRef,
         _ctx: &RuntimeContext,
     ) -> SetAttrResult {
-        self.attrs.borrow_mut().insert(name.to_owned(), value.clone());
+        self.attrs.write().unwrap().insert(name.to_owned(), value.clone());
         Ok(())
     }
 }
@@ -69,8 +71,8 @@ impl Object for Custom {
 /// keys are checked to see if they're all the same. If they are, only
 /// then are the values checked for equality.
 fn attrs_equal(lhs: &Attrs, rhs: &Attrs, ctx: &RuntimeContext) -> bool {
-    let lhs = lhs.borrow();
-    let rhs = rhs.borrow();
+    let lhs = lhs.read().unwrap();
+    let rhs = rhs.read().unwrap();
     if !(lhs.len() == rhs.len() && lhs.keys().all(|k| rhs.contains_key(k))) {
         return false;
     }
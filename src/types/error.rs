@@ -0,0 +1,75 @@
+//! Error type. Error objects are created by `raise` and by the VM when
+//! a runtime error is caught by a `catch` block.
+use std::any::Any;
+use std::fmt;
+
+use crate::vm::RuntimeContext;
+
+use super::builtin_types::BUILTIN_TYPES;
+use super::class::TypeRef;
+use super::object::{Object, ObjectExt};
+use super::result::GetAttrResult;
+
+pub struct Error {
+    kind: String,
+    message: String,
+}
+
+impl Error {
+    pub fn new<S: Into<String>>(kind: S, message: S) -> Self {
+        Self { kind: kind.into(), message: message.into() }
+    }
+
+    /// The kind of error (e.g., "TypeErr").
+    pub fn kind(&self) -> &str {
+        self.kind.as_str()
+    }
+
+    pub fn message(&self) -> &str {
+        self.message.as_str()
+    }
+}
+
+impl Object for Error {
+    fn class(&self) -> &TypeRef {
+        BUILTIN_TYPES.get("Error").unwrap()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn is_equal(&self, rhs: &dyn Object, _ctx: &RuntimeContext) -> bool {
+        if let Some(rhs) = rhs.as_any().downcast_ref::<Self>() {
+            self.is(rhs) || (self.kind == rhs.kind && self.message == rhs.message)
+        } else {
+            false
+        }
+    }
+
+    fn get_attr(&self, name: &str, ctx: &RuntimeContext) -> GetAttrResult {
+        if let Some(attr) = self.get_base_attr(name, ctx) {
+            return Ok(attr);
+        }
+        let attr = match name {
+            "kind" => ctx.builtins.new_str(self.kind()),
+            "message" => ctx.builtins.new_str(self.message()),
+            _ => return Err(self.attr_does_not_exist(name)),
+        };
+        Ok(attr)
+    }
+}
+
+// Display -------------------------------------------------------------
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind, self.message)
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
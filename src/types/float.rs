@@ -4,13 +4,15 @@ use std::fmt;
 
 use num_traits::ToPrimitive;
 
+use crate::builtin_funcs::float;
 use crate::vm::{RuntimeBoolResult, RuntimeContext, RuntimeErr, RuntimeObjResult};
 
 use super::builtin_types::BUILTIN_TYPES;
 use super::class::TypeRef;
 use super::int::Int;
-use super::object::{Object, ObjectExt};
-use super::util::{eq_int_float, gt_int_float, lt_int_float};
+use super::object::Object;
+use super::result::GetAttrResult;
+use super::util::{eq_int_float, gt_int_float, lt_int_float, mod_float};
 
 pub struct Float {
     value: f64,
@@ -26,20 +28,45 @@ impl Float {
     }
 }
 
+/// Get the value of the RHS of a binary operation as an f64.
+fn rhs_f64(rhs: &dyn Object) -> Option<f64> {
+    if let Some(rhs) = rhs.as_any().downcast_ref::<Float>() {
+        Some(*rhs.value())
+    } else {
+        rhs.as_any().downcast_ref::<Int>().map(|rhs| rhs.value().to_f64().unwrap())
+    }
+}
+
+/// Get the value of the RHS of a division operation as an f64,
+/// checking that it's not zero.
+///
+/// NOTE: Dividing by zero is an error even though IEEE semantics would
+/// produce NaN or an infinity. NaN and infinities can still be created
+/// explicitly via `float("nan")`, `float("inf")`, and `float("-inf")`.
+fn divisor_f64(rhs: &dyn Object, preposition: &str) -> Result<f64, RuntimeErr> {
+    match rhs_f64(rhs) {
+        Some(0.0) => Err(RuntimeErr::new_division_by_zero()),
+        Some(value) => Ok(value),
+        None => Err(RuntimeErr::new_type_err(format!(
+            "Could not divide {} {preposition} Float",
+            rhs.type_name()
+        ))),
+    }
+}
+
 macro_rules! make_op {
-    ( $meth:ident, $op:tt, $message:literal, $trunc:literal ) => {
+    ( $meth:ident, $op:tt, $message:literal ) => {
         fn $meth(&self, rhs: &dyn Object, ctx: &RuntimeContext) -> RuntimeObjResult {
-            let value = if let Some(rhs) = rhs.as_any().downcast_ref::<Float>() {
-                *rhs.value()
-            } else if let Some(rhs) = rhs.as_any().downcast_ref::<Int>() {
-                rhs.value().to_f64().unwrap()
-            } else {
-                return Err(RuntimeErr::new_type_err(format!($message, rhs.type_name())));
+            let value = match rhs_f64(rhs) {
+                Some(value) => value,
+                None => {
+                    return Err(RuntimeErr::new_type_err(format!(
+                        $message,
+                        rhs.type_name()
+                    )))
+                }
             };
-            let mut value = &self.value $op value;
-            if $trunc {
-                value = value.trunc();
-            }
+            let value = &self.value $op value;
             let value = ctx.builtins.new_float(value);
             Ok(value)
         }
@@ -60,12 +87,14 @@ impl Object for Float {
     }
 
     fn as_bool(&self, _ctx: &RuntimeContext) -> RuntimeBoolResult {
-        Ok(if *self.value() == 0.0 { false } else { true })
+        Ok(*self.value() != 0.0)
     }
 
+    /// Floats follow IEEE semantics, so NaN isn't equal to anything,
+    /// including itself.
     fn is_equal(&self, rhs: &dyn Object, _ctx: &RuntimeContext) -> bool {
         if let Some(rhs) = rhs.as_any().downcast_ref::<Self>() {
-            self.is(rhs) || self.value() == rhs.value()
+            self.value() == rhs.value()
         } else if let Some(rhs) = rhs.as_any().downcast_ref::<Int>() {
             eq_int_float(rhs, self)
         } else {
@@ -77,7 +106,7 @@ impl Object for Float {
         if let Some(rhs) = rhs.as_any().downcast_ref::<Self>() {
             Ok(self.value() < rhs.value())
         } else if let Some(rhs) = rhs.as_any().downcast_ref::<Int>() {
-            Ok(lt_int_float(rhs, self))
+            Ok(gt_int_float(rhs, self))
         } else {
             Err(RuntimeErr::new_type_err(format!(
                 "Could not compare {} to {}: <",
@@ -95,7 +124,7 @@ impl Object for Float {
         if let Some(rhs) = rhs.as_any().downcast_ref::<Self>() {
             Ok(self.value() > rhs.value())
         } else if let Some(rhs) = rhs.as_any().downcast_ref::<Int>() {
-            Ok(gt_int_float(rhs, self))
+            Ok(lt_int_float(rhs, self))
         } else {
             Err(RuntimeErr::new_type_err(format!(
                 "Could not compare {} to {}: >",
@@ -122,12 +151,42 @@ impl Object for Float {
         Ok(value)
     }
 
-    make_op!(modulo, %, "Could not divide {} with Float", false);
-    make_op!(mul, *, "Could not multiply {} with Float", false);
-    make_op!(div, /, "Could not divide {} into Float", false);
-    make_op!(floor_div, /, "Could not divide {} into Float", true); // truncates
-    make_op!(add, +, "Could not add {} to Float", false);
-    make_op!(sub, -, "Could not subtract {} from Float", false);
+    make_op!(mul, *, "Could not multiply {} with Float");
+    make_op!(add, +, "Could not add {} to Float");
+    make_op!(sub, -, "Could not subtract {} from Float");
+
+    fn div(&self, rhs: &dyn Object, ctx: &RuntimeContext) -> RuntimeObjResult {
+        let value = divisor_f64(rhs, "into")?;
+        Ok(ctx.builtins.new_float(self.value / value))
+    }
+
+    // Float floor division returns a Float with no fractional part
+    fn floor_div(&self, rhs: &dyn Object, ctx: &RuntimeContext) -> RuntimeObjResult {
+        let value = divisor_f64(rhs, "into")?;
+        Ok(ctx.builtins.new_float((self.value / value).floor()))
+    }
+
+    fn modulo(&self, rhs: &dyn Object, ctx: &RuntimeContext) -> RuntimeObjResult {
+        let value = divisor_f64(rhs, "with")?;
+        Ok(ctx.builtins.new_float(mod_float(self.value, value)))
+    }
+
+    fn get_attr(&self, name: &str, ctx: &RuntimeContext) -> GetAttrResult {
+        if let Some(attr) = self.get_base_attr(name, ctx) {
+            return Ok(attr);
+        }
+        let func = match name {
+            "abs" => float::abs,
+            "floor" => float::floor,
+            "ceil" => float::ceil,
+            "round" => float::round,
+            "trunc" => float::trunc,
+            _ => {
+                return Err(self.attr_does_not_exist(name));
+            }
+        };
+        Ok(ctx.builtins.new_builtin_func(name, Some(vec!["this"]), func))
+    }
 }
 
 // Display -------------------------------------------------------------
@@ -2,24 +2,43 @@
 use std::any::Any;
 use std::fmt;
 
-use crate::types::{Args, CallResult, Params};
-use crate::vm::{Chunk, VM};
+use crate::builtin_funcs::func;
+use crate::types::Params;
+use crate::util::Location;
+use crate::vm::{Chunk, RuntimeContext};
 
 use super::builtin_types::BUILTIN_TYPES;
 use super::class::TypeRef;
 use super::object::Object;
+use super::result::GetAttrResult;
 
 pub struct Func {
     pub name: String,
     pub params: Params,
     pub arity: Option<usize>,
+    /// Compiled default value expressions for the trailing params.
+    /// These are evaluated in the call scope when the corresponding
+    /// args aren't passed.
+    pub defaults: Vec<Chunk>,
+    /// Name of the param that collects any extra args into a tuple
+    pub rest: Option<String>,
     pub chunk: Chunk,
+    /// Source location of each instruction in the chunk. This may be
+    /// empty if location info isn't available.
+    pub locations: Vec<Location>,
 }
 
 impl Func {
-    pub fn new<S: Into<String>>(name: S, params: Params, chunk: Chunk) -> Self {
+    pub fn new<S: Into<String>>(
+        name: S,
+        params: Params,
+        defaults: Vec<Chunk>,
+        rest: Option<String>,
+        chunk: Chunk,
+        locations: Vec<Location>,
+    ) -> Self {
         let arity = if let Some(params) = &params { Some(params.len()) } else { None };
-        Self { name: name.into(), params, arity, chunk }
+        Self { name: name.into(), params, arity, defaults, rest, chunk, locations }
     }
 }
 
@@ -32,20 +51,19 @@ impl Object for Func {
         self
     }
 
-    /// This provides a way to call user functions from builtin
-    /// functions. Perhaps there's a better way to do this?
-    fn call(&self, args: Args, vm: &mut VM) -> CallResult {
-        vm.scope_stack.push(vm.value_stack.size());
-        vm.ctx.enter_scope();
-        vm.check_call_args(self.name.as_str(), &self.params, &args, true)?;
-        let result = if let Err(err) = vm.execute(&self.chunk, false) {
-            Err(err)
-        } else {
-            let result = vm.pop_obj()?;
-            Ok(Some(result))
+    fn get_attr(&self, name: &str, ctx: &RuntimeContext) -> GetAttrResult {
+        if let Some(attr) = self.get_base_attr(name, ctx) {
+            return Ok(attr);
+        }
+        let attr = match name {
+            "dis" => {
+                ctx.builtins.new_builtin_func("dis", Some(vec!["this"]), func::dis)
+            }
+            _ => {
+                return Err(self.attr_does_not_exist(name));
+            }
         };
-        vm.ctx.exit_scopes(1);
-        result
+        Ok(attr)
     }
 }
 
@@ -54,9 +72,10 @@ impl Object for Func {
 impl fmt::Display for Func {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let name = &self.name;
-        let num_args = match self.arity {
-            Some(n) => n.to_string(),
-            None => "...".to_string(),
+        let num_args = match (self.arity, &self.rest) {
+            (Some(n), None) => n.to_string(),
+            (Some(n), Some(_)) => format!("{n}..."),
+            (None, _) => "...".to_string(),
         };
         let id = self.id();
         write!(f, "function {name}/{num_args} @ {id}")
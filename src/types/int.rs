@@ -3,15 +3,19 @@ use std::any::Any;
 use std::fmt;
 
 use num_bigint::BigInt;
-use num_traits::{FromPrimitive, ToPrimitive, Zero};
+use num_traits::{FromPrimitive, Signed, ToPrimitive, Zero};
 
+use crate::builtin_funcs::int;
 use crate::vm::{RuntimeBoolResult, RuntimeContext, RuntimeErr, RuntimeObjResult};
 
 use super::builtin_types::BUILTIN_TYPES;
 use super::class::TypeRef;
 use super::float::Float;
 use super::object::{Object, ObjectExt};
-use super::util::{eq_int_float, gt_int_float, lt_int_float};
+use super::result::GetAttrResult;
+use super::util::{
+    eq_int_float, floor_div_int, gt_int_float, lt_int_float, mod_float, mod_int,
+};
 
 pub struct Int {
     value: BigInt,
@@ -39,6 +43,9 @@ impl Int {
                 rhs.type_name()
             )));
         };
+        if rhs_val == 0.0 {
+            return Err(RuntimeErr::new_division_by_zero());
+        }
         Ok(lhs_val / rhs_val)
     }
 }
@@ -63,6 +70,20 @@ macro_rules! make_op {
     };
 }
 
+macro_rules! make_bit_op {
+    ( $meth:ident, $op:tt, $message:literal ) => {
+        fn $meth(&self, rhs: &dyn Object, ctx: &RuntimeContext) -> RuntimeObjResult {
+            if let Some(rhs) = rhs.as_any().downcast_ref::<Self>() {
+                let value = self.value() $op rhs.value();
+                let value = ctx.builtins.new_int(value);
+                Ok(value)
+            } else {
+                Err(RuntimeErr::new_type_err(format!($message, rhs.type_name())))
+            }
+        }
+    };
+}
+
 impl Object for Int {
     fn class(&self) -> &TypeRef {
         BUILTIN_TYPES.get("Int").unwrap()
@@ -77,7 +98,7 @@ impl Object for Int {
     }
 
     fn as_bool(&self, _ctx: &RuntimeContext) -> RuntimeBoolResult {
-        Ok(self.value().is_zero())
+        Ok(!self.value().is_zero())
     }
 
     fn is_equal(&self, rhs: &dyn Object, _ctx: &RuntimeContext) -> bool {
@@ -124,9 +145,27 @@ impl Object for Int {
 
     fn pow(&self, rhs: &dyn Object, ctx: &RuntimeContext) -> RuntimeObjResult {
         if let Some(rhs) = rhs.as_any().downcast_ref::<Self>() {
-            // XXX: Return Int
             let base = self.value();
-            let exp = rhs.value().to_u32().unwrap();
+            let exp = rhs.value();
+            if exp.is_negative() {
+                // A negative exponent produces a Float, e.g. 2 ** -1 is
+                // 0.5. Like division, raising zero to a negative power
+                // is an error rather than infinity.
+                if base.is_zero() {
+                    return Err(RuntimeErr::new_division_by_zero());
+                }
+                let base = base.to_f64().unwrap();
+                let exp = exp.to_f64().unwrap();
+                return Ok(ctx.builtins.new_float(base.powf(exp)));
+            }
+            let exp = match exp.to_u32() {
+                Some(exp) => exp,
+                None => {
+                    return Err(RuntimeErr::new_value_err(format!(
+                        "Exponent is too large: {exp}"
+                    )))
+                }
+            };
             let value = base.pow(exp);
             let value = ctx.builtins.new_int(value);
             Ok(value)
@@ -146,11 +185,15 @@ impl Object for Int {
         }
     }
 
-    make_op!(modulo, %, "Could not divide {} with Int");
     make_op!(mul, *, "Could not multiply {} with Int");
     make_op!(add, +, "Could not add {} to Int");
     make_op!(sub, -, "Could not subtract {} from Int");
 
+    // Bitwise operations are only defined for Ints
+    make_bit_op!(bit_and, &, "Could not bitwise AND {} with Int");
+    make_bit_op!(bit_or, |, "Could not bitwise OR {} with Int");
+    make_bit_op!(bit_xor, ^, "Could not bitwise XOR {} with Int");
+
     // Int division *always* returns a Float
     fn div(&self, rhs: &dyn Object, ctx: &RuntimeContext) -> RuntimeObjResult {
         let value = self.div_f64(rhs)?;
@@ -160,10 +203,70 @@ impl Object for Int {
 
     // Int *floor* division *always* returns an Int
     fn floor_div(&self, rhs: &dyn Object, ctx: &RuntimeContext) -> RuntimeObjResult {
-        let value = self.div_f64(rhs)?;
-        let value = BigInt::from_f64(value).unwrap();
-        let value = ctx.builtins.new_int(value);
-        Ok(value)
+        let value = if let Some(rhs) = rhs.as_any().downcast_ref::<Self>() {
+            if rhs.value().is_zero() {
+                return Err(RuntimeErr::new_division_by_zero());
+            }
+            floor_div_int(self.value(), rhs.value())
+        } else {
+            let value = self.div_f64(rhs)?.floor();
+            match BigInt::from_f64(value) {
+                Some(value) => value,
+                None => {
+                    return Err(RuntimeErr::new_value_err(format!(
+                        "Result of floor division is not a finite number: {value}"
+                    )))
+                }
+            }
+        };
+        Ok(ctx.builtins.new_int(value))
+    }
+
+    fn modulo(&self, rhs: &dyn Object, ctx: &RuntimeContext) -> RuntimeObjResult {
+        if let Some(rhs) = rhs.as_any().downcast_ref::<Self>() {
+            if rhs.value().is_zero() {
+                return Err(RuntimeErr::new_division_by_zero());
+            }
+            Ok(ctx.builtins.new_int(mod_int(self.value(), rhs.value())))
+        } else if let Some(rhs) = rhs.as_any().downcast_ref::<Float>() {
+            if *rhs.value() == 0.0 {
+                return Err(RuntimeErr::new_division_by_zero());
+            }
+            let value = mod_float(self.value().to_f64().unwrap(), *rhs.value());
+            Ok(ctx.builtins.new_float(value))
+        } else {
+            Err(RuntimeErr::new_type_err(format!(
+                "Could not divide {} with Int",
+                rhs.type_name()
+            )))
+        }
+    }
+
+    fn get_attr(&self, name: &str, ctx: &RuntimeContext) -> GetAttrResult {
+        if let Some(attr) = self.get_base_attr(name, ctx) {
+            return Ok(attr);
+        }
+        let func = match name {
+            "to_u8" => int::to_u8,
+            "to_i8" => int::to_i8,
+            "to_u16" => int::to_u16,
+            "to_i16" => int::to_i16,
+            "to_u32" => int::to_u32,
+            "to_i32" => int::to_i32,
+            "to_u64" => int::to_u64,
+            "to_i64" => int::to_i64,
+            "abs" => int::abs,
+            "is_even" => int::is_even,
+            "is_odd" => int::is_odd,
+            "floor" => int::floor,
+            "ceil" => int::ceil,
+            "round" => int::round,
+            "trunc" => int::trunc,
+            _ => {
+                return Err(self.attr_does_not_exist(name));
+            }
+        };
+        Ok(ctx.builtins.new_builtin_func(name, Some(vec!["this"]), func))
     }
 }
 
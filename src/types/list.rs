@@ -0,0 +1,175 @@
+//! List type. Unlike tuples, lists can be modified in place. Since
+//! objects are shared by reference, modifications are visible through
+//! all references to a list.
+use std::any::Any;
+use std::fmt;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::builtin_funcs::{list, seq};
+use crate::vm::{RuntimeBoolResult, RuntimeContext};
+
+use super::builtin_types::BUILTIN_TYPES;
+use super::class::TypeRef;
+use super::object::{Object, ObjectExt, ObjectRef};
+use super::result::{GetAttrResult, SetAttrResult};
+use super::util::seq_index;
+
+pub struct List {
+    items: RwLock<Vec<ObjectRef>>,
+}
+
+impl List {
+    pub fn new(items: Vec<ObjectRef>) -> Self {
+        Self { items: RwLock::new(items) }
+    }
+
+    pub fn items(&self) -> RwLockReadGuard<'_, Vec<ObjectRef>> {
+        self.items.read().unwrap()
+    }
+
+    pub fn items_mut(&self) -> RwLockWriteGuard<'_, Vec<ObjectRef>> {
+        self.items.write().unwrap()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.read().unwrap().len()
+    }
+}
+
+impl Object for List {
+    fn class(&self) -> &TypeRef {
+        BUILTIN_TYPES.get("List").unwrap()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn is_equal(&self, rhs: &dyn Object, _ctx: &RuntimeContext) -> bool {
+        if let Some(rhs) = rhs.as_any().downcast_ref::<Self>() {
+            if self.is(rhs) {
+                return true;
+            }
+            if self.len() != rhs.len() {
+                return false;
+            }
+            for (a, b) in self.items().iter().zip(rhs.items().iter()) {
+                if !a.is_equal(&**b, _ctx) {
+                    return false;
+                }
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    fn contains(&self, item: &ObjectRef, ctx: &RuntimeContext) -> RuntimeBoolResult {
+        Ok(self.items().iter().any(|a| a.is_equal(&**item, ctx)))
+    }
+
+    fn len(&self) -> Option<usize> {
+        Some(self.items.read().unwrap().len())
+    }
+
+    fn get_attr(&self, name: &str, ctx: &RuntimeContext) -> GetAttrResult {
+        if let Some(attr) = self.get_base_attr(name, ctx) {
+            return Ok(attr);
+        }
+        let attr = match name {
+            "length" => ctx.builtins.new_int(self.len()),
+            "fill" => ctx.builtins.new_builtin_func(
+                "fill",
+                Some(vec!["this", "value"]),
+                list::fill,
+            ),
+            "resize" => ctx.builtins.new_builtin_func(
+                "resize",
+                Some(vec!["this", "n", "value"]),
+                list::resize,
+            ),
+            "reverse" => ctx.builtins.new_builtin_func(
+                "reverse",
+                Some(vec!["this"]),
+                list::reverse,
+            ),
+            "binary_search" => ctx.builtins.new_builtin_func(
+                "binary_search",
+                Some(vec!["this", "item"]),
+                seq::binary_search,
+            ),
+            "rotate" => ctx.builtins.new_builtin_func(
+                "rotate",
+                Some(vec!["this", "n"]),
+                seq::rotate,
+            ),
+            "split_at" => ctx.builtins.new_builtin_func(
+                "split_at",
+                Some(vec!["this", "i"]),
+                seq::split_at,
+            ),
+            "each_with_index" => ctx.builtins.new_builtin_func(
+                "each_with_index",
+                Some(vec!["this", "f"]),
+                seq::each_with_index,
+            ),
+            "positions" => ctx.builtins.new_builtin_func(
+                "positions",
+                Some(vec!["this", "pred"]),
+                seq::positions,
+            ),
+            "sum" => ctx.builtins.new_builtin_func("sum", Some(vec!["this"]), seq::sum),
+            "product" => ctx.builtins.new_builtin_func(
+                "product",
+                Some(vec!["this"]),
+                seq::product,
+            ),
+            "mean" => {
+                ctx.builtins.new_builtin_func("mean", Some(vec!["this"]), seq::mean)
+            }
+            "median" => {
+                ctx.builtins.new_builtin_func("median", Some(vec!["this"]), seq::median)
+            }
+            "to_set" => {
+                ctx.builtins.new_builtin_func("to_set", Some(vec!["this"]), seq::to_set)
+            }
+            _ => {
+                return Err(self.attr_does_not_exist(name));
+            }
+        };
+        Ok(attr)
+    }
+
+    fn get_item(&self, index: &ObjectRef, _ctx: &RuntimeContext) -> GetAttrResult {
+        let items = self.items();
+        let index = seq_index(self, index, items.len())?;
+        Ok(items[index].clone())
+    }
+
+    fn set_item(
+        &self,
+        index: &ObjectRef,
+        value: ObjectRef,
+        _ctx: &RuntimeContext,
+    ) -> SetAttrResult {
+        let mut items = self.items_mut();
+        let index = seq_index(self, index, items.len())?;
+        items[index] = value;
+        Ok(())
+    }
+}
+
+// Display -------------------------------------------------------------
+
+impl fmt::Display for List {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let items: Vec<String> = self.items().iter().map(|item| item.repr()).collect();
+        write!(f, "[{}]", items.join(", "))
+    }
+}
+
+impl fmt::Debug for List {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
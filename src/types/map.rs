@@ -0,0 +1,228 @@
+//! Map type. Maps associate keys with values. Only Int and Str objects
+//! can be used as keys. Entries are kept in insertion order.
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{RwLock, RwLockReadGuard};
+
+use num_bigint::BigInt;
+
+use crate::builtin_funcs::map;
+use crate::vm::{RuntimeBoolResult, RuntimeContext, RuntimeErr};
+
+use super::builtin_types::BUILTIN_TYPES;
+use super::class::TypeRef;
+use super::object::{Object, ObjectExt, ObjectRef};
+use super::result::{GetAttrResult, SetAttrResult};
+
+/// Since `ObjectRef` isn't hashable, map keys are converted to this
+/// for lookup.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    Int(BigInt),
+    Str(String),
+}
+
+impl MapKey {
+    pub fn from_obj(obj: &ObjectRef) -> Result<Self, RuntimeErr> {
+        if let Some(value) = obj.int_val() {
+            Ok(Self::Int(value))
+        } else if let Some(value) = obj.str_val() {
+            Ok(Self::Str(value))
+        } else {
+            let type_name = obj.type_name();
+            Err(RuntimeErr::new_type_err(format!(
+                "Unhashable map key type: {type_name}"
+            )))
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Entries {
+    /// Key => position in `items`
+    positions: HashMap<MapKey, usize>,
+    /// (key, value) pairs in insertion order
+    items: Vec<(ObjectRef, ObjectRef)>,
+}
+
+impl Entries {
+    pub fn iter(&self) -> impl Iterator<Item = &(ObjectRef, ObjectRef)> {
+        self.items.iter()
+    }
+}
+
+#[derive(Default)]
+pub struct Map {
+    entries: RwLock<Entries>,
+}
+
+impl Map {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn entries(&self) -> RwLockReadGuard<'_, Entries> {
+        self.entries.read().unwrap()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap().items.len()
+    }
+
+    /// Get the value for the key, if present.
+    pub fn get(&self, key: &ObjectRef) -> Result<Option<ObjectRef>, RuntimeErr> {
+        let map_key = MapKey::from_obj(key)?;
+        let entries = self.entries.read().unwrap();
+        Ok(entries.positions.get(&map_key).map(|&i| entries.items[i].1.clone()))
+    }
+
+    /// Set the value for the key, overwriting the existing value if the
+    /// key is already present.
+    pub fn insert(&self, key: ObjectRef, value: ObjectRef) -> Result<(), RuntimeErr> {
+        let map_key = MapKey::from_obj(&key)?;
+        let mut entries = self.entries.write().unwrap();
+        if let Some(&i) = entries.positions.get(&map_key) {
+            entries.items[i].1 = value;
+        } else {
+            let i = entries.items.len();
+            entries.positions.insert(map_key, i);
+            entries.items.push((key, value));
+        }
+        Ok(())
+    }
+
+    pub fn keys(&self) -> Vec<ObjectRef> {
+        self.entries().iter().map(|(k, _)| k.clone()).collect()
+    }
+
+    pub fn values(&self) -> Vec<ObjectRef> {
+        self.entries().iter().map(|(_, v)| v.clone()).collect()
+    }
+}
+
+/// Names of the attributes maps have in addition to the base
+/// attributes. Str keys with these names are shadowed by the
+/// attributes when accessed via the dot operator.
+const ATTR_NAMES: [&str; 5] =
+    ["length", "keys", "values", "keys_where", "to_namespace"];
+
+impl Object for Map {
+    fn class(&self) -> &TypeRef {
+        BUILTIN_TYPES.get("Map").unwrap()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    /// Maps are equal when they have the same keys and the values for
+    /// each key are equal. Order doesn't matter.
+    fn is_equal(&self, rhs: &dyn Object, ctx: &RuntimeContext) -> bool {
+        if let Some(rhs) = rhs.as_any().downcast_ref::<Self>() {
+            if self.is(rhs) {
+                return true;
+            }
+            if self.len() != rhs.len() {
+                return false;
+            }
+            for (key, a) in self.entries().iter() {
+                match rhs.get(key) {
+                    Ok(Some(b)) if a.is_equal(&*b, ctx) => (),
+                    _ => return false,
+                }
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Check whether the item is a key in this map.
+    fn contains(&self, item: &ObjectRef, _ctx: &RuntimeContext) -> RuntimeBoolResult {
+        Ok(self.get(item)?.is_some())
+    }
+
+    fn len(&self) -> Option<usize> {
+        Some(self.len())
+    }
+
+    fn get_attr(&self, name: &str, ctx: &RuntimeContext) -> GetAttrResult {
+        if let Some(attr) = self.get_base_attr(name, ctx) {
+            return Ok(attr);
+        }
+        let attr = match name {
+            "length" => ctx.builtins.new_int(self.len()),
+            "keys" => {
+                ctx.builtins.new_builtin_func("keys", Some(vec!["this"]), map::keys)
+            }
+            "values" => {
+                ctx.builtins.new_builtin_func("values", Some(vec!["this"]), map::values)
+            }
+            "keys_where" => ctx.builtins.new_builtin_func(
+                "keys_where",
+                Some(vec!["this", "pred"]),
+                map::keys_where,
+            ),
+            "to_namespace" => ctx.builtins.new_builtin_func(
+                "to_namespace",
+                Some(vec!["this"]),
+                map::to_namespace,
+            ),
+            // Str keys can be accessed like attributes (`m.key`) when
+            // they don't have the same name as an attribute.
+            _ => return self.get_item(&ctx.builtins.new_str(name), ctx),
+        };
+        Ok(attr)
+    }
+
+    fn set_attr(
+        &self,
+        name: &str,
+        value: ObjectRef,
+        ctx: &RuntimeContext,
+    ) -> SetAttrResult {
+        if ATTR_NAMES.contains(&name) || self.get_base_attr(name, ctx).is_some() {
+            return Err(RuntimeErr::new_attr_cannot_be_set(
+                self.qualified_type_name().as_str(),
+                name,
+            ));
+        }
+        self.set_item(&ctx.builtins.new_str(name), value, ctx)
+    }
+
+    fn get_item(&self, index: &ObjectRef, _ctx: &RuntimeContext) -> GetAttrResult {
+        match self.get(index)? {
+            Some(value) => Ok(value),
+            None => Err(self.item_does_not_exist(index)),
+        }
+    }
+
+    fn set_item(
+        &self,
+        index: &ObjectRef,
+        value: ObjectRef,
+        _ctx: &RuntimeContext,
+    ) -> SetAttrResult {
+        self.insert(index.clone(), value)
+    }
+}
+
+// Display -------------------------------------------------------------
+
+impl fmt::Display for Map {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let items: Vec<String> = self
+            .entries()
+            .iter()
+            .map(|(k, v)| format!("{}: {}", k.repr(), v.repr()))
+            .collect();
+        write!(f, "{{{}}}", items.join(", "))
+    }
+}
+
+impl fmt::Debug for Map {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
@@ -1,8 +1,9 @@
 pub(crate) use builtin_func::BuiltinFn;
 pub(crate) use builtin_types::BUILTIN_TYPES;
 pub(crate) use builtins::Builtins;
+pub(crate) use func::Func;
 pub(crate) use namespace::Namespace;
-pub(crate) use object::{ObjectExt, ObjectRef};
+pub use object::{Object, ObjectExt, ObjectRef};
 pub(crate) use result::{Args, CallResult, Params};
 
 mod bool;
@@ -11,13 +12,17 @@ mod builtin_types;
 mod builtins;
 mod class;
 mod custom;
+mod error;
 mod float;
 mod func;
 mod int;
+mod list;
+mod map;
 mod namespace;
 mod nil;
 mod object;
 mod result;
+mod set;
 mod str;
 mod tuple;
 mod util;
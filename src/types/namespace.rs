@@ -11,33 +11,46 @@ use super::result::GetAttrResult;
 
 // Namespace -----------------------------------------------------------
 
+/// Vars are stored in slots, which are assigned in the order vars are
+/// added. Vars can be accessed by name or, when the compiler was able
+/// to determine a var's slot, directly by slot.
 pub struct Namespace {
-    objects: HashMap<String, ObjectRef>,
+    /// var name => slot index
+    names: HashMap<String, usize>,
+    slots: Vec<ObjectRef>,
     nil_obj: ObjectRef,
 }
 
 impl Namespace {
     pub fn new(nil: ObjectRef) -> Self {
-        Namespace { objects: HashMap::new(), nil_obj: nil }
+        Namespace { names: HashMap::new(), slots: vec![], nil_obj: nil }
     }
 
     pub fn clear(&mut self) {
-        self.objects.clear();
+        self.names.clear();
+        self.slots.clear();
     }
 
     pub fn size(&self) -> usize {
-        self.objects.len()
+        self.slots.len()
     }
 
-    /// Add a var, settings its initial value to nil.
+    /// Add a var, settings its initial value to nil. If the var was
+    /// already added, it keeps its slot.
     pub fn add_var<S: Into<String>>(&mut self, name: S) {
-        self.objects.insert(name.into(), self.nil_obj.clone());
+        let name = name.into();
+        if let Some(&index) = self.names.get(&name) {
+            self.slots[index] = self.nil_obj.clone();
+        } else {
+            self.names.insert(name, self.slots.len());
+            self.slots.push(self.nil_obj.clone());
+        }
     }
 
     /// Set a var's value.
     pub fn set_var(&mut self, name: &str, obj: ObjectRef) -> bool {
-        if self.objects.contains_key(name) {
-            self.objects.insert(name.to_owned(), obj);
+        if let Some(&index) = self.names.get(name) {
+            self.slots[index] = obj;
             true
         } else {
             false
@@ -52,7 +65,27 @@ impl Namespace {
 
     /// Get a var.
     pub fn get_var(&self, name: &str) -> Option<&ObjectRef> {
-        self.objects.get(name)
+        self.names.get(name).map(|&index| &self.slots[index])
+    }
+
+    /// Set the value of the var in the specified slot.
+    pub fn set_slot(&mut self, index: usize, obj: ObjectRef) -> bool {
+        if let Some(slot) = self.slots.get_mut(index) {
+            *slot = obj;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Get the var in the specified slot.
+    pub fn get_slot(&self, index: usize) -> Option<&ObjectRef> {
+        self.slots.get(index)
+    }
+
+    /// Iterate over vars in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &ObjectRef)> {
+        self.names.iter().map(|(name, &index)| (name, &self.slots[index]))
     }
 }
 
@@ -3,6 +3,7 @@ use std::fmt;
 use std::sync::Arc;
 
 use num_bigint::BigInt;
+use num_traits::ToPrimitive;
 
 use crate::vm::{RuntimeBoolResult, RuntimeContext, RuntimeErr, RuntimeObjResult, VM};
 
@@ -12,11 +13,14 @@ use super::bool::Bool;
 use super::builtin_func::BuiltinFunc;
 use super::class::{Type, TypeRef};
 use super::custom::Custom;
+use super::error::Error;
 use super::float::Float;
 use super::func::Func;
 use super::int::Int;
-use super::namespace::Namespace;
+use super::list::List;
+use super::map::Map;
 use super::nil::Nil;
+use super::set::Set;
 use super::str::Str;
 use super::tuple::Tuple;
 
@@ -84,7 +88,7 @@ macro_rules! make_bin_op {
 }
 
 /// Represents an instance of some type (AKA "class").
-pub trait Object {
+pub trait Object: fmt::Display + Send + Sync {
     fn class(&self) -> &TypeRef;
     fn as_any(&self) -> &dyn Any;
 
@@ -102,6 +106,13 @@ pub trait Object {
         self.class().qualified_name()
     }
 
+    /// Get the representation of the object that's shown in the REPL
+    /// and inside collections. By default, this is the same as the
+    /// object's `Display` output, which is what `print` shows.
+    fn repr(&self) -> String {
+        self.to_string()
+    }
+
     // Type checkers ---------------------------------------------------
 
     make_type_checker!(is_nil, Nil);
@@ -110,6 +121,9 @@ pub trait Object {
     make_type_checker!(is_float, Float);
     make_type_checker!(is_str, Str);
     make_type_checker!(is_tuple, Tuple);
+    make_type_checker!(is_list, List);
+    make_type_checker!(is_map, Map);
+    make_type_checker!(is_set, Set);
     make_type_checker!(is_func, Func);
     make_type_checker!(is_builtin_func, BuiltinFunc);
 
@@ -121,14 +135,40 @@ pub trait Object {
     make_type_converter!(as_func, Func);
     make_type_converter!(as_builtin_func, BuiltinFunc);
     make_type_converter!(as_tuple, Tuple);
+    make_type_converter!(as_list, List);
+    make_type_converter!(as_map, Map);
+    make_type_converter!(as_set, Set);
+    make_type_converter!(as_error, Error);
+    make_type_converter!(as_custom, Custom);
 
     // Value extractors ------------------------------------------------
     //
     // These extract the inner value from an object.
 
+    make_value_extractor!(bool_val, Bool, bool, clone);
+    make_value_extractor!(float_val, Float, f64, clone);
     make_value_extractor!(int_val, Int, BigInt, clone);
     make_value_extractor!(str_val, Str, String, to_owned);
 
+    // Rust value converters -------------------------------------------
+    //
+    // These convert objects to native Rust values for host code.
+
+    /// Get the value of an Int as an i64, if it fits.
+    fn as_i64(&self) -> Option<i64> {
+        self.int_val().and_then(|value| value.to_i64())
+    }
+
+    /// Get the value of a Float or Int as an f64.
+    fn as_f64(&self) -> Option<f64> {
+        self.float_val().or_else(|| self.int_val().and_then(|value| value.to_f64()))
+    }
+
+    /// Get the value of a Str as a String.
+    fn as_string(&self) -> Option<String> {
+        self.str_val()
+    }
+
     // Unary operations ------------------------------------------------
 
     make_unary_op!(negate, "-", RuntimeObjResult);
@@ -157,7 +197,15 @@ pub trait Object {
     make_bin_op!(less_than, "<", RuntimeBoolResult);
     make_bin_op!(greater_than, ">", RuntimeBoolResult);
 
-    make_bin_op!(pow, "^", RuntimeObjResult);
+    /// Check whether the object contains the item (`item in obj`).
+    fn contains(&self, _item: &ObjectRef, _ctx: &RuntimeContext) -> RuntimeBoolResult {
+        Err(RuntimeErr::new_type_err(format!(
+            "Binary operator in (contains) not implemented for {}",
+            self.class()
+        )))
+    }
+
+    make_bin_op!(pow, "**", RuntimeObjResult);
     make_bin_op!(modulo, "%", RuntimeObjResult);
     make_bin_op!(mul, "*", RuntimeObjResult);
     make_bin_op!(div, "/", RuntimeObjResult);
@@ -166,6 +214,9 @@ pub trait Object {
     make_bin_op!(sub, "-", RuntimeObjResult);
     make_bin_op!(and, "&&", RuntimeBoolResult);
     make_bin_op!(or, "||", RuntimeBoolResult);
+    make_bin_op!(bit_and, "&", RuntimeObjResult);
+    make_bin_op!(bit_or, "|", RuntimeObjResult);
+    make_bin_op!(bit_xor, "^", RuntimeObjResult);
 
     // Call ------------------------------------------------------------
 
@@ -174,6 +225,21 @@ pub trait Object {
         Err(RuntimeErr::new_type_err(format!("Call not implemented for type {class}")))
     }
 
+    // Length ----------------------------------------------------------
+
+    /// Get the length of the object. For containers, this is the
+    /// number of items; for strings, it's the number of chars. Types
+    /// that don't have a meaningful length return `None`.
+    fn len(&self) -> Option<usize> {
+        None
+    }
+
+    /// Check whether the object is empty. Types that don't have a
+    /// meaningful length return `None`.
+    fn is_empty(&self) -> Option<bool> {
+        self.len().map(|len| len == 0)
+    }
+
     // Attributes (accessed by name) -----------------------------------
 
     /// Attributes that all objects have. This should NOT be overridden.
@@ -211,28 +277,28 @@ pub trait Object {
         RuntimeErr::new_attr_does_not_exist(self.qualified_type_name().as_str(), name)
     }
 
-    // Items (accessed by index) ---------------------------------------
+    // Items (accessed by index or key) --------------------------------
 
-    fn get_item(&self, index: &BigInt, _ctx: &RuntimeContext) -> GetAttrResult {
+    fn get_item(&self, index: &ObjectRef, _ctx: &RuntimeContext) -> GetAttrResult {
         Err(self.item_does_not_exist(index))
     }
 
     fn set_item(
         &self,
-        index: &BigInt,
+        index: &ObjectRef,
         _value: ObjectRef,
         _ctx: &RuntimeContext,
     ) -> SetAttrResult {
         Err(RuntimeErr::new_item_cannot_be_set(
             self.qualified_type_name(),
-            index.clone(),
+            format!("{index:?}"),
         ))
     }
 
-    fn item_does_not_exist(&self, index: &BigInt) -> RuntimeErr {
+    fn item_does_not_exist(&self, index: &ObjectRef) -> RuntimeErr {
         RuntimeErr::new_item_does_not_exist(
-            self.qualified_type_name().as_str(),
-            index.clone(),
+            self.qualified_type_name(),
+            format!("{index:?}"),
         )
     }
 }
@@ -241,6 +307,10 @@ pub trait Object {
 
 /// Methods that aren't "object safe"
 pub trait ObjectExt: Object {
+    /// Check whether two objects are the same object (i.e., they're at
+    /// the same address). Note that small Ints, `nil`, `true`, and
+    /// `false` are interned, so all instances with the same value are
+    /// identical.
     fn is(&self, other: &Self) -> bool {
         self.id() == other.id()
     }
@@ -249,66 +319,12 @@ pub trait ObjectExt: Object {
 impl<T: Object + ?Sized> ObjectExt for T {}
 
 // Display -------------------------------------------------------------
-
-/// Downcast Object to concrete type/object and display that.
-macro_rules! write_instance {
-    ( $f:ident, $a:ident, $($A:ty),+ ) => { $(
-        if let Some(a) = $a.as_any().downcast_ref::<$A>() {
-            return write!($f, "{}", a);
-        }
-    )+ };
-}
-
-macro_rules! debug_instance {
-    ( $f:ident, $a:ident, $($A:ty),+ ) => { $(
-        if let Some(a) = $a.as_any().downcast_ref::<$A>() {
-            return write!($f, "{:?}", a);
-        }
-    )+ };
-}
-
-impl fmt::Display for dyn Object {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write_instance!(
-            f,
-            self,
-            Bool,
-            BuiltinFunc,
-            Type,
-            Custom,
-            Float,
-            Func,
-            Int,
-            Namespace,
-            Nil,
-            Str,
-            Tuple
-        );
-        // Fallback
-        let type_name = self.class().qualified_name();
-        let id = self.id();
-        write!(f, "<{type_name}> object @ {id}")
-    }
-}
+//
+// Display for `dyn Object` is provided automatically since `Display` is
+// a supertrait of `Object`. Debug shows the object's repr.
 
 impl fmt::Debug for dyn Object {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        debug_instance!(
-            f,
-            self,
-            Bool,
-            BuiltinFunc,
-            Type,
-            Custom,
-            Float,
-            Func,
-            Int,
-            Namespace,
-            Nil,
-            Str,
-            Tuple
-        );
-        // Fallback
-        write!(f, "{self}")
+        write!(f, "{}", self.repr())
     }
 }
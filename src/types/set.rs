@@ -0,0 +1,219 @@
+//! Set type. Sets hold unique items. Like map keys, only Int and Str
+//! objects can be added to a set. Items are kept in insertion order.
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::RwLock;
+
+use crate::builtin_funcs::set;
+use crate::vm::{RuntimeBoolResult, RuntimeContext, RuntimeErr};
+
+use super::builtin_types::BUILTIN_TYPES;
+use super::class::TypeRef;
+use super::map::MapKey;
+use super::object::{Object, ObjectExt, ObjectRef};
+use super::result::GetAttrResult;
+
+#[derive(Default)]
+struct Items {
+    /// Key => position in `items`
+    positions: HashMap<MapKey, usize>,
+    /// Items in insertion order
+    items: Vec<(MapKey, ObjectRef)>,
+}
+
+#[derive(Default)]
+pub struct Set {
+    items: RwLock<Items>,
+}
+
+impl Set {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.read().unwrap().items.len()
+    }
+
+    /// Copy the items in insertion order.
+    pub fn items(&self) -> Vec<ObjectRef> {
+        self.items.read().unwrap().items.iter().map(|(_, item)| item.clone()).collect()
+    }
+
+    pub fn has(&self, item: &ObjectRef) -> Result<bool, RuntimeErr> {
+        let key = MapKey::from_obj(item)?;
+        Ok(self.items.read().unwrap().positions.contains_key(&key))
+    }
+
+    /// Add an item if it's not already present.
+    ///
+    /// Returns true if the item was added
+    pub fn add(&self, item: ObjectRef) -> Result<bool, RuntimeErr> {
+        let key = MapKey::from_obj(&item)?;
+        let mut items = self.items.write().unwrap();
+        if items.positions.contains_key(&key) {
+            return Ok(false);
+        }
+        let i = items.items.len();
+        items.positions.insert(key.clone(), i);
+        items.items.push((key, item));
+        Ok(true)
+    }
+
+    /// Remove an item, preserving the order of the remaining items.
+    ///
+    /// Returns true if the item was present
+    pub fn remove(&self, item: &ObjectRef) -> Result<bool, RuntimeErr> {
+        let key = MapKey::from_obj(item)?;
+        let mut items = self.items.write().unwrap();
+        let i = match items.positions.remove(&key) {
+            Some(i) => i,
+            None => return Ok(false),
+        };
+        items.items.remove(i);
+        let Items { positions, items } = &mut *items;
+        for (key, _) in items[i..].iter() {
+            *positions.get_mut(key).unwrap() -= 1;
+        }
+        Ok(true)
+    }
+
+    /// Check whether every item in this set is also in the other set.
+    pub fn is_subset(&self, other: &Set) -> bool {
+        let other = other.items.read().unwrap();
+        self.items
+            .read()
+            .unwrap()
+            .positions
+            .keys()
+            .all(|key| other.positions.contains_key(key))
+    }
+
+    /// Get the other operand of a set comparison, which must also be
+    /// a set.
+    fn comparison_rhs<'a>(
+        &self,
+        op: &str,
+        rhs: &'a dyn Object,
+    ) -> Result<&'a Set, RuntimeErr> {
+        match rhs.as_any().downcast_ref::<Self>() {
+            Some(rhs) => Ok(rhs),
+            None => Err(RuntimeErr::new_type_err(format!(
+                "Cannot compare Set to {} with {op}",
+                rhs.type_name()
+            ))),
+        }
+    }
+}
+
+impl Object for Set {
+    fn class(&self) -> &TypeRef {
+        BUILTIN_TYPES.get("Set").unwrap()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    /// Sets are equal when they contain the same items. Order doesn't
+    /// matter.
+    fn is_equal(&self, rhs: &dyn Object, _ctx: &RuntimeContext) -> bool {
+        if let Some(rhs) = rhs.as_any().downcast_ref::<Self>() {
+            if self.is(rhs) {
+                return true;
+            }
+            let lhs = self.items.read().unwrap();
+            let rhs = rhs.items.read().unwrap();
+            lhs.items.len() == rhs.items.len()
+                && lhs.positions.keys().all(|key| rhs.positions.contains_key(key))
+        } else {
+            false
+        }
+    }
+
+    /// A set is less than another set when it's a proper subset of the
+    /// other set, so `a <= b` checks whether `a` is a subset of `b`.
+    fn less_than(&self, rhs: &dyn Object, _ctx: &RuntimeContext) -> RuntimeBoolResult {
+        let rhs = self.comparison_rhs("<", rhs)?;
+        Ok(self.len() < rhs.len() && self.is_subset(rhs))
+    }
+
+    /// A set is greater than another set when it's a proper superset of
+    /// the other set, so `a >= b` checks whether `a` is a superset of
+    /// `b`.
+    fn greater_than(
+        &self,
+        rhs: &dyn Object,
+        _ctx: &RuntimeContext,
+    ) -> RuntimeBoolResult {
+        let rhs = self.comparison_rhs(">", rhs)?;
+        Ok(self.len() > rhs.len() && rhs.is_subset(self))
+    }
+
+    fn contains(&self, item: &ObjectRef, _ctx: &RuntimeContext) -> RuntimeBoolResult {
+        self.has(item)
+    }
+
+    fn len(&self) -> Option<usize> {
+        Some(self.len())
+    }
+
+    fn get_attr(&self, name: &str, ctx: &RuntimeContext) -> GetAttrResult {
+        if let Some(attr) = self.get_base_attr(name, ctx) {
+            return Ok(attr);
+        }
+        let attr = match name {
+            "length" => ctx.builtins.new_int(self.len()),
+            "add" => ctx.builtins.new_builtin_func(
+                "add",
+                Some(vec!["this", "item"]),
+                set::add,
+            ),
+            "remove" => ctx.builtins.new_builtin_func(
+                "remove",
+                Some(vec!["this", "item"]),
+                set::remove,
+            ),
+            "has" => ctx.builtins.new_builtin_func(
+                "has",
+                Some(vec!["this", "item"]),
+                set::has,
+            ),
+            "union" => ctx.builtins.new_builtin_func(
+                "union",
+                Some(vec!["this", "other"]),
+                set::union,
+            ),
+            "intersection" => ctx.builtins.new_builtin_func(
+                "intersection",
+                Some(vec!["this", "other"]),
+                set::intersection,
+            ),
+            "difference" => ctx.builtins.new_builtin_func(
+                "difference",
+                Some(vec!["this", "other"]),
+                set::difference,
+            ),
+            _ => {
+                return Err(self.attr_does_not_exist(name));
+            }
+        };
+        Ok(attr)
+    }
+}
+
+// Display -------------------------------------------------------------
+
+impl fmt::Display for Set {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let items: Vec<String> = self.items().iter().map(|item| item.repr()).collect();
+        write!(f, "{{{}}}", items.join(", "))
+    }
+}
+
+impl fmt::Debug for Set {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
@@ -2,11 +2,24 @@
 use std::any::Any;
 use std::fmt;
 
+use num_traits::{Signed, ToPrimitive};
+
+use crate::builtin_funcs::seq;
+use crate::builtin_funcs::str as str_funcs;
 use crate::vm::{RuntimeBoolResult, RuntimeContext, RuntimeErr, RuntimeObjResult};
 
+use super::builtin_func::BuiltinFn;
 use super::builtin_types::BUILTIN_TYPES;
 use super::class::TypeRef;
-use super::object::{Object, ObjectExt};
+use super::int::Int;
+use super::object::{Object, ObjectExt, ObjectRef};
+use super::result::GetAttrResult;
+use super::util::seq_index;
+
+/// Max length, in bytes, of a Str produced by repetition. Allocating a
+/// much larger string would most likely fail, which aborts instead of
+/// raising an error.
+const MAX_REPEAT_LEN: usize = 1 << 30; // 1 GiB
 
 pub struct Str {
     value: String,
@@ -57,6 +70,64 @@ impl Object for Str {
         }
     }
 
+    /// Repeat the string `rhs` times, where `rhs` is a non-negative
+    /// Int.
+    fn mul(&self, rhs: &dyn Object, ctx: &RuntimeContext) -> RuntimeObjResult {
+        let count = if let Some(rhs) = rhs.as_any().downcast_ref::<Int>() {
+            rhs.value()
+        } else {
+            return Err(RuntimeErr::new_type_err(format!(
+                "Cannot repeat {} by {}",
+                self.class(),
+                rhs.class(),
+            )));
+        };
+        if count.is_negative() {
+            return Err(RuntimeErr::new_value_err(format!(
+                "Cannot repeat Str a negative number of times: {count}"
+            )));
+        }
+        let len = self.value.len();
+        match count.to_usize().filter(|&n| n == 0 || len <= MAX_REPEAT_LEN / n) {
+            Some(n) => Ok(ctx.builtins.new_str(self.value.repeat(n))),
+            None => Err(RuntimeErr::new_value_err(format!(
+                "Cannot repeat Str {count} times; result is too large"
+            ))),
+        }
+    }
+
+    /// Show the string in quotes with special chars escaped, so that
+    /// the result could be pasted back in as a string literal.
+    fn repr(&self) -> String {
+        let mut repr = String::with_capacity(self.value.len() + 2);
+        repr.push('"');
+        for c in self.value.chars() {
+            match c {
+                '\\' => repr.push_str("\\\\"),
+                '"' => repr.push_str("\\\""),
+                '\0' => repr.push_str("\\0"),
+                '\x07' => repr.push_str("\\a"),
+                '\x08' => repr.push_str("\\b"),
+                '\x0c' => repr.push_str("\\f"),
+                '\n' => repr.push_str("\\n"),
+                '\r' => repr.push_str("\\r"),
+                '\t' => repr.push_str("\\t"),
+                '\x0b' => repr.push_str("\\v"),
+                c => repr.push(c),
+            }
+        }
+        repr.push('"');
+        repr
+    }
+
+    /// Get the char at the index as a Str.
+    fn get_item(&self, index: &ObjectRef, ctx: &RuntimeContext) -> GetAttrResult {
+        let len = self.value.chars().count();
+        let index = seq_index(self, index, len)?;
+        let c = self.value.chars().nth(index).unwrap();
+        Ok(ctx.builtins.new_str(c.to_string()))
+    }
+
     fn less_than(&self, rhs: &dyn Object, _ctx: &RuntimeContext) -> RuntimeBoolResult {
         if let Some(rhs) = rhs.as_any().downcast_ref::<Self>() {
             Ok(self.value() < rhs.value())
@@ -84,6 +155,42 @@ impl Object for Str {
             )))
         }
     }
+
+    /// Check whether the item is a substring of this string.
+    fn contains(&self, item: &ObjectRef, _ctx: &RuntimeContext) -> RuntimeBoolResult {
+        if let Some(item) = item.as_any().downcast_ref::<Self>() {
+            Ok(self.value().contains(item.value()))
+        } else {
+            Err(RuntimeErr::new_type_err(format!(
+                "Cannot check if {} is in {}",
+                item.class(),
+                self.class(),
+            )))
+        }
+    }
+
+    fn len(&self) -> Option<usize> {
+        Some(self.value.chars().count())
+    }
+
+    fn get_attr(&self, name: &str, ctx: &RuntimeContext) -> GetAttrResult {
+        if let Some(attr) = self.get_base_attr(name, ctx) {
+            return Ok(attr);
+        }
+        let (params, func): (_, BuiltinFn) = match name {
+            "split_once" => (vec!["this", "sep"], str_funcs::split_once),
+            "rsplit" => (vec!["this", "sep"], str_funcs::rsplit),
+            "is_alpha" => (vec!["this"], str_funcs::is_alpha),
+            "is_digit" => (vec!["this"], str_funcs::is_digit),
+            "is_alnum" => (vec!["this"], str_funcs::is_alnum),
+            "split_at" => (vec!["this", "i"], seq::split_at),
+            "positions" => (vec!["this", "pred"], seq::positions),
+            _ => {
+                return Err(self.attr_does_not_exist(name));
+            }
+        };
+        Ok(ctx.builtins.new_builtin_func(name, Some(params), func))
+    }
 }
 
 // Display -------------------------------------------------------------
@@ -96,6 +203,6 @@ impl fmt::Display for Str {
 
 impl fmt::Debug for Str {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "\"{}\"", self.value())
+        write!(f, "{}", self.repr())
     }
 }
@@ -2,16 +2,14 @@
 use std::any::Any;
 use std::fmt;
 
-use num_bigint::BigInt;
-use num_traits::ToPrimitive;
-
-use crate::builtin_funcs::tuple;
-use crate::vm::{RuntimeContext, RuntimeErr};
+use crate::builtin_funcs::{seq, tuple};
+use crate::vm::{RuntimeBoolResult, RuntimeContext, RuntimeErr, RuntimeObjResult};
 
 use super::builtin_types::BUILTIN_TYPES;
 use super::class::TypeRef;
 use super::object::{Object, ObjectExt, ObjectRef};
 use super::result::GetAttrResult;
+use super::util::seq_index;
 
 pub struct Tuple {
     items: Vec<ObjectRef>,
@@ -59,6 +57,29 @@ impl Object for Tuple {
         }
     }
 
+    fn contains(&self, item: &ObjectRef, ctx: &RuntimeContext) -> RuntimeBoolResult {
+        Ok(self.items.iter().any(|a| a.is_equal(&**item, ctx)))
+    }
+
+    /// Concatenate tuples, producing a new tuple.
+    fn add(&self, rhs: &dyn Object, ctx: &RuntimeContext) -> RuntimeObjResult {
+        if let Some(rhs) = rhs.as_any().downcast_ref::<Self>() {
+            let mut items = self.items.clone();
+            items.extend(rhs.items.iter().cloned());
+            Ok(ctx.builtins.new_tuple(items))
+        } else {
+            Err(RuntimeErr::new_type_err(format!(
+                "Cannot concatenate {} to {}",
+                rhs.class(),
+                self.class(),
+            )))
+        }
+    }
+
+    fn len(&self) -> Option<usize> {
+        Some(self.items.len())
+    }
+
     fn get_attr(&self, name: &str, ctx: &RuntimeContext) -> GetAttrResult {
         if let Some(attr) = self.get_base_attr(name, ctx) {
             return Ok(attr);
@@ -70,6 +91,51 @@ impl Object for Tuple {
                 Some(vec!["this", "map_fn"]),
                 tuple::map,
             ),
+            "slice" => ctx.builtins.new_builtin_func(
+                "slice",
+                Some(vec!["this", "start", "end"]),
+                tuple::slice,
+            ),
+            "binary_search" => ctx.builtins.new_builtin_func(
+                "binary_search",
+                Some(vec!["this", "item"]),
+                seq::binary_search,
+            ),
+            "rotate" => ctx.builtins.new_builtin_func(
+                "rotate",
+                Some(vec!["this", "n"]),
+                seq::rotate,
+            ),
+            "split_at" => ctx.builtins.new_builtin_func(
+                "split_at",
+                Some(vec!["this", "i"]),
+                seq::split_at,
+            ),
+            "each_with_index" => ctx.builtins.new_builtin_func(
+                "each_with_index",
+                Some(vec!["this", "f"]),
+                seq::each_with_index,
+            ),
+            "positions" => ctx.builtins.new_builtin_func(
+                "positions",
+                Some(vec!["this", "pred"]),
+                seq::positions,
+            ),
+            "sum" => ctx.builtins.new_builtin_func("sum", Some(vec!["this"]), seq::sum),
+            "product" => ctx.builtins.new_builtin_func(
+                "product",
+                Some(vec!["this"]),
+                seq::product,
+            ),
+            "mean" => {
+                ctx.builtins.new_builtin_func("mean", Some(vec!["this"]), seq::mean)
+            }
+            "median" => {
+                ctx.builtins.new_builtin_func("median", Some(vec!["this"]), seq::median)
+            }
+            "to_set" => {
+                ctx.builtins.new_builtin_func("to_set", Some(vec!["this"]), seq::to_set)
+            }
             _ => {
                 return Err(self.attr_does_not_exist(name));
             }
@@ -77,12 +143,9 @@ impl Object for Tuple {
         Ok(attr)
     }
 
-    fn get_item(&self, index: &BigInt, _ctx: &RuntimeContext) -> GetAttrResult {
-        let index = index.to_usize().unwrap();
-        match self.items.get(index) {
-            Some(obj) => Ok(obj.clone()),
-            None => return Err(RuntimeErr::new_index_out_of_bounds(index)),
-        }
+    fn get_item(&self, index: &ObjectRef, _ctx: &RuntimeContext) -> GetAttrResult {
+        let index = seq_index(self, index, self.len())?;
+        Ok(self.items[index].clone())
     }
 }
 
@@ -92,7 +155,7 @@ impl fmt::Display for Tuple {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let items = self.items();
         let num_items = items.len();
-        let items: Vec<String> = items.iter().map(|item| format!("{item:?}")).collect();
+        let items: Vec<String> = items.iter().map(|item| item.repr()).collect();
         let items_str = items.join(", ");
         let trailing_comma = if num_items == 1 { "," } else { "" };
         write!(f, "({}{})", items_str, trailing_comma)
@@ -1,10 +1,40 @@
 use num_bigint::BigInt;
-use num_traits::{FromPrimitive, ToPrimitive};
+use num_traits::{FromPrimitive, Signed, ToPrimitive, Zero};
 
 use crate::types::float::Float;
 use crate::types::int::Int;
+use crate::vm::RuntimeErr;
 
-/// Compare Int and Float for equality.
+use super::object::{Object, ObjectRef};
+
+/// Convert an index object to an index into a sequence with the
+/// specified length. The index must be an Int that's in bounds.
+/// Negative indexes count back from the end of the sequence, so -1 is
+/// the index of the last item.
+pub fn seq_index(
+    seq: &dyn Object,
+    index: &ObjectRef,
+    len: usize,
+) -> Result<usize, RuntimeErr> {
+    let index_val = match index.int_val() {
+        Some(index_val) => index_val,
+        None => {
+            let type_name = seq.type_name();
+            return Err(RuntimeErr::new_type_err(format!(
+                "{type_name} index must be an Int; got {index:?}"
+            )));
+        }
+    };
+    let normalized =
+        if index_val.is_negative() { &index_val + len } else { index_val.clone() };
+    match normalized.to_usize() {
+        Some(i) if i < len => Ok(i),
+        _ => Err(RuntimeErr::new_index_out_of_bounds(index_val, len)),
+    }
+}
+
+/// Compare Int and Float for equality. NaN and infinities are never
+/// equal to an Int.
 pub fn eq_int_float(int: &Int, float: &Float) -> bool {
     let float_val = float.value();
     if float_val.fract() == 0.0 {
@@ -16,14 +46,67 @@ pub fn eq_int_float(int: &Int, float: &Float) -> bool {
     }
 }
 
-/// Compare Int and Float for less than.
+/// Compare Int and Float for less than. The comparison is exact
+/// rather than converting the Int to a Float, which could lose
+/// precision or overflow to infinity. Comparisons with NaN are always
+/// false.
 pub fn lt_int_float(int: &Int, float: &Float) -> bool {
-    let int_as_float = int.value().to_f64().unwrap();
-    int_as_float < *float.value()
+    let float_val = *float.value();
+    if float_val.is_nan() {
+        false
+    } else if float_val.is_infinite() {
+        float_val.is_sign_positive()
+    } else {
+        *int.value() < BigInt::from_f64(float_val.ceil()).unwrap()
+    }
 }
 
-/// Compare Int and Float for greater than.
+/// Compare Int and Float for greater than. See [`lt_int_float`].
 pub fn gt_int_float(int: &Int, float: &Float) -> bool {
-    let int_as_float = int.value().to_f64().unwrap();
-    int_as_float > *float.value()
+    let float_val = *float.value();
+    if float_val.is_nan() {
+        false
+    } else if float_val.is_infinite() {
+        float_val.is_sign_negative()
+    } else {
+        *int.value() > BigInt::from_f64(float_val.floor()).unwrap()
+    }
+}
+
+// Division ------------------------------------------------------------
+//
+// Floor division and modulo round toward negative infinity, so the
+// result of `a % b` always has the same sign as `b` and `a == (a // b)
+// * b + a % b` holds. These assume `b` is non-zero; callers are
+// responsible for checking that.
+
+/// Floor division of Ints.
+pub fn floor_div_int(a: &BigInt, b: &BigInt) -> BigInt {
+    let q = a / b;
+    let r = a % b;
+    if !r.is_zero() && (r.is_negative() != b.is_negative()) {
+        q - 1
+    } else {
+        q
+    }
+}
+
+/// Modulo of Ints.
+pub fn mod_int(a: &BigInt, b: &BigInt) -> BigInt {
+    let r = a % b;
+    if !r.is_zero() && (r.is_negative() != b.is_negative()) {
+        r + b
+    } else {
+        r
+    }
+}
+
+/// Modulo of Floats.
+pub fn mod_float(a: f64, b: f64) -> f64 {
+    let r = a % b;
+    if r != 0.0 && (r < 0.0) != (b < 0.0) {
+        r + b
+    } else {
+        r
+    }
 }
@@ -3,7 +3,9 @@ pub(crate) use source::{
     source_from_file, source_from_stdin, source_from_text, Location, Source,
 };
 pub(crate) use stack::Stack;
+pub(crate) use suggest::closest_name;
 
 mod operators;
 mod source;
 mod stack;
+mod suggest;
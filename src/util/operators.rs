@@ -66,8 +66,12 @@ pub enum BinaryOperator {
     LessThanOrEqual,
     GreaterThan,
     GreaterThanOrEqual,
+    In,
     And,
     Or,
+    BitAnd,
+    BitOr,
+    BitXor,
     Assign,
     Comma,
     Dot,
@@ -78,7 +82,7 @@ impl str::FromStr for BinaryOperator {
 
     fn from_str(op: &str) -> Result<Self, Self::Err> {
         let op = match op {
-            "^" => Self::Pow,
+            "**" => Self::Pow,
             "*" => Self::Mul,
             "/" => Self::Div,
             "//" => Self::FloorDiv,
@@ -94,8 +98,12 @@ impl str::FromStr for BinaryOperator {
             "<=" => Self::LessThanOrEqual,
             ">" => Self::GreaterThan,
             ">=" => Self::GreaterThanOrEqual,
+            "in" => Self::In,
             "&&" => Self::And,
             "||" => Self::Or,
+            "&" => Self::BitAnd,
+            "|" => Self::BitOr,
+            "^" => Self::BitXor,
             "=" => Self::Assign,
             "," => Self::Comma,
             "." => Self::Dot,
@@ -110,7 +118,7 @@ impl str::FromStr for BinaryOperator {
 impl fmt::Display for BinaryOperator {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let string = match self {
-            Self::Pow => "^",
+            Self::Pow => "**",
             Self::Mul => "*",
             Self::Div => "/",
             Self::FloorDiv => "//",
@@ -126,8 +134,12 @@ impl fmt::Display for BinaryOperator {
             Self::LessThanOrEqual => "<=",
             Self::GreaterThan => ">",
             Self::GreaterThanOrEqual => ">=",
+            Self::In => "in",
             Self::And => "&&",
             Self::Or => "||",
+            Self::BitAnd => "&",
+            Self::BitOr => "|",
+            Self::BitXor => "^",
             Self::Assign => "=",
             Self::Comma => ",",
             Self::Dot => ".",
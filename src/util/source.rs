@@ -21,6 +21,12 @@ const INITIAL_CAPACITY: usize = 255; // 2^8 - 1
 const MAX_LINE_LENGTH: u64 = 4096; // 2^12
 const MAX_LINE_LENGTH_USIZE: usize = MAX_LINE_LENGTH as usize;
 
+/// Default maximum number of lines kept in the line cache. When the
+/// cache is full, the oldest line is dropped to make room for the next
+/// line. With the max line length, this caps the cache at ~256 MB, but
+/// in practice lines are much shorter.
+const DEFAULT_MAX_CACHED_LINES: usize = 65_536; // 2^16
+
 /// Create source from the specified file.
 pub fn source_from_file(file_path: &str) -> Result<Source<BufReader<File>>, io::Error> {
     let file = File::open(file_path)?;
@@ -54,7 +60,9 @@ pub fn source_from_stdin() -> Source<BufReader<io::Stdin>> {
 ///   newline.
 /// - Normalizes \r\n line endings to \n. NOTE: \r as a line ending
 ///   is *not* handled. TODO: Detect use of \r as line ending?
-/// - Tracks current line and column.
+/// - Tracks current line, column, and byte offset into the line.
+/// - Caches lines as they're read so they can be retrieved later,
+///   e.g. to show the line where an error occurred.
 /// - Panics when lines are too long.
 pub struct Source<T: BufRead> {
     stream: Take<T>,
@@ -63,12 +71,20 @@ pub struct Source<T: BufRead> {
     /// The queue of characters for the current line.
     queue: VecDeque<char>,
     pub line_no: usize,
+    /// Column of the current char, counted in chars.
     pub col: usize,
+    /// Byte offset of the current char in the current line.
+    pub offset: usize,
+    /// Byte offset of the next char in the current line.
+    next_offset: usize,
     pub current_line: Option<String>,
     pub current_char: Option<char>,
     // Indicates whether a newline was added because the source didn't
     // end with one.
     pub newline_added: bool,
+    /// Lines read so far, oldest first, up to `max_cached_lines`.
+    line_cache: VecDeque<String>,
+    max_cached_lines: Option<usize>,
 }
 
 impl<T: BufRead> Source<T> {
@@ -79,9 +95,13 @@ impl<T: BufRead> Source<T> {
             queue: VecDeque::with_capacity(INITIAL_CAPACITY),
             line_no: 0,
             col: 0,
+            offset: 0,
+            next_offset: 0,
             current_line: None,
             current_char: None,
             newline_added: false,
+            line_cache: VecDeque::new(),
+            max_cached_lines: Some(DEFAULT_MAX_CACHED_LINES),
         };
         source.queue.push_back('\n');
         source
@@ -95,6 +115,38 @@ impl<T: BufRead> Source<T> {
         }
     }
 
+    /// Set the maximum number of lines to keep in the line cache.
+    /// `None` means the cache is unbounded. If the cache currently
+    /// holds more lines than the new max, the oldest lines are dropped.
+    pub fn set_max_cached_lines(&mut self, max: Option<usize>) {
+        self.max_cached_lines = max;
+        self.trim_line_cache();
+    }
+
+    /// Get the line with the specified line number (starting from 1),
+    /// without its line ending, if it has been read and is still in the
+    /// line cache.
+    pub fn get_line(&self, line_no: usize) -> Option<&str> {
+        let first_line_no = self.line_no + 1 - self.line_cache.len();
+        if line_no < first_line_no || line_no > self.line_no {
+            return None;
+        }
+        self.line_cache.get(line_no - first_line_no).map(|line| line.as_str())
+    }
+
+    fn cache_line(&mut self, line: String) {
+        self.line_cache.push_back(line);
+        self.trim_line_cache();
+    }
+
+    fn trim_line_cache(&mut self) {
+        if let Some(max) = self.max_cached_lines {
+            while self.line_cache.len() > max {
+                self.line_cache.pop_front();
+            }
+        }
+    }
+
     fn fill_queue(&mut self) {
         if self.queue.is_empty() {
             // See if character queue can be refilled from next line.
@@ -109,8 +161,13 @@ impl<T: BufRead> Source<T> {
                     }
                     self.line_no += 1;
                     self.col = 0;
+                    self.offset = 0;
+                    self.next_offset = 0;
                     // Store unmodified copy of current line.
                     self.current_line = Some(self.buffer.clone());
+                    self.cache_line(
+                        self.buffer.trim_end_matches(['\r', '\n']).to_owned(),
+                    );
                     self.queue.extend(self.buffer.chars());
                     if self.queue.back() == Some(&'\n') {
                         if self.queue.len() > 1 {
@@ -138,6 +195,8 @@ impl<T: BufRead> Source<T> {
         if let Some(c) = self.queue.pop_front() {
             self.current_char = Some(c);
             self.col += 1;
+            self.offset = self.next_offset;
+            self.next_offset += c.len_utf8();
             return self.current_char;
         }
         None
@@ -163,8 +222,14 @@ impl<T: BufRead> Source<T> {
         return (queue.get(0), queue.get(1), queue.get(2));
     }
 
+    /// Get the location of the current char.
     pub fn loc(&self) -> Location {
-        Location::new(self.line_no, self.col)
+        Location::new_with_offset(self.line_no, self.col, self.offset)
+    }
+
+    /// Get the location of the next char.
+    pub fn next_loc(&self) -> Location {
+        Location::new_with_offset(self.line_no, self.col + 1, self.next_offset)
     }
 }
 
@@ -176,16 +241,32 @@ impl<T: BufRead> Iterator for Source<T> {
     }
 }
 
-/// Represents a line and column in the source.
+/// Represents a line and column in the source. The column is counted
+/// in chars and is used for display. The offset is the byte offset of
+/// the column in its line and is used for slicing the line.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Location {
     pub line: usize,
     pub col: usize,
+    pub offset: usize,
 }
 
 impl Location {
+    /// Create a location with its offset derived from its column. This
+    /// is only correct when the line is ASCII up to the column; use
+    /// `new_with_offset` when the offset is known.
     pub fn new(line: usize, col: usize) -> Self {
-        Self { line, col }
+        Self { line, col, offset: col.saturating_sub(1) }
+    }
+
+    pub fn new_with_offset(line: usize, col: usize, offset: usize) -> Self {
+        Self { line, col, offset }
+    }
+
+    /// Get the location `n` columns after this one, assuming the chars
+    /// in between are ASCII.
+    pub fn advance(&self, n: usize) -> Self {
+        Self::new_with_offset(self.line, self.col + n, self.offset + n)
     }
 }
 
@@ -5,17 +5,46 @@ use std::fmt;
 #[derive(Debug)]
 pub struct Stack<T> {
     storage: Vec<T>,
+    /// Maximum number of items that can be pushed via `push_checked`.
+    /// `None` means the stack is unbounded.
+    max_size: Option<usize>,
 }
 
 impl<T> Stack<T> {
     pub fn new() -> Self {
-        Stack { storage: Vec::new() }
+        Stack { storage: Vec::new(), max_size: None }
+    }
+
+    /// Create a stack that holds at most `max_size` items when items
+    /// are pushed via `push_checked`.
+    pub fn with_max_size(max_size: usize) -> Self {
+        Stack { storage: Vec::new(), max_size: Some(max_size) }
+    }
+
+    pub fn max_size(&self) -> Option<usize> {
+        self.max_size
+    }
+
+    pub fn set_max_size(&mut self, max_size: Option<usize>) {
+        self.max_size = max_size;
     }
 
     pub fn push(&mut self, item: T) {
         self.storage.push(item);
     }
 
+    /// Push item if the stack isn't full. If it is, the item is
+    /// returned in the `Err`.
+    pub fn push_checked(&mut self, item: T) -> Result<(), T> {
+        match self.max_size {
+            Some(max_size) if self.size() >= max_size => Err(item),
+            _ => {
+                self.storage.push(item);
+                Ok(())
+            }
+        }
+    }
+
     pub fn pop(&mut self) -> Option<T> {
         self.storage.pop()
     }
@@ -36,6 +65,17 @@ impl<T> Stack<T> {
         self.storage.last()
     }
 
+    /// Peek at top N items if at least N items are present. The top
+    /// item in the stack will be at the *end* of the returned slice.
+    pub fn peek_n(&self, n: usize) -> Option<&[T]> {
+        let size = self.size();
+        if size < n {
+            None
+        } else {
+            Some(&self.storage[size - n..])
+        }
+    }
+
     pub fn is_empty(&self) -> bool {
         self.storage.is_empty()
     }
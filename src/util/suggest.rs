@@ -0,0 +1,39 @@
+//! Suggestions for misspelled names.
+
+/// Get the Levenshtein distance between two strings, which is the
+/// number of single-char insertions, deletions, and substitutions
+/// needed to turn one string into the other.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    // Distances from the current prefix of `a` to each prefix of `b`
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let cost = if a_char == *b_char { 0 } else { 1 };
+            let distance = (diagonal + cost).min(row[j] + 1).min(row[j + 1] + 1);
+            diagonal = row[j + 1];
+            row[j + 1] = distance;
+        }
+    }
+    row[b.len()]
+}
+
+/// Find the candidate closest to `name`. Only candidates that are a
+/// small number of edits away are considered: roughly one edit per
+/// three chars, and at least one. Ties are broken alphabetically so
+/// the suggestion doesn't depend on the order of the candidates.
+pub fn closest_name<'a, I>(name: &str, candidates: I) -> Option<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let max_distance = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
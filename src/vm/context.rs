@@ -2,10 +2,14 @@ use std::slice::Iter;
 use std::sync::Arc;
 
 use crate::builtin_funcs::get_builtin_func_specs;
-use crate::types::{Builtins, Namespace, ObjectRef, BUILTIN_TYPES};
+use crate::types::{Args, Builtins, CallResult, Namespace, ObjectRef, BUILTIN_TYPES};
+use crate::util::closest_name;
 
 use super::objects::Objects;
 use super::result::{RuntimeErr, RuntimeResult};
+#[cfg(test)]
+use super::serialize::{self, SerializeResult};
+use super::vm::VM;
 
 pub struct RuntimeContext {
     pub builtins: Builtins,
@@ -26,6 +30,12 @@ impl RuntimeContext {
         self.constants.iter()
     }
 
+    /// Iterate over namespaces, starting with the global namespace
+    /// (depth 0).
+    pub fn iter_namespaces(&self) -> Iter<'_, Namespace> {
+        self.namespace_stack.iter()
+    }
+
     fn current_namespace(&mut self) -> &mut Namespace {
         let index = self.depth();
         &mut self.namespace_stack[index]
@@ -68,6 +78,21 @@ impl RuntimeContext {
         self.constants.get(index)
     }
 
+    /// Serialize constants so they can be saved along with a
+    /// serialized chunk that references them by index.
+    #[cfg(test)]
+    pub fn serialize_constants(&self) -> SerializeResult<Vec<u8>> {
+        serialize::serialize_constants(self.constants.iter())
+    }
+
+    /// Replace *all* constants with the deserialized constants.
+    #[cfg(test)]
+    pub fn deserialize_constants(&mut self, bytes: &[u8]) -> SerializeResult<()> {
+        let constants = serialize::deserialize_constants(bytes, &self.builtins)?;
+        self.constants = Objects::new(constants);
+        Ok(())
+    }
+
     // Vars ------------------------------------------------------------
 
     /// Declare a new var in the current namespace. This adds a slot for
@@ -120,6 +145,64 @@ impl RuntimeContext {
         }
     }
 
+    /// Get the absolute depth of the namespace that's `up` levels
+    /// above the current namespace.
+    pub fn outer_depth(&self, up: usize) -> Result<usize, RuntimeErr> {
+        match self.depth().checked_sub(up) {
+            Some(depth) => Ok(depth),
+            None => {
+                let message = format!("No namespace {up} levels up");
+                Err(RuntimeErr::new_name_err(message))
+            }
+        }
+    }
+
+    /// Assign value to the var in the specified slot of the namespace
+    /// at depth.
+    pub fn assign_var_in_slot(
+        &mut self,
+        depth: usize,
+        slot: usize,
+        obj: ObjectRef,
+    ) -> RuntimeResult {
+        if self.namespace_stack[depth].set_slot(slot, obj) {
+            Ok(())
+        } else {
+            let message = format!("Slot not defined at depth {depth}: {slot}");
+            Err(RuntimeErr::new_name_err(message))
+        }
+    }
+
+    // Builtins --------------------------------------------------------
+
+    /// Register a function implemented in Rust as a builtin function.
+    /// Like the standard builtin functions, it's added to the builtins
+    /// namespace and aliased in the global scope.
+    pub fn register_builtin_func<F>(
+        &mut self,
+        name: &str,
+        params: Option<Vec<&str>>,
+        func: F,
+    ) -> RuntimeResult
+    where
+        F: Fn(Args, &mut VM) -> CallResult + Send + Sync + 'static,
+    {
+        let func = self.builtins.new_builtin_closure(name, params, func);
+        // The builtins namespace is shared, so it's replaced with an
+        // updated copy rather than being modified in place.
+        let mut builtins_ns = Namespace::new(self.builtins.nil_obj.clone());
+        let current = self.get_var_at_depth(0, "builtins")?.clone();
+        if let Some(current) = current.as_any().downcast_ref::<Namespace>() {
+            for (var_name, obj) in current.iter() {
+                builtins_ns.add_and_set_var(var_name, obj.clone());
+            }
+        }
+        builtins_ns.add_and_set_var(name, func.clone());
+        self.assign_var_at_depth(0, "builtins", Arc::new(builtins_ns))?;
+        self.namespace_stack[0].add_and_set_var(name, func);
+        Ok(())
+    }
+
     /// Get the depth of the namespace where the specified var is
     /// defined.
     pub fn get_var_depth(&mut self, name: &str) -> Result<usize, RuntimeErr> {
@@ -130,13 +213,29 @@ impl RuntimeContext {
                 break Ok(depth);
             }
             if depth == 0 {
-                let message = format!("Name not found: {name}");
+                let message = match self.suggest_var_name(name) {
+                    Some(suggestion) => {
+                        format!("Name not found: {name}; did you mean `{suggestion}`?")
+                    }
+                    None => format!("Name not found: {name}"),
+                };
                 break Err(RuntimeErr::new_name_err(message));
             }
             depth -= 1;
         }
     }
 
+    /// Find the var name in any visible namespace that's closest to
+    /// the specified name, if there's one that's close enough to be a
+    /// likely typo.
+    fn suggest_var_name(&self, name: &str) -> Option<String> {
+        let names = self
+            .namespace_stack
+            .iter()
+            .flat_map(|namespace| namespace.iter().map(|(name, _)| name.as_str()));
+        closest_name(name, names).map(|name| name.to_owned())
+    }
+
     /// Get var from current namespace.
     pub fn get_var_in_current_namespace(
         &mut self,
@@ -164,6 +263,21 @@ impl RuntimeContext {
             Err(RuntimeErr::new_name_err(message))
         }
     }
+
+    /// Reach into the namespace at depth and get the var in the
+    /// specified slot.
+    pub fn get_var_in_slot(
+        &self,
+        depth: usize,
+        slot: usize,
+    ) -> Result<&ObjectRef, RuntimeErr> {
+        if let Some(obj) = self.namespace_stack[depth].get_slot(slot) {
+            Ok(obj)
+        } else {
+            let message = format!("Slot not defined at depth {depth}: {slot}");
+            Err(RuntimeErr::new_name_err(message))
+        }
+    }
 }
 
 impl Default for RuntimeContext {
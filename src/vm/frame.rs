@@ -0,0 +1,46 @@
+use crate::types::ObjectRef;
+
+/// A call frame is pushed onto the VM's frame stack when a user
+/// function is called and popped when the function returns.
+pub struct CallFrame {
+    /// The function that was called.
+    pub func: ObjectRef,
+    /// The address of the call instruction in the caller's chunk.
+    /// This will be `None` when the function was called from a builtin
+    /// function (e.g., a callback passed to `map`).
+    pub return_ip: Option<usize>,
+    /// The size of the value stack when the function was called. When
+    /// the function returns, the stack is truncated back to this size
+    /// before the return value is pushed.
+    pub stack_pointer: usize,
+}
+
+impl CallFrame {
+    pub fn new(
+        func: ObjectRef,
+        return_ip: Option<usize>,
+        stack_pointer: usize,
+    ) -> Self {
+        Self { func, return_ip, stack_pointer }
+    }
+}
+
+/// An error handler is pushed onto the VM's handler stack when a `try`
+/// block is entered. It records the state of the VM at that point so
+/// that, when an error is caught, the VM can be unwound back to it.
+pub struct ErrorHandler {
+    /// The address of the catch block.
+    pub catch_addr: usize,
+    /// The size of the value stack when the handler was installed.
+    pub stack_pointer: usize,
+    /// The size of the scope stack when the handler was installed.
+    pub scope_stack_size: usize,
+    /// The namespace depth when the handler was installed. The handler
+    /// is removed when a scope exit brings the depth back to this
+    /// depth, which happens when the try block's scope is exited.
+    pub scope_depth: usize,
+    /// The size of the frame stack when the handler was installed.
+    /// Only errors that occur in the same call frame are caught
+    /// directly; errors in nested calls propagate back to it first.
+    pub frame_stack_size: usize,
+}
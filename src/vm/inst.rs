@@ -1,3 +1,4 @@
+use crate::format::FormatSpec;
 use crate::util::{BinaryOperator, UnaryOperator};
 
 pub type Chunk = Vec<Inst>;
@@ -10,6 +11,9 @@ pub enum Inst {
     // the top.
     Truncate(usize),
 
+    // Pop the top item off the stack, discarding it.
+    Pop,
+
     LoadConst(usize),
 
     ScopeStart,
@@ -17,8 +21,18 @@ pub enum Inst {
 
     DeclareVar(String),
     AssignVar(String),
+    // Declare var in the current scope and assign the top item on the
+    // stack to it. This is equivalent to DeclareVar + AssignVar.
+    DeclareAndAssignVar(String),
     LoadVar(String),
 
+    // Load or store a var by slot instead of by name. The first arg is
+    // the number of scopes up from the current scope and the second is
+    // the var's slot in that scope. The compiler emits these for vars
+    // in local scopes when it can determine their slots.
+    LoadLocal(usize, usize),
+    StoreLocal(usize, usize),
+
     // Jumps -----------------------------------------------------------
     //
     // NOTE: For all jump instructions, the last arg is the scope exit
@@ -40,12 +54,48 @@ pub enum Inst {
     UnaryOp(UnaryOperator),
     BinaryOp(BinaryOperator),
 
+    // Set an item on an object. The object, index, and value are the
+    // top 3 items on the stack. The value is left on the stack. If the
+    // index is a Str, the attribute with that name is set instead,
+    // mirroring how `.` gets attributes and items.
+    SetItem,
+
     Call(usize), // Call function with N values from top of stack
+
+    // Call method with N values from top of stack. The receiver and
+    // method name are below the args. If the method's first param is
+    // `this`, the receiver is passed as the first arg.
+    CallMethod(usize),
     Return,
 
     // These make compound objects from the top N items on the stack.
     MakeString(usize),
     MakeTuple(usize),
+    MakeList(usize),
+
+    // Unpack the tuple at the top of the stack, which must have N
+    // items. The items are pushed in reverse order so that the first
+    // item is on top. The tuple is left on the stack below the items.
+    UnpackTuple(usize),
+
+    // Format the top item on the stack according to the spec, replacing
+    // it with the resulting string. This is used for format string
+    // items with a spec, like `{n:x}`.
+    FormatValue(FormatSpec),
+
+    // Errors ----------------------------------------------------------
+
+    // Install an error handler. If an error occurs while the handler
+    // is installed, the stacks are unwound to where they were when the
+    // handler was installed, the error is pushed (as an Error object),
+    // and execution continues at the specified address. The handler is
+    // removed when the scope entered after it (the try block) is
+    // exited.
+    PushHandler(usize), // catch address
+
+    // Pop the top item off the stack and raise it as an error. If the
+    // item isn't an Error, it's wrapped in one.
+    Raise,
 
     Placeholder(usize, Box<Inst>, String),
     BreakPlaceholder(usize, usize), // address, scope depth
@@ -54,3 +104,47 @@ pub enum Inst {
     Halt(u8),
     HaltTop,
 }
+
+impl Inst {
+    /// The instruction's name as shown in disassembly, without any of
+    /// its args.
+    pub fn name(&self) -> &'static str {
+        use Inst::*;
+        match self {
+            NoOp => "NOOP",
+            Truncate(_) => "TRUNCATE",
+            Pop => "POP",
+            LoadConst(_) => "LOAD_CONST",
+            ScopeStart => "SCOPE_START",
+            ScopeEnd => "SCOPE_END",
+            DeclareVar(_) => "DECLARE_VAR",
+            AssignVar(_) => "ASSIGN_VAR",
+            DeclareAndAssignVar(_) => "DECLARE_ASSIGN",
+            LoadVar(_) => "LOAD_VAR",
+            LoadLocal(..) => "LOAD_LOCAL",
+            StoreLocal(..) => "STORE_LOCAL",
+            Jump(..) => "JUMP",
+            JumpIf(..) => "JUMP_IF",
+            JumpIfNot(..) => "JUMP_IF_NOT",
+            JumpIfElse(..) => "JUMP_IF_ELSE",
+            UnaryOp(_) => "UNARY_OP",
+            BinaryOp(_) => "BINARY_OP",
+            SetItem => "SET_ITEM",
+            Call(_) => "CALL",
+            CallMethod(_) => "CALL_METHOD",
+            Return => "RETURN",
+            MakeString(_) => "MAKE_STRING",
+            MakeTuple(_) => "MAKE_TUPLE",
+            MakeList(_) => "MAKE_LIST",
+            UnpackTuple(_) => "UNPACK_TUPLE",
+            FormatValue(_) => "FORMAT_VALUE",
+            PushHandler(_) => "PUSH_HANDLER",
+            Raise => "RAISE",
+            Placeholder(..) | BreakPlaceholder(..) | ContinuePlaceholder(..) => {
+                "PLACEHOLDER"
+            }
+            Halt(_) => "HALT",
+            HaltTop => "HALT_TOP",
+        }
+    }
+}
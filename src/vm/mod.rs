@@ -4,10 +4,17 @@ pub(crate) use result::VMState;
 pub(crate) use result::{
     RuntimeBoolResult, RuntimeErr, RuntimeErrKind, RuntimeObjResult,
 };
+// Serialization isn't used outside of tests yet
+#[cfg(test)]
+pub(crate) use serialize::{ChunkBytes, SerializeErr, SerializeResult};
 pub(crate) use vm::VM;
 
 mod context;
+mod frame;
 mod inst;
 mod objects;
+mod profile;
 mod result;
+#[cfg(test)]
+mod serialize;
 mod vm;
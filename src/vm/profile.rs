@@ -0,0 +1,40 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Collects per-instruction execution counts and timings. Instructions
+/// are grouped by name (i.e., by `Inst` variant, ignoring args).
+///
+/// NOTE: Times are inclusive, so the time for a call instruction
+///       includes the time spent executing the called function.
+#[derive(Default)]
+pub struct Profiler {
+    entries: HashMap<&'static str, (u64, Duration)>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count an execution of the named instruction.
+    pub fn count(&mut self, name: &'static str) {
+        self.entries.entry(name).or_default().0 += 1;
+    }
+
+    /// Add to the time spent executing the named instruction.
+    pub fn add_time(&mut self, name: &'static str, elapsed: Duration) {
+        self.entries.entry(name).or_default().1 += elapsed;
+    }
+
+    /// Get (name, count, total time) for each instruction that was
+    /// executed, most frequently executed first.
+    pub fn report(&self) -> Vec<(String, u64, Duration)> {
+        let mut report: Vec<(String, u64, Duration)> = self
+            .entries
+            .iter()
+            .map(|(name, (count, time))| (name.to_string(), *count, *time))
+            .collect();
+        report.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        report
+    }
+}
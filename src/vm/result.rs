@@ -5,9 +5,11 @@ use std::fmt::Formatter;
 use crate::compiler::CompErr;
 use crate::parser::ParseErr;
 use crate::types::ObjectRef;
+use crate::util::Location;
 
 pub type ExeResult = Result<VMState, RuntimeErr>;
 pub type RuntimeResult = Result<(), RuntimeErr>;
+pub type StepResult = Result<(usize, VMState), RuntimeErr>;
 pub type RuntimeObjResult = Result<ObjectRef, RuntimeErr>;
 pub type RuntimeBoolResult = Result<bool, RuntimeErr>;
 pub type PopObjResult = Result<ObjectRef, RuntimeErr>;
@@ -25,11 +27,37 @@ pub enum VMState {
 #[derive(Clone, Debug)]
 pub struct RuntimeErr {
     pub kind: RuntimeErrKind,
+    /// Entries are added as the error propagates out of nested calls,
+    /// so the innermost call comes first and the top level code comes
+    /// last.
+    pub traceback: Vec<TraceEntry>,
 }
 
 impl RuntimeErr {
     pub fn new(kind: RuntimeErrKind) -> Self {
-        Self { kind }
+        Self { kind, traceback: vec![] }
+    }
+
+    /// Get the source location where the error occurred, if known.
+    pub fn loc(&self) -> Option<Location> {
+        self.traceback.first().and_then(|entry| entry.loc)
+    }
+
+    /// Format the traceback with the outermost call first, one line per
+    /// entry. Each line names the function (or `top_level_name` for top
+    /// level code) and the line where the call or error occurred.
+    pub fn format_traceback(&self, top_level_name: &str) -> Vec<String> {
+        self.traceback
+            .iter()
+            .rev()
+            .map(|entry| {
+                let name = entry.func_name.as_deref().unwrap_or(top_level_name);
+                match entry.loc {
+                    Some(loc) => format!("{name}, line {}", loc.line),
+                    None => format!("{name}, line ?"),
+                }
+            })
+            .collect()
     }
 
     pub fn new_object_not_found_err(index: usize) -> Self {
@@ -44,6 +72,10 @@ impl RuntimeErr {
         Self::new(RuntimeErrKind::TypeErr(message.into()))
     }
 
+    pub fn new_value_err<S: Into<String>>(message: S) -> Self {
+        Self::new(RuntimeErrKind::ValueErr(message.into()))
+    }
+
     pub fn new_attr_does_not_exist<S: Into<String>>(type_name: S, name: S) -> Self {
         Self::new(RuntimeErrKind::AttrDoesNotExist(type_name.into(), name.into()))
     }
@@ -52,27 +84,61 @@ impl RuntimeErr {
         Self::new(RuntimeErrKind::AttrCannotBeSet(type_name.into(), name.into()))
     }
 
-    pub fn new_item_does_not_exist<S: Into<String>>(
-        type_name: S,
-        index: BigInt,
-    ) -> Self {
-        Self::new(RuntimeErrKind::ItemDoesNotExist(type_name.into(), index))
+    pub fn new_item_does_not_exist<S: Into<String>>(type_name: S, index: S) -> Self {
+        Self::new(RuntimeErrKind::ItemDoesNotExist(type_name.into(), index.into()))
+    }
+
+    pub fn new_item_cannot_be_set<S: Into<String>>(type_name: S, index: S) -> Self {
+        Self::new(RuntimeErrKind::ItemCannotBeSet(type_name.into(), index.into()))
+    }
+
+    pub fn new_index_out_of_bounds(index: BigInt, len: usize) -> Self {
+        Self::new(RuntimeErrKind::IndexOutOfBounds(index, len))
+    }
+
+    pub fn new_io_err<S: Into<String>>(message: S) -> Self {
+        Self::new(RuntimeErrKind::IoErr(message.into()))
     }
 
-    pub fn new_item_cannot_be_set<S: Into<String>>(
-        type_name: S,
-        index: BigInt,
-    ) -> Self {
-        Self::new(RuntimeErrKind::ItemCannotBeSet(type_name.into(), index))
+    pub fn new_division_by_zero() -> Self {
+        Self::new(RuntimeErrKind::DivisionByZero)
     }
 
-    pub fn new_index_out_of_bounds(index: usize) -> Self {
-        Self::new(RuntimeErrKind::IndexOutOfBounds(index))
+    pub fn new_scope_underflow<S: Into<String>>(message: S) -> Self {
+        Self::new(RuntimeErrKind::ScopeUnderflow(message.into()))
     }
 
     pub fn new_not_callable(obj: ObjectRef) -> Self {
         Self::new(RuntimeErrKind::NotCallable(obj))
     }
+
+    pub fn new_raised(obj: ObjectRef) -> Self {
+        Self::new(RuntimeErrKind::Raised(obj))
+    }
+
+    pub fn new_assertion_failed<S: Into<String>>(message: S) -> Self {
+        Self::new(RuntimeErrKind::AssertionFailed(message.into()))
+    }
+}
+
+/// Where a runtime error occurred in a function or in the top level
+/// code.
+#[derive(Clone, Debug)]
+pub struct TraceEntry {
+    /// Name of function or `None` for top level code.
+    pub func_name: Option<String>,
+    /// Address of the instruction being executed in the function or
+    /// top level code when the error occurred. For outer calls, this is
+    /// the call instruction.
+    pub ip: usize,
+    /// Source location of the instruction, if known.
+    pub loc: Option<Location>,
+}
+
+impl TraceEntry {
+    pub fn new(ip: usize) -> Self {
+        Self { func_name: None, ip, loc: None }
+    }
 }
 
 impl fmt::Display for RuntimeErr {
@@ -87,21 +153,70 @@ pub enum RuntimeErrKind {
     NotEnoughValuesOnStack(usize),
     ObjectNotFound(usize),
     ExpectedVar(String),
-    ParseErr(ParseErr),
-    CompErr(CompErr),
+    ParseErr(Box<ParseErr>),
+    CompErr(Box<CompErr>),
     UnhandledInstruction(String),
     TypeErr(String),
+    ValueErr(String),
     NameErr(String),
     StringFormatErr(String),
     AttrDoesNotExist(String, String),
     AttrCannotBeSet(String, String),
-    ItemDoesNotExist(String, BigInt),
-    ItemCannotBeSet(String, BigInt),
-    IndexOutOfBounds(usize),
+    ItemDoesNotExist(String, String),
+    ItemCannotBeSet(String, String),
+    IndexOutOfBounds(BigInt, usize), // index, length
+    DivisionByZero,
     NotCallable(ObjectRef),
+    ExitCodeOutOfRange(BigInt),
+    RecursionDepthExceeded(usize),
+    // The value stack is full; the max stack size is included
+    StackOverflow(usize),
+    StepLimitExceeded(usize),
+    ReturnOutsideOfFunc,
+    ScopeUnderflow(String),
+    AssertionFailed(String),
+    // Raised via `raise`; the object is an Error
+    Raised(ObjectRef),
+    IoErr(String),
+}
 
-    // Move?
-    CouldNotReadFile(String),
+impl RuntimeErrKind {
+    /// Get the name of the kind of error (e.g., "TypeErr"). This is the
+    /// kind that's shown to users, e.g. in `assert_raises` and as the
+    /// `kind` of caught errors, so it shouldn't change when a variant
+    /// is changed.
+    pub fn name(&self) -> &'static str {
+        use RuntimeErrKind::*;
+        match self {
+            EmptyStack => "EmptyStack",
+            NotEnoughValuesOnStack(_) => "NotEnoughValuesOnStack",
+            ObjectNotFound(_) => "ObjectNotFound",
+            ExpectedVar(_) => "ExpectedVar",
+            ParseErr(_) => "ParseErr",
+            CompErr(_) => "CompErr",
+            UnhandledInstruction(_) => "UnhandledInstruction",
+            TypeErr(_) => "TypeErr",
+            ValueErr(_) => "ValueErr",
+            NameErr(_) => "NameErr",
+            StringFormatErr(_) => "StringFormatErr",
+            AttrDoesNotExist(..) => "AttrDoesNotExist",
+            AttrCannotBeSet(..) => "AttrCannotBeSet",
+            ItemDoesNotExist(..) => "ItemDoesNotExist",
+            ItemCannotBeSet(..) => "ItemCannotBeSet",
+            IndexOutOfBounds(..) => "IndexOutOfBounds",
+            DivisionByZero => "DivisionByZero",
+            NotCallable(_) => "NotCallable",
+            ExitCodeOutOfRange(_) => "ExitCodeOutOfRange",
+            RecursionDepthExceeded(_) => "RecursionDepthExceeded",
+            StackOverflow(_) => "StackOverflow",
+            StepLimitExceeded(_) => "StepLimitExceeded",
+            ReturnOutsideOfFunc => "ReturnOutsideOfFunc",
+            ScopeUnderflow(_) => "ScopeUnderflow",
+            AssertionFailed(_) => "AssertionFailed",
+            Raised(_) => "Raised",
+            IoErr(_) => "IoErr",
+        }
+    }
 }
 
 impl fmt::Display for RuntimeErrKind {
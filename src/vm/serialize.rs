@@ -0,0 +1,503 @@
+//! Binary serialization for compiled code. This makes it possible to
+//! compile a program once and load it later without reparsing it.
+//!
+//! Serialized data starts with a header comprising a magic number, a
+//! format version, and a section tag indicating whether the data is a
+//! chunk of instructions or a set of constants. All integers are
+//! written in little-endian order.
+use std::str::FromStr;
+
+use num_bigint::BigInt;
+
+use crate::format::FormatSpec;
+use crate::types::{Builtins, ObjectRef};
+use crate::util::{BinaryOperator, Location, UnaryOperator};
+
+use super::inst::{Chunk, Inst};
+
+const MAGIC: &[u8; 4] = b"FEIB";
+const VERSION: u16 = 4;
+
+const CHUNK_SECTION: u8 = b'C';
+const CONSTANTS_SECTION: u8 = b'K';
+
+// Constant type tags
+const NIL: u8 = 0;
+const TRUE: u8 = 1;
+const FALSE: u8 = 2;
+const INT: u8 = 3;
+const FLOAT: u8 = 4;
+const STR: u8 = 5;
+const FUNC: u8 = 6;
+
+pub type SerializeResult<T> = Result<T, SerializeErr>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SerializeErr {
+    BadMagic,
+    UnsupportedVersion(u16),
+    UnexpectedSection(u8),
+    UnexpectedEnd,
+    UnknownOpcode(u8),
+    UnknownConstantTag(u8),
+    UnknownOperator(String),
+    InvalidFormatSpec(String),
+    InvalidUtf8,
+    UnsupportedConstant(String),
+}
+
+/// Chunks are plain vectors of instructions, so serialization is
+/// provided via this trait.
+pub trait ChunkBytes: Sized {
+    fn to_bytes(&self) -> Vec<u8>;
+    fn from_bytes(bytes: &[u8]) -> SerializeResult<Self>;
+}
+
+impl ChunkBytes for Chunk {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer::new(CHUNK_SECTION);
+        writer.write_chunk(self);
+        writer.bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> SerializeResult<Self> {
+        let mut reader = Reader::new(bytes, CHUNK_SECTION)?;
+        reader.read_chunk()
+    }
+}
+
+/// Serialize constants. Only the types of objects that the compiler
+/// adds as constants are supported: Nil, Bool, Int, Float, Str, and
+/// Func.
+pub fn serialize_constants<'a>(
+    constants: impl Iterator<Item = &'a ObjectRef>,
+) -> SerializeResult<Vec<u8>> {
+    let constants: Vec<&ObjectRef> = constants.collect();
+    let mut writer = Writer::new(CONSTANTS_SECTION);
+    writer.write_usize(constants.len());
+    for obj in constants {
+        writer.write_constant(obj)?;
+    }
+    Ok(writer.bytes)
+}
+
+/// Deserialize constants. Nil and Bool constants are mapped to the
+/// corresponding builtin singletons.
+pub fn deserialize_constants(
+    bytes: &[u8],
+    builtins: &Builtins,
+) -> SerializeResult<Vec<ObjectRef>> {
+    let mut reader = Reader::new(bytes, CONSTANTS_SECTION)?;
+    let count = reader.read_usize()?;
+    let mut constants = reader.vec_with_capacity(count);
+    for _ in 0..count {
+        constants.push(reader.read_constant(builtins)?);
+    }
+    Ok(constants)
+}
+
+// Writer --------------------------------------------------------------
+
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn new(section: u8) -> Self {
+        let mut writer = Self { bytes: vec![] };
+        writer.bytes.extend_from_slice(MAGIC);
+        writer.bytes.extend_from_slice(&VERSION.to_le_bytes());
+        writer.write_u8(section);
+        writer
+    }
+
+    fn write_u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        self.bytes.extend_from_slice(&(value as u64).to_le_bytes());
+    }
+
+    fn write_bytes(&mut self, value: &[u8]) {
+        self.write_usize(value.len());
+        self.bytes.extend_from_slice(value);
+    }
+
+    fn write_str(&mut self, value: &str) {
+        self.write_bytes(value.as_bytes());
+    }
+
+    fn write_chunk(&mut self, chunk: &Chunk) {
+        self.write_usize(chunk.len());
+        for inst in chunk {
+            self.write_inst(inst);
+        }
+    }
+
+    fn write_inst(&mut self, inst: &Inst) {
+        use Inst::*;
+        match inst {
+            NoOp => self.write_u8(0),
+            Truncate(size) => {
+                self.write_u8(1);
+                self.write_usize(*size);
+            }
+            Pop => self.write_u8(2),
+            LoadConst(index) => {
+                self.write_u8(3);
+                self.write_usize(*index);
+            }
+            ScopeStart => self.write_u8(4),
+            ScopeEnd => self.write_u8(5),
+            DeclareVar(name) => {
+                self.write_u8(6);
+                self.write_str(name);
+            }
+            AssignVar(name) => {
+                self.write_u8(7);
+                self.write_str(name);
+            }
+            LoadVar(name) => {
+                self.write_u8(8);
+                self.write_str(name);
+            }
+            Jump(addr, count) => {
+                self.write_u8(9);
+                self.write_usize(*addr);
+                self.write_usize(*count);
+            }
+            JumpIf(addr, count) => {
+                self.write_u8(10);
+                self.write_usize(*addr);
+                self.write_usize(*count);
+            }
+            JumpIfNot(addr, count) => {
+                self.write_u8(11);
+                self.write_usize(*addr);
+                self.write_usize(*count);
+            }
+            JumpIfElse(if_addr, else_addr, count) => {
+                self.write_u8(12);
+                self.write_usize(*if_addr);
+                self.write_usize(*else_addr);
+                self.write_usize(*count);
+            }
+            UnaryOp(op) => {
+                self.write_u8(13);
+                self.write_str(&op.to_string());
+            }
+            BinaryOp(op) => {
+                self.write_u8(14);
+                self.write_str(&op.to_string());
+            }
+            Call(n) => {
+                self.write_u8(15);
+                self.write_usize(*n);
+            }
+            CallMethod(n) => {
+                self.write_u8(16);
+                self.write_usize(*n);
+            }
+            Return => self.write_u8(17),
+            MakeString(n) => {
+                self.write_u8(18);
+                self.write_usize(*n);
+            }
+            MakeTuple(n) => {
+                self.write_u8(19);
+                self.write_usize(*n);
+            }
+            MakeList(n) => {
+                self.write_u8(20);
+                self.write_usize(*n);
+            }
+            Placeholder(addr, inst, message) => {
+                self.write_u8(21);
+                self.write_usize(*addr);
+                self.write_inst(inst);
+                self.write_str(message);
+            }
+            BreakPlaceholder(addr, depth) => {
+                self.write_u8(22);
+                self.write_usize(*addr);
+                self.write_usize(*depth);
+            }
+            ContinuePlaceholder(addr, depth) => {
+                self.write_u8(23);
+                self.write_usize(*addr);
+                self.write_usize(*depth);
+            }
+            Halt(code) => {
+                self.write_u8(24);
+                self.write_u8(*code);
+            }
+            HaltTop => self.write_u8(25),
+            FormatValue(spec) => {
+                self.write_u8(26);
+                self.write_str(&spec.to_string());
+            }
+            SetItem => self.write_u8(27),
+            UnpackTuple(n) => {
+                self.write_u8(28);
+                self.write_usize(*n);
+            }
+            PushHandler(addr) => {
+                self.write_u8(29);
+                self.write_usize(*addr);
+            }
+            Raise => self.write_u8(30),
+            DeclareAndAssignVar(name) => {
+                self.write_u8(31);
+                self.write_str(name);
+            }
+            LoadLocal(up, slot) => {
+                self.write_u8(32);
+                self.write_usize(*up);
+                self.write_usize(*slot);
+            }
+            StoreLocal(up, slot) => {
+                self.write_u8(33);
+                self.write_usize(*up);
+                self.write_usize(*slot);
+            }
+        }
+    }
+
+    fn write_constant(&mut self, obj: &ObjectRef) -> SerializeResult<()> {
+        if obj.is_nil() {
+            self.write_u8(NIL);
+        } else if let Some(value) = obj.bool_val() {
+            self.write_u8(if value { TRUE } else { FALSE });
+        } else if let Some(value) = obj.int_val() {
+            self.write_u8(INT);
+            self.write_bytes(&value.to_signed_bytes_le());
+        } else if let Some(value) = obj.float_val() {
+            self.write_u8(FLOAT);
+            self.bytes.extend_from_slice(&value.to_le_bytes());
+        } else if let Some(value) = obj.str_val() {
+            self.write_u8(STR);
+            self.write_str(&value);
+        } else if let Some(func) = obj.as_func() {
+            self.write_u8(FUNC);
+            self.write_str(&func.name);
+            match &func.params {
+                Some(params) => {
+                    self.write_u8(1);
+                    self.write_usize(params.len());
+                    for param in params {
+                        self.write_str(param);
+                    }
+                }
+                None => self.write_u8(0),
+            }
+            self.write_usize(func.defaults.len());
+            for chunk in func.defaults.iter() {
+                self.write_chunk(chunk);
+            }
+            match &func.rest {
+                Some(rest) => {
+                    self.write_u8(1);
+                    self.write_str(rest);
+                }
+                None => self.write_u8(0),
+            }
+            self.write_chunk(&func.chunk);
+            self.write_usize(func.locations.len());
+            for loc in func.locations.iter() {
+                self.write_usize(loc.line);
+                self.write_usize(loc.col);
+                self.write_usize(loc.offset);
+            }
+        } else {
+            return Err(SerializeErr::UnsupportedConstant(obj.type_name()));
+        }
+        Ok(())
+    }
+}
+
+// Reader --------------------------------------------------------------
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// Create a reader and check the header.
+    fn new(bytes: &'a [u8], section: u8) -> SerializeResult<Self> {
+        let mut reader = Self { bytes, pos: 0 };
+        if reader.read_n(MAGIC.len())? != MAGIC {
+            return Err(SerializeErr::BadMagic);
+        }
+        let version = u16::from_le_bytes(reader.read_n(2)?.try_into().unwrap());
+        if version != VERSION {
+            return Err(SerializeErr::UnsupportedVersion(version));
+        }
+        let actual_section = reader.read_u8()?;
+        if actual_section != section {
+            return Err(SerializeErr::UnexpectedSection(actual_section));
+        }
+        Ok(reader)
+    }
+
+    fn read_n(&mut self, n: usize) -> SerializeResult<&'a [u8]> {
+        let end = match self.pos.checked_add(n) {
+            Some(end) if end <= self.bytes.len() => end,
+            _ => return Err(SerializeErr::UnexpectedEnd),
+        };
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    /// Create a vector to hold `count` items read from the input. Since
+    /// `count` comes from the input, it's untrusted, so the capacity is
+    /// capped at the number of bytes left (each item takes up at least
+    /// one byte).
+    fn vec_with_capacity<T>(&self, count: usize) -> Vec<T> {
+        Vec::with_capacity(count.min(self.bytes.len() - self.pos))
+    }
+
+    fn read_u8(&mut self) -> SerializeResult<u8> {
+        Ok(self.read_n(1)?[0])
+    }
+
+    fn read_usize(&mut self) -> SerializeResult<usize> {
+        let bytes = self.read_n(8)?.try_into().unwrap();
+        Ok(u64::from_le_bytes(bytes) as usize)
+    }
+
+    fn read_bytes(&mut self) -> SerializeResult<&'a [u8]> {
+        let len = self.read_usize()?;
+        self.read_n(len)
+    }
+
+    fn read_string(&mut self) -> SerializeResult<String> {
+        let bytes = self.read_bytes()?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| SerializeErr::InvalidUtf8)
+    }
+
+    fn read_chunk(&mut self) -> SerializeResult<Chunk> {
+        let len = self.read_usize()?;
+        let mut chunk = Chunk::new();
+        for _ in 0..len {
+            chunk.push(self.read_inst()?);
+        }
+        Ok(chunk)
+    }
+
+    fn read_inst(&mut self) -> SerializeResult<Inst> {
+        use Inst::*;
+        let opcode = self.read_u8()?;
+        let inst = match opcode {
+            0 => NoOp,
+            1 => Truncate(self.read_usize()?),
+            2 => Pop,
+            3 => LoadConst(self.read_usize()?),
+            4 => ScopeStart,
+            5 => ScopeEnd,
+            6 => DeclareVar(self.read_string()?),
+            7 => AssignVar(self.read_string()?),
+            8 => LoadVar(self.read_string()?),
+            9 => Jump(self.read_usize()?, self.read_usize()?),
+            10 => JumpIf(self.read_usize()?, self.read_usize()?),
+            11 => JumpIfNot(self.read_usize()?, self.read_usize()?),
+            12 => {
+                JumpIfElse(self.read_usize()?, self.read_usize()?, self.read_usize()?)
+            }
+            13 => {
+                let op = self.read_string()?;
+                UnaryOp(
+                    UnaryOperator::from_str(&op)
+                        .map_err(SerializeErr::UnknownOperator)?,
+                )
+            }
+            14 => {
+                let op = self.read_string()?;
+                BinaryOp(
+                    BinaryOperator::from_str(&op)
+                        .map_err(SerializeErr::UnknownOperator)?,
+                )
+            }
+            15 => Call(self.read_usize()?),
+            16 => CallMethod(self.read_usize()?),
+            17 => Return,
+            18 => MakeString(self.read_usize()?),
+            19 => MakeTuple(self.read_usize()?),
+            20 => MakeList(self.read_usize()?),
+            21 => Placeholder(
+                self.read_usize()?,
+                Box::new(self.read_inst()?),
+                self.read_string()?,
+            ),
+            22 => BreakPlaceholder(self.read_usize()?, self.read_usize()?),
+            23 => ContinuePlaceholder(self.read_usize()?, self.read_usize()?),
+            24 => Halt(self.read_u8()?),
+            25 => HaltTop,
+            26 => {
+                let spec = self.read_string()?;
+                match FormatSpec::parse(&spec) {
+                    Some(spec) => FormatValue(spec),
+                    None => return Err(SerializeErr::InvalidFormatSpec(spec)),
+                }
+            }
+            27 => SetItem,
+            28 => UnpackTuple(self.read_usize()?),
+            29 => PushHandler(self.read_usize()?),
+            30 => Raise,
+            31 => DeclareAndAssignVar(self.read_string()?),
+            32 => LoadLocal(self.read_usize()?, self.read_usize()?),
+            33 => StoreLocal(self.read_usize()?, self.read_usize()?),
+            _ => return Err(SerializeErr::UnknownOpcode(opcode)),
+        };
+        Ok(inst)
+    }
+
+    fn read_constant(&mut self, builtins: &Builtins) -> SerializeResult<ObjectRef> {
+        let tag = self.read_u8()?;
+        let obj: ObjectRef = match tag {
+            NIL => builtins.nil_obj.clone(),
+            TRUE => builtins.true_obj.clone(),
+            FALSE => builtins.false_obj.clone(),
+            INT => builtins.new_int(BigInt::from_signed_bytes_le(self.read_bytes()?)),
+            FLOAT => {
+                let bytes = self.read_n(8)?.try_into().unwrap();
+                builtins.new_float(f64::from_le_bytes(bytes))
+            }
+            STR => builtins.new_str(self.read_string()?),
+            FUNC => {
+                let name = self.read_string()?;
+                let params = if self.read_u8()? == 1 {
+                    let count = self.read_usize()?;
+                    let mut params = self.vec_with_capacity(count);
+                    for _ in 0..count {
+                        params.push(self.read_string()?);
+                    }
+                    Some(params)
+                } else {
+                    None
+                };
+                let count = self.read_usize()?;
+                let mut defaults = self.vec_with_capacity(count);
+                for _ in 0..count {
+                    defaults.push(self.read_chunk()?);
+                }
+                let rest =
+                    if self.read_u8()? == 1 { Some(self.read_string()?) } else { None };
+                let chunk = self.read_chunk()?;
+                let count = self.read_usize()?;
+                let mut locations = self.vec_with_capacity(count);
+                for _ in 0..count {
+                    let line = self.read_usize()?;
+                    let col = self.read_usize()?;
+                    let offset = self.read_usize()?;
+                    locations.push(Location::new_with_offset(line, col, offset));
+                }
+                builtins.new_func(name, params, defaults, rest, chunk, locations)
+            }
+            _ => return Err(SerializeErr::UnknownConstantTag(tag)),
+        };
+        Ok(obj)
+    }
+}
@@ -3,23 +3,39 @@
 //! execute. After instructions are executed, it goes back into idle
 //! mode.
 use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::time::{Duration, Instant};
 
 use num_traits::ToPrimitive;
 
-use crate::types::{Args, ObjectExt, ObjectRef, Params};
+use crate::format::FormatSpec;
+use crate::types::{Args, Func, ObjectExt, ObjectRef, Params};
 use crate::util::{BinaryOperator, Stack, UnaryOperator};
 
 use super::context::RuntimeContext;
+use super::frame::{CallFrame, ErrorHandler};
 use super::inst::{Chunk, Inst};
+use super::profile::Profiler;
 use super::result::{
-    ExeResult, PeekObjResult, PopNObjResult, PopObjResult, RuntimeErr, RuntimeErrKind,
-    RuntimeResult, VMState,
+    ExeResult, PeekObjResult, PopNObjResult, PopObjResult, RuntimeBoolResult,
+    RuntimeErr, RuntimeErrKind, RuntimeObjResult, RuntimeResult, StepResult,
+    TraceEntry, VMState,
 };
 
+/// The default maximum number of nested function calls. This guards
+/// against runaway recursion overflowing the native stack.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 256;
+
+/// The default maximum number of items on the value stack. This guards
+/// against runaway programs exhausting memory.
+pub const DEFAULT_MAX_STACK_SIZE: usize = 65_536; // 2^16
+
 #[derive(Clone)]
 pub enum ValueStackKind {
     Constant(usize),
     Var(usize, String),
+    // A var accessed by slot: (depth, slot)
+    Local(usize, usize),
     Temp(ObjectRef),
     ReturnVal(ObjectRef),
 }
@@ -27,13 +43,37 @@ pub enum ValueStackKind {
 pub struct VM {
     pub ctx: RuntimeContext,
     // The value stack contains "pointers" to the different value types:
-    // constants, vars, temporaries, and return values.
+    // constants, vars, temporaries, and return values. Pushing onto a
+    // full value stack is an error; see `set_max_stack_size`.
     pub(crate) value_stack: Stack<ValueStackKind>,
     // The scope stack contains value stack sizes. Each size is the size
     // that the stack was just before a scope was entered. When a scope
     // is exited, these sizes are used to truncate the value stack back
     // to its previous size so that items can be freed.
     pub(crate) scope_stack: Stack<usize>,
+    // The frame stack contains a frame for each user function call
+    // that's in progress.
+    pub(crate) frame_stack: Stack<CallFrame>,
+    // The handler stack contains a handler for each `try` block that's
+    // in progress, innermost last.
+    pub(crate) handler_stack: Stack<ErrorHandler>,
+    // Calling a function when there are already this many frames on
+    // the frame stack is an error.
+    pub max_call_depth: usize,
+    // When profiling is enabled, instruction counts and timings are
+    // collected as instructions are executed.
+    pub profile: bool,
+    profiler: Profiler,
+    // Lines are read from here by builtins like `input()`. This is
+    // stdin by default but can be replaced (e.g., for testing).
+    pub stdin: Box<dyn BufRead>,
+    // Output from builtins like `print()` is written here. This is
+    // stdout by default but can be replaced (e.g., for testing).
+    pub stdout: Box<dyn Write>,
+    // When a step limit is set via `execute_with_limit`, this holds
+    // the limit and the number of instructions that can still be
+    // executed, including instructions in function calls.
+    step_limit: Option<(usize, usize)>,
 }
 
 impl Default for VM {
@@ -44,7 +84,25 @@ impl Default for VM {
 
 impl VM {
     pub fn new(ctx: RuntimeContext) -> Self {
-        VM { ctx, value_stack: Stack::new(), scope_stack: Stack::new() }
+        VM {
+            ctx,
+            value_stack: Stack::with_max_size(DEFAULT_MAX_STACK_SIZE),
+            scope_stack: Stack::new(),
+            frame_stack: Stack::new(),
+            handler_stack: Stack::new(),
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            profile: false,
+            profiler: Profiler::new(),
+            stdin: Box::new(BufReader::new(io::stdin())),
+            stdout: Box::new(io::stdout()),
+            step_limit: None,
+        }
+    }
+
+    /// Set the maximum number of items on the value stack. `None`
+    /// means the value stack is unbounded.
+    pub fn set_max_stack_size(&mut self, max_size: Option<usize>) {
+        self.value_stack.set_max_size(max_size);
     }
 
     /// Execute the specified instructions and return the VM's state. If
@@ -54,276 +112,405 @@ impl VM {
     /// cleared; it can be "restarted" by passing more instructions to
     /// execute.
     pub fn execute(&mut self, chunk: &Chunk, dis: bool) -> ExeResult {
+        let stack_pointer = self.value_stack.size();
+        let scope_stack_size = self.scope_stack.size();
+        let scope_depth = self.ctx.depth();
+        let mut ip = 0;
+        loop {
+            let result = self.count_step().and_then(|()| self.step(chunk, ip));
+            let (next_ip, state) = match result {
+                Ok(result) => result,
+                Err(mut err) => {
+                    err.traceback.push(TraceEntry::new(ip));
+                    match self.catch_err(err) {
+                        Ok(catch_addr) => (catch_addr, VMState::Idle),
+                        Err(err) => {
+                            // Discard the values and scopes left over
+                            // from the failed instructions (e.g., the
+                            // operands of a failed binary op) so they
+                            // don't pile up across executions.
+                            self.value_stack.truncate(stack_pointer);
+                            self.scope_stack.truncate(scope_stack_size);
+                            self.ctx.exit_scopes(
+                                self.ctx.depth().saturating_sub(scope_depth),
+                            );
+                            return Err(err);
+                        }
+                    }
+                }
+            };
+            #[cfg(debug_assertions)]
+            self.dis(dis, ip, chunk);
+            ip = next_ip;
+            if matches!(state, VMState::Halted(_)) || ip >= chunk.len() {
+                break Ok(state);
+            }
+        }
+    }
+
+    /// Execute the specified instructions like `execute` but return an
+    /// error if more than `max_steps` instructions are executed. This
+    /// can be used to bound the execution of untrusted code.
+    pub fn execute_with_limit(
+        &mut self,
+        chunk: &Chunk,
+        max_steps: usize,
+        dis: bool,
+    ) -> ExeResult {
+        self.step_limit = Some((max_steps, max_steps));
+        let result = self.execute(chunk, dis);
+        self.step_limit = None;
+        result
+    }
+
+    /// Count an instruction against the step limit, if one is set.
+    fn count_step(&mut self) -> RuntimeResult {
+        if let Some((max_steps, remaining)) = self.step_limit.as_mut() {
+            if *remaining == 0 {
+                return Err(RuntimeErr::new(RuntimeErrKind::StepLimitExceeded(
+                    *max_steps,
+                )));
+            }
+            *remaining -= 1;
+        }
+        Ok(())
+    }
+
+    /// Execute the single instruction at `ip` and return the ip of the
+    /// next instruction to execute along with the VM's state. Execution
+    /// is finished when the state is `Halted` or the next ip is past the
+    /// end of the chunk. This can be used to step through a program one
+    /// instruction at a time, e.g. to inspect the stack and vars in
+    /// between.
+    pub fn step(&mut self, chunk: &Chunk, ip: usize) -> StepResult {
         use Inst::*;
         use RuntimeErrKind::*;
         use ValueStackKind::*;
 
-        let mut ip: usize = 0;
-        let mut jump_ip = 0;
-        let mut is_jump = false;
+        let mut next_ip = ip + 1;
 
-        loop {
-            match &chunk[ip] {
-                NoOp => {
-                    // do nothing
-                }
-                Truncate(size) => {
-                    self.value_stack.truncate(*size);
-                }
-                // Constants
-                LoadConst(index) => {
-                    self.push(Constant(*index));
-                }
-                // Scopes
-                ScopeStart => {
-                    self.scope_stack.push(self.value_stack.size());
-                    self.ctx.enter_scope();
-                }
-                ScopeEnd => {
-                    self.exit_scopes(1);
-                }
-                // Vars
-                DeclareVar(name) => {
-                    if self.ctx.get_var_in_current_namespace(name).is_err() {
-                        self.ctx.declare_var(name.as_str());
-                    }
-                }
-                AssignVar(name) => {
-                    let obj = self.pop_obj()?;
-                    let depth = self.ctx.assign_var(name, obj)?;
-                    self.push(Var(depth, name.clone()));
-                }
-                LoadVar(name) => {
-                    let depth = self.ctx.get_var_depth(name.as_str())?;
-                    self.push(Var(depth, name.clone()));
-                }
-                // Jumps
-                Jump(addr, scope_exit_count) => {
-                    self.exit_scopes(*scope_exit_count);
-                    jump_ip = *addr;
-                    is_jump = true;
-                }
-                JumpIf(addr, scope_exit_count) => {
-                    self.exit_scopes(*scope_exit_count);
-                    let obj = self.pop_obj()?;
-                    if obj.as_bool(&self.ctx)? {
-                        jump_ip = *addr;
-                        is_jump = true;
-                    }
+        let profile_start = if self.profile {
+            self.profiler.count(chunk[ip].name());
+            Some(Instant::now())
+        } else {
+            None
+        };
+
+        match &chunk[ip] {
+            NoOp => {
+                // do nothing
+            }
+            Truncate(size) => {
+                self.value_stack.truncate(*size);
+            }
+            Pop => {
+                if self.pop().is_none() {
+                    return Err(RuntimeErr::new(EmptyStack));
                 }
-                JumpIfNot(addr, scope_exit_count) => {
-                    self.exit_scopes(*scope_exit_count);
-                    let obj = self.pop_obj()?;
-                    if !obj.as_bool(&self.ctx)? {
-                        jump_ip = *addr;
-                        is_jump = true;
-                    }
+            }
+            // Constants
+            LoadConst(index) => {
+                self.push(Constant(*index))?;
+            }
+            // Scopes
+            ScopeStart => {
+                self.scope_stack.push(self.value_stack.size());
+                self.ctx.enter_scope();
+            }
+            ScopeEnd => {
+                self.exit_scopes(1)?;
+            }
+            // Vars
+            DeclareVar(name) => {
+                if self.ctx.get_var_in_current_namespace(name).is_err() {
+                    self.ctx.declare_var(name.as_str());
                 }
-                JumpIfElse(if_addr, else_addr, scope_exit_count) => {
-                    self.exit_scopes(*scope_exit_count);
-                    let obj = self.pop_obj()?;
-                    let addr =
-                        if obj.as_bool(&self.ctx)? { *if_addr } else { *else_addr };
-                    jump_ip = addr;
-                    is_jump = true;
+            }
+            AssignVar(name) => {
+                let obj = self.pop_obj()?;
+                let depth = self.ctx.assign_var(name, obj)?;
+                self.push(Var(depth, name.clone()))?;
+            }
+            DeclareAndAssignVar(name) => {
+                let obj = self.pop_obj()?;
+                let depth = self.ctx.declare_and_assign_var(name, obj)?;
+                self.push(Var(depth, name.clone()))?;
+            }
+            LoadVar(name) => {
+                let depth = self.ctx.get_var_depth(name.as_str())?;
+                self.push(Var(depth, name.clone()))?;
+            }
+            LoadLocal(up, slot) => {
+                self.handle_load_local(*up, *slot)?;
+            }
+            StoreLocal(up, slot) => {
+                self.handle_store_local(*up, *slot)?;
+            }
+            // Jumps
+            Jump(addr, scope_exit_count) => {
+                self.exit_scopes(*scope_exit_count)?;
+                next_ip = *addr;
+            }
+            JumpIf(addr, scope_exit_count) => {
+                self.exit_scopes(*scope_exit_count)?;
+                let obj = self.pop_obj()?;
+                if self.as_bool(&obj)? {
+                    next_ip = *addr;
                 }
-                // Operations
-                UnaryOp(op) => {
-                    use UnaryOperator::*;
-                    let a = self.pop_obj()?;
-                    match op {
-                        Plus | Negate => {
-                            let result = match op {
-                                Plus => a, // no-op
-                                Negate => a.negate(&self.ctx)?,
-                                _ => unreachable!(),
-                            };
-                            self.push(Temp(result));
-                        }
-                        // Operators that return bool
-                        _ => {
-                            let result = match op {
-                                AsBool => a.as_bool(&self.ctx)?,
-                                Not => a.not(&self.ctx)?,
-                                _ => unreachable!(),
-                            };
-                            let obj = self.ctx.builtins.bool_obj_from_bool(result);
-                            self.push(Temp(obj));
-                        }
-                    };
+            }
+            JumpIfNot(addr, scope_exit_count) => {
+                self.exit_scopes(*scope_exit_count)?;
+                let obj = self.pop_obj()?;
+                if !self.as_bool(&obj)? {
+                    next_ip = *addr;
                 }
-                BinaryOp(op) => {
-                    use BinaryOperator::*;
-                    // Operands for the binary operation.
-                    let (a_kind, a, b) = if let Some(kinds) = self.pop_n(2) {
-                        let a_kind = kinds[0].clone();
-                        let a = self.get_obj(kinds[0].clone())?;
-                        let b = self.get_obj(kinds[1].clone())?;
-                        (a_kind, a, b)
-                    } else {
-                        return self.err(NotEnoughValuesOnStack(2));
-                    };
-                    match op {
-                        Dot => {
-                            let result = if let Some(name) = b.str_val() {
-                                a.get_attr(name.as_str(), &self.ctx)?
-                            } else if let Some(int) = b.int_val() {
-                                a.get_item(&int, &self.ctx)?
-                            } else {
-                                let message =
-                                    format!("Not an attribute name or index: {b:?}");
-                                return Err(RuntimeErr::new_type_err(message));
-                            };
-                            self.push(Temp(result));
-                        }
-                        // In-place update operators
-                        AddEqual | SubEqual => {
-                            if let Var(depth, name) = a_kind {
-                                let b = &*b;
-                                let result = match op {
-                                    AddEqual => a.add(b, &self.ctx)?,
-                                    SubEqual => a.sub(b, &self.ctx)?,
-                                    _ => unreachable!(),
-                                };
-                                self.ctx.assign_var_at_depth(
-                                    depth,
-                                    name.as_str(),
-                                    result,
-                                )?;
-                                self.push(Var(depth, name));
-                            } else {
-                                return self
-                                    .err(ExpectedVar(format!("Binary op: {}", op)));
+            }
+            JumpIfElse(if_addr, else_addr, scope_exit_count) => {
+                self.exit_scopes(*scope_exit_count)?;
+                let obj = self.pop_obj()?;
+                let addr = if self.as_bool(&obj)? { *if_addr } else { *else_addr };
+                next_ip = addr;
+            }
+            // Operations
+            UnaryOp(op) => {
+                self.handle_unary_op(op)?;
+            }
+            BinaryOp(op) => {
+                self.handle_binary_op(op)?;
+            }
+            SetItem => {
+                self.handle_set_item()?;
+            }
+            // Object construction
+            MakeString(n) => {
+                self.handle_make_string(*n)?;
+            }
+            MakeTuple(n) => {
+                self.handle_make_tuple(*n)?;
+            }
+            MakeList(n) => {
+                self.handle_make_list(*n)?;
+            }
+            UnpackTuple(n) => {
+                self.handle_unpack_tuple(*n)?;
+            }
+            FormatValue(spec) => {
+                self.handle_format_value(spec)?;
+            }
+            // Functions
+            Call(n) => {
+                self.handle_call(*n, ip)?;
+            }
+            CallMethod(n) => {
+                self.handle_call_method(*n, ip)?;
+            }
+            Return => {
+                self.handle_return()?;
+                // Return is always the last instruction in a function's
+                // chunk, so execution of the function is finished.
+                return Ok((chunk.len(), VMState::Idle));
+            }
+            // Errors
+            PushHandler(catch_addr) => {
+                self.handler_stack.push(ErrorHandler {
+                    catch_addr: *catch_addr,
+                    stack_pointer: self.value_stack.size(),
+                    scope_stack_size: self.scope_stack.size(),
+                    scope_depth: self.ctx.depth(),
+                    frame_stack_size: self.frame_stack.size(),
+                });
+            }
+            Raise => {
+                let obj = self.pop_obj()?;
+                let obj = if obj.as_error().is_some() {
+                    obj
+                } else {
+                    self.ctx.builtins.new_error("Error".to_owned(), obj.to_string())
+                };
+                return Err(RuntimeErr::new_raised(obj));
+            }
+            // Placeholders
+            Placeholder(addr, inst, message) => {
+                self.halt();
+                eprintln!("Placeholder at {addr} was not updated: {inst:?}\n{message}");
+                return Ok((next_ip, VMState::Halted(255)));
+            }
+            BreakPlaceholder(addr, _) => {
+                self.halt();
+                eprintln!("Break placeholder at {addr} was not updated");
+                return Ok((next_ip, VMState::Halted(255)));
+            }
+            ContinuePlaceholder(addr, _) => {
+                self.halt();
+                eprintln!("Continue placeholder at {addr} was not updated");
+                return Ok((next_ip, VMState::Halted(255)));
+            }
+            // VM control
+            Halt(code) => {
+                self.halt();
+                return Ok((next_ip, VMState::Halted(*code)));
+            }
+            HaltTop => {
+                let obj = self.pop_obj()?;
+                let return_code = match obj.int_val() {
+                    Some(int) => {
+                        // Exit codes that don't fit in a u8 are
+                        // errors rather than being clamped.
+                        let return_code = match int.to_u8() {
+                            Some(return_code) => return_code,
+                            None => {
+                                return Err(RuntimeErr::new(ExitCodeOutOfRange(int)))
                             }
-                        }
-                        // Math operators
-                        Pow | Mul | Div | FloorDiv | Mod | Add | Sub => {
-                            let b = &*b;
-                            let result = match op {
-                                Pow => a.pow(b, &self.ctx)?,
-                                Mul => a.mul(b, &self.ctx)?,
-                                Div => a.div(b, &self.ctx)?,
-                                FloorDiv => a.floor_div(b, &self.ctx)?,
-                                Mod => a.modulo(b, &self.ctx)?,
-                                Add => a.add(b, &self.ctx)?,
-                                Sub => a.sub(b, &self.ctx)?,
-                                _ => unreachable!(),
-                            };
-                            self.push(Temp(result));
-                        }
-                        // Operators that return bool
-                        _ => {
-                            let b = &*b;
-                            let result = match op {
-                                IsEqual => a.is_equal(b, &self.ctx),
-                                Is => a.is(b),
-                                NotEqual => a.not_equal(b, &self.ctx),
-                                And => a.and(b, &self.ctx)?,
-                                Or => a.or(b, &self.ctx)?,
-                                LessThan => a.less_than(b, &self.ctx)?,
-                                LessThanOrEqual => {
-                                    a.less_than(b, &self.ctx)?
-                                        || a.is_equal(b, &self.ctx)
-                                }
-                                GreaterThan => a.greater_than(b, &self.ctx)?,
-                                GreaterThanOrEqual => {
-                                    a.greater_than(b, &self.ctx)?
-                                        || a.is_equal(b, &self.ctx)
-                                }
-                                _ => unreachable!(),
-                            };
-                            let obj = self.ctx.builtins.bool_obj_from_bool(result);
-                            self.push(Temp(obj));
-                        }
+                        };
+                        self.halt();
+                        return_code
                     }
+                    None => 0,
+                };
+                return Ok((next_ip, VMState::Halted(return_code)));
+            }
+        }
+
+        if let Some(start) = profile_start {
+            self.profiler.add_time(chunk[ip].name(), start.elapsed());
+        }
+
+        Ok((next_ip, VMState::Idle))
+    }
+
+    // Handlers --------------------------------------------------------
+
+    fn handle_load_local(&mut self, up: usize, slot: usize) -> RuntimeResult {
+        let depth = self.ctx.outer_depth(up)?;
+        self.ctx.get_var_in_slot(depth, slot)?;
+        self.push(ValueStackKind::Local(depth, slot))
+    }
+
+    fn handle_store_local(&mut self, up: usize, slot: usize) -> RuntimeResult {
+        let depth = self.ctx.outer_depth(up)?;
+        let obj = self.pop_obj()?;
+        self.ctx.assign_var_in_slot(depth, slot, obj)?;
+        self.push(ValueStackKind::Local(depth, slot))
+    }
+
+    fn handle_unary_op(&mut self, op: &UnaryOperator) -> RuntimeResult {
+        use UnaryOperator::*;
+        use ValueStackKind::*;
+        let a = self.pop_obj()?;
+        match op {
+            Plus | Negate => {
+                let result = match op {
+                    Plus => a, // no-op
+                    Negate => a.negate(&self.ctx)?,
+                    _ => unreachable!(),
+                };
+                self.push(Temp(result))?;
+            }
+            // Operators that return bool
+            _ => {
+                let result = match op {
+                    AsBool => self.as_bool(&a)?,
+                    Not => !self.as_bool(&a)?,
+                    _ => unreachable!(),
+                };
+                let obj = self.ctx.builtins.bool_obj_from_bool(result);
+                self.push(Temp(obj))?;
+            }
+        };
+        Ok(())
+    }
+
+    fn handle_binary_op(&mut self, op: &BinaryOperator) -> RuntimeResult {
+        use BinaryOperator::*;
+        use RuntimeErrKind::*;
+        use ValueStackKind::*;
+        // Operands for the binary operation. They're left on the stack
+        // until the operation succeeds.
+        let (a, b) = self.peek_two_obj()?;
+        match op {
+            Dot => {
+                let result = self.get_attr_or_item(&a, &b)?;
+                self.replace_top_two(Temp(result))?;
+            }
+            // In-place update operators
+            AddEqual | SubEqual => {
+                let a_kind = self.value_stack.peek_n(2).unwrap()[0].clone();
+                if !matches!(a_kind, Var(..) | Local(..)) {
+                    return Err(RuntimeErr::new(ExpectedVar(format!(
+                        "Binary op: {}",
+                        op
+                    ))));
                 }
-                // Object construction
-                MakeString(n) => {
-                    let objects = self.pop_n_obj(*n)?;
-                    let mut string = String::with_capacity(32);
-                    for obj in objects {
-                        string.push_str(obj.to_string().as_str());
+                let b = &*b;
+                let result = match op {
+                    AddEqual => a.add(b, &self.ctx)?,
+                    SubEqual => a.sub(b, &self.ctx)?,
+                    _ => unreachable!(),
+                };
+                match &a_kind {
+                    Var(depth, name) => {
+                        self.ctx.assign_var_at_depth(*depth, name.as_str(), result)?
                     }
-                    let string_obj = self.ctx.builtins.new_str(string);
-                    self.push(Temp(string_obj));
-                }
-                MakeTuple(n) => {
-                    let objects = self.pop_n_obj(*n)?;
-                    let mut items = vec![];
-                    for obj in objects {
-                        items.push(obj.clone());
+                    Local(depth, slot) => {
+                        self.ctx.assign_var_in_slot(*depth, *slot, result)?
                     }
-                    let tuple = self.ctx.builtins.new_tuple(items);
-                    self.push(Temp(tuple));
-                }
-                // Functions
-                Call(n) => {
-                    self.handle_call(*n)?;
-                }
-                Return => {
-                    // Return is a no-op
-                }
-                // Placeholders
-                Placeholder(addr, inst, message) => {
-                    self.halt();
-                    eprintln!(
-                        "Placeholder at {addr} was not updated: {inst:?}\n{message}"
-                    );
-                    break Ok(VMState::Halted(255));
-                }
-                BreakPlaceholder(addr, _) => {
-                    self.halt();
-                    eprintln!("Break placeholder at {addr} was not updated");
-                    break Ok(VMState::Halted(255));
-                }
-                ContinuePlaceholder(addr, _) => {
-                    self.halt();
-                    eprintln!("Continue placeholder at {addr} was not updated");
-                    break Ok(VMState::Halted(255));
-                }
-                // VM control
-                Halt(code) => {
-                    self.halt();
-                    #[cfg(debug_assertions)]
-                    self.dis(dis, ip, &chunk);
-                    break Ok(VMState::Halted(*code));
-                }
-                HaltTop => {
-                    let obj = self.pop_obj()?;
-                    let return_code = match obj.int_val() {
-                        Some(int) => {
-                            self.halt();
-                            #[cfg(debug_assertions)]
-                            self.dis(dis, ip, &chunk);
-                            int.to_u8().unwrap_or(255)
-                        }
-                        None => 0,
-                    };
-                    break Ok(VMState::Halted(return_code));
+                    _ => unreachable!(),
                 }
+                self.replace_top_two(a_kind)?;
             }
-
-            #[cfg(debug_assertions)]
-            self.dis(dis, ip, &chunk);
-
-            if is_jump {
-                ip = jump_ip;
-                jump_ip = 0;
-                is_jump = false;
-            } else {
-                ip += 1;
+            // Math operators
+            Pow | Mul | Div | FloorDiv | Mod | Add | Sub | BitAnd | BitOr | BitXor => {
+                let b = &*b;
+                let result = match op {
+                    Pow => a.pow(b, &self.ctx)?,
+                    Mul => a.mul(b, &self.ctx)?,
+                    Div => a.div(b, &self.ctx)?,
+                    FloorDiv => a.floor_div(b, &self.ctx)?,
+                    Mod => a.modulo(b, &self.ctx)?,
+                    Add => a.add(b, &self.ctx)?,
+                    Sub => a.sub(b, &self.ctx)?,
+                    BitAnd => a.bit_and(b, &self.ctx)?,
+                    BitOr => a.bit_or(b, &self.ctx)?,
+                    BitXor => a.bit_xor(b, &self.ctx)?,
+                    _ => unreachable!(),
+                };
+                self.replace_top_two(Temp(result))?;
             }
-
-            if ip == chunk.len() {
-                break Ok(VMState::Idle);
+            // Operators that return bool
+            _ => {
+                let b = &*b;
+                let result = match op {
+                    IsEqual => a.is_equal(b, &self.ctx),
+                    Is => a.is(b),
+                    NotEqual => a.not_equal(b, &self.ctx),
+                    And => a.and(b, &self.ctx)?,
+                    Or => a.or(b, &self.ctx)?,
+                    LessThan => a.less_than(b, &self.ctx)?,
+                    LessThanOrEqual => {
+                        a.less_than(b, &self.ctx)? || a.is_equal(b, &self.ctx)
+                    }
+                    GreaterThan => a.greater_than(b, &self.ctx)?,
+                    GreaterThanOrEqual => {
+                        a.greater_than(b, &self.ctx)? || a.is_equal(b, &self.ctx)
+                    }
+                    In => b.contains(&a, &self.ctx)?,
+                    _ => unreachable!(),
+                };
+                let obj = self.ctx.builtins.bool_obj_from_bool(result);
+                self.replace_top_two(Temp(obj))?;
             }
         }
+        Ok(())
     }
 
-    // Handlers --------------------------------------------------------
+    /// Replace the top two items on the stack (the operands of a binary
+    /// operation) with the result.
+    fn replace_top_two(&mut self, kind: ValueStackKind) -> RuntimeResult {
+        self.value_stack.truncate(self.value_stack.size() - 2);
+        self.push(kind)
+    }
 
-    fn handle_call(&mut self, n: usize) -> RuntimeResult {
-        use ValueStackKind::ReturnVal;
+    fn handle_call(&mut self, n: usize, ip: usize) -> RuntimeResult {
         let objects = self.pop_n_obj(n + 1)?;
         let callable = objects.get(0).unwrap();
         let mut args: Args = vec![];
@@ -332,8 +519,152 @@ impl VM {
                 args.push(objects.get(i).unwrap().clone());
             }
         }
+        self.call(callable, args, Some(ip))
+    }
+
+    /// Handle a method call. The receiver and method name are below the
+    /// N args on the stack. If the method's first param is `this`, the
+    /// receiver is passed as the first arg; otherwise, the attribute is
+    /// called like a regular function.
+    fn handle_call_method(&mut self, n: usize, ip: usize) -> RuntimeResult {
+        let mut objects = self.pop_n_obj(n + 2)?;
+        let mut args: Args = objects.split_off(2);
+        let (receiver, name) = (&objects[0], &objects[1]);
+        let callable = self.get_attr_or_item(receiver, name)?;
+        let params = if let Some(func) = callable.as_builtin_func() {
+            &func.params
+        } else if let Some(func) = callable.as_func() {
+            &func.params
+        } else {
+            &None
+        };
+        let is_method = match params {
+            Some(params) => params.first().map(|p| p == "this").unwrap_or(false),
+            None => false,
+        };
+        if is_method {
+            args.insert(0, receiver.clone());
+        }
+        self.call(&callable, args, Some(ip))
+    }
+
+    /// Replace the top N objects on the stack with a string made by
+    /// concatenating their display strings.
+    fn handle_make_string(&mut self, n: usize) -> RuntimeResult {
+        let objects = self.pop_n_obj(n)?;
+        let mut string = String::with_capacity(32);
+        for obj in objects {
+            string.push_str(self.display_string(&obj)?.as_str());
+        }
+        let string_obj = self.ctx.builtins.new_str(string);
+        self.push(ValueStackKind::Temp(string_obj))
+    }
+
+    /// Replace the top N objects on the stack with a tuple of them.
+    fn handle_make_tuple(&mut self, n: usize) -> RuntimeResult {
+        let objects = self.pop_n_obj(n)?;
+        let mut items = vec![];
+        for obj in objects {
+            items.push(obj.clone());
+        }
+        let tuple = self.ctx.builtins.new_tuple(items);
+        self.push(ValueStackKind::Temp(tuple))
+    }
+
+    /// Replace the top N objects on the stack with a list of them.
+    fn handle_make_list(&mut self, n: usize) -> RuntimeResult {
+        let items = self.pop_n_obj(n)?;
+        let list = self.ctx.builtins.new_list(items);
+        self.push(ValueStackKind::Temp(list))
+    }
+
+    /// Replace the object at the top of the stack with a string
+    /// formatted according to the spec.
+    fn handle_format_value(&mut self, spec: &FormatSpec) -> RuntimeResult {
+        let obj = self.pop_obj()?;
+        let string = spec.format(&obj, self)?;
+        let string_obj = self.ctx.builtins.new_str(string);
+        self.push(ValueStackKind::Temp(string_obj))?;
+        Ok(())
+    }
+
+    fn handle_set_item(&mut self) -> RuntimeResult {
+        let objects = self.pop_n_obj(3)?;
+        let (obj, index, value) = (&objects[0], &objects[1], &objects[2]);
+        if let Some(name) = index.str_val() {
+            obj.set_attr(name.as_str(), value.clone(), &self.ctx)?;
+        } else if index.is_int() {
+            obj.set_item(index, value.clone(), &self.ctx)?;
+        } else {
+            let message = format!("Not an attribute name or index: {index:?}");
+            return Err(RuntimeErr::new_type_err(message));
+        }
+        self.push(ValueStackKind::Temp(value.clone()))?;
+        Ok(())
+    }
+
+    fn handle_unpack_tuple(&mut self, n: usize) -> RuntimeResult {
+        let obj = match self.peek_obj()? {
+            Some(obj) => obj,
+            None => return Err(RuntimeErr::new(RuntimeErrKind::EmptyStack)),
+        };
+        let tuple = match obj.as_tuple() {
+            Some(tuple) => tuple,
+            None => {
+                let type_name = obj.type_name();
+                let message = format!("Cannot unpack {type_name}; expected Tuple");
+                return Err(RuntimeErr::new_type_err(message));
+            }
+        };
+        if tuple.len() != n {
+            let len = tuple.len();
+            let message = format!("Expected {n} items to unpack; got {len}");
+            return Err(RuntimeErr::new_value_err(message));
+        }
+        for item in tuple.items().iter().rev() {
+            self.push(ValueStackKind::Temp(item.clone()))?;
+        }
+        Ok(())
+    }
+
+    /// Pop the current call frame and leave the function's return value
+    /// on top of the stack. The return value is the value at the top of
+    /// the stack, if the function left one there, or nil otherwise.
+    fn handle_return(&mut self) -> RuntimeResult {
+        use ValueStackKind::ReturnVal;
+        let frame = match self.frame_stack.pop() {
+            Some(frame) => frame,
+            None => return Err(RuntimeErr::new(RuntimeErrKind::ReturnOutsideOfFunc)),
+        };
+        let return_val = if self.value_stack.size() > frame.stack_pointer {
+            self.pop_obj()?
+        } else {
+            self.ctx.builtins.nil_obj.clone()
+        };
+        self.value_stack.truncate(frame.stack_pointer);
+        // Exit the scope where the function's args were bound.
+        self.exit_scopes(1)?;
+        self.push(ReturnVal(return_val))?;
+        Ok(())
+    }
+
+    /// Call an object from a builtin function and return the result.
+    pub fn call_obj(&mut self, callable: &ObjectRef, args: Args) -> RuntimeObjResult {
+        self.call(callable, args, None)?;
+        self.pop_obj()
+    }
+
+    /// Call an object with the specified args, leaving the result on
+    /// top of the stack.
+    fn call(
+        &mut self,
+        callable: &ObjectRef,
+        args: Args,
+        return_ip: Option<usize>,
+    ) -> RuntimeResult {
+        use ValueStackKind::ReturnVal;
         if let Some(func) = callable.as_builtin_func() {
-            self.check_call_args(&func.name, &func.params, &args, false)?;
+            self.check_call_args(&func.name, &func.params, 0, None, &args, false)?;
             let result = callable.call(args, self)?;
             let return_val = match result {
                 Some(return_val) => return_val,
@@ -341,35 +672,173 @@ impl VM {
             };
             // For builtin functions, the return value isn't on the
             // stack, so we have to put it there.
-            self.push(ReturnVal(return_val));
-        } else if let Some(func) = callable.as_func() {
-            // Wrap the function call in a scope where the
-            // function's locals are defined. After the
-            // call, this scope will be cleared out.
-            self.scope_stack.push(self.value_stack.size());
-            self.ctx.enter_scope();
-            self.check_call_args(&func.name, &func.params, &args, true)?;
-            self.execute(&func.chunk, false)?;
-            self.exit_scopes(1);
+            self.push(ReturnVal(return_val))?;
+        } else if callable.is_func() {
+            self.call_func(callable, args, return_ip)?;
         } else {
             return Err(RuntimeErr::new_not_callable(callable.clone()));
         }
         Ok(())
     }
 
+    /// Call a user function in a new call frame. The function's args are
+    /// bound in a new scope, which is exited when the function returns.
+    ///
+    /// If an error occurs, the frame, scope, and value stacks are
+    /// restored to their state before the call.
+    fn call_func(
+        &mut self,
+        func_obj: &ObjectRef,
+        args: Args,
+        return_ip: Option<usize>,
+    ) -> RuntimeResult {
+        if self.frame_stack.size() >= self.max_call_depth {
+            let max_depth = self.max_call_depth;
+            return Err(RuntimeErr::new(RuntimeErrKind::RecursionDepthExceeded(
+                max_depth,
+            )));
+        }
+        let func = func_obj.as_func().unwrap();
+        let frame_stack_size = self.frame_stack.size();
+        let scope_stack_size = self.scope_stack.size();
+        let scope_depth = self.ctx.depth();
+        let stack_pointer = self.value_stack.size();
+        self.frame_stack.push(CallFrame::new(
+            func_obj.clone(),
+            return_ip,
+            stack_pointer,
+        ));
+        self.scope_stack.push(stack_pointer);
+        self.ctx.enter_scope();
+        let num_defaults = func.defaults.len();
+        let rest = func.rest.as_deref();
+        let result = match self
+            .check_call_args(&func.name, &func.params, num_defaults, rest, &args, true)
+            .and_then(|()| self.bind_defaults(func, args.len()))
+        {
+            Ok(()) => {
+                self.execute(&func.chunk, false).map(|_| ()).map_err(|mut err| {
+                    // Fill in the traceback entry for the function body.
+                    if let Some(entry) = err.traceback.last_mut() {
+                        entry.func_name = Some(func.name.clone());
+                        entry.loc = func.locations.get(entry.ip).copied();
+                    }
+                    err
+                })
+            }
+            Err(err) => Err(err),
+        };
+        if result.is_err() {
+            self.frame_stack.truncate(frame_stack_size);
+            self.scope_stack.truncate(scope_stack_size);
+            self.value_stack.truncate(stack_pointer);
+            self.ctx.exit_scopes(self.ctx.depth() - scope_depth);
+            self.remove_exited_handlers();
+        }
+        result
+    }
+
+    /// If an error handler was installed in the current call frame,
+    /// unwind the stacks to where they were when it was installed, push
+    /// the error as an Error object, and return the address of the
+    /// catch block. Otherwise, return the error so that it propagates
+    /// to the caller, which may have its own handler.
+    ///
+    /// Exceeding the step limit can't be caught, since that would
+    /// defeat the purpose of the limit.
+    fn catch_err(&mut self, err: RuntimeErr) -> Result<usize, RuntimeErr> {
+        let frame_stack_size = self.frame_stack.size();
+        let in_frame =
+            |handler: &ErrorHandler| handler.frame_stack_size == frame_stack_size;
+        if let RuntimeErrKind::StepLimitExceeded(_) = err.kind {
+            while self.handler_stack.peek().is_some_and(in_frame) {
+                self.handler_stack.pop();
+            }
+            return Err(err);
+        }
+        let handler = match self.handler_stack.peek() {
+            Some(handler) if in_frame(handler) => self.handler_stack.pop().unwrap(),
+            _ => return Err(err),
+        };
+        self.value_stack.truncate(handler.stack_pointer);
+        self.scope_stack.truncate(handler.scope_stack_size);
+        self.ctx.exit_scopes(self.ctx.depth() - handler.scope_depth);
+        let err_obj = self.error_obj(err);
+        self.push(ValueStackKind::Temp(err_obj))?;
+        Ok(handler.catch_addr)
+    }
+
+    /// Get the Error object for a caught error. Errors raised via
+    /// `raise` already have one; other runtime errors are converted,
+    /// using the name of the error kind as the Error's kind.
+    fn error_obj(&self, err: RuntimeErr) -> ObjectRef {
+        use RuntimeErrKind::*;
+        let kind_name = err.kind.name();
+        let message = match err.kind {
+            Raised(obj) => return obj,
+            NameErr(message)
+            | TypeErr(message)
+            | ValueErr(message)
+            | StringFormatErr(message)
+            | AssertionFailed(message)
+            | IoErr(message) => message,
+            kind => kind.to_string(),
+        };
+        self.ctx.builtins.new_error(kind_name.to_owned(), message)
+    }
+
+    /// Remove the handlers for `try` blocks that have been exited. This
+    /// is called whenever scopes are exited, so handlers don't outlive
+    /// their `try` blocks when they're exited early (e.g., via `break`).
+    fn remove_exited_handlers(&mut self) {
+        let depth = self.ctx.depth();
+        while let Some(handler) = self.handler_stack.peek() {
+            if handler.scope_depth < depth {
+                break;
+            }
+            self.handler_stack.pop();
+        }
+    }
+
+    /// Get an attribute of an object by name or an item by index.
+    fn get_attr_or_item(&self, obj: &ObjectRef, name: &ObjectRef) -> RuntimeObjResult {
+        if let Some(name) = name.str_val() {
+            obj.get_attr(name.as_str(), &self.ctx)
+        } else if name.is_int() {
+            obj.get_item(name, &self.ctx)
+        } else {
+            let message = format!("Not an attribute name or index: {name:?}");
+            Err(RuntimeErr::new_type_err(message))
+        }
+    }
+
     /// Check call args to ensure they're valid. If they are, bind them
     /// to names in the call scope (if `bind` is specified).
+    ///
+    /// The last `num_defaults` params have defaults, so the args for
+    /// them may be omitted. Binding the defaults is handled separately
+    /// by `bind_defaults`. If there's a `rest` param, any extra args
+    /// are bound to it as a tuple.
     pub fn check_call_args(
         &mut self,
         name: &str,
         params: &Params,
+        num_defaults: usize,
+        rest: Option<&str>,
         args: &Args,
         bind: bool,
     ) -> RuntimeResult {
         if let Some(params) = &params {
             let arity = params.len();
             let num_args = args.len();
-            if num_args != arity {
+            if rest.is_some() && num_args + num_defaults < arity {
+                let required = arity - num_defaults;
+                let ess = if required == 1 { "" } else { "s" };
+                return Err(RuntimeErr::new_type_err(format!(
+                    "{name}() expected at least {required} arg{ess}; got {num_args}"
+                )));
+            }
+            if rest.is_none() && (num_args > arity || num_args + num_defaults < arity) {
                 let ess = if arity == 1 { "" } else { "s" };
                 return Err(RuntimeErr::new_type_err(format!(
                     "{name}() expected {arity} arg{ess}; got {num_args}"
@@ -379,6 +848,11 @@ impl VM {
                 for (name, arg) in params.iter().zip(args) {
                     self.ctx.declare_and_assign_var(name, arg.clone())?;
                 }
+                if let Some(rest) = rest {
+                    let extra = args.iter().skip(arity).cloned().collect();
+                    let extra = self.ctx.builtins.new_tuple(extra);
+                    self.ctx.declare_and_assign_var(rest, extra)?;
+                }
             }
         } else {
             if bind {
@@ -389,32 +863,120 @@ impl VM {
         Ok(())
     }
 
+    /// Evaluate the defaults for params that weren't passed and bind
+    /// them to names in the call scope. Defaults are evaluated in
+    /// order, so a default can refer to the params before it.
+    fn bind_defaults(&mut self, func: &Func, num_args: usize) -> RuntimeResult {
+        let params = match &func.params {
+            Some(params) => params,
+            None => return Ok(()),
+        };
+        let num_required = params.len() - func.defaults.len();
+        for (name, chunk) in params[num_required..]
+            .iter()
+            .zip(&func.defaults)
+            .skip(num_args - num_required)
+        {
+            self.execute(chunk, false).map_err(|mut err| {
+                if let Some(entry) = err.traceback.last_mut() {
+                    entry.func_name = Some(func.name.clone());
+                }
+                err
+            })?;
+            let value = self.pop_obj()?;
+            self.ctx.declare_and_assign_var(name, value)?;
+        }
+        Ok(())
+    }
+
     /// When exiting a scope, we first save the top of the stack (which
     /// is the "return value" of the scope), remove all stack values
     /// added in the scope, and finally push the scope's "return value"
     /// back onto the stack. After taking care of the VM stack, the
     /// scope's namespace is then cleared and removed.
-    fn exit_scopes(&mut self, count: usize) {
+    ///
+    /// If no values were added in the innermost scope, there's no
+    /// "return value" to save. This happens when jumping out of a
+    /// scope via a `jump` statement, for example.
+    ///
+    /// Exiting more scopes than have been entered indicates a compiler
+    /// bug (unbalanced scope instructions). This is reported as a
+    /// `ScopeUnderflow` error rather than panicking.
+    fn exit_scopes(&mut self, count: usize) -> Result<(), RuntimeErr> {
         if count == 0 {
-            return;
+            return Ok(());
         }
-        if count > 1 {
-            let drop_count = count - 1;
-            self.scope_stack.truncate(self.scope_stack.size() - drop_count);
-            self.value_stack.truncate(self.value_stack.size() - drop_count);
+        let scope_stack_size = self.scope_stack.size();
+        let depth = self.ctx.depth();
+        if count > scope_stack_size || count > depth {
+            return Err(RuntimeErr::new_scope_underflow(format!(
+                "Cannot exit {count} scope(s); scope stack size: \
+                {scope_stack_size}; namespace depth: {depth}; value stack \
+                size: {}",
+                self.value_stack.size()
+            )));
         }
-        let return_val = self.pop_obj();
-        if let Some(size) = self.scope_stack.pop() {
-            self.value_stack.truncate(size);
+        let innermost_size = *self.scope_stack.peek().unwrap();
+        let return_val = if self.value_stack.size() > innermost_size {
+            Some(self.pop_obj())
         } else {
-            panic!("Scope stack unexpectedly empty when exiting scope(s): {count}");
+            None
         };
-        if let Ok(obj) = return_val {
-            self.push(ValueStackKind::Temp(obj));
-        } else {
-            panic!("Stack unexpectedly empty when exiting scope(s): {count}");
+        let mut size = innermost_size;
+        for _ in 0..count {
+            size = self.scope_stack.pop().unwrap();
+        }
+        self.value_stack.truncate(size);
+        if let Some(Ok(obj)) = return_val {
+            self.push(ValueStackKind::Temp(obj))?;
         }
         self.ctx.exit_scopes(count);
+        self.remove_exited_handlers();
+        Ok(())
+    }
+
+    /// Convert an object to a Str. If the object is a custom object
+    /// with a `$str` attribute, it will be called with the object as
+    /// its only arg and must return a Str. Otherwise, the object's
+    /// default display string will be used.
+    pub fn str_obj(&mut self, obj: &ObjectRef) -> RuntimeObjResult {
+        if let Some(str_method) = obj.as_custom().and_then(|obj| obj.attr("$str")) {
+            let result = self.call_obj(&str_method, vec![obj.clone()])?;
+            return match result {
+                result if result.is_str() => Ok(result),
+                _ => Err(RuntimeErr::new_type_err(format!(
+                    "$str on {} must return a Str",
+                    obj.class()
+                ))),
+            };
+        }
+        Ok(self.ctx.builtins.new_str(obj.to_string()))
+    }
+
+    /// Get the string that's shown for an object by `print` and when
+    /// it's embedded in a format string. This uses `$str` if the object
+    /// has it, so it agrees with `str(obj)`.
+    pub fn display_string(&mut self, obj: &ObjectRef) -> Result<String, RuntimeErr> {
+        let str_obj = self.str_obj(obj)?;
+        Ok(str_obj.str_val().unwrap())
+    }
+
+    /// Convert an object to a bool. If the object is a custom object
+    /// with a `$bool` attribute, it will be called with the object as
+    /// its only arg and must return a Bool. Otherwise, the object's
+    /// `as_bool` will be used.
+    pub fn as_bool(&mut self, obj: &ObjectRef) -> RuntimeBoolResult {
+        if let Some(bool_method) = obj.as_custom().and_then(|obj| obj.attr("$bool")) {
+            let result = self.call_obj(&bool_method, vec![obj.clone()])?;
+            return match result.bool_val() {
+                Some(value) => Ok(value),
+                None => Err(RuntimeErr::new_type_err(format!(
+                    "$bool on {} must return a Bool",
+                    obj.class()
+                ))),
+            };
+        }
+        obj.as_bool(&self.ctx)
     }
 
     pub fn halt(&mut self) {
@@ -423,8 +985,14 @@ impl VM {
 
     // Const stack -----------------------------------------------------
 
-    pub(crate) fn push(&mut self, kind: ValueStackKind) {
-        self.value_stack.push(kind);
+    /// Push an item onto the value stack. If the value stack is full,
+    /// this is a stack overflow error.
+    pub(crate) fn push(&mut self, kind: ValueStackKind) -> RuntimeResult {
+        if self.value_stack.push_checked(kind).is_err() {
+            let max_size = self.value_stack.max_size().unwrap_or_default();
+            return Err(RuntimeErr::new(RuntimeErrKind::StackOverflow(max_size)));
+        }
+        Ok(())
     }
 
     fn pop(&mut self) -> Option<ValueStackKind> {
@@ -433,7 +1001,7 @@ impl VM {
 
     pub(crate) fn pop_obj(&mut self) -> PopObjResult {
         match self.pop() {
-            Some(kind) => self.get_obj(kind),
+            Some(kind) => self.get_obj(&kind),
             None => Err(RuntimeErr::new(RuntimeErrKind::EmptyStack)),
         }
     }
@@ -447,7 +1015,7 @@ impl VM {
             Some(kinds) => {
                 let mut objects = vec![];
                 for kind in kinds {
-                    objects.push(self.get_obj(kind)?);
+                    objects.push(self.get_obj(&kind)?);
                 }
                 Ok(objects)
             }
@@ -462,19 +1030,32 @@ impl VM {
     pub fn peek_obj(&mut self) -> PeekObjResult {
         match self.peek() {
             Some(kind) => {
-                let obj = self.get_obj(kind.clone())?;
+                let obj = self.get_obj(kind)?;
                 Ok(Some(obj))
             }
             None => Ok(None),
         }
     }
 
-    fn get_obj(&self, kind: ValueStackKind) -> Result<ObjectRef, RuntimeErr> {
+    /// Get the top two objects on the stack without popping them. The
+    /// top object is the second item in the returned tuple.
+    fn peek_two_obj(&self) -> Result<(ObjectRef, ObjectRef), RuntimeErr> {
+        match self.value_stack.peek_n(2) {
+            Some([a, b]) => Ok((self.get_obj(a)?, self.get_obj(b)?)),
+            _ => Err(RuntimeErr::new(RuntimeErrKind::NotEnoughValuesOnStack(2))),
+        }
+    }
+
+    fn get_obj(&self, kind: &ValueStackKind) -> Result<ObjectRef, RuntimeErr> {
         use ValueStackKind::*;
         match kind {
-            Constant(index) => Ok(self.ctx.get_const(index)?.clone()),
+            Constant(index) => Ok(self.ctx.get_const(*index)?.clone()),
             Var(depth, name) => {
-                let val = self.ctx.get_var_at_depth(depth, name.as_str())?;
+                let val = self.ctx.get_var_at_depth(*depth, name.as_str())?;
+                Ok(val.clone())
+            }
+            Local(depth, slot) => {
+                let val = self.ctx.get_var_in_slot(*depth, *slot)?;
                 Ok(val.clone())
             }
             Temp(obj) => Ok(obj.clone()),
@@ -484,17 +1065,13 @@ impl VM {
 
     // Utilities -------------------------------------------------------
 
-    fn err(&self, kind: RuntimeErrKind) -> ExeResult {
-        Err(RuntimeErr::new(kind))
-    }
-
     /// Show the contents of the stack (top first).
     pub fn display_stack(&self) {
         if self.value_stack.is_empty() {
             return eprintln!("[EMPTY]");
         }
         for (i, kind) in self.value_stack.iter().enumerate() {
-            let obj = self.get_obj(kind.clone());
+            let obj = self.get_obj(kind);
             match obj {
                 Ok(obj) => {
                     eprintln!("{:0>8} {:?}", i, obj)
@@ -504,6 +1081,41 @@ impl VM {
         }
     }
 
+    /// Show the call stack (innermost call first).
+    pub fn display_call_stack(&self) {
+        if self.frame_stack.is_empty() {
+            return eprintln!("[EMPTY]");
+        }
+        for (i, frame) in self.frame_stack.iter().enumerate() {
+            let return_ip = match frame.return_ip {
+                Some(ip) => ip.to_string(),
+                None => "[BUILTIN]".to_owned(),
+            };
+            eprintln!("{:0>4} {} called from {}", i, frame.func, return_ip);
+        }
+    }
+
+    /// Get the profiling report, which contains (instruction name,
+    /// execution count, total time) for each instruction that was
+    /// executed while profiling was enabled, most frequent first.
+    ///
+    /// NOTE: Time isn't recorded for instructions that exit the
+    ///       current chunk (RETURN, HALT, etc).
+    pub fn profile_report(&self) -> Vec<(String, u64, Duration)> {
+        self.profiler.report()
+    }
+
+    /// Show profiling report.
+    pub fn display_profile_report(&self) {
+        let report = self.profile_report();
+        if report.is_empty() {
+            return eprintln!("[EMPTY]");
+        }
+        for (name, count, time) in report {
+            eprintln!("{name: <16}{count: >12}{: >16}", format!("{time:?}"));
+        }
+    }
+
     /// Show constants.
     pub fn display_constants(&self) {
         for (index, obj) in self.ctx.iter_constants().enumerate() {
@@ -513,7 +1125,28 @@ impl VM {
 
     /// Show vars.
     pub fn display_vars(&self) {
-        // TODO:
+        for line in self.format_vars() {
+            eprintln!("{line}");
+        }
+    }
+
+    /// Format vars in all scopes, starting with the current scope and
+    /// working outward to the global scope. Each scope is labeled with
+    /// its depth and its vars are sorted by name.
+    pub fn format_vars(&self) -> Vec<String> {
+        let mut lines = vec![];
+        for (depth, namespace) in self.ctx.iter_namespaces().enumerate().rev() {
+            lines.push(format!("DEPTH {depth}"));
+            let mut vars: Vec<_> = namespace.iter().collect();
+            if vars.is_empty() {
+                lines.push("    [EMPTY]".to_owned());
+            }
+            vars.sort_by_key(|(name, _)| *name);
+            for (name, obj) in vars {
+                lines.push(format!("    {name} = {}", format_obj(obj)));
+            }
+        }
+        lines
     }
 
     // Disassembler ----------------------------------------------------
@@ -523,12 +1156,19 @@ impl VM {
 
     /// Disassemble a list of instructions.
     pub fn dis_list(&mut self, chunk: &Chunk) -> ExeResult {
-        for (ip, _) in chunk.iter().enumerate() {
-            self.dis(true, ip, chunk);
+        for line in self.format_chunk(chunk) {
+            eprintln!("{line}");
         }
         Ok(VMState::Halted(0))
     }
 
+    /// Disassemble a list of instructions into lines of text, one per
+    /// instruction, without printing them. Since the instructions
+    /// aren't being executed, the values of vars aren't shown.
+    pub fn format_chunk(&mut self, chunk: &Chunk) -> Vec<String> {
+        (0..chunk.len()).map(|ip| self.format_line(ip, chunk, false)).collect()
+    }
+
     /// Disassemble functions, returning the number of functions that
     /// were disassembled.
     pub fn dis_functions(&mut self) -> usize {
@@ -558,23 +1198,30 @@ impl VM {
     /// we don't have to wrap every call in `if dis { self.dis(...) }`.
     pub fn dis(&mut self, flag: bool, ip: usize, chunk: &Chunk) {
         if flag {
-            let inst = &chunk[ip];
-            let formatted = self.format_instruction(chunk, inst);
-            eprintln!("{:0>4} {}", ip, formatted);
+            eprintln!("{}", self.format_line(ip, chunk, true));
         }
     }
 
-    fn format_instruction(&mut self, chunk: &Chunk, inst: &Inst) -> String {
+    /// Format the instruction at `ip` along with its address. When
+    /// `show_vars` is set, the value of the var on top of the stack is
+    /// shown for var instructions.
+    fn format_line(&mut self, ip: usize, chunk: &Chunk, show_vars: bool) -> String {
+        let formatted = self.format_instruction(chunk, &chunk[ip], show_vars);
+        format!("{:0>4} {}", ip, formatted)
+    }
+
+    fn format_instruction(
+        &mut self,
+        chunk: &Chunk,
+        inst: &Inst,
+        show_vars: bool,
+    ) -> String {
         use Inst::*;
         use ValueStackKind::*;
 
         let obj_str = |kind_opt: Option<&ValueStackKind>| match kind_opt {
-            Some(kind) => match self.get_obj(kind.clone()) {
-                Ok(obj) => {
-                    let type_name = obj.type_name();
-                    let str = format!("{obj:?} <{type_name}>");
-                    str.replace("\n", "\\n").replace("\r", "\\r")
-                }
+            Some(kind) => match self.get_obj(kind) {
+                Ok(obj) => format_obj(&obj),
                 Err(err) => format!("[ERROR: Could not get object: {err}]"),
             },
             None => format!("[Object not found]"),
@@ -583,6 +1230,7 @@ impl VM {
         match inst {
             NoOp => format!("NOOP"),
             Truncate(size) => self.format_aligned("TRUNCATE", format!("{size}")),
+            Pop => "POP".to_owned(),
             LoadConst(index) => {
                 let obj_str = obj_str(Some(&Constant(*index)));
                 self.format_aligned("LOAD_CONST", format!("{index} : {obj_str}"))
@@ -590,14 +1238,37 @@ impl VM {
             ScopeStart => format!("SCOPE_START"),
             ScopeEnd => format!("SCOPE_END"),
             DeclareVar(name) => self.format_aligned("DECLARE_VAR", name),
+            AssignVar(name) if !show_vars => self.format_aligned("ASSIGN_VAR", name),
+            DeclareAndAssignVar(name) if !show_vars => {
+                self.format_aligned("DECLARE_ASSIGN", name)
+            }
+            LoadVar(name) if !show_vars => self.format_aligned("LOAD_VAR", name),
             AssignVar(name) => {
                 let obj_str = obj_str(self.peek());
                 self.format_aligned("ASSIGN_VAR", format!("{name} = {obj_str}"))
             }
+            DeclareAndAssignVar(name) => {
+                let obj_str = obj_str(self.peek());
+                self.format_aligned("DECLARE_ASSIGN", format!("{name} = {obj_str}"))
+            }
             LoadVar(name) => {
                 let obj_str = obj_str(self.peek());
                 self.format_aligned("LOAD_VAR", format!("{name} = {obj_str}"))
             }
+            LoadLocal(up, slot) if !show_vars => {
+                self.format_aligned("LOAD_LOCAL", format!("{up} : {slot}"))
+            }
+            StoreLocal(up, slot) if !show_vars => {
+                self.format_aligned("STORE_LOCAL", format!("{up} : {slot}"))
+            }
+            LoadLocal(up, slot) => {
+                let obj_str = obj_str(self.peek());
+                self.format_aligned("LOAD_LOCAL", format!("{up} : {slot} = {obj_str}"))
+            }
+            StoreLocal(up, slot) => {
+                let obj_str = obj_str(self.peek());
+                self.format_aligned("STORE_LOCAL", format!("{up} : {slot} = {obj_str}"))
+            }
             Jump(addr, _) => self.format_aligned("JUMP", format!("{addr}",)),
             JumpIf(addr, _) => self.format_aligned("JUMP_IF", format!("{addr}",)),
             JumpIfNot(addr, _) => {
@@ -610,11 +1281,18 @@ impl VM {
             BinaryOp(operator) => self.format_aligned("BINARY_OP", operator),
             MakeString(n) => self.format_aligned("MAKE_STRING", n),
             MakeTuple(n) => self.format_aligned("MAKE_TUPLE", n),
+            MakeList(n) => self.format_aligned("MAKE_LIST", n),
+            UnpackTuple(n) => self.format_aligned("UNPACK_TUPLE", n),
+            FormatValue(spec) => self.format_aligned("FORMAT_VALUE", spec),
+            PushHandler(addr) => self.format_aligned("PUSH_HANDLER", addr),
+            Raise => "RAISE".to_owned(),
+            SetItem => "SET_ITEM".to_owned(),
             Call(n) => self.format_aligned("CALL", n),
-            Return => format!("RETURN"),
+            CallMethod(n) => self.format_aligned("CALL_METHOD", n),
+            Return => "RETURN".to_owned(),
             Halt(code) => self.format_aligned("HALT", code),
             Placeholder(addr, inst, message) => {
-                let formatted_inst = self.format_instruction(chunk, inst);
+                let formatted_inst = self.format_instruction(chunk, inst, show_vars);
                 self.format_aligned(
                     "PLACEHOLDER",
                     format!("{formatted_inst} @ {addr} ({message})"),
@@ -635,3 +1313,12 @@ impl VM {
         format!("{: <w$}{: <x$}", name, value, w = 16, x = 4)
     }
 }
+
+/// Format an object along with its type for display in disassembly and
+/// debugging output. Newlines are escaped so that each object is shown
+/// on a single line.
+fn format_obj(obj: &ObjectRef) -> String {
+    let type_name = obj.type_name();
+    let str = format!("{} <{type_name}>", obj.repr());
+    str.replace('\n', "\\n").replace('\r', "\\r")
+}